@@ -1,7 +1,12 @@
 info = Info
 toggle_side_panel = Toggle the side panel visibility
 pause = Pause
+play = Play
+replay = Replay
+replay_speed = Speed
 quit = Quit
+save_layout = Save Layout
+layout_saved = Saved the layout
 
 # SidePanel
 ## Device Info
@@ -49,6 +54,9 @@ memory_clock = Memory Clock
 resizable_bar = ResizableBAR
 ecc_memory = ECC Memory
 ecc_memory_error_count = ECC Memory Error Count
+ras_error_count = RAS Error Count
+block = Block
+last_increased = Last Increased
 corrected = Corrected
 uncorrected = UnCorrected
 
@@ -75,8 +83,26 @@ power_cap = Power Cap.
 power_cap_default = Power Cap. (Default)
 
 pcie_link_speed = PCIe Link Speed
+pcie_link_downtrained = PCIe Link Downtrained
+expected = Expected
 pci_power_state = PCI Power State
 power_profile = Power Profile
+power_control = Power Control
+performance_level = Performance Level
+apply = Apply
+overdrive = Overdrive
+commit = Commit
+discard = Discard
+restore = Restore
+reset_to_defaults = Reset to Defaults
+fan_control = Fan Control
+fan_speed = Fan Speed
+fan_curve = Fan Curve
+acoustic_limit = Acoustic Limit
+zero_rpm = Zero RPM
+target = Target
+restore_auto_fan = Restore Automatic Fan Control
+energy = Energy
 # Dynamic Power Management (DPM)
 dpm = DPM
 max = Max
@@ -166,15 +192,19 @@ dma = DMA
 vcn = VCN
 # Video Compression Engine
 vpe = VPE
+jpeg = JPEG
+uvd_enc = UVD ENC
 # Process Name
 name = Name
 pid = PID
+client_id = Client ID
 
 sensor = Sensors
 
 pcie_bw = PCIe Bandwidth
 sent = Sent
 received = Received
+total = Total
 
 gpu_metrics = GPU Metrics
 avg = Avg.
@@ -195,5 +225,27 @@ avg_activity = Average Activity
 activity = Activity
 throttle_status = Throttle Status
 throttling_log = Throttling Log
+throttling = Throttling
+throttler = Throttler
+residency = Residency
+transitions = Transitions
+active = Active
+dpm_residency = DPM Residency
+fine_grained = Fine-grained
+gfxoff = GFXOFF
+gfxoff_status = Status
+devcoredump = GPU Hang Reports
+devcoredump_kernel = Kernel
+devcoredump_process = Process
+devcoredump_ring = Timed Out Ring
+devcoredump_page_fault = Page Fault
+devcoredump_ibs = IBs
+devcoredump_ip_dump = IP Dump
+devcoredump_saved_to = Saved to
+gpu_resets = GPU Resets
+gfxoff_blocked_by_pc = Kept out of GFXOFF by the register reads of the performance counters (--no-pc)
+alerts = Alerts
+no_alerts = No alerts
+session_stats = Session Statistics
 
 failed_to_set_up_gui = Failed to set up a graphics context.
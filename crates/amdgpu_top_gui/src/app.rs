@@ -1,19 +1,63 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use eframe::wgpu::AdapterInfo;
 use crate::egui::{self, RichText, util::History};
 use crate::{BASE, MEDIUM, HISTORY_LENGTH};
 use crate::{GuiAppData, util::*, fl};
+use crate::gui_control::{GuiFanControl, GuiOverdrive, GuiPowerControl};
 use egui_plot::{Corner, Legend, Line, Plot, PlotPoint, PlotPoints};
 
 use libamdgpu_top::{
-    AMDGPU::RasErrorCount,
+    config::{self, Settings},
     DevicePath,
     PCI,
-    stat::{FdInfoSortType, PerfCounter},
+    record::Player,
+    stat::{FdInfoSortType, FdInfoUsage, PcieBwSource, PerfCounter, RasStat},
 };
 
 const SPACING: [f32; 2] = [16.0; 2];
 
+fn fdinfo_usage_labels(
+    ui: &mut egui::Ui,
+    usage: &FdInfoUsage,
+    cpu_usage: Option<i64>,
+    energy: Option<f64>, // J
+    [has_vcn, has_vcn_unified, has_vpe]: [bool; 3],
+    mib: &str,
+) {
+    ui.label(format!("{:5} {mib}", usage.vram_usage >> 10));
+    ui.label(format!("{:5} {mib}", usage.gtt_usage >> 10));
+
+    if let Some(cpu_usage) = cpu_usage {
+        ui.label(format!("{cpu_usage:3} %"));
+    } else {
+        ui.label("");
+    }
+
+    for (usage, flag) in [
+        (usage.gfx, true),
+        (usage.compute, true),
+        (usage.dma, true),
+        (usage.dec, !has_vcn_unified),
+        (usage.enc, true),
+        (usage.vcn_jpeg, has_vcn),
+        (usage.uvd_enc, !has_vcn),
+        (usage.vpe, has_vpe),
+    ] {
+        if !flag { continue }
+
+        ui.label(format!("{usage:5.1} %"));
+    }
+
+    if let Some(energy) = energy {
+        ui.label(format!("{:7.2} Wh", energy / 3600.0));
+    } else {
+        ui.label("");
+    }
+}
+
 const SENSORS_HEIGHT: f32 = 96.0;
 const SENSORS_WIDTH: f32 = SENSORS_HEIGHT * 4.0;
 const FDINFO_LIST_HEIGHT: f32 = 208.0;
@@ -23,6 +67,7 @@ const PLOT_WIDTH: f32 = PLOT_HEIGHT * 5.0;
 pub struct MyApp {
     pub fdinfo_sort: FdInfoSortType,
     pub reverse_sort: bool,
+    pub expanded_fdinfo_pids: HashSet<i32>,
     pub buf_data: GuiAppData,
     pub buf_vec_data: Vec<GuiAppData>,
     pub arc_data: Arc<Mutex<Vec<GuiAppData>>>,
@@ -34,6 +79,15 @@ pub struct MyApp {
     pub no_pc: bool,
     pub pause: bool,
     pub full_fdinfo_list: bool,
+    pub replay: Option<Arc<Mutex<Player>>>,
+    pub has_alert_rules: bool,
+    pub has_session_stats: bool,
+    pub config_path: Option<PathBuf>,
+    pub config_status: Option<String>,
+    pub enable_control: bool,
+    pub power_control: Option<GuiPowerControl>,
+    pub overdrive: Option<GuiOverdrive>,
+    pub fan_control: Option<GuiFanControl>,
 }
 
 pub fn grid(ui: &mut egui::Ui, v: &[(&str, &str)]) {
@@ -44,20 +98,32 @@ pub fn grid(ui: &mut egui::Ui, v: &[(&str, &str)]) {
     }
 }
 
-pub trait GuiMemoryErrorCount {
+pub trait GuiRasErrorCount {
     fn ui(&self, ui: &mut egui::Ui);
 }
 
-impl GuiMemoryErrorCount for RasErrorCount {
+impl GuiRasErrorCount for RasStat {
     fn ui(&self, ui: &mut egui::Ui) {
-        egui::Grid::new("ECC Memory Error Count").show(ui, |ui| {
+        egui::Grid::new("RAS Error Count").show(ui, |ui| {
+            ui.label(fl!("block"));
             ui.label(fl!("corrected"));
-            ui.label(self.corrected.to_string());
-            ui.end_row();
-
             ui.label(fl!("uncorrected"));
-            ui.label(self.uncorrected.to_string());
+            ui.label(fl!("last_increased"));
             ui.end_row();
+
+            for block in &self.blocks {
+                ui.label(&block.name);
+                ui.label(format!("{:7} (+{})", block.count.corrected, block.delta.corrected));
+                ui.label(format!("{:7} (+{})", block.count.uncorrected, block.delta.uncorrected));
+
+                if let Some(ago) = block.last_increased.and_then(|t| SystemTime::now().duration_since(t).ok()) {
+                    ui.label(format!("{}s", ago.as_secs()));
+                } else {
+                    ui.label("");
+                }
+
+                ui.end_row();
+            }
         });
     }
 }
@@ -168,19 +234,19 @@ impl MyApp {
             .map(|_| Vec::<[f64; 2]>::with_capacity(HISTORY_LENGTH.end));
 
         for (i, usage) in self.buf_data.history.fdinfo_history.iter() {
-            gfx.push([i, usage.gfx as f64]);
-            compute.push([i, usage.compute as f64]);
-            dma.push([i, usage.dma as f64]);
+            gfx.push([i, usage.gfx]);
+            compute.push([i, usage.compute]);
+            dma.push([i, usage.dma]);
 
             if has_vcn_unified {
-                media.push([i, usage.media as f64]);
+                media.push([i, usage.media]);
             } else {
-                dec.push([i, usage.total_dec as f64]);
-                enc.push([i, usage.total_enc as f64]);
+                dec.push([i, usage.total_dec]);
+                enc.push([i, usage.total_enc]);
             }
 
             if has_vpe {
-                vpe.push([i, usage.vpe as f64]);
+                vpe.push([i, usage.vpe]);
             }
         }
 
@@ -216,6 +282,9 @@ impl MyApp {
     }
 
     pub fn egui_fdinfo_list(&mut self, ui: &mut egui::Ui, has_vcn_unified: bool, has_vpe: bool) {
+        let has_vcn = self.buf_data.stat.fdinfo.has_vcn;
+        let flags = [has_vcn, has_vcn_unified, has_vpe];
+
         egui::Grid::new("fdinfo").show(ui, |ui| {
             ui.style_mut().override_font_id = Some(MEDIUM);
             ui.label(rt_base(format!("{:^15}", fl!("name")))).highlight();
@@ -229,10 +298,13 @@ impl MyApp {
                 (fl!("gfx"), 5, FdInfoSortType::GFX, true),
                 (fl!("compute"), 9, FdInfoSortType::Compute, true),
                 (fl!("dma"), 5, FdInfoSortType::DMA, true),
-                (fl!("media"), 5, FdInfoSortType::MediaEngine, has_vcn_unified),
                 (fl!("decode"), 9, FdInfoSortType::Decode, !has_vcn_unified),
                 (fl!("encode"), 9, FdInfoSortType::Encode, !has_vcn_unified),
+                (fl!("vcn"), 9, FdInfoSortType::Encode, has_vcn_unified),
+                (fl!("jpeg"), 9, FdInfoSortType::JPEG, has_vcn),
+                (fl!("uvd_enc"), 9, FdInfoSortType::UvdEnc, !has_vcn),
                 (fl!("vpe"), 5, FdInfoSortType::VPE, has_vpe),
+                (fl!("energy"), 10, FdInfoSortType::Energy, true),
             ] {
                 if !flag { continue; }
 
@@ -256,32 +328,39 @@ impl MyApp {
             let mib = fl!("mib");
 
             for pu in &self.buf_data.stat.fdinfo.proc_usage {
-                ui.label(pu.name.to_string());
-                ui.label(format!("{:>8}", pu.pid));
-                ui.label(if pu.is_kfd_process { " Y " } else { "" });
-                ui.label(format!("{:5} {mib}", pu.usage.vram_usage >> 10));
-                ui.label(format!("{:5} {mib}", pu.usage.gtt_usage >> 10));
-                for usage in [
-                    pu.cpu_usage,
-                    pu.usage.gfx,
-                    pu.usage.compute,
-                    pu.usage.dma,
-                ] {
-                    ui.label(format!("{usage:3} %"));
-                }
+                let expanded = self.expanded_fdinfo_pids.contains(&pu.pid);
 
-                if has_vcn_unified {
-                    ui.label(format!("{:3} %", pu.usage.media));
-                } else {
-                    ui.label(format!("{:3} %", pu.usage.total_dec));
-                    ui.label(format!("{:3} %", pu.usage.total_enc));
-                }
+                if pu.clients.len() > 1 {
+                    let mark = if expanded { "▼" } else { "▶" };
 
-                if has_vpe {
-                    ui.label(format!("{:3} %", pu.usage.vpe));
+                    if ui.selectable_label(expanded, format!("{mark} {}", pu.name)).clicked() {
+                        if expanded {
+                            self.expanded_fdinfo_pids.remove(&pu.pid);
+                        } else {
+                            self.expanded_fdinfo_pids.insert(pu.pid);
+                        }
+                    }
+                } else {
+                    ui.label(pu.name.to_string());
                 }
 
+                ui.label(format!("{:>8}", pu.pid));
+                ui.label(if pu.is_kfd_process { " Y " } else { "" });
+                fdinfo_usage_labels(ui, &pu.usage, Some(pu.cpu_usage), Some(pu.energy), flags, &mib);
                 ui.end_row();
+
+                if !expanded || pu.clients.len() < 2 { continue }
+
+                for client in &pu.clients {
+                    let name = client.fdinfo.client_name.clone()
+                        .unwrap_or_else(|| format!("{} {}", fl!("client_id"), client.client_id));
+
+                    ui.label(format!("    {name}"));
+                    ui.label(format!("{:>8}", format!("fd {}", client.fd)));
+                    ui.label("");
+                    fdinfo_usage_labels(ui, &client.usage, None, None, flags, &mib);
+                    ui.end_row();
+                }
             } // proc_usage
         });
     }
@@ -467,6 +546,49 @@ impl MyApp {
             ));
         }
 
+        if let Some(fan) = &sensors.fan {
+            let mut s = format!("{} =>", fl!("fan_control"));
+
+            if let Some(mode) = fan.mode {
+                s.push_str(&format!(" {mode}"));
+            }
+
+            if let Some(per) = fan.pwm_percent() {
+                s.push_str(&format!(", PWM {per:>3}%"));
+            }
+
+            if let Some(target) = fan.target_rpm {
+                s.push_str(&format!(" ({} {target} {})", fl!("target"), fl!("rpm")));
+            }
+
+            ui.label(s);
+        }
+
+        let monitor = &self.buf_data.stat.pcie_link_monitor;
+
+        if let (Some(cur), Some(exp)) = (monitor.downtrained, monitor.expected) {
+            ui.label(RichText::new(format!(
+                "{} => Gen{}x{} ({} Gen{}x{})",
+                fl!("pcie_link_downtrained"),
+                cur.gen,
+                cur.width,
+                fl!("expected"),
+                exp.gen,
+                exp.width,
+            )).color(egui::Color32::RED));
+        }
+
+        let energy = &self.buf_data.stat.energy;
+
+        if let Some(source) = energy.source {
+            ui.label(format!(
+                "{} => {:.2} Wh ({:.0} J, {source})",
+                fl!("energy"),
+                energy.wh(),
+                energy.total,
+            ));
+        }
+
         if let Some(power_state) = &sensors.pci_power_state {
             ui.label(format!(
                 "{}: {}",
@@ -533,6 +655,11 @@ impl MyApp {
         let fl_rec = fl!("received");
         let mib_s = fl!("mib_s");
 
+        if self.buf_data.pcie_bw_source == Some(PcieBwSource::GpuMetrics) {
+            self.egui_pcie_bw_total(ui);
+            return;
+        }
+
         let [sent, rec] = {
             let [mut sent_history, mut rec_history] = [0; 2].map(|_| Vec::<[f64; 2]>::new());
 
@@ -565,6 +692,35 @@ impl MyApp {
         }
     }
 
+    /// `pcie_bandwidth_{inst,acc}` in gpu_metrics, the total of both directions
+    fn egui_pcie_bw_total(&self, ui: &mut egui::Ui) {
+        let label_fmt = |name: &str, val: &PlotPoint| {
+            format!("{:.1}s : {name} {:.0} {}", val.x, val.y, fl!("mib_s"))
+        };
+        let fl_total = fl!("total");
+        let mib_s = fl!("mib_s");
+        let points: Vec<[f64; 2]> = self.buf_data.history.pcie_bw_history
+            .iter()
+            .map(|(i, (total, _))| [i, total as f64])
+            .collect();
+
+        default_plot("pcie_bw plot")
+            .label_formatter(label_fmt)
+            .auto_bounds([true, true].into())
+            .height(PLOT_HEIGHT)
+            .width(PLOT_WIDTH.min(ui.available_width()))
+            .legend(Legend::default().position(Corner::LeftTop))
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(PlotPoints::new(points)).name(&fl_total));
+            });
+
+        if let Some((total, _)) = self.buf_data.history.pcie_bw_history.latest() {
+            ui.label(format!("{fl_total}: {total:5} {mib_s} (gpu_metrics)"));
+        } else {
+            ui.label(format!("{fl_total}: _ {mib_s} (gpu_metrics)"));
+        }
+    }
+
     pub fn egui_activity(&self, ui: &mut egui::Ui) {
         let label_fmt = |name: &str, val: &PlotPoint| {
             format!("{:.1}s : {name} {:.0}%", val.x, val.y)
@@ -690,6 +846,235 @@ impl MyApp {
             });
     }
 
+    pub fn egui_throttling(&self, ui: &mut egui::Ui) {
+        let thr = &self.buf_data.stat.throttling;
+        let tracked = thr.tracked.unwrap_or_default().as_secs_f64();
+
+        egui::Grid::new("Throttling").show(ui, |ui| {
+            ui.label(fl!("throttler"));
+            ui.label(fl!("residency"));
+            ui.label(fl!("transitions"));
+            ui.end_row();
+
+            for r in &thr.reasons {
+                ui.label(&r.name);
+                ui.label(format!(
+                    "{:9.1}s ({:5.1}%)",
+                    r.residency.as_secs_f64(),
+                    thr.residency_percent(r),
+                ));
+                ui.label(format!("{:5}", r.transitions));
+                if r.active { ui.label(fl!("active")); }
+                ui.end_row();
+            }
+        });
+
+        if thr.reasons.is_empty() { return }
+
+        // Each throttler is drawn as a step line, high while it is active.
+        let periods = thr.active_periods();
+        let label_fmt = |name: &str, val: &PlotPoint| {
+            format!("{:.1}s : {name}", val.x)
+        };
+
+        Plot::new("Throttling Timeline")
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .show_axes([true, false])
+            .include_x(0.0)
+            .include_x(tracked)
+            .label_formatter(label_fmt)
+            .height(PLOT_HEIGHT)
+            .width(PLOT_WIDTH.min(ui.available_width() - 100.0))
+            .legend(Legend::default().position(Corner::LeftTop))
+            .show(ui, |plot_ui| {
+                for (i, (name, periods)) in periods.into_iter().enumerate() {
+                    let base = i as f64;
+                    let mut points = vec![[0.0, base]];
+
+                    for [start, end] in periods {
+                        points.extend([[start, base], [start, base + 0.8], [end, base + 0.8], [end, base]]);
+                    }
+
+                    points.push([tracked, base]);
+                    plot_ui.line(Line::new(PlotPoints::new(points)).name(name));
+                }
+            });
+    }
+
+    pub fn egui_dpm(&self, ui: &mut egui::Ui) {
+        for clock in &self.buf_data.stat.dpm.clocks {
+            let mut header = format!("{} ({:.1}s)", clock.clock, clock.tracked.as_secs_f64());
+
+            if clock.fine_grained {
+                header.push_str(&format!(" {}", fl!("fine_grained")));
+            }
+
+            ui.label(RichText::new(header).font(MEDIUM));
+
+            egui::Grid::new(format!("DPM {}", clock.clock)).show(ui, |ui| {
+                for r in &clock.residency {
+                    let per = clock.residency_percent(r);
+                    let text = format!("{:9.1}s ({per:5.1}%)", r.residency.as_secs_f64());
+                    let bar = egui::ProgressBar::new(per as f32 / 100.0)
+                        .text(RichText::new(&text).font(BASE));
+
+                    ui.label(&r.name);
+                    ui.add_sized([360.0, 16.0], bar);
+                    if r.active { ui.label(fl!("active")); }
+                    ui.end_row();
+                }
+            });
+        }
+    }
+
+    pub fn egui_gfxoff(&self, ui: &mut egui::Ui) {
+        let Some(gfxoff) = &self.buf_data.stat.gfxoff else { return };
+
+        egui::Grid::new("GFXOFF").show(ui, |ui| {
+            ui.label(fl!("gfxoff_status"));
+            if gfxoff.mode.is_disabled() {
+                ui.label(fl!("disabled"));
+            } else {
+                ui.label(gfxoff.status.to_string());
+            }
+            ui.end_row();
+
+            if let Some(per) = gfxoff.residency_percent() {
+                ui.label(fl!("residency"));
+                ui.label(format!("{per:5.1}%"));
+                ui.end_row();
+            }
+
+            ui.label(fl!("transitions"));
+            ui.label(format!("{:5}", gfxoff.transitions));
+            ui.end_row();
+        });
+
+        if gfxoff.blocked_by_pc {
+            ui.label(RichText::new(fl!("gfxoff_blocked_by_pc")).color(egui::Color32::YELLOW));
+        }
+    }
+
+    /// The banner of the last GPU hang, and the decoded reports
+    pub fn egui_devcoredump(&self, ui: &mut egui::Ui) {
+        let monitor = &self.buf_data.stat.devcoredump;
+        let Some(last) = monitor.last() else { return };
+
+        ui.label(RichText::new(last.to_string()).font(MEDIUM).color(egui::Color32::RED).strong());
+
+        let title = match monitor.resets {
+            Some(resets) => format!("{} ({}, {}: {resets})", fl!("devcoredump"), monitor.count, fl!("gpu_resets")),
+            None => format!("{} ({})", fl!("devcoredump"), monitor.count),
+        };
+        let hex = |v: &[u64]| v.iter().map(|addr| format!("{addr:#x}")).collect::<Vec<_>>().join(", ");
+
+        collapsing_with_id(ui, &title, "devcoredump", false, |ui| {
+            for (i, report) in monitor.reports.iter().enumerate().rev() {
+                egui::Grid::new(format!("devcoredump {i}")).show(ui, |ui| {
+                    for (label, val) in [
+                        (fl!("devcoredump_kernel"), report.kernel.clone()),
+                        (
+                            fl!("devcoredump_process"),
+                            report.process_name.as_ref().map(|name| format!("{name} ({})", report.pid.unwrap_or_default())),
+                        ),
+                        (fl!("devcoredump_ring"), report.ring.clone()),
+                        (fl!("devcoredump_page_fault"), report.page_fault.map(|addr| format!("{addr:#x}"))),
+                        (fl!("devcoredump_ibs"), (!report.ibs.is_empty()).then(|| hex(&report.ibs))),
+                        (fl!("devcoredump_ip_dump"), (!report.ip_dumps.is_empty()).then(|| report.ip_dumps.join(", "))),
+                        (fl!("devcoredump_saved_to"), report.path.as_ref().map(|path| path.display().to_string())),
+                    ] {
+                        let Some(val) = val else { continue };
+
+                        ui.label(label);
+                        ui.label(val);
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+            }
+        });
+    }
+
+    pub fn save_layout(&mut self, ctx: &egui::Context) {
+        let Some(path) = &self.config_path else { return };
+        let settings = Settings {
+            is_dark_mode: Some(ctx.theme() == egui::Theme::Dark),
+            fdinfo_sort: Some(self.fdinfo_sort),
+            reverse_sort: Some(self.reverse_sort),
+            side_panel: Some(self.show_sidepanel),
+            full_fdinfo_list: Some(self.full_fdinfo_list),
+            ..Default::default()
+        };
+
+        self.config_status = Some(match config::save_settings(path, Some(self.selected_pci_bus), &settings) {
+            Ok(()) => fl!("layout_saved"),
+            Err(err) => format!("{err:#}"),
+        });
+    }
+
+    pub fn egui_alerts(&self, ui: &mut egui::Ui) {
+        if self.buf_data.alerts.is_empty() {
+            ui.label(fl!("no_alerts"));
+            return;
+        }
+
+        for alert in &self.buf_data.alerts {
+            let text = RichText::new(format!("[{}] {}", alert.rule, alert.message));
+
+            ui.label(if alert.highlight { text.color(egui::Color32::RED).strong() } else { text });
+        }
+    }
+
+    pub fn egui_session_stats(&self, ui: &mut egui::Ui) {
+        let Some(text) = &self.buf_data.session_stats else { return };
+
+        ui.label(RichText::new(text).monospace());
+    }
+
+    pub fn egui_replay_control(&self, ui: &mut egui::Ui) {
+        const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+        let Some(player) = &self.replay else { return };
+        let Ok(mut player) = player.lock() else { return };
+
+        {
+            let label = if player.is_paused() { fl!("play") } else { fl!("pause") };
+
+            if ui.button(RichText::new(label).font(BASE)).clicked() {
+                player.toggle_pause();
+            }
+        }
+
+        {
+            let duration = player.duration().as_secs_f64();
+            let mut position = player.position().as_secs_f64();
+            let res = ui.add(
+                egui::Slider::new(&mut position, 0.0..=duration)
+                    .text(fl!("replay"))
+                    .suffix("s")
+                    .fixed_decimals(1)
+            );
+
+            if res.changed() {
+                player.seek(std::time::Duration::from_secs_f64(position));
+            }
+        }
+
+        {
+            let mut speed = player.speed();
+            let mut changed = false;
+
+            egui::ComboBox::from_id_salt("Replay Speed")
+                .selected_text(format!("{} x{speed}", fl!("replay_speed")))
+                .show_ui(ui, |ui| for s in SPEEDS {
+                    changed |= ui.selectable_value(&mut speed, s, format!("x{s}")).changed();
+                });
+
+            if changed {
+                player.set_speed(speed);
+            }
+        }
+    }
 }
 
 fn default_plot(id: &str) -> Plot {
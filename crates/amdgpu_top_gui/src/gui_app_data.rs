@@ -2,6 +2,8 @@ use crate::egui::util::History;
 use crate::HISTORY_LENGTH;
 
 use libamdgpu_top::{AppDeviceInfo, ConnectorInfo, DevicePath, PCI};
+use libamdgpu_top::alert::{AlertEvent, AlertMonitor};
+use libamdgpu_top::session_stats::{update_session_stats, SessionStats};
 use libamdgpu_top::app::{
     AppAmdgpuTop,
     AppAmdgpuTopStat,
@@ -9,6 +11,7 @@ use libamdgpu_top::app::{
 use libamdgpu_top::AMDGPU::{MetricsInfo, ThrottleStatus};
 use libamdgpu_top::stat::{
     FdInfoUsage,
+    PcieBwSource,
     Sensors,
     gpu_metrics_util,
 };
@@ -95,11 +98,13 @@ pub struct GuiAppData {
     pub stat: AppAmdgpuTopStat,
     pub device_info: AppDeviceInfo,
     pub pci_bus: PCI::BUS_INFO,
-    pub support_pcie_bw: bool,
+    pub pcie_bw_source: Option<PcieBwSource>,
     pub history: HistoryData,
     pub vec_connector_info: Vec<ConnectorInfo>,
     pub xdna_device_path: Option<DevicePath>,
     pub xdna_fw_version: Option<String>,
+    pub alerts: Vec<AlertEvent>,
+    pub session_stats: Option<String>,
 }
 
 impl GuiAppData {
@@ -141,7 +146,9 @@ impl GuiAppData {
             stat: app.stat.clone(),
             device_info: app.device_info.clone(),
             pci_bus: app.device_info.pci_bus,
-            support_pcie_bw: app.stat.arc_pcie_bw.is_some(),
+            pcie_bw_source: app.stat.arc_pcie_bw
+                .as_ref()
+                .map(|arc| arc.lock().unwrap().source),
             history: HistoryData {
                 grbm_history,
                 grbm2_history,
@@ -164,21 +171,36 @@ impl GuiAppData {
             vec_connector_info: libamdgpu_top::connector_info(&app.device_path),
             xdna_device_path,
             xdna_fw_version,
+            alerts: Vec::new(),
+            session_stats: None,
         }
     }
 
+    pub fn update_alerts(&mut self, app: &AppAmdgpuTop, monitor: &mut Option<AlertMonitor>) {
+        let Some(monitor) = monitor else { return };
+
+        monitor.check(app);
+        self.alerts = monitor.active().to_vec();
+    }
+
+    pub fn update_session_stats(&mut self, app: &AppAmdgpuTop, list: &mut Option<Vec<SessionStats>>) {
+        let Some(list) = list else { return };
+
+        self.session_stats = update_session_stats(list, app).text().ok();
+    }
+
     pub fn update_history(&mut self, secs: f64, no_pc: bool) {
         if let Some(arc_pcie_bw) = &self.stat.arc_pcie_bw {
             let lock = arc_pcie_bw.try_lock();
             if let Ok(pcie_bw) = lock {
-                if let (Some(sent), Some(rec), Some(mps)) = (
-                    pcie_bw.sent,
-                    pcie_bw.received,
-                    pcie_bw.max_payload_size,
-                ) {
-                    let sent = (sent * mps as u64) >> 20;
-                    let rec = (rec * mps as u64) >> 20;
-                    self.history.pcie_bw_history.add(secs, (sent, rec));
+                // gpu_metrics reports the total of both directions
+                let sent_rec = match pcie_bw.source {
+                    PcieBwSource::Sysfs => pcie_bw.sent_mib().zip(pcie_bw.received_mib()),
+                    PcieBwSource::GpuMetrics => pcie_bw.total_mib().map(|total| (total, 0)),
+                };
+
+                if let Some(sent_rec) = sent_rec {
+                    self.history.pcie_bw_history.add(secs, sent_rec);
                 }
             }
         }
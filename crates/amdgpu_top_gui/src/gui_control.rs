@@ -0,0 +1,459 @@
+use eframe::egui::{self, RichText};
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use libamdgpu_top::AMDGPU::PowerCap;
+use libamdgpu_top::PCI;
+use libamdgpu_top::control::{
+    FanControl,
+    FanCtrlValue,
+    FanCurve,
+    FanCurvePoint,
+    OdEdit,
+    OverdriveEditor,
+    OverdriveTable,
+    PerformanceLevel,
+    PowerControl,
+    PowerProfileMode,
+};
+use libamdgpu_top::stat::FanStat;
+
+use crate::{app::MyApp, fl};
+
+/// The state of the power control for the selected device (`--enable-control`)
+pub struct GuiPowerControl {
+    control: PowerControl,
+    power_cap: Option<PowerCap>,
+    performance_level: Option<PerformanceLevel>,
+    power_profiles: Vec<PowerProfileMode>,
+    /// The value of the slider
+    staged_power_cap: u32,
+    status: Option<Result<String, String>>,
+}
+
+impl GuiPowerControl {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> Self {
+        let mut s = Self {
+            control: PowerControl::new(pci_bus),
+            power_cap: None,
+            performance_level: None,
+            power_profiles: Vec::new(),
+            staged_power_cap: 0,
+            status: None,
+        };
+
+        s.refresh();
+
+        s
+    }
+
+    /// The values are read when the device is selected and after a change,
+    /// not at every frame.
+    fn refresh(&mut self) {
+        self.power_cap = self.control.power_cap();
+        self.performance_level = self.control.performance_level();
+        self.power_profiles = self.control.power_profiles();
+        self.staged_power_cap = self.power_cap.as_ref().map_or(0, |cap| cap.current);
+    }
+
+    fn apply<E: std::fmt::Display>(&mut self, r: Result<(), E>, msg: String) {
+        self.status = status(r, msg);
+        self.refresh();
+    }
+}
+
+impl MyApp {
+    pub fn egui_power_control(&mut self, ui: &mut egui::Ui) {
+        let pci_bus = self.buf_data.pci_bus;
+
+        if !matches!(&self.power_control, Some(pc) if pc.control.pci_bus == pci_bus) {
+            self.power_control = Some(GuiPowerControl::new(pci_bus));
+        }
+
+        let Some(pc) = &mut self.power_control else { return };
+
+        egui::Grid::new("Power Control").show(ui, |ui| {
+            if let Some(cap) = pc.power_cap.clone() {
+                ui.label(fl!("power_cap"));
+                ui.add(egui::Slider::new(&mut pc.staged_power_cap, cap.min..=cap.max).suffix(" W"));
+
+                if ui.button(fl!("apply")).clicked() {
+                    let watts = pc.staged_power_cap;
+                    let r = pc.control.set_power_cap(watts);
+                    pc.apply(r, format!("{}: {watts} W", fl!("power_cap")));
+                }
+
+                if ui.button(format!("{} ({} W)", fl!("power_cap_default"), cap.default)).clicked() {
+                    let r = pc.control.reset_power_cap();
+                    pc.apply(r, format!("{}: {} W", fl!("power_cap"), cap.default));
+                }
+
+                ui.end_row();
+            }
+
+            if let Some(current) = pc.performance_level {
+                let mut level = current;
+
+                ui.label(fl!("performance_level"));
+                egui::ComboBox::from_id_salt("Performance Level")
+                    .selected_text(level.to_string())
+                    .show_ui(ui, |ui| for l in PerformanceLevel::ALL {
+                        ui.selectable_value(&mut level, l, l.to_string());
+                    });
+                ui.end_row();
+
+                if level != current {
+                    let r = pc.control.set_performance_level(level);
+                    pc.apply(r, format!("{}: {level}", fl!("performance_level")));
+                }
+            }
+
+            if !pc.power_profiles.is_empty() {
+                let current = pc.power_profiles.iter().find(|mode| mode.is_current).map(|mode| mode.name.clone());
+                let mut name = current.clone().unwrap_or_default();
+
+                ui.label(fl!("power_profile"));
+                egui::ComboBox::from_id_salt("Power Profile")
+                    .selected_text(&name)
+                    .show_ui(ui, |ui| for mode in &pc.power_profiles {
+                        ui.selectable_value(&mut name, mode.name.clone(), format!("{:2} {}", mode.index, mode.name));
+                    });
+                ui.end_row();
+
+                if !name.is_empty() && current.as_ref() != Some(&name) {
+                    let r = pc.control.set_power_profile(&name);
+                    pc.apply(r, format!("{}: {name}", fl!("power_profile")));
+                }
+            }
+        });
+
+        match &pc.status {
+            Some(Ok(msg)) => { ui.label(msg); },
+            Some(Err(err)) => { ui.label(RichText::new(err).color(egui::Color32::RED)); },
+            None => {},
+        }
+    }
+}
+
+/// The overdrive table of the selected device, and the values being edited (`--enable-control`)
+pub struct GuiOverdrive {
+    pci_bus: PCI::BUS_INFO,
+    /// `None` if overdrive is not enabled or not supported
+    editor: Option<OverdriveEditor>,
+    /// The values of the DragValues
+    draft: OverdriveTable,
+    status: Option<Result<String, String>>,
+}
+
+impl GuiOverdrive {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> Self {
+        let editor = OverdriveEditor::new(pci_bus).ok();
+        let draft = editor.as_ref().map(|e| e.table.clone()).unwrap_or_default();
+
+        Self { pci_bus, editor, draft, status: None }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.editor.is_some()
+    }
+
+    /// Stage the entries changed in the draft, and commit them.
+    fn commit(editor: &mut OverdriveEditor, draft: &OverdriveTable) -> Result<(), String> {
+        editor.discard();
+
+        for section in draft.sections.iter().filter(|section| section.command().is_some()) {
+            for entry in &section.entries {
+                let current = editor.table.section(&section.name).and_then(|s| s.entry(entry.index));
+
+                if current == Some(entry) { continue }
+
+                editor.stage(OdEdit {
+                    section: section.name.clone(),
+                    index: entry.index,
+                    values: entry.values.iter().map(|v| v.value).collect(),
+                }).map_err(|err| format!("{err:#}"))?;
+            }
+        }
+
+        editor.commit().map_err(|err| format!("{err:#}"))
+    }
+}
+
+fn status<E: std::fmt::Display>(r: Result<(), E>, msg: String) -> Option<Result<String, String>> {
+    Some(r.map(|_| msg).map_err(|err| format!("{err:#}")))
+}
+
+impl MyApp {
+    /// Read the overdrive table when the device is selected.
+    pub fn update_overdrive(&mut self) {
+        let pci_bus = self.buf_data.pci_bus;
+
+        if !matches!(&self.overdrive, Some(od) if od.pci_bus == pci_bus) {
+            self.overdrive = Some(GuiOverdrive::new(pci_bus));
+        }
+    }
+
+    pub fn egui_overdrive(&mut self, ui: &mut egui::Ui) {
+        let enable_control = self.enable_control;
+        let Some(od) = &mut self.overdrive else { return };
+        let Some(editor) = &mut od.editor else { return };
+
+        egui::Grid::new("Overdrive").show(ui, |ui| {
+            for section in od.draft.sections.iter_mut() {
+                let editable = enable_control && section.command().is_some();
+
+                ui.label(section.name.as_str());
+                ui.end_row();
+
+                for entry in section.entries.iter_mut() {
+                    match entry.index {
+                        Some(i) => ui.label(format!("  {i}")),
+                        None => ui.label(""),
+                    };
+
+                    for (pos, v) in entry.values.iter_mut().enumerate() {
+                        if editable {
+                            let drag = egui::DragValue::new(&mut v.value).suffix(format!(" {}", v.unit.as_str()));
+                            let drag = match editor.table.range_for(&section.name, entry.index, pos) {
+                                Some(range) => drag.range(range.min.value..=range.max.value),
+                                None => drag,
+                            };
+
+                            ui.add(drag);
+                        } else {
+                            ui.label(v.to_string());
+                        }
+                    }
+
+                    ui.end_row();
+                }
+            }
+
+            if !editor.table.ranges.is_empty() {
+                ui.label("OD_RANGE");
+                ui.end_row();
+            }
+
+            for range in &editor.table.ranges {
+                ui.label(format!("  {}", range.name));
+                ui.label(range.min.to_string());
+                ui.label(range.max.to_string());
+                ui.end_row();
+            }
+        });
+
+        if !enable_control { return }
+
+        ui.horizontal(|ui| {
+            let changed = od.draft != editor.table;
+
+            if ui.add_enabled(changed, egui::Button::new(fl!("commit"))).clicked() {
+                let r = GuiOverdrive::commit(editor, &od.draft);
+                od.status = status(r, fl!("commit"));
+            } else if ui.add_enabled(changed, egui::Button::new(fl!("discard"))).clicked() {
+                editor.discard();
+                od.status = None;
+            } else if ui.add_enabled(editor.previous.is_some(), egui::Button::new(fl!("restore"))).clicked() {
+                let r = editor.restore();
+                od.status = status(r, fl!("restore"));
+            } else if ui.button(fl!("reset_to_defaults")).clicked() {
+                let r = editor.reset();
+                od.status = status(r, fl!("reset_to_defaults"));
+            } else {
+                return;
+            }
+
+            od.draft = editor.table.clone();
+        });
+
+        match &od.status {
+            Some(Ok(msg)) => { ui.label(msg); },
+            Some(Err(err)) => { ui.label(RichText::new(err).color(egui::Color32::RED)); },
+            None => {},
+        }
+    }
+}
+
+/// The state of the fan control for the selected device (`--enable-control`)
+pub struct GuiFanControl {
+    control: FanControl,
+    fan: Option<FanStat>,
+    fan_curve: Option<FanCurve>,
+    acoustic_limit: Option<FanCtrlValue>,
+    zero_rpm: Option<FanCtrlValue>,
+    /// The values being edited
+    staged_speed: u32,
+    staged_points: Vec<FanCurvePoint>,
+    staged_acoustic_limit: i64,
+    status: Option<Result<String, String>>,
+}
+
+impl GuiFanControl {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> Self {
+        let mut s = Self {
+            control: FanControl::new(pci_bus),
+            fan: None,
+            fan_curve: None,
+            acoustic_limit: None,
+            zero_rpm: None,
+            staged_speed: 0,
+            staged_points: Vec::new(),
+            staged_acoustic_limit: 0,
+            status: None,
+        };
+
+        s.refresh();
+
+        s
+    }
+
+    fn refresh(&mut self) {
+        self.fan = self.control.fan_stat();
+        self.fan_curve = self.control.fan_curve();
+        self.acoustic_limit = self.control.acoustic_limit();
+        self.zero_rpm = self.control.zero_rpm();
+        self.staged_speed = self.fan.as_ref().and_then(|fan| fan.pwm_percent()).unwrap_or(0);
+        self.staged_points = self.fan_curve.as_ref().map(|curve| curve.points.clone()).unwrap_or_default();
+        self.staged_acoustic_limit = self.acoustic_limit.as_ref().map_or(0, |v| v.value);
+    }
+
+    fn apply<E: std::fmt::Display>(&mut self, r: Result<(), E>, msg: String) {
+        self.status = status(r, msg);
+        self.refresh();
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.fan.as_ref().is_some_and(|fan| fan.mode.is_some())
+            || self.fan_curve.is_some()
+            || self.acoustic_limit.is_some()
+            || self.zero_rpm.is_some()
+    }
+}
+
+impl MyApp {
+    /// Read the fan control when the device is selected.
+    pub fn update_fan_control(&mut self) {
+        let pci_bus = self.buf_data.pci_bus;
+
+        if !matches!(&self.fan_control, Some(fc) if fc.control.pci_bus == pci_bus) {
+            self.fan_control = Some(GuiFanControl::new(pci_bus));
+        }
+    }
+
+    pub fn egui_fan_control(&mut self, ui: &mut egui::Ui) {
+        let Some(fc) = &mut self.fan_control else { return };
+
+        egui::Grid::new("Fan Control").show(ui, |ui| {
+            if let Some(mode) = fc.fan.as_ref().and_then(|fan| fan.mode) {
+                ui.label(format!("{} ({mode})", fl!("fan_speed")));
+                ui.add(egui::Slider::new(&mut fc.staged_speed, 0..=100).suffix("%"));
+
+                if ui.button(fl!("apply")).clicked() {
+                    let per = fc.staged_speed;
+                    let r = fc.control.set_manual_speed(per);
+                    fc.apply(r, format!("{}: {per}%", fl!("fan_speed")));
+                }
+
+                ui.end_row();
+            }
+
+            if let Some(limit) = fc.acoustic_limit.clone() {
+                let drag = egui::DragValue::new(&mut fc.staged_acoustic_limit).suffix(format!(" {}", fl!("rpm")));
+                let drag = match &limit.range {
+                    Some(range) => drag.range(range.min.value..=range.max.value),
+                    None => drag,
+                };
+
+                ui.label(fl!("acoustic_limit"));
+                ui.add(drag);
+
+                if ui.button(fl!("apply")).clicked() {
+                    let rpm = fc.staged_acoustic_limit as u32;
+                    let r = fc.control.set_acoustic_limit(rpm);
+                    fc.apply(r, format!("{}: {rpm} {}", fl!("acoustic_limit"), fl!("rpm")));
+                }
+
+                ui.end_row();
+            }
+
+            if let Some(zero_rpm) = fc.zero_rpm.clone() {
+                let mut enable = zero_rpm.value != 0;
+
+                ui.label(fl!("zero_rpm"));
+
+                if ui.checkbox(&mut enable, "").changed() {
+                    let r = fc.control.set_zero_rpm(enable);
+                    fc.apply(r, format!("{}: {enable}", fl!("zero_rpm")));
+                }
+
+                ui.end_row();
+            }
+        });
+
+        if let Some(curve) = fc.fan_curve.clone() {
+            ui.label(fl!("fan_curve"));
+
+            egui::Grid::new("Fan Curve").show(ui, |ui| {
+                for (i, point) in fc.staged_points.iter_mut().enumerate() {
+                    let temp = egui::DragValue::new(&mut point.temp).suffix(" C");
+                    let speed = egui::DragValue::new(&mut point.speed).suffix("%");
+                    let temp = match &curve.temp_range {
+                        Some(range) => temp.range(range.min.value..=range.max.value),
+                        None => temp,
+                    };
+                    let speed = match &curve.speed_range {
+                        Some(range) => speed.range(range.min.value..=range.max.value),
+                        None => speed,
+                    };
+
+                    ui.label(format!("{i}"));
+                    ui.add(temp);
+                    ui.add(speed);
+                    ui.end_row();
+                }
+            });
+
+            let points: PlotPoints = fc.staged_points.iter().map(|p| [p.temp as f64, p.speed as f64]).collect();
+            let markers: PlotPoints = fc.staged_points.iter().map(|p| [p.temp as f64, p.speed as f64]).collect();
+
+            Plot::new("Fan Curve Plot")
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .allow_drag(false)
+                .include_x(0.0)
+                .include_x(100.0)
+                .include_y(0.0)
+                .include_y(100.0)
+                .height(120.0)
+                .width(240.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(points));
+                    plot_ui.points(Points::new(markers).radius(3.0));
+                });
+
+            ui.horizontal(|ui| {
+                let changed = fc.staged_points != curve.points;
+
+                if ui.add_enabled(changed, egui::Button::new(fl!("apply"))).clicked() {
+                    let points = fc.staged_points.clone();
+                    let r = fc.control.set_fan_curve(&points);
+                    fc.apply(r, fl!("fan_curve"));
+                } else if ui.add_enabled(changed, egui::Button::new(fl!("discard"))).clicked() {
+                    fc.staged_points = curve.points.clone();
+                } else if ui.button(fl!("reset_to_defaults")).clicked() {
+                    let r = fc.control.reset_fan_curve();
+                    fc.apply(r, format!("{}: {}", fl!("fan_curve"), fl!("reset_to_defaults")));
+                }
+            });
+        }
+
+        if ui.button(fl!("restore_auto_fan")).clicked() {
+            let r = fc.control.set_auto();
+            fc.apply(r, fl!("restore_auto_fan"));
+        }
+
+        match &fc.status {
+            Some(Ok(msg)) => { ui.label(msg); },
+            Some(Err(err)) => { ui.label(RichText::new(err).color(egui::Color32::RED)); },
+            None => {},
+        }
+    }
+}
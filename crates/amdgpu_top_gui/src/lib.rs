@@ -11,7 +11,9 @@ use libamdgpu_top::{
         GpuMetrics,
         MetricsInfo,
     },
-    app::AppAmdgpuTop,
+    alert::AlertMonitor,
+    app::{AppAmdgpuTop, AppOption},
+    signal,
     stat::{
         self,
         PerfCounter,
@@ -19,6 +21,7 @@ use libamdgpu_top::{
     AppDeviceInfo,
     DevicePath,
     GuiWgpuBackend,
+    record::{Player, Recorder},
     Sampling,
     UiArgs,
     PCI,
@@ -28,7 +31,7 @@ mod gui_app_data;
 use gui_app_data::GuiAppData;
 
 mod app;
-use app::{GuiMemoryErrorCount, MyApp};
+use app::{GuiRasErrorCount, MyApp};
 
 mod gui_gpu_metrics;
 use gui_gpu_metrics::GuiGpuMetrics;
@@ -36,6 +39,8 @@ use gui_gpu_metrics::GuiGpuMetrics;
 mod gui_device_info;
 use gui_device_info::{GuiInfo, GuiConnectorInfo, GuiHwIpInfo, GuiIpDiscovery, GuiVbiosInfo, GuiVideoCapsInfo, GuiXdnaInfo};
 
+mod gui_control;
+
 mod util;
 use util::*;
 
@@ -73,6 +78,14 @@ pub fn run(
         no_pc,
         is_dark_mode,
         gui_wgpu_backend,
+        record,
+        replay,
+        alert_rules,
+        settings,
+        config_path,
+        session_stats,
+        enable_control,
+        gfxoff_residency,
         ..
     }: UiArgs,
 ) {
@@ -84,17 +97,27 @@ pub fn run(
         eprintln!("Error while loading languages for library_fluent {error}");
     }
 
-    let (mut vec_app, mut suspended_devices) = AppAmdgpuTop::create_app_and_suspended_list(
-        &device_path_list,
-        &Default::default(),
-    );
+    let replay = replay.map(|recording| Arc::new(Mutex::new(Player::new(recording))));
+    let mut recorder = record.map(|path| Recorder::create(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to create {path:?}: {err}");
+        std::process::exit(1);
+    }));
+
+    let (mut vec_app, mut suspended_devices) = if let Some(player) = &replay {
+        (player.lock().unwrap().recording.create_app_list(), Vec::new())
+    } else {
+        AppAmdgpuTop::create_app_and_suspended_list(
+            &device_path_list,
+            &AppOption { gfxoff_residency, ..Default::default() },
+        )
+    };
 
     for app in vec_app.iter_mut() {
         app.stat.grbm.get_i18n_index(&LANGUAGE_LOADER);
         app.stat.grbm2.get_i18n_index(&LANGUAGE_LOADER);
     }
 
-    {
+    if replay.is_none() {
         let mut device_paths: Vec<DevicePath> = device_path_list.clone();
 
         if let Some(xdna_device_path) = vec_app
@@ -108,6 +131,13 @@ pub fn run(
     }
 
     let mut vec_data: Vec<_> = vec_app.iter().map(GuiAppData::new).collect();
+    let mut vec_alert_monitor: Vec<_> = vec_app
+        .iter()
+        .map(|_| alert_rules.clone().map(AlertMonitor::new))
+        .collect();
+    // The statistics are not collected during a replay.
+    let has_session_stats = session_stats && replay.is_none();
+    let mut session_stats = has_session_stats.then(Vec::new);
 
     let sample = Sampling::low();
 
@@ -127,21 +157,35 @@ pub fn run(
         .clone();
 
     let mut gui_app = MyApp {
-        fdinfo_sort: Default::default(),
-        reverse_sort: false,
+        fdinfo_sort: settings.fdinfo_sort.unwrap_or_default(),
+        reverse_sort: settings.reverse_sort.unwrap_or(false),
+        expanded_fdinfo_pids: Default::default(),
         buf_data: data,
         buf_vec_data: vec_data.clone(),
         arc_data: Arc::new(Mutex::new(vec_data.clone())),
         device_path_list,
-        show_sidepanel: true,
+        show_sidepanel: settings.side_panel.unwrap_or(true),
         wgpu_adapter_info: None,
         rocm_version: libamdgpu_top::get_rocm_version(),
         selected_pci_bus,
         no_pc,
         pause: false,
-        full_fdinfo_list: false,
+        full_fdinfo_list: settings.full_fdinfo_list.unwrap_or(false),
+        replay: replay.clone(),
+        has_alert_rules: alert_rules.is_some(),
+        has_session_stats,
+        config_path,
+        config_status: None,
+        // The device is not controlled during a replay.
+        enable_control: enable_control && replay.is_none(),
+        power_control: None,
+        overdrive: None,
+        fan_control: None,
     };
 
+    // Close the window on SIGINT/SIGTERM, to stop the residency logging of GFXOFF.
+    if gfxoff_residency { signal::catch_exit_signals() }
+
     let options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
             .with_inner_size(egui::vec2(1080.0, 840.0))
@@ -164,6 +208,41 @@ pub fn run(
         let share_data = gui_app.arc_data.clone();
 
         std::thread::spawn(move || loop {
+            if let Some(player) = &replay {
+                std::thread::sleep(sample.to_duration());
+
+                {
+                    let mut player = player.lock().unwrap();
+                    player.tick();
+
+                    let seeked = player.take_seeked();
+                    let secs = player.position().as_secs_f64();
+
+                    for ((app, data), monitor) in vec_app
+                        .iter_mut()
+                        .zip(vec_data.iter_mut())
+                        .zip(vec_alert_monitor.iter_mut())
+                    {
+                        // clear the history, because it cannot go back in time
+                        if seeked {
+                            *data = GuiAppData::new(app);
+                        }
+
+                        player.apply(app);
+                        data.stat = app.stat.clone();
+                        data.update_history(secs, no_pc);
+                        data.update_alerts(app, monitor);
+                    }
+                }
+
+                let lock = share_data.lock();
+                if let Ok(mut share_data) = lock {
+                    share_data.clone_from(&vec_data);
+                }
+
+                continue;
+            }
+
             if !no_pc {
                 for _ in 0..sample.count {
                     for app in vec_app.iter_mut() {
@@ -184,9 +263,21 @@ pub fn run(
                 app.update(sample.to_duration());
             }
 
-            for (app, data) in vec_app.iter_mut().zip(vec_data.iter_mut()) {
+            for ((app, data), monitor) in vec_app
+                .iter_mut()
+                .zip(vec_data.iter_mut())
+                .zip(vec_alert_monitor.iter_mut())
+            {
                 data.stat = app.stat.clone();
                 data.update_history(now.elapsed().as_secs_f64(), no_pc);
+                data.update_alerts(app, monitor);
+                data.update_session_stats(app, &mut session_stats);
+
+                if let Some(Err(err)) = recorder.as_mut().map(|r| r.record(app)) {
+                    eprintln!("Failed to record: {err}");
+                    recorder = None;
+                }
+
                 if !no_pc { app.clear_pc(); }
             }
 
@@ -201,13 +292,14 @@ pub fn run(
                 let is_active = dev.check_if_device_is_active();
 
                 if is_active {
-                    let Ok(amdgpu_dev) = dev.init() else { return true };
-                    let Some(app) = AppAmdgpuTop::new(
+                    let Ok(amdgpu_dev) = dev.init_backend() else { return true };
+                    let Some(app) = AppAmdgpuTop::new_with_backend(
                         amdgpu_dev,
                         dev.clone(),
-                        &Default::default(),
+                        &AppOption { gfxoff_residency, ..Default::default() },
                     ) else { return true };
                     vec_data.push(GuiAppData::new(&app));
+                    vec_alert_monitor.push(alert_rules.clone().map(AlertMonitor::new));
                     vec_app.push(app);
                 }
 
@@ -257,7 +349,8 @@ pub fn run(
                 cc.egui_ctx.set_fonts(fonts);
             }
 
-            {
+            // the config file takes precedence
+            if settings.side_panel.is_none() {
                 let id = *SIDE_PANEL_STATE_ID;
                 let s: Option<bool> = cc.egui_ctx.data_mut(|id_map| id_map.get_persisted(id));
 
@@ -305,6 +398,8 @@ pub fn run(
         eprintln!("{err}");
         panic!();
     });
+
+    stat::stop_gfxoff_residency_logging();
 }
 
 impl MyApp {
@@ -402,6 +497,21 @@ impl MyApp {
     fn egui_central_panel(&mut self, ui: &mut egui::Ui) {
         // ui.set_min_width(540.0);
         egui::ScrollArea::both().show(ui, |ui| {
+            if !self.buf_data.stat.devcoredump.reports.is_empty() {
+                self.egui_devcoredump(ui);
+                ui.add_space(SPACE);
+            }
+
+            if self.has_alert_rules {
+                collapsing(ui, &fl!("alerts"), true, |ui| self.egui_alerts(ui));
+                ui.add_space(SPACE);
+            }
+
+            if self.has_session_stats {
+                collapsing(ui, &fl!("session_stats"), true, |ui| self.egui_session_stats(ui));
+                ui.add_space(SPACE);
+            }
+
             if !self.no_pc {
                 collapsing(ui, &fl!("grbm"), true, |ui| self.egui_perf_counter(
                     ui,
@@ -435,14 +545,35 @@ impl MyApp {
                 collapsing(ui, &fl!("sensor"), true, |ui| self.egui_sensors(ui));
             }
 
-            if self.buf_data.support_pcie_bw {
+            if self.enable_control {
+                ui.add_space(SPACE);
+                collapsing(ui, &fl!("power_control"), true, |ui| self.egui_power_control(ui));
+                self.update_fan_control();
+
+                if self.fan_control.as_ref().is_some_and(|fc| fc.is_available()) {
+                    ui.add_space(SPACE);
+                    collapsing(ui, &fl!("fan_control"), false, |ui| self.egui_fan_control(ui));
+                }
+            }
+
+            // The overdrive table is read from sysfs, not from the recording.
+            if self.replay.is_none() {
+                self.update_overdrive();
+
+                if self.overdrive.as_ref().is_some_and(|od| od.is_available()) {
+                    ui.add_space(SPACE);
+                    collapsing(ui, &fl!("overdrive"), false, |ui| self.egui_overdrive(ui));
+                }
+            }
+
+            if self.buf_data.pcie_bw_source.is_some() {
                 ui.add_space(SPACE);
                 collapsing(ui, &fl!("pcie_bw"), true, |ui| self.egui_pcie_bw(ui));
             }
 
-            if let Some(ecc) = &self.buf_data.stat.memory_error_count {
+            if let Some(ras) = &self.buf_data.stat.ras {
                 ui.add_space(SPACE);
-                collapsing(ui, &fl!("ecc_memory_error_count"), true, |ui| ecc.ui(ui));
+                collapsing(ui, &fl!("ras_error_count"), true, |ui| ras.ui(ui));
             }
 
             if let Some(metrics) = &self.buf_data.stat.metrics {
@@ -496,6 +627,18 @@ impl MyApp {
                 }
             }
 
+            if self.buf_data.stat.throttling.is_supported() {
+                collapsing(ui, &fl!("throttling"), true, |ui| self.egui_throttling(ui));
+            }
+
+            if self.buf_data.stat.dpm.is_supported() {
+                collapsing(ui, &fl!("dpm_residency"), true, |ui| self.egui_dpm(ui));
+            }
+
+            if self.buf_data.stat.gfxoff.is_some() {
+                collapsing(ui, &fl!("gfxoff"), true, |ui| self.egui_gfxoff(ui));
+            }
+
             collapsing(ui, &fl!("throttling_log"), false, |ui| {
                 for (time, value) in self.buf_data.history.throttling_history.iter() {
                     ui.label(format!("{time:.1}s: {:?}", value.get_all_throttler()));
@@ -532,7 +675,7 @@ impl eframe::App for MyApp {
             pub const CLOSE_KEY: KeyboardShortcut =
                 KeyboardShortcut::new(Modifiers::CTRL, Key::Q);
 
-            if ctx.input_mut(|i| i.consume_shortcut(&CLOSE_KEY)) {
+            if ctx.input_mut(|i| i.consume_shortcut(&CLOSE_KEY)) || signal::exit_requested() {
                 ctx.send_viewport_cmd(ViewportCommand::Close);
             }
         }
@@ -612,6 +755,22 @@ impl eframe::App for MyApp {
                     RichText::new(fl!("pause")).font(BASE),
                 );
 
+                if self.replay.is_some() {
+                    ui.separator();
+                    self.egui_replay_control(ui);
+                }
+
+                let config_hover_text = self.config_path.as_ref().map(|path| {
+                    self.config_status.clone().unwrap_or_else(|| path.display().to_string())
+                });
+
+                if let Some(hover_text) = config_hover_text {
+                    ui.separator();
+                    if ui.button(RichText::new(fl!("save_layout")).font(BASE)).on_hover_text(hover_text).clicked() {
+                        self.save_layout(ctx);
+                    }
+                }
+
                 ui.separator();
                 if ui.button(RichText::new(fl!("quit") + " (Ctrl+Q)").font(SMALL)).clicked() {
                     ctx.send_viewport_cmd(ViewportCommand::Close);
@@ -5,6 +5,7 @@ use libamdgpu_top::{
         GpuMetrics,
     },
     app::AppAmdgpuTop,
+    control::{self, OverdriveTable},
     DevicePath,
 };
 use serde_json::{json, Map, Value};
@@ -40,8 +41,8 @@ pub fn gpu_metrics_json(_title: &str, device_path_list: &[DevicePath]) {
 
 pub fn dump_json(device_path_list: &[DevicePath]) {
     let vec_json_info: Vec<Value> = device_path_list.iter().filter_map(|device_path| {
-        let amdgpu_dev = device_path.init().ok()?;
-        let mut app = AppAmdgpuTop::new(amdgpu_dev, device_path.clone(), &Default::default())?;
+        let amdgpu_dev = device_path.init_backend().ok()?;
+        let mut app = AppAmdgpuTop::new_with_backend(amdgpu_dev, device_path.clone(), &Default::default())?;
 
         let mut m = Map::new();
         let mut info = app.json_info();
@@ -144,6 +145,24 @@ impl JsonInfo for AppAmdgpuTop {
             Value::Null
         };
 
+        let overdrive = {
+            let sysfs_path = &self.device_info.sysfs_path;
+            let table = OverdriveTable::from_sysfs(sysfs_path);
+            let gpu_od: Map<String, Value> = control::gpu_od_tables(sysfs_path)
+                .into_iter()
+                .map(|(name, table)| (name, table.json()))
+                .collect();
+
+            if table.is_some() || !gpu_od.is_empty() {
+                json!({
+                    "pp_od_clk_voltage": table.map(|t| t.json()),
+                    "gpu_od": gpu_od,
+                })
+            } else {
+                Value::Null
+            }
+        };
+
         let json = json!({
             "amdgpu_top_version": amdgpu_top_version(),
             "drm_version": drm,
@@ -189,6 +208,7 @@ impl JsonInfo for AppAmdgpuTop {
             "Video Caps": video_caps,
             "PCIe Link": link_speed_width,
             "Power Profiles": power_profiles,
+            "Overdrive": overdrive,
             "NPU": self.xdna_device_path.as_ref().map(|x| x.device_name.clone()),
         });
 
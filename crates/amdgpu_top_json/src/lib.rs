@@ -1,12 +1,17 @@
-use libamdgpu_top::{DevicePath, stat};
+use libamdgpu_top::{DevicePath, signal, stat};
 use libamdgpu_top::app::*;
+use libamdgpu_top::record::Recorder;
+use libamdgpu_top::alert::{AlertMonitor, AlertRules};
+use libamdgpu_top::session_stats::{self, SessionStats};
 use serde_json::{json, Value};
 use std::time::{Duration, Instant};
+use std::sync::Arc;
 use std::path::PathBuf;
 use std::io::Write;
 
 mod output_json;
 mod dump;
+mod prometheus;
 pub use dump::{dump_json, drm_info_json, gpu_metrics_json, JsonInfo};
 
 pub fn version_json(title: &str) {
@@ -41,6 +46,9 @@ pub struct JsonApp {
     pub amdgpu_top_version: Value,
     pub rocm_version: Value,
     pub title: String,
+    pub recorder: Option<Recorder>,
+    pub alert_rules: Option<Arc<AlertRules>>,
+    pub session_stats: Option<Vec<SessionStats>>,
 }
 
 impl JsonApp {
@@ -90,9 +98,30 @@ impl JsonApp {
             amdgpu_top_version: amdgpu_top_version(),
             rocm_version: libamdgpu_top::get_rocm_version().map_or(Value::Null, Value::String),
             title: title.to_string(),
+            recorder: None,
+            alert_rules: None,
+            session_stats: None,
         }
     }
 
+    /// Accumulate the session statistics, and print them when `run` exits (including SIGINT/SIGTERM).
+    pub fn enable_session_stats(&mut self) {
+        self.session_stats = Some(Vec::new());
+        signal::catch_exit_signals();
+    }
+
+    pub fn session_stats_json(&self) -> Value {
+        let stats: Vec<Value> = self.session_stats
+            .iter()
+            .flatten()
+            .map(|stats| stats.json())
+            .collect();
+
+        json!({
+            "session_stats": stats,
+        })
+    }
+
     pub fn update(&mut self) {
         if !self.no_pc {
             for device in self.vec_device_info.iter_mut() {
@@ -117,20 +146,45 @@ impl JsonApp {
 
         for device in self.vec_device_info.iter_mut() {
             device.app.update(self.interval);
+
+            // GPU hangs are reported as the events on stderr, like the alerts
+            for report in device.app.stat.devcoredump.new_reports() {
+                eprintln!("{}", report.json());
+            }
+
+            // stop recording on the first error, e.g. the disk is full
+            if let Some(Err(err)) = self.recorder.as_mut().map(|r| r.record(&device.app)) {
+                eprintln!("Failed to record: {err}");
+                self.recorder = None;
+            }
+
+            if let Some(rules) = &self.alert_rules {
+                let monitor = device.alert_monitor.get_or_insert_with(|| AlertMonitor::new(rules.clone()));
+
+                for event in monitor.check(&device.app) {
+                    if event.stderr {
+                        eprintln!("{}", event.json());
+                    }
+                }
+            }
+
+            if let Some(list) = &mut self.session_stats {
+                session_stats::update_session_stats(list, &device.app);
+            }
         }
 
         self.sus_app_list.retain(|sus_device| {
             let is_active = sus_device.check_if_device_is_active();
 
             if is_active {
-                let Some(amdgpu_dev) = sus_device.init().ok() else { return true };
-                let Some(mut app) = AppAmdgpuTop::new(
+                let Some(amdgpu_dev) = sus_device.init_backend().ok() else { return true };
+                let Some(mut app) = AppAmdgpuTop::new_with_backend(
                     amdgpu_dev,
                     sus_device.clone(),
                     &Default::default(),
                 ) else { return true };
                 let info = app.json_info();
-                self.vec_device_info.push(JsonDeviceInfo { app, info });
+                self.vec_device_info.push(JsonDeviceInfo { app, info, alert_monitor: None });
             }
 
             !is_active
@@ -177,11 +231,17 @@ impl JsonApp {
 
             println!("{s}");
 
+            if signal::exit_requested() { break; }
+
             if self.iterations != 0 {
                 n += 1;
                 if self.iterations == n { break; }
             }
         }
+
+        if self.session_stats.is_some() {
+            println!("{}", self.session_stats_json());
+        }
     }
 
     pub fn run_fifo(&mut self, fifo_path: PathBuf) {
@@ -198,6 +258,8 @@ impl JsonApp {
 
             f.write_all(s.as_bytes()).unwrap();
             f.flush().unwrap();
+
+            if signal::exit_requested() { break; }
         }
     }
 }
@@ -205,6 +267,7 @@ impl JsonApp {
 pub struct JsonDeviceInfo {
     pub app: AppAmdgpuTop,
     pub info: Value,
+    pub alert_monitor: Option<AlertMonitor>,
 }
 
 impl JsonDeviceInfo {
@@ -221,7 +284,7 @@ impl JsonDeviceInfo {
             .map(|mut app| {
                 let info = app.json_info();
 
-                Self { app, info }
+                Self { app, info, alert_monitor: None }
             })
             .collect();
 
@@ -240,6 +303,21 @@ impl JsonDeviceInfo {
             "Total fdinfo": self.app.stat.fdinfo.fold_fdinfo_usage().json(),
             "gpu_metrics": self.app.stat.metrics.as_ref().map(|m| m.json()),
             "gpu_activity": self.app.stat.activity.json(),
+            "Energy": self.app.stat.energy.source.map(|_| self.app.stat.energy.json()),
+            "PCIe Bandwidth": self.app.stat.arc_pcie_bw
+                .as_ref()
+                .and_then(|arc| arc.lock().ok().map(|pcie_bw| pcie_bw.json())),
+            "PCIe Link Monitor": self.app.stat.pcie_link_monitor.expected
+                .map(|_| self.app.stat.pcie_link_monitor.json()),
+            "ras": self.app.stat.ras.as_ref().map(|ras| ras.json()),
+            "throttling": self.app.stat.throttling.is_supported().then(|| self.app.stat.throttling.json()),
+            "DPM": self.app.stat.dpm.is_supported().then(|| self.app.stat.dpm.json()),
+            "GFXOFF": self.app.stat.gfxoff.as_ref().map(|gfxoff| gfxoff.json()),
+            "devcoredump": self.app.stat.devcoredump.reports.iter().map(|report| report.json()).collect::<Vec<Value>>(),
+            "GPU resets": self.app.stat.devcoredump.resets,
+            "alerts": self.alert_monitor.as_ref().map(|monitor| {
+                monitor.active().iter().map(|alert| alert.json()).collect::<Vec<Value>>()
+            }),
         })
     }
 }
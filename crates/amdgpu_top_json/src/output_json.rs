@@ -1,4 +1,9 @@
 use libamdgpu_top::{
+    alert::AlertEvent,
+    profile_daemon::ProfileChange,
+    control::{OdValue, OverdriveTable},
+    exec_report::ExecReport,
+    session_stats::{SessionStats, Summary},
     DevicePath,
     stat,
     xdna,
@@ -9,11 +14,261 @@ use libamdgpu_top::{
     drmModePropType,
     drmModeModeInfo,
 };
-use stat::{FdInfoStat, FdInfoUsage, GpuActivity, Sensors, PerfCounter, ProcUsage};
+use stat::{DevCoredumpReport, DpmStat, EnergyStat, EngineUsage, GfxoffMonitor, PcieBw, PcieBwSource, PcieLinkMonitor, RasCount, RasStat, ThrottlingStat, FdInfoClient, FdInfoStat, FdInfoUsage, GpuActivity, Sensors, PerfCounter, ProcUsage};
 use xdna::{XdnaFdInfoUsage, XdnaFdInfoStat};
 use serde_json::{json, Map, Value};
 use crate::OutputJson;
 
+impl OutputJson for AlertEvent {
+    fn json(&self) -> Value {
+        json!({
+            "alert": self.rule,
+            "state": self.state.as_str(),
+            "pci": self.pci.to_string(),
+            "pid": self.pid,
+            "process": self.process,
+            "value": self.value,
+            "message": self.message,
+            "time": self.unix_time(),
+        })
+    }
+}
+
+impl OutputJson for ProfileChange {
+    fn json(&self) -> Value {
+        json!({
+            "pci": self.pci.to_string(),
+            "rule": self.rule,
+            "reason": self.reason,
+            "setting": self.setting,
+            "from": self.from,
+            "to": self.to,
+            "error": self.error,
+            "time": self.unix_time(),
+        })
+    }
+}
+
+impl OutputJson for DevCoredumpReport {
+    fn json(&self) -> Value {
+        json!({
+            "event": "devcoredump",
+            "summary": self.to_string(),
+            "pci": self.pci.map(|pci| pci.to_string()),
+            "kernel": self.kernel,
+            "uptime": self.uptime,
+            "process": self.process_name,
+            "pid": self.pid,
+            "ring": self.ring,
+            "page_fault": self.page_fault.map(|addr| format!("{addr:#x}")),
+            "IBs": self.ibs.iter().map(|addr| format!("{addr:#x}")).collect::<Vec<String>>(),
+            "IP_dump": self.ip_dumps,
+            "vram_lost": self.vram_lost,
+            "reset": self.reset,
+            "path": self.path.as_ref().map(|path| path.display().to_string()),
+            "time": self.unix_time(),
+        })
+    }
+}
+
+impl OutputJson for Summary {
+    fn json(&self) -> Value {
+        json!({
+            "count": self.count,
+            "min": self.min,
+            "max": self.max,
+            "avg": self.avg,
+            "p50": self.p50,
+            "p95": self.p95,
+            "p99": self.p99,
+        })
+    }
+}
+
+impl OutputJson for SessionStats {
+    fn json(&self) -> Value {
+        let summary_map = |m: &mut Map<String, Value>, name: String, unit: &str, sum: Summary| {
+            let mut v = sum.json();
+            v["unit"] = unit.into();
+            m.insert(name, v);
+        };
+        let mut device = Map::new();
+
+        for (metric, series) in &self.device {
+            let Some(sum) = series.summary() else { continue };
+            summary_map(&mut device, metric.to_string(), metric.unit(), sum);
+        }
+
+        let procs: Vec<Value> = self.proc_summaries()
+            .into_iter()
+            .map(|proc| {
+                let mut m = Map::new();
+
+                for (metric, sum) in proc.metrics {
+                    let name = libamdgpu_top::alert::Metric::Proc(metric).to_string();
+                    summary_map(&mut m, name, metric.unit(), sum);
+                }
+
+                json!({
+                    "pid": proc.pid,
+                    "name": proc.name,
+                    "metrics": m,
+                })
+            })
+            .collect();
+
+        json!({
+            "pci": self.pci.to_string(),
+            "DeviceName": self.device_name,
+            "duration": {
+                "value": self.duration().as_millis(),
+                "unit": "ms",
+            },
+            "samples": self.samples,
+            "metrics": device,
+            "processes": procs,
+        })
+    }
+}
+
+impl OutputJson for EnergyStat {
+    fn json(&self) -> Value {
+        json!({
+            "value": self.total,
+            "unit": "J",
+            "kWh": self.kwh(),
+            "source": self.source.map(|s| s.to_string()),
+        })
+    }
+}
+
+impl OutputJson for ThrottlingStat {
+    fn json(&self) -> Value {
+        let reasons: Map<String, Value> = self.reasons
+            .iter()
+            .map(|r| (r.name.clone(), json!({
+                "value": r.residency.as_secs_f64(),
+                "unit": "s",
+                "percent": self.residency_percent(r),
+                "transitions": r.transitions,
+                "active": r.active,
+            })))
+            .collect();
+
+        json!({
+            "tracked": {
+                "value": self.tracked.unwrap_or_default().as_secs_f64(),
+                "unit": "s",
+            },
+            "reasons": reasons,
+        })
+    }
+}
+
+impl OutputJson for DpmStat {
+    fn json(&self) -> Value {
+        let clocks: Map<String, Value> = self.clocks
+            .iter()
+            .map(|clock| {
+                let levels: Vec<Value> = clock.levels
+                    .iter()
+                    .map(|level| json!({
+                        "level": level.label,
+                        "value": level.mhz,
+                        "unit": "MHz",
+                        "link": level.link.map(|link| format!("Gen{} x{}", link.gen, link.width)),
+                        "active": level.active,
+                    }))
+                    .collect();
+                let residency: Map<String, Value> = clock.residency
+                    .iter()
+                    .map(|r| (r.name.clone(), json!({
+                        "value": r.residency.as_secs_f64(),
+                        "unit": "s",
+                        "percent": clock.residency_percent(r),
+                        "active": r.active,
+                    })))
+                    .collect();
+
+                (clock.clock.to_string(), json!({
+                    "levels": levels,
+                    "fine_grained": clock.fine_grained,
+                    "residency": residency,
+                    "tracked": {
+                        "value": clock.tracked.as_secs_f64(),
+                        "unit": "s",
+                    },
+                }))
+            })
+            .collect();
+
+        Value::Object(clocks)
+    }
+}
+
+impl OutputJson for GfxoffMonitor {
+    fn json(&self) -> Value {
+        json!({
+            "enabled": self.mode.is_enabled(),
+            "status": self.status.to_string(),
+            "residency": self.residency_percent().map(|per| json!({
+                "value": per,
+                "unit": "%",
+            })),
+            "count": self.count,
+            "transitions": self.transitions,
+            "blocked_by_pc": self.blocked_by_pc,
+        })
+    }
+}
+
+impl OutputJson for ExecReport {
+    fn json(&self) -> Value {
+        let kib = |v: u64| json!({ "value": v >> 10, "unit": "MiB" });
+        let devices: Vec<Value> = self.devices
+            .iter()
+            .map(|d| {
+                let engines: Map<String, Value> = d.engines
+                    .iter()
+                    .map(|e| (e.name.to_string(), json!({
+                        "value": e.busy as f64 / 1_000_000_000.0,
+                        "unit": "s",
+                        "capacity": e.capacity,
+                    })))
+                    .collect();
+
+                json!({
+                    "pci": d.pci.to_string(),
+                    "DeviceName": d.device_name,
+                    "engines": engines,
+                    "peak_vram": kib(d.peak_vram),
+                    "avg_vram": kib(d.avg_vram),
+                    "peak_gtt": kib(d.peak_gtt),
+                    "avg_gtt": kib(d.avg_gtt),
+                    "peak_evicted_vram": kib(d.peak_evicted_vram),
+                    "energy": d.energy.map(|v| json!({ "value": v, "unit": "J" })),
+                    "job_energy": d.job_energy.map(|v| json!({
+                        "value": v,
+                        "unit": "J",
+                        "kWh": v / 3_600_000.0,
+                    })),
+                    "peak_temp": d.peak_temp.map(|v| json!({ "value": v, "unit": "C" })),
+                })
+            })
+            .collect();
+
+        json!({
+            "command": self.command,
+            "exit_code": self.exit_code,
+            "wall_time": {
+                "value": self.wall_time.as_secs_f64(),
+                "unit": "s",
+            },
+            "devices": devices,
+        })
+    }
+}
+
 impl OutputJson for VramUsage {
     fn json(&self) -> Value {
         let mut m = Map::new();
@@ -66,6 +321,8 @@ impl OutputJson for Sensors {
             ("VDDGFX", self.vddgfx, "mV"),
             ("Fan", self.fan_rpm, "RPM"),
             ("Fan Max", self.fan_max_rpm, "RPM"),
+            ("Fan Target", self.fan.as_ref().and_then(|fan| fan.target_rpm), "RPM"),
+            ("Fan PWM", self.fan.as_ref().and_then(|fan| fan.pwm_percent()), "%"),
         ] {
             m.insert(
                 label.to_string(),
@@ -119,6 +376,11 @@ impl OutputJson for Sensors {
             self.power_profile.map_or(Value::Null, |pp| Value::String(pp.to_string())),
         );
 
+        m.insert(
+            "Fan Mode".to_string(),
+            self.fan.as_ref().and_then(|fan| fan.mode).map_or(Value::Null, |mode| Value::String(mode.to_string())),
+        );
+
         m.into()
     }
 }
@@ -168,51 +430,110 @@ pub trait FdInfoJson {
     fn usage_json(&self, has_vcn: bool, has_vcn_unified: bool, has_vpe: bool) -> Value;
 }
 
-impl FdInfoJson for ProcUsage {
-    fn usage_json(&self, has_vcn: bool, has_vcn_unified: bool, has_vpe: bool) -> Value {
-        let mut sub = Map::new();
-        sub.insert(
-            "VRAM".to_string(),
-            json!({
-                "value": self.usage.vram_usage >> 10,
-                "unit": "MiB",
-            }),
-        );
+fn fdinfo_usage_json(
+    usage: &FdInfoUsage,
+    cpu_usage: Option<i64>,
+    has_vcn: bool,
+    has_vcn_unified: bool,
+    has_vpe: bool,
+) -> Map<String, Value> {
+    let mut sub = Map::new();
+    sub.insert(
+        "VRAM".to_string(),
+        json!({
+            "value": usage.vram_usage >> 10,
+            "unit": "MiB",
+        }),
+    );
+    sub.insert(
+        "GTT".to_string(),
+        json!({
+            "value": usage.gtt_usage >> 10,
+            "unit": "MiB",
+        }),
+    );
+
+    for (label, val) in [
+        ("GFX", Some(usage.gfx)),
+        ("Compute", Some(usage.compute)),
+        ("DMA", Some(usage.dma)),
+        ("Decode", if !has_vcn_unified { Some(usage.total_dec) } else { None }),
+        ("Encode", if !has_vcn_unified { Some(usage.total_enc) } else { None }),
+        ("CPU", cpu_usage),
+        ("Media", Some(usage.media)),
+        ("VCN_JPEG", if has_vcn { Some(usage.vcn_jpeg) } else { None }),
+        ("VPE", if has_vpe { Some(usage.vpe) } else { None }),
+    ] {
         sub.insert(
-            "GTT".to_string(),
-            json!({
-                "value": self.usage.gtt_usage >> 10,
-                "unit": "MiB",
-            }),
+            label.to_string(),
+            if let Some(val) = val {
+                json!({
+                    "value": val,
+                    "unit": "%",
+                })
+            } else {
+                Value::Null
+            },
         );
+    }
 
-        for (label, val) in [
-            ("GFX", Some(self.usage.gfx)),
-            ("Compute", Some(self.usage.compute)),
-            ("DMA", Some(self.usage.dma)),
-            ("Decode", if !has_vcn_unified { Some(self.usage.total_dec) } else { None }),
-            ("Encode", if !has_vcn_unified { Some(self.usage.total_enc) } else { None }),
-            ("CPU", Some(self.cpu_usage)),
-            ("Media", Some(self.usage.media)),
-            ("VCN_JPEG", if has_vcn { Some(self.usage.vcn_jpeg) } else { None }),
-            ("VPE", if has_vpe { Some(self.usage.vpe) } else { None }),
-        ] {
-            sub.insert(
-                label.to_string(),
-                if let Some(val) = val {
-                    json!({
-                        "value": val,
-                        "unit": "%",
-                    })
-                } else {
-                    Value::Null
-                },
-            );
-        }
+    sub
+}
+
+/// Each engine separately, normalized by the engine capacity
+fn engines_json(engines: &[EngineUsage]) -> Value {
+    let m: Map<String, Value> = engines
+        .iter()
+        .map(|e| (e.name.to_string(), json!({
+            "value": e.usage,
+            "unit": "%",
+            "capacity": e.capacity,
+        })))
+        .collect();
+
+    m.into()
+}
+
+impl FdInfoJson for ProcUsage {
+    fn usage_json(&self, has_vcn: bool, has_vcn_unified: bool, has_vpe: bool) -> Value {
+        let sub = fdinfo_usage_json(&self.usage, Some(self.cpu_usage), has_vcn, has_vcn_unified, has_vpe);
 
         json!({
             "name": self.name,
             "usage": sub,
+            "engines": engines_json(&self.engines),
+        })
+    }
+}
+
+impl FdInfoJson for FdInfoClient {
+    fn usage_json(&self, has_vcn: bool, has_vcn_unified: bool, has_vpe: bool) -> Value {
+        let sub = fdinfo_usage_json(&self.usage, None, has_vcn, has_vcn_unified, has_vpe);
+        let memory: Map<String, Value> = self.fdinfo.memory
+            .iter()
+            .map(|(region, m)| (region.to_string(), json!({
+                "total": m.total,
+                "shared": m.shared,
+                "resident": m.resident_or_memory(),
+                "purgeable": m.purgeable,
+                "active": m.active,
+                "unit": "KiB",
+            })))
+            .collect();
+        let others: Map<String, Value> = self.fdinfo.others
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect();
+
+        json!({
+            "client_id": self.client_id,
+            "fd": self.fd,
+            "pdev": self.fdinfo.pdev,
+            "client_name": self.fdinfo.client_name,
+            "usage": sub,
+            "engines": engines_json(&self.engines),
+            "memory": memory,
+            "others": others,
         })
     }
 }
@@ -230,6 +551,15 @@ impl OutputJson for FdInfoStat {
                 json!({
                     "name": pu.name,
                     "usage": pu.usage_json(has_vcn, has_vcn_unified, has_vpe),
+                    "engines": engines_json(&pu.engines),
+                    "energy": {
+                        "value": pu.energy,
+                        "unit": "J",
+                    },
+                    "clients": pu.clients
+                        .iter()
+                        .map(|client| client.usage_json(has_vcn, has_vcn_unified, has_vpe))
+                        .collect::<Vec<_>>(),
                 }),
             );
         }
@@ -437,6 +767,127 @@ impl OutputJson for PCI::LINK {
     }
 }
 
+impl OutputJson for PcieBw {
+    fn json(&self) -> Value {
+        let mib_s = |v: Option<u64>| v.map(|v| json!({ "value": v, "unit": "MiB/s" }));
+
+        json!({
+            "sent": mib_s(self.sent_mib()),
+            "received": mib_s(self.received_mib()),
+            "total": mib_s(self.total_mib()),
+            "source": match self.source {
+                PcieBwSource::Sysfs => "pcie_bw",
+                PcieBwSource::GpuMetrics => "gpu_metrics",
+            },
+        })
+    }
+}
+
+impl OutputJson for PcieLinkMonitor {
+    fn json(&self) -> Value {
+        let events: Vec<Value> = self.events
+            .iter()
+            .map(|ev| json!({
+                "time": ev.unix_time(),
+                "current": ev.current.json(),
+                "expected": ev.expected.json(),
+            }))
+            .collect();
+
+        json!({
+            "expected": self.expected.map(|link| link.json()),
+            "downtrained": self.downtrained.map(|link| link.json()),
+            "events": events,
+        })
+    }
+}
+
+impl OutputJson for OdValue {
+    fn json(&self) -> Value {
+        json!({
+            "value": self.value,
+            "unit": self.unit.as_str(),
+        })
+    }
+}
+
+impl OutputJson for OverdriveTable {
+    fn json(&self) -> Value {
+        let mut sections = Map::new();
+        let mut ranges = Map::new();
+
+        for section in &self.sections {
+            let entries: Vec<Value> = section.entries
+                .iter()
+                .map(|entry| json!({
+                    "index": entry.index,
+                    "values": entry.values.iter().map(|v| v.json()).collect::<Vec<Value>>(),
+                }))
+                .collect();
+
+            sections.insert(section.name.clone(), entries.into());
+        }
+
+        for range in &self.ranges {
+            ranges.insert(
+                range.name.clone(),
+                json!({
+                    "min": range.min.json(),
+                    "max": range.max.json(),
+                }),
+            );
+        }
+
+        json!({
+            "sections": sections,
+            "ranges": ranges,
+        })
+    }
+}
+
+impl OutputJson for RasCount {
+    fn json(&self) -> Value {
+        json!({
+            "corrected": self.corrected,
+            "uncorrected": self.uncorrected,
+        })
+    }
+}
+
+impl OutputJson for RasStat {
+    fn json(&self) -> Value {
+        let mut m = Map::new();
+
+        for block in &self.blocks {
+            m.insert(
+                block.name.clone(),
+                json!({
+                    "count": block.count.json(),
+                    "delta": block.delta.json(),
+                    "last_increased": block.last_increased
+                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs()),
+                }),
+            );
+        }
+
+        let events: Vec<Value> = self.events
+            .iter()
+            .map(|ev| json!({
+                "time": ev.unix_time(),
+                "block": ev.block,
+                "delta": ev.delta.json(),
+            }))
+            .collect();
+
+        json!({
+            "blocks": m,
+            "total": self.total().json(),
+            "events": events,
+        })
+    }
+}
+
 impl OutputJson for DevicePath {
     fn json(&self) -> Value {
         json!({
@@ -0,0 +1,581 @@
+//! OpenMetrics text exposition for Prometheus.
+//! ref: <https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md>
+
+use libamdgpu_top::AMDGPU::GPU_INFO;
+use libamdgpu_top::signal;
+use libamdgpu_top::stat::{FdInfoStat, FdInfoUsage, ProcUsage};
+use std::fmt::{self, Write as _};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::{JsonApp, JsonDeviceInfo};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+/// A client that does not send the request (or read the response) in time is dropped.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// The maximum size of the request line and the headers
+const MAX_REQUEST_LEN: u64 = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricType {
+    Gauge,
+    Counter,
+    Info,
+}
+
+impl fmt::Display for MetricType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Gauge => "gauge",
+            Self::Counter => "counter",
+            Self::Info => "info",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+struct MetricFamily {
+    name: &'static str,
+    type_: MetricType,
+    unit: &'static str,
+    help: &'static str,
+    samples: Vec<(String, String)>,
+}
+
+impl MetricFamily {
+    const fn new(
+        name: &'static str,
+        type_: MetricType,
+        unit: &'static str,
+        help: &'static str,
+    ) -> Self {
+        Self { name, type_, unit, help, samples: Vec::new() }
+    }
+
+    fn push<T: fmt::Display>(&mut self, labels: &Labels, value: T) {
+        self.samples.push((labels.to_string(), value.to_string()));
+    }
+
+    fn write_to(&self, buf: &mut String) -> fmt::Result {
+        if self.samples.is_empty() { return Ok(()) }
+
+        let suffix = match self.type_ {
+            MetricType::Gauge => "",
+            MetricType::Counter => "_total",
+            MetricType::Info => "_info",
+        };
+
+        writeln!(buf, "# TYPE {} {}", self.name, self.type_)?;
+        if !self.unit.is_empty() {
+            writeln!(buf, "# UNIT {} {}", self.name, self.unit)?;
+        }
+        writeln!(buf, "# HELP {} {}", self.name, self.help)?;
+
+        for (labels, value) in &self.samples {
+            writeln!(buf, "{}{suffix}{{{labels}}} {value}", self.name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct Labels(Vec<(&'static str, String)>);
+
+impl Labels {
+    fn with(&self, name: &'static str, value: &str) -> Self {
+        let mut labels = self.clone();
+        labels.0.push((name, value.to_string()));
+        labels
+    }
+}
+
+impl fmt::Display for Labels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i != 0 { write!(f, ",")?; }
+            write!(f, "{name}=\"")?;
+
+            for c in value.chars() {
+                match c {
+                    '\\' => write!(f, "\\\\")?,
+                    '"' => write!(f, "\\\"")?,
+                    '\n' => write!(f, "\\n")?,
+                    _ => write!(f, "{c}")?,
+                }
+            }
+
+            write!(f, "\"")?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Families {
+    device: MetricFamily,
+    grbm: MetricFamily,
+    grbm2: MetricFamily,
+    memory_total: MetricFamily,
+    memory_used: MetricFamily,
+    activity: MetricFamily,
+    clock: MetricFamily,
+    voltage: MetricFamily,
+    temperature: MetricFamily,
+    power: MetricFamily,
+    power_cap: MetricFamily,
+    fan_speed: MetricFamily,
+    fan_max_speed: MetricFamily,
+    fan_pwm: MetricFamily,
+    pcie_link_gen: MetricFamily,
+    pcie_link_width: MetricFamily,
+    pcie_link_downtrained: MetricFamily,
+    pcie_bandwidth: MetricFamily,
+    ras_errors: MetricFamily,
+    energy: MetricFamily,
+    throttle_residency: MetricFamily,
+    dpm_residency: MetricFamily,
+    gfxoff_residency: MetricFamily,
+    gfxoff_transitions: MetricFamily,
+    devcoredumps: MetricFamily,
+    gpu_resets: MetricFamily,
+    proc_memory: MetricFamily,
+    proc_engine: MetricFamily,
+    proc_cpu: MetricFamily,
+    proc_energy: MetricFamily,
+}
+
+impl Families {
+    fn new() -> Self {
+        use MetricType::*;
+
+        Self {
+            device: MetricFamily::new(
+                "amdgpu_device", Info, "",
+                "AMDGPU device information"),
+            grbm: MetricFamily::new(
+                "amdgpu_grbm_usage_percent", Gauge, "percent",
+                "Usage of blocks read from the GRBM performance counter"),
+            grbm2: MetricFamily::new(
+                "amdgpu_grbm2_usage_percent", Gauge, "percent",
+                "Usage of blocks read from the GRBM2 performance counter"),
+            memory_total: MetricFamily::new(
+                "amdgpu_memory_total_bytes", Gauge, "bytes",
+                "Total size of the memory heap"),
+            memory_used: MetricFamily::new(
+                "amdgpu_memory_used_bytes", Gauge, "bytes",
+                "Usage of the memory heap"),
+            activity: MetricFamily::new(
+                "amdgpu_activity_percent", Gauge, "percent",
+                "GPU activity reported by gpu_metrics or sysfs"),
+            clock: MetricFamily::new(
+                "amdgpu_clock_mhz", Gauge, "mhz",
+                "Current clock frequency"),
+            voltage: MetricFamily::new(
+                "amdgpu_voltage_millivolts", Gauge, "millivolts",
+                "Current voltage"),
+            temperature: MetricFamily::new(
+                "amdgpu_temperature_celsius", Gauge, "celsius",
+                "Current temperature"),
+            power: MetricFamily::new(
+                "amdgpu_power_watts", Gauge, "watts",
+                "Power consumption reported by hwmon"),
+            power_cap: MetricFamily::new(
+                "amdgpu_power_cap_watts", Gauge, "watts",
+                "Current power cap"),
+            fan_speed: MetricFamily::new(
+                "amdgpu_fan_speed_rpm", Gauge, "rpm",
+                "Current fan speed"),
+            fan_max_speed: MetricFamily::new(
+                "amdgpu_fan_max_speed_rpm", Gauge, "rpm",
+                "Maximum fan speed"),
+            fan_pwm: MetricFamily::new(
+                "amdgpu_fan_pwm_percent", Gauge, "percent",
+                "Fan PWM duty cycle"),
+            pcie_link_gen: MetricFamily::new(
+                "amdgpu_pcie_link_gen", Gauge, "",
+                "Current PCIe link generation"),
+            pcie_link_width: MetricFamily::new(
+                "amdgpu_pcie_link_width", Gauge, "",
+                "Current PCIe link width"),
+            pcie_link_downtrained: MetricFamily::new(
+                "amdgpu_pcie_link_downtrained", Gauge, "",
+                "1 if the PCIe link is below the expected link under load"),
+            pcie_bandwidth: MetricFamily::new(
+                "amdgpu_pcie_bandwidth_bytes_per_second", Gauge, "bytes_per_second",
+                "PCIe bandwidth usage"),
+            ras_errors: MetricFamily::new(
+                "amdgpu_ras_errors", Counter, "",
+                "Number of RAS errors"),
+            energy: MetricFamily::new(
+                "amdgpu_energy_joules", Counter, "joules",
+                "Energy consumed by the device since the start"),
+            throttle_residency: MetricFamily::new(
+                "amdgpu_throttle_residency_seconds", Counter, "seconds",
+                "Time while the throttler is active since the start"),
+            dpm_residency: MetricFamily::new(
+                "amdgpu_dpm_residency_seconds", Counter, "seconds",
+                "Time spent at the DPM level (or the clock range) since the start"),
+            gfxoff_residency: MetricFamily::new(
+                "amdgpu_gfxoff_residency_percent", Gauge, "percent",
+                "GFXOFF residency"),
+            gfxoff_transitions: MetricFamily::new(
+                "amdgpu_gfxoff_transitions", Counter, "",
+                "Number of the entries into GFXOFF since the start"),
+            devcoredumps: MetricFamily::new(
+                "amdgpu_devcoredumps", Counter, "",
+                "Number of the device coredumps (GPU hangs) since the start"),
+            gpu_resets: MetricFamily::new(
+                "amdgpu_gpu_resets", Counter, "",
+                "Number of the GPU resets in the kernel log since the start"),
+            proc_memory: MetricFamily::new(
+                "amdgpu_process_memory_bytes", Gauge, "bytes",
+                "Memory usage per process reported by fdinfo"),
+            proc_engine: MetricFamily::new(
+                "amdgpu_process_engine_usage_percent", Gauge, "percent",
+                "Engine usage per process reported by fdinfo"),
+            proc_cpu: MetricFamily::new(
+                "amdgpu_process_cpu_usage_percent", Gauge, "percent",
+                "CPU usage per process"),
+            proc_energy: MetricFamily::new(
+                "amdgpu_process_energy_joules", Counter, "joules",
+                "Energy attributed to the process by the engine usage"),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &MetricFamily> {
+        [
+            &self.device,
+            &self.grbm,
+            &self.grbm2,
+            &self.memory_total,
+            &self.memory_used,
+            &self.activity,
+            &self.clock,
+            &self.voltage,
+            &self.temperature,
+            &self.power,
+            &self.power_cap,
+            &self.fan_speed,
+            &self.fan_max_speed,
+            &self.fan_pwm,
+            &self.pcie_link_gen,
+            &self.pcie_link_width,
+            &self.pcie_link_downtrained,
+            &self.pcie_bandwidth,
+            &self.ras_errors,
+            &self.energy,
+            &self.throttle_residency,
+            &self.dpm_residency,
+            &self.gfxoff_residency,
+            &self.gfxoff_transitions,
+            &self.devcoredumps,
+            &self.gpu_resets,
+            &self.proc_memory,
+            &self.proc_engine,
+            &self.proc_cpu,
+            &self.proc_energy,
+        ].into_iter()
+    }
+
+    fn add_device(&mut self, device: &JsonDeviceInfo, no_pc: bool) {
+        let app = &device.app;
+        let labels = Labels(vec![
+            ("pci", app.device_info.pci_bus.to_string()),
+            ("device_name", app.device_info.marketing_name.clone()),
+        ]);
+
+        self.device.push(
+            &labels
+                .with("device_id", &format!("{:#06X}", app.device_info.ext_info.device_id()))
+                .with("revision_id", &format!("{:#04X}", app.device_info.ext_info.pci_rev_id()))
+                .with("asic", &app.device_info.asic_name.to_string()),
+            1,
+        );
+
+        if !no_pc {
+            for (family, pc) in [
+                (&mut self.grbm, &app.stat.grbm),
+                (&mut self.grbm2, &app.stat.grbm2),
+            ] {
+                for pc_index in &pc.pc_index {
+                    family.push(&labels.with("block", &pc_index.name), pc_index.usage);
+                }
+            }
+        }
+
+        {
+            let vram = &app.stat.vram_usage.0;
+
+            for (memory, heap) in [
+                ("vram", &vram.vram),
+                ("cpu_visible_vram", &vram.cpu_accessible_vram),
+                ("gtt", &vram.gtt),
+            ] {
+                let labels = labels.with("memory", memory);
+                self.memory_total.push(&labels, heap.total_heap_size);
+                self.memory_used.push(&labels, heap.heap_usage);
+            }
+        }
+
+        for (engine, usage) in [
+            ("gfx", app.stat.activity.gfx),
+            ("memory", app.stat.activity.umc),
+            ("media", app.stat.activity.media),
+        ] {
+            let Some(usage) = usage else { continue };
+            self.activity.push(&labels.with("engine", engine), usage);
+        }
+
+        if let Some(sensors) = &app.stat.sensors {
+            for (clock, val) in [
+                ("sclk", sensors.sclk),
+                ("mclk", sensors.mclk),
+            ] {
+                let Some(val) = val else { continue };
+                self.clock.push(&labels.with("clock", clock), val);
+            }
+
+            for (rail, val) in [
+                ("vddgfx", sensors.vddgfx),
+                ("vddnb", sensors.vddnb),
+            ] {
+                let Some(val) = val else { continue };
+                self.voltage.push(&labels.with("rail", rail), val);
+            }
+
+            for (sensor, temp) in [
+                ("edge", &sensors.edge_temp),
+                ("junction", &sensors.junction_temp),
+                ("memory", &sensors.memory_temp),
+            ] {
+                let Some(temp) = temp else { continue };
+                self.temperature.push(&labels.with("sensor", sensor), temp.current);
+            }
+
+            for (type_, power) in [
+                ("average", &sensors.average_power),
+                ("input", &sensors.input_power),
+            ] {
+                let Some(power) = power else { continue };
+                self.power.push(&labels.with("type", type_), power.value);
+            }
+
+            if let Some(cap) = &sensors.power_cap {
+                self.power_cap.push(&labels, cap.current);
+            }
+
+            if let Some(rpm) = sensors.fan_rpm {
+                self.fan_speed.push(&labels, rpm);
+            }
+
+            if let Some(rpm) = sensors.fan_max_rpm {
+                self.fan_max_speed.push(&labels, rpm);
+            }
+
+            if let Some(per) = sensors.fan.as_ref().and_then(|fan| fan.pwm_percent()) {
+                self.fan_pwm.push(&labels, per);
+            }
+
+            if let Some(link) = sensors.current_link {
+                self.pcie_link_gen.push(&labels, link.gen);
+                self.pcie_link_width.push(&labels, link.width);
+            }
+        }
+
+        if app.stat.pcie_link_monitor.expected.is_some() {
+            self.pcie_link_downtrained.push(&labels, app.stat.pcie_link_monitor.is_downtrained() as u8);
+        }
+
+        if let Some(pcie_bw) = app.stat.arc_pcie_bw.as_ref().and_then(|arc| arc.lock().ok()) {
+            for (direction, mib) in [
+                ("sent", pcie_bw.sent_mib()),
+                ("received", pcie_bw.received_mib()),
+                ("total", pcie_bw.total_mib()),
+            ] {
+                let Some(mib) = mib else { continue };
+                self.pcie_bandwidth.push(&labels.with("direction", direction), mib << 20);
+            }
+        }
+
+        for block in app.stat.ras.iter().flat_map(|ras| &ras.blocks) {
+            let labels = labels.with("block", &block.name.to_lowercase());
+
+            self.ras_errors.push(&labels.with("type", "corrected"), block.count.corrected);
+            self.ras_errors.push(&labels.with("type", "uncorrected"), block.count.uncorrected);
+        }
+
+        if app.stat.energy.source.is_some() {
+            self.energy.push(&labels, app.stat.energy.total);
+        }
+
+        for r in &app.stat.throttling.reasons {
+            self.throttle_residency.push(&labels.with("throttler", &r.name), r.residency.as_secs_f64());
+        }
+
+        for clock in &app.stat.dpm.clocks {
+            let clock_labels = labels.with("clock", clock.clock.as_str());
+
+            for r in &clock.residency {
+                self.dpm_residency.push(&clock_labels.with("level", &r.name), r.residency.as_secs_f64());
+            }
+        }
+
+        if let Some(gfxoff) = &app.stat.gfxoff {
+            if let Some(per) = gfxoff.residency_percent() {
+                self.gfxoff_residency.push(&labels, per);
+            }
+
+            self.gfxoff_transitions.push(&labels, gfxoff.transitions);
+        }
+
+        self.devcoredumps.push(&labels, app.stat.devcoredump.count);
+
+        if let Some(resets) = app.stat.devcoredump.resets {
+            self.gpu_resets.push(&labels, resets);
+        }
+
+        for pu in &app.stat.fdinfo.proc_usage {
+            self.add_proc_usage(&labels, pu, &app.stat.fdinfo);
+        }
+    }
+
+    fn add_proc_usage(
+        &mut self,
+        labels: &Labels,
+        pu: &ProcUsage,
+        fdinfo: &FdInfoStat,
+    ) {
+        let labels = labels
+            .with("pid", &pu.pid.to_string())
+            .with("name", &pu.name);
+        let FdInfoUsage { vram_usage, gtt_usage, system_cpu_memory_usage, .. } = pu.usage;
+
+        for (memory, kib) in [
+            ("vram", vram_usage),
+            ("gtt", gtt_usage),
+            ("cpu", system_cpu_memory_usage),
+        ] {
+            self.proc_memory.push(&labels.with("memory", memory), kib << 10);
+        }
+
+        for (engine, usage) in [
+            ("gfx", Some(pu.usage.gfx)),
+            ("compute", Some(pu.usage.compute)),
+            ("dma", Some(pu.usage.dma)),
+            ("decode", if !fdinfo.has_vcn_unified { Some(pu.usage.total_dec) } else { None }),
+            ("encode", if !fdinfo.has_vcn_unified { Some(pu.usage.total_enc) } else { None }),
+            ("media", Some(pu.usage.media)),
+            ("vcn_jpeg", if fdinfo.has_vcn { Some(pu.usage.vcn_jpeg) } else { None }),
+            ("vpe", if fdinfo.has_vpe { Some(pu.usage.vpe) } else { None }),
+        ] {
+            let Some(usage) = usage else { continue };
+            self.proc_engine.push(&labels.with("engine", engine), usage);
+        }
+
+        self.proc_cpu.push(&labels, pu.cpu_usage);
+        self.proc_energy.push(&labels, pu.energy);
+    }
+}
+
+impl JsonApp {
+    pub fn prometheus(&self) -> String {
+        let mut families = Families::new();
+        let mut buf = String::new();
+
+        for device in &self.vec_device_info {
+            families.add_device(device, self.no_pc);
+        }
+
+        for family in families.iter() {
+            family.write_to(&mut buf).unwrap();
+        }
+
+        buf.push_str("# EOF\n");
+
+        buf
+    }
+
+    pub fn run_prometheus(&mut self, addr: &str) {
+        let listener = TcpListener::bind(addr).unwrap_or_else(|err| {
+            eprintln!("Failed to bind {addr:?}: {err}");
+            std::process::exit(1);
+        });
+        let metrics = Arc::new(Mutex::new(self.prometheus()));
+
+        {
+            let metrics = metrics.clone();
+
+            // Scrapes are served one at a time, a stalled client is dropped by the timeouts.
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { continue };
+                    let _ = handle_connection(stream, &metrics);
+                }
+            });
+        }
+
+        eprintln!("Serving metrics on http://{addr}/metrics");
+
+        loop {
+            self.update();
+
+            let s = self.prometheus();
+
+            if let Ok(mut metrics) = metrics.lock() {
+                *metrics = s;
+            }
+
+            if signal::exit_requested() { break; }
+        }
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, metrics: &Mutex<String>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    // the timeouts apply to each read, so the whole request is also limited
+    let deadline = Instant::now() + TIMEOUT;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_LEN));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // skip request headers
+    loop {
+        if deadline < Instant::now() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() { break }
+    }
+
+    let mut split = request_line.split_whitespace();
+    let (method, path) = (split.next().unwrap_or(""), split.next().unwrap_or(""));
+    let path = path.split('?').next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let body = metrics.lock().map(|m| m.clone()).unwrap_or_default();
+            ("200 OK", CONTENT_TYPE, body)
+        },
+        ("GET", "/") => (
+            "200 OK",
+            "text/plain; charset=utf-8",
+            "amdgpu_top exporter\nMetrics are served at /metrics\n".to_string(),
+        ),
+        ("GET", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method Not Allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    )?;
+
+    stream.flush()
+}
@@ -1,11 +1,14 @@
 use cursive::align::HAlign;
-use cursive::views::{LinearLayout, TextView, Panel, ResizedView};
+use cursive::views::{LinearLayout, TextContent, TextView, Panel, ResizedView};
 use cursive::view::SizeConstraint;
 
 use libamdgpu_top::AMDGPU::{GPU_INFO, MetricsInfo};
 use libamdgpu_top::{AppDeviceInfo, DevicePath, Sampling};
+use libamdgpu_top::alert::{AlertMonitor, AlertRules};
+use libamdgpu_top::session_stats::update_session_stats;
+use std::sync::Arc;
 
-use crate::{ToggleOptions, view::*};
+use crate::{SharedSessionStats, ToggleOptions, view::*};
 
 use libamdgpu_top::app::{AppAmdgpuTop, AppAmdgpuTopStat, AppOption};
 
@@ -14,10 +17,12 @@ const WIDE_TERM_COLS: u16 = 150;
 pub const TOGGLE_HELP: &str = concat!(
     " (g)rbm g(r)bm2 (v)ram_usage (a)ctivity (f)dinfo se(n)sor (m)etrics \n",
     " (P): sort_by_pid (V): sort_by_vram (G): sort_by_gfx (M): sort_by_media \n",
-    " (R): reverse sort (T): switch theme (light/dark) \n",
+    " (R): reverse sort (T): switch theme (light/dark) (/): process filter \n",
     " (h)igh_freq (q)uit \n",
 );
 
+pub const REPLAY_HELP: &str = " (p): pause/resume (<)/(>): seek -/+10s (-)/(+): speed ";
+
 #[derive(Clone)]
 pub(crate) struct AppLayout {
     pub no_pc: bool,
@@ -30,7 +35,13 @@ pub(crate) struct AppLayout {
     pub xdna_fdinfo_view: AppTextView,
     pub sensors_view: AppTextView,
     pub gpu_metrics_view: AppTextView,
-    pub ecc_view: AppTextView,
+    pub ras_view: AppTextView,
+    pub throttling_view: AppTextView,
+    pub dpm_view: AppTextView,
+    pub devcoredump_view: AppTextView,
+    pub alert_view: Option<AppTextView>,
+    pub stats_view: Option<AppTextView>,
+    pub replay_status: Option<TextContent>,
 }
 
 impl AppLayout {
@@ -46,7 +57,13 @@ impl AppLayout {
             xdna_fdinfo_view: Default::default(),
             sensors_view: Default::default(),
             gpu_metrics_view: Default::default(),
-            ecc_view: Default::default(),
+            ras_view: Default::default(),
+            throttling_view: Default::default(),
+            dpm_view: Default::default(),
+            devcoredump_view: Default::default(),
+            alert_view: None,
+            stats_view: None,
+            replay_status: None,
         }
     }
 
@@ -69,7 +86,13 @@ impl AppLayout {
             xdna_fdinfo_view: Default::default(),
             sensors_view: Default::default(),
             gpu_metrics_view: Default::default(),
-            ecc_view: Default::default(),
+            ras_view: Default::default(),
+            throttling_view: Default::default(),
+            dpm_view: Default::default(),
+            devcoredump_view: Default::default(),
+            alert_view: None,
+            stats_view: None,
+            replay_status: None,
         }
     }
 
@@ -90,6 +113,13 @@ impl AppLayout {
                 .title_position(HAlign::Center)
             );
 
+        // the banner of GPU hangs
+        layout.add_child(TextView::new_with_content(self.devcoredump_view.text.content.clone()));
+
+        if let Some(alert_view) = &self.alert_view {
+            layout.add_child(alert_view.text.resized_panel("Alerts", self.index));
+        }
+
         if !self.no_pc {
             let grbm_view = self.grbm_view.resized_panel(&stat.grbm);
             let grbm2_view = self.grbm2_view.resized_panel(&stat.grbm2);
@@ -161,8 +191,16 @@ impl AppLayout {
             }
         }
 
-        if stat.memory_error_count.is_some() {
-            layout.add_child(self.ecc_view.text.resized_panel("ECC Error Count", self.index));
+        if stat.ras.is_some() {
+            layout.add_child(self.ras_view.text.resized_panel("RAS Error Count", self.index));
+        }
+
+        if stat.metrics.as_ref().is_some_and(|m| m.get_throttle_status_info().is_some()) {
+            layout.add_child(self.throttling_view.text.resized_panel("Throttling", self.index));
+        }
+
+        if stat.dpm.is_supported() {
+            layout.add_child(self.dpm_view.text.resized_panel("DPM Residency", self.index));
         }
 
         if let Some(xdna_device_path) = xdna_device_path {
@@ -170,6 +208,15 @@ impl AppLayout {
             layout.add_child(self.xdna_fdinfo_view.text.resized_panel(&title, self.index));
         }
 
+        if let Some(stats_view) = &self.stats_view {
+            layout.add_child(stats_view.text.resized_panel("Session Statistics", self.index));
+        }
+
+        if let Some(replay_status) = &self.replay_status {
+            layout.add_child(TextView::new_with_content(replay_status.clone()));
+            layout.add_child(TextView::new(REPLAY_HELP));
+        }
+
         layout.add_child(TextView::new(TOGGLE_HELP));
 
         ResizedView::new(SizeConstraint::Free, SizeConstraint::Full, layout)
@@ -182,6 +229,9 @@ pub(crate) struct SuspendedTuiApp {
     pub no_pc: bool,
     pub index: usize,
     pub layout: AppLayout,
+    pub alert_rules: Option<Arc<AlertRules>>,
+    pub session_stats: Option<SharedSessionStats>,
+    pub gfxoff_residency: bool,
 }
 
 impl SuspendedTuiApp {
@@ -191,15 +241,18 @@ impl SuspendedTuiApp {
             no_pc,
             index,
             layout: AppLayout::new(no_pc, index),
+            alert_rules: None,
+            session_stats: None,
+            gfxoff_residency: false,
         }
     }
 
     pub fn to_tui_app(&self) -> Option<TuiApp> {
-        let amdgpu_dev = self.device_path.init().ok()?;
-        let app_amdgpu_top = AppAmdgpuTop::new(
+        let amdgpu_dev = self.device_path.init_backend().ok()?;
+        let app_amdgpu_top = AppAmdgpuTop::new_with_backend(
             amdgpu_dev,
             self.device_path.clone(),
-            &AppOption { pcie_bw: true },
+            &AppOption { pcie_bw: true, gfxoff_residency: self.gfxoff_residency },
         )?;
 
         Some(TuiApp {
@@ -207,6 +260,8 @@ impl SuspendedTuiApp {
             no_pc: self.no_pc,
             index: self.index,
             layout: self.layout.clone(),
+            alert_monitor: self.alert_rules.clone().map(AlertMonitor::new),
+            session_stats: self.session_stats.clone(),
         })
     }
 
@@ -220,6 +275,8 @@ pub(crate) struct TuiApp {
     pub no_pc: bool,
     pub index: usize,
     pub layout: AppLayout,
+    pub alert_monitor: Option<AlertMonitor>,
+    pub session_stats: Option<SharedSessionStats>,
 }
 
 impl TuiApp {
@@ -235,9 +292,21 @@ impl TuiApp {
             no_pc,
             index,
             layout,
+            alert_monitor: None,
+            session_stats: None,
         }
     }
 
+    pub fn set_alert_rules(&mut self, alert_rules: Option<Arc<AlertRules>>) {
+        self.layout.alert_view = alert_rules.as_ref().map(|_| Default::default());
+        self.alert_monitor = alert_rules.map(AlertMonitor::new);
+    }
+
+    pub fn set_session_stats(&mut self, session_stats: Option<SharedSessionStats>) {
+        self.layout.stats_view = session_stats.as_ref().map(|_| Default::default());
+        self.session_stats = session_stats;
+    }
+
     pub fn view(&self, title: &str) -> ResizedView<LinearLayout> {
         self.layout.view(
             title,
@@ -249,11 +318,32 @@ impl TuiApp {
 
     pub fn update(&mut self, flags: &ToggleOptions, sample: &Sampling) {
         self.app_amdgpu_top.update(sample.to_duration());
+        self.update_session_stats();
+        self.update_view(flags);
+    }
+
+    fn update_session_stats(&mut self) {
+        let (Some(session_stats), Some(stats_view)) = (&self.session_stats, &mut self.layout.stats_view)
+            else { return };
+        let mut list = session_stats.lock().unwrap();
+        let text = update_session_stats(&mut list, &self.app_amdgpu_top).text().unwrap_or_default();
+
+        stats_view.text.content.set_content(text);
+    }
+
+    pub fn update_view(&mut self, flags: &ToggleOptions) {
+        self.layout.devcoredump_view.print_devcoredump(&self.app_amdgpu_top.stat.devcoredump);
+
+        if let (Some(monitor), Some(alert_view)) = (&mut self.alert_monitor, &mut self.layout.alert_view) {
+            monitor.check(&self.app_amdgpu_top);
+            alert_view.print_alerts(monitor.active());
+        }
 
         let _ = self.layout.fdinfo_view.print_fdinfo(
             &mut self.app_amdgpu_top.stat.fdinfo,
             flags.fdinfo_sort,
             flags.reverse_sort,
+            flags.fdinfo_clients,
         );
 
         if self.app_amdgpu_top.xdna_device_path.is_some() {
@@ -265,6 +355,12 @@ impl TuiApp {
 
         if let Some(ref sensors) = &self.app_amdgpu_top.stat.sensors {
             let _ = self.layout.sensors_view.print_sensors(sensors);
+            let _ = self.layout.sensors_view.print_energy(&self.app_amdgpu_top.stat.energy);
+            let _ = self.layout.sensors_view.print_pcie_link_monitor(&self.app_amdgpu_top.stat.pcie_link_monitor);
+
+            if let Some(gfxoff) = &self.app_amdgpu_top.stat.gfxoff {
+                let _ = self.layout.sensors_view.print_gfxoff(gfxoff);
+            }
         }
 
         {
@@ -276,14 +372,22 @@ impl TuiApp {
             }
         }
 
-        if let Some(ecc) = &self.app_amdgpu_top.stat.memory_error_count {
-            let _ = self.layout.ecc_view.print_memory_error_count(ecc);
+        if let Some(ras) = &self.app_amdgpu_top.stat.ras {
+            let _ = self.layout.ras_view.print_ras(ras);
         }
 
         if let Some(metrics) = &self.app_amdgpu_top.stat.metrics {
             let _ = self.layout.gpu_metrics_view.print_gpu_metrics(metrics);
         }
 
+        if self.app_amdgpu_top.stat.throttling.is_supported() {
+            let _ = self.layout.throttling_view.print_throttling(&self.app_amdgpu_top.stat.throttling);
+        }
+
+        if self.app_amdgpu_top.stat.dpm.is_supported() {
+            let _ = self.layout.dpm_view.print_dpm(&self.app_amdgpu_top.stat.dpm);
+        }
+
         if !self.no_pc {
             self.layout.grbm_view.set_value(&self.app_amdgpu_top.stat.grbm);
             self.layout.grbm2_view.set_value(&self.app_amdgpu_top.stat.grbm2);
@@ -292,8 +396,10 @@ impl TuiApp {
         self.layout.sensors_view.text.set();
         self.layout.fdinfo_view.text.set();
         self.layout.xdna_fdinfo_view.text.set();
-        self.layout.ecc_view.text.set();
+        self.layout.ras_view.text.set();
         self.layout.gpu_metrics_view.text.set();
+        self.layout.throttling_view.text.set();
+        self.layout.dpm_view.text.set();
     }
 
     pub fn label(&self) -> String {
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use cursive::view::{Nameable, Resizable, Scrollable};
+use cursive::views::{Dialog, EditView, LinearLayout, Panel, SelectView, TextView};
+use libamdgpu_top::PCI;
+use libamdgpu_top::control::{FanControl, OdEdit, OverdriveEditor, PerformanceLevel, PowerControl};
+
+use crate::Opt;
+
+/// Open the dialog to set the power cap, the performance level, the power profile
+/// and the fan speed of the selected device. (`--enable-control`)
+pub fn power_control_cb(pci_list: Vec<(usize, PCI::BUS_INFO)>) -> impl Fn(&mut cursive::Cursive) {
+    move |siv| {
+        let select_index = siv.user_data::<Opt>().unwrap().lock().unwrap().select_index;
+        let Some(pci) = pci_list
+            .iter()
+            .find_map(|(index, pci)| (*index == select_index).then_some(*pci))
+        else { return };
+
+        siv.add_layer(power_control_dialog(PowerControl::new(pci)));
+    }
+}
+
+/// Open the dialog to show the overdrive table of the selected device,
+/// and to edit it with `--enable-control`.
+pub fn overdrive_cb(pci_list: Vec<(usize, PCI::BUS_INFO)>, enable_control: bool) -> impl Fn(&mut cursive::Cursive) {
+    move |siv| {
+        let select_index = siv.user_data::<Opt>().unwrap().lock().unwrap().select_index;
+        let Some(pci) = pci_list
+            .iter()
+            .find_map(|(index, pci)| (*index == select_index).then_some(*pci))
+        else { return };
+
+        match OverdriveEditor::new(pci) {
+            Ok(editor) => siv.add_layer(overdrive_dialog(editor, enable_control)),
+            Err(err) => siv.add_layer(Dialog::info(format!("{err:#}"))),
+        }
+    }
+}
+
+const OD_TABLE_NAME: &str = "overdrive_table";
+
+fn overdrive_text(editor: &OverdriveEditor) -> String {
+    let mut s = editor.table.to_string();
+
+    if !editor.staged.is_empty() {
+        s.push_str("Staged:\n");
+
+        for edit in &editor.staged {
+            s.push_str(&format!("  {edit}\n"));
+        }
+    }
+
+    s
+}
+
+fn overdrive_dialog(editor: OverdriveEditor, enable_control: bool) -> Dialog {
+    let title = format!("Overdrive ({})", editor.pci_bus);
+    let text = TextView::new(overdrive_text(&editor)).with_name(OD_TABLE_NAME);
+
+    if !enable_control {
+        return Dialog::around(text.scrollable()).title(title).dismiss_button("Close");
+    }
+
+    let editor = Arc::new(Mutex::new(editor));
+    let edit = {
+        let editor = editor.clone();
+
+        EditView::new().on_submit(move |siv, s| {
+            let r = s.parse::<OdEdit>().and_then(|edit| editor.lock().unwrap().stage(edit));
+            update_overdrive(siv, &editor, r, None);
+        })
+    };
+    let layout = LinearLayout::vertical()
+        .child(text.scrollable())
+        .child(Panel::new(edit).title("Stage: s|m <Index> <MHz>, vc <Point> <MHz> <mV>, vo <mV> [Enter]"))
+        .min_width(60);
+    let mut dialog = Dialog::around(layout).title(title);
+
+    for (label, f) in [
+        ("Commit", OverdriveEditor::commit as fn(&mut OverdriveEditor) -> _),
+        ("Restore", OverdriveEditor::restore),
+        ("Reset", OverdriveEditor::reset),
+    ] {
+        let editor = editor.clone();
+
+        dialog.add_button(label, move |siv| {
+            let r = f(&mut editor.lock().unwrap());
+            update_overdrive(siv, &editor, r, Some(label));
+        });
+    }
+
+    {
+        let editor = editor.clone();
+
+        dialog.add_button("Discard", move |siv| {
+            editor.lock().unwrap().discard();
+            update_overdrive(siv, &editor, Ok::<(), String>(()), None);
+        });
+    }
+
+    dialog.dismiss_button("Close")
+}
+
+fn update_overdrive<E: fmt::Display>(
+    siv: &mut cursive::Cursive,
+    editor: &Mutex<OverdriveEditor>,
+    r: Result<(), E>,
+    done: Option<&str>,
+) {
+    let text = overdrive_text(&editor.lock().unwrap());
+
+    siv.call_on_name(OD_TABLE_NAME, |view: &mut TextView| view.set_content(text));
+
+    match (r, done) {
+        (Err(err), _) => siv.add_layer(Dialog::info(format!("{err:#}"))),
+        (Ok(()), Some(done)) => siv.add_layer(Dialog::info(format!("{done}: done"))),
+        _ => {},
+    }
+}
+
+fn power_control_dialog(control: PowerControl) -> Dialog {
+    let mut layout = LinearLayout::vertical();
+
+    if let Some(cap) = control.power_cap() {
+        let c = control.clone();
+        let edit = EditView::new()
+            .content(cap.current.to_string())
+            .on_submit(move |siv, s| {
+                let Ok(watts) = s.trim().parse::<u32>() else {
+                    siv.add_layer(Dialog::info(format!("invalid power cap: {s:?}")));
+                    return;
+                };
+
+                show_result(siv, c.set_power_cap(watts), format!("Set the power cap to {watts} W"));
+            });
+
+        layout.add_child(Panel::new(edit).title(format!(
+            "Power Cap ({}-{} W, default: {} W) [Enter]",
+            cap.min,
+            cap.max,
+            cap.default,
+        )));
+    }
+
+    if let Some(current) = control.performance_level() {
+        let c = control.clone();
+        let select = SelectView::new()
+            .with_all(PerformanceLevel::ALL.map(|level| (level.to_string(), level)))
+            .selected(PerformanceLevel::ALL.iter().position(|level| *level == current).unwrap_or(0))
+            .on_submit(move |siv, level: &PerformanceLevel| {
+                show_result(siv, c.set_performance_level(*level), format!("Set the performance level to {level}"));
+            });
+
+        layout.add_child(Panel::new(select).title(format!("Performance Level ({current}) [Enter]")));
+    }
+
+    let profiles = control.power_profiles();
+
+    if !profiles.is_empty() {
+        let c = control.clone();
+        let select = SelectView::new()
+            .with_all(profiles.iter().map(|mode| (format!("{:2} {}", mode.index, mode.name), mode.name.clone())))
+            .selected(profiles.iter().position(|mode| mode.is_current).unwrap_or(0))
+            .on_submit(move |siv, name: &String| {
+                show_result(siv, c.set_power_profile(name), format!("Set the power profile to {name}"));
+            });
+
+        layout.add_child(Panel::new(select).title("Power Profile [Enter]"));
+    }
+
+    let fan_control = FanControl::new(control.pci_bus);
+
+    if let Some(fan) = fan_control.fan_stat().filter(|fan| fan.mode.is_some()) {
+        let edit = EditView::new()
+            .content(fan.pwm_percent().map_or("auto".to_string(), |per| per.to_string()))
+            .on_submit(move |siv, s| {
+                let s = s.trim();
+                let (r, msg) = if s == "auto" {
+                    (fan_control.set_auto(), "Restored the automatic fan control".to_string())
+                } else if let Ok(per) = s.trim_end_matches('%').parse::<u32>() {
+                    (fan_control.set_manual_speed(per), format!("Set the fan speed to {per}%"))
+                } else {
+                    siv.add_layer(Dialog::info(format!("invalid fan speed: {s:?}")));
+                    return;
+                };
+
+                show_result(siv, r, msg);
+            });
+        let mode = fan.mode.map(|mode| mode.to_string()).unwrap_or_default();
+
+        layout.add_child(Panel::new(edit).title(format!("Fan Speed ({mode}, 0-100%|auto) [Enter]")));
+    }
+
+    if layout.is_empty() {
+        layout.add_child(TextView::new("The power management interfaces are not supported."));
+    }
+
+    Dialog::around(layout.min_width(50))
+        .title(format!("Power Control ({})", control.pci_bus))
+        .dismiss_button("Close")
+}
+
+fn show_result<E: fmt::Display>(siv: &mut cursive::Cursive, r: Result<(), E>, msg: String) {
+    let msg = match r {
+        Ok(()) => msg,
+        Err(err) => format!("{err:#}"),
+    };
+
+    siv.add_layer(Dialog::info(msg));
+}
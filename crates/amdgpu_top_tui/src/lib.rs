@@ -1,10 +1,14 @@
 use std::sync::{Arc, Mutex};
-use cursive::view::{Nameable, Scrollable};
+use cursive::view::{Nameable, Resizable, Scrollable};
 use cursive::{event::Key, menu, traits::With};
+use cursive::views::{Dialog, EditView, TextContent};
 use cursive::theme::{BorderStyle, Theme, Palette};
 
-use libamdgpu_top::{app::AppAmdgpuTop, DevicePath, Sampling, UiArgs};
-use libamdgpu_top::stat::{self, FdInfoSortType, PCType};
+use libamdgpu_top::{app::{AppAmdgpuTop, AppOption}, signal, DevicePath, PCI, Sampling, UiArgs};
+use libamdgpu_top::config::{self, Settings};
+use libamdgpu_top::record::{Player, Recorder};
+use libamdgpu_top::session_stats::SessionStats;
+use libamdgpu_top::stat::{self, FdInfoSortType, PCType, ProcFilter};
 
 mod view;
 use view::*;
@@ -15,6 +19,8 @@ use app::*;
 mod smi;
 pub use smi::run_smi;
 
+mod control;
+
 #[derive(Debug, Clone)]
 struct ToggleOptions {
     grbm: bool,
@@ -25,6 +31,7 @@ struct ToggleOptions {
     high_freq: bool,
     fdinfo: bool,
     fdinfo_sort: FdInfoSortType,
+    fdinfo_clients: bool,
     reverse_sort: bool,
     gpu_metrics: bool,
     select_index: usize,
@@ -43,6 +50,7 @@ impl Default for ToggleOptions {
             high_freq: false,
             fdinfo: true,
             fdinfo_sort: Default::default(),
+            fdinfo_clients: false,
             reverse_sort: false,
             gpu_metrics: true,
             select_index: 0,
@@ -52,7 +60,36 @@ impl Default for ToggleOptions {
     }
 }
 
+impl ToggleOptions {
+    fn to_settings(&self) -> Settings {
+        Settings {
+            is_dark_mode: Some(self.is_dark_mode),
+            hide_fdinfo: Some(!self.fdinfo),
+            fdinfo_sort: Some(self.fdinfo_sort),
+            reverse_sort: Some(self.reverse_sort),
+            fdinfo_clients: Some(self.fdinfo_clients),
+            high_freq: Some(self.high_freq),
+            grbm: Some(self.grbm),
+            grbm2: Some(self.grbm2),
+            vram: Some(self.vram),
+            activity: Some(self.activity),
+            sensors: Some(self.sensor),
+            gpu_metrics: Some(self.gpu_metrics),
+            ..Default::default()
+        }
+    }
+}
+
 type Opt = Arc<Mutex<ToggleOptions>>;
+type SharedSessionStats = Arc<Mutex<Vec<SessionStats>>>;
+
+fn print_session_stats(session_stats: &SharedSessionStats) {
+    for stats in session_stats.lock().unwrap().iter() {
+        if let Ok(text) = stats.text() {
+            println!("{text}");
+        }
+    }
+}
 
 pub fn run(
     title: &str,
@@ -63,36 +100,83 @@ pub fn run(
         no_pc,
         is_dark_mode,
         hide_fdinfo,
+        record,
+        replay,
+        alert_rules,
+        settings,
+        config_path,
+        session_stats,
+        enable_control,
+        gfxoff_residency,
         ..
     }: UiArgs,
 ) {
     let is_dark_mode = is_dark_mode == Some(true); // The default theme for TUI is light.
     let title = title.to_string();
-    let mut toggle_opt = ToggleOptions { is_dark_mode, fdinfo: !hide_fdinfo, ..Default::default() };
-
-    let (vec_app, suspended_devices) = AppAmdgpuTop::create_app_and_suspended_list(
-        &device_path_list,
-        &Default::default(),
-    );
+    let mut toggle_opt = ToggleOptions {
+        is_dark_mode,
+        fdinfo_sort: settings.fdinfo_sort.unwrap_or_default(),
+        reverse_sort: settings.reverse_sort.unwrap_or(false),
+        fdinfo_clients: settings.fdinfo_clients.unwrap_or(false),
+        high_freq: settings.high_freq.unwrap_or(false),
+        ..Default::default()
+    };
+
+    let replay = replay.map(|recording| Arc::new(Mutex::new(Player::new(recording))));
+    let replay_status = replay.as_ref().map(|_| TextContent::new(""));
+    // The statistics are not collected during a replay.
+    let session_stats: Option<SharedSessionStats> = (session_stats && replay.is_none())
+        .then(|| Arc::new(Mutex::new(Vec::new())));
+    let mut recorder = record.map(|path| Recorder::create(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to create {path:?}: {err}");
+        std::process::exit(1);
+    }));
+
+    let (vec_app, suspended_devices) = if let Some(player) = &replay {
+        (player.lock().unwrap().recording.create_app_list(), Vec::new())
+    } else {
+        AppAmdgpuTop::create_app_and_suspended_list(
+            &device_path_list,
+            &AppOption { gfxoff_residency, ..Default::default() },
+        )
+    };
     let mut vec_app: Vec<_> = vec_app
         .into_iter()
         .enumerate()
-        .map(|(i, app)| TuiApp::new_with_app(app, no_pc, i))
+        .map(|(i, app)| {
+            let mut tui_app = TuiApp::new_with_app(app, no_pc, i);
+            tui_app.layout.replay_status.clone_from(&replay_status);
+            tui_app.set_alert_rules(alert_rules.clone());
+            tui_app.set_session_stats(session_stats.clone());
+            tui_app
+        })
         .collect();
     let app_len = vec_app.len();
     let mut vec_sus_app: Vec<_> = suspended_devices
         .into_iter()
         .enumerate()
-        .map(|(i, app)| SuspendedTuiApp::new(app, no_pc, app_len+i))
+        .map(|(i, app)| {
+            let mut sus_app = SuspendedTuiApp::new(app, no_pc, app_len+i);
+            sus_app.layout.alert_view = alert_rules.as_ref().map(|_| Default::default());
+            sus_app.alert_rules.clone_from(&alert_rules);
+            sus_app.layout.stats_view = session_stats.as_ref().map(|_| Default::default());
+            sus_app.session_stats.clone_from(&session_stats);
+            sus_app.gfxoff_residency = gfxoff_residency;
+            sus_app
+        })
         .collect();
 
     for app in vec_app.iter_mut() {
-        app.update(&toggle_opt, &Sampling::low());
+        if replay.is_some() {
+            app.update_view(&toggle_opt);
+        } else {
+            app.update(&toggle_opt, &Sampling::low());
+        }
     }
 
     toggle_opt.indexes = vec_app.iter().map(|app| app.index).collect();
 
-    {
+    if replay.is_none() {
         let mut device_paths: Vec<DevicePath> = device_path_list;
 
         if let Some(xdna_device_path) = vec_app
@@ -177,6 +261,7 @@ pub fn run(
         siv.add_global_callback('v', VramUsageView::cb);
         siv.add_global_callback('a', ActivityView::cb);
         siv.add_global_callback('f', AppTextView::cb_fdinfo);
+        siv.add_global_callback('e', AppTextView::cb_fdinfo_clients);
         siv.add_global_callback('R', AppTextView::cb_reverse_sort);
         siv.add_global_callback('P', AppTextView::cb_sort_by_pid);
         siv.add_global_callback('V', AppTextView::cb_sort_by_vram);
@@ -203,13 +288,90 @@ pub fn run(
         siv.add_global_callback(Key::Esc, |siv| siv.select_menubar());
     }
 
-    if hide_fdinfo {
-        AppTextView::cb_fdinfo(&mut siv);
+    // The fdinfo of a recording is not filtered.
+    if replay.is_none() {
+        siv.add_global_callback('/', proc_filter_cb);
+    }
+
+    if let Some(player) = &replay {
+        for (key, f) in [
+            ('p', Player::toggle_pause as fn(&mut Player)),
+            ('<', |p: &mut Player| p.seek_relative(-10.0)),
+            ('>', |p: &mut Player| p.seek_relative(10.0)),
+            ('-', Player::slower),
+            ('+', Player::faster),
+            ('=', Player::faster),
+        ] {
+            let player = player.clone();
+            siv.add_global_callback(key, move |_| f(&mut player.lock().unwrap()));
+        }
+    }
+
+    // The device is not controlled during a replay.
+    if replay.is_none() {
+        let pci_list: Vec<(usize, PCI::BUS_INFO)> = vec_app
+            .iter()
+            .map(|app| (app.index, app.app_amdgpu_top.device_info.pci_bus))
+            .collect();
+
+        siv.add_global_callback('o', control::overdrive_cb(pci_list.clone(), enable_control));
+
+        if enable_control {
+            siv.add_global_callback('c', control::power_control_cb(pci_list));
+        }
+    }
+
+    if let Some(config_path) = config_path {
+        let pci_list: Vec<(usize, PCI::BUS_INFO)> = vec_app
+            .iter()
+            .map(|app| (app.index, app.app_amdgpu_top.device_info.pci_bus))
+            .collect();
+
+        siv.add_global_callback('S', move |siv| {
+            let (settings, pci) = {
+                let opt = siv.user_data::<Opt>().unwrap().lock().unwrap();
+                let pci = pci_list
+                    .iter()
+                    .find_map(|(index, pci)| (*index == opt.select_index).then_some(*pci));
+
+                (opt.to_settings(), pci)
+            };
+            let msg = match config::save_settings(&config_path, pci, &settings) {
+                Ok(()) => format!("Saved the layout to {config_path:?}"),
+                Err(err) => format!("{err:#}"),
+            };
+
+            siv.add_layer(Dialog::info(msg));
+        });
+    }
+
+    // hide the panels after the views are added
+    for (visible, cb) in [
+        (!hide_fdinfo, AppTextView::cb_fdinfo as fn(&mut cursive::Cursive)),
+        (settings.vram != Some(false), VramUsageView::cb),
+        (settings.activity != Some(false), ActivityView::cb),
+        (settings.sensors != Some(false), AppTextView::cb_sensors),
+        (settings.gpu_metrics != Some(false), AppTextView::cb_gpu_metrics),
+    ] {
+        if !visible { cb(&mut siv) }
+    }
+
+    if !no_pc {
+        for (visible, pc_type) in [(settings.grbm, PCType::GRBM), (settings.grbm2, PCType::GRBM2)] {
+            if visible == Some(false) { pc_type_cb(pc_type)(&mut siv) }
+        }
     }
 
     let cb_sink = siv.cb_sink().clone();
 
+    // Quit via cursive on SIGINT/SIGTERM, to stop the residency logging of GFXOFF.
+    if gfxoff_residency { signal::catch_exit_signals() }
+
     std::thread::spawn(move || loop {
+        if signal::exit_requested() {
+            let _ = cb_sink.send(Box::new(cursive::Cursive::quit));
+        }
+
         {
             let lock = toggle_opt.try_lock();
             if let Ok(opt) = lock {
@@ -219,6 +381,25 @@ pub fn run(
 
         let sample = if flags.high_freq { Sampling::high() } else { Sampling::low() };
 
+        if let Some(player) = &replay {
+            std::thread::sleep(sample.to_duration());
+
+            let mut player = player.lock().unwrap();
+            player.tick();
+
+            for app in vec_app.iter_mut() {
+                player.apply(&mut app.app_amdgpu_top);
+                app.update_view(&flags);
+            }
+
+            if let Some(replay_status) = &replay_status {
+                replay_status.set_content(player.status());
+            }
+
+            cb_sink.send(Box::new(cursive::Cursive::noop)).unwrap();
+            continue;
+        }
+
         {
             let selected_app = vec_app
                 .iter_mut()
@@ -238,6 +419,12 @@ pub fn run(
             }
 
             selected_app.update(&flags, &sample);
+
+            if let Some(Err(err)) = recorder.as_mut().map(|r| r.record(&selected_app.app_amdgpu_top)) {
+                eprintln!("Failed to record: {err}");
+                recorder = None;
+            }
+
             if !no_pc { selected_app.app_amdgpu_top.clear_pc(); }
         }
 
@@ -300,6 +487,35 @@ pub fn run(
     });
 
     siv.run();
+    stat::stop_gfxoff_residency_logging();
+
+    if let Some(session_stats) = &session_stats {
+        print_session_stats(session_stats);
+    }
+}
+
+fn proc_filter_cb(siv: &mut cursive::Cursive) {
+    let filter = stat::get_proc_filter().map(|f| f.to_string()).unwrap_or_default();
+    let edit = EditView::new()
+        .content(filter)
+        .on_submit(|siv, s| match ProcFilter::parse(s) {
+            Ok(filter) => {
+                stat::set_proc_filter(filter);
+                siv.pop_layer();
+            },
+            Err(err) => siv.add_layer(Dialog::info(format!("{err:#}"))),
+        })
+        .min_width(40);
+
+    siv.add_layer(
+        Dialog::around(edit)
+            .title("Process filter: pid:<PIDs> user:<Name|UID> <Name|Regex>")
+            .button("Clear", |siv| {
+                stat::set_proc_filter(Default::default());
+                siv.pop_layer();
+            })
+            .dismiss_button("Cancel")
+    );
 }
 
 fn dark_mode() -> Theme {
@@ -1,13 +1,15 @@
 use std::fmt::Write;
+use std::sync::{Arc, Mutex};
 use cursive::align::HAlign;
 use cursive::view::{Nameable, Scrollable};
 use cursive::views::{HideableView, LinearLayout, TextContent, TextView, Panel};
 
 use libamdgpu_top::AMDGPU::MetricsInfo;
-use libamdgpu_top::{stat, DevicePath, Sampling, UiArgs};
-use stat::{GfxoffMonitor, GfxoffStatus, FdInfoSortType};
+use libamdgpu_top::{signal, stat, DevicePath, Sampling, UiArgs};
+use libamdgpu_top::session_stats::update_session_stats;
+use stat::FdInfoSortType;
 
-use crate::{Text, AppTextView};
+use crate::{Text, AppTextView, SharedSessionStats, print_session_stats};
 
 const GPU_NAME_LEN: usize = 25;
 const LINE_LEN: usize = 150;
@@ -16,24 +18,20 @@ const ECC_LABEL: &str = "ECC_UnCorr.";
 const ECC_LEN: usize = ECC_LABEL.len()-2;
 const PROC_TITLE: &str = "Processes";
 
-use libamdgpu_top::app::AppAmdgpuTop;
+use libamdgpu_top::app::{AppAmdgpuTop, AppOption};
 
 struct SmiApp {
     app_amdgpu_top: AppAmdgpuTop,
     index: usize,
-    gfxoff_monitor: Option<GfxoffMonitor>,
     fdinfo_view: AppTextView,
     info_text: Text,
 }
 
 impl SmiApp {
     pub fn new(app_amdgpu_top: AppAmdgpuTop, index: usize) -> Option<Self> {
-        let gfxoff_monitor = GfxoffMonitor::new(app_amdgpu_top.device_path.pci).ok();
-
         Some(Self {
             app_amdgpu_top,
             index,
-            gfxoff_monitor,
             fdinfo_view: Default::default(),
             info_text: Default::default(),
         })
@@ -122,13 +120,8 @@ impl SmiApp {
             _ => write!(self.info_text.buf, " ___/___W ")?,
         }
 
-        if let Some(ref mut gfxoff_monitor) = self.gfxoff_monitor {
-            let _ = gfxoff_monitor.update();
-
-            match gfxoff_monitor.status {
-                GfxoffStatus::InGFXOFF => write!(self.info_text.buf, "GFXOFF |")?,
-                _ => write!(self.info_text.buf, "       |")?,
-            }
+        if self.app_amdgpu_top.stat.gfxoff.as_ref().is_some_and(|gfxoff| gfxoff.is_in_gfxoff()) {
+            write!(self.info_text.buf, "GFXOFF |")?;
         } else {
             write!(self.info_text.buf, "       |")?;
         }
@@ -197,6 +190,7 @@ impl SmiApp {
             &mut self.app_amdgpu_top.stat.fdinfo,
             FdInfoSortType::default(),
             false,
+            false,
         );
 
         let _ = self.update_info_text();
@@ -209,10 +203,11 @@ struct SuspendedSmiApp {
     index: usize,
     fdinfo_view: AppTextView,
     info_text: Text,
+    gfxoff_residency: bool,
 }
 
 impl SuspendedSmiApp {
-    fn new(device_path: DevicePath, index: usize) -> Self {
+    fn new(device_path: DevicePath, index: usize, gfxoff_residency: bool) -> Self {
         let mut info_text: Text = Default::default();
 
         if let [Some(did), Some(rid)] = [device_path.device_id, device_path.revision_id] {
@@ -238,6 +233,7 @@ impl SuspendedSmiApp {
             index,
             fdinfo_view: Default::default(),
             info_text,
+            gfxoff_residency,
         }
     }
 
@@ -257,14 +253,13 @@ impl SuspendedSmiApp {
     }
 
     fn to_smi_app(&self) -> Option<SmiApp> {
-        let amdgpu_dev = self.device_path.init().ok()?;
-        let app_amdgpu_top = AppAmdgpuTop::new(amdgpu_dev, self.device_path.clone(), &Default::default())?;
-        let gfxoff_monitor = GfxoffMonitor::new(self.device_path.pci).ok();
+        let amdgpu_dev = self.device_path.init_backend().ok()?;
+        let opt = AppOption { gfxoff_residency: self.gfxoff_residency, ..Default::default() };
+        let app_amdgpu_top = AppAmdgpuTop::new_with_backend(amdgpu_dev, self.device_path.clone(), &opt)?;
 
         Some(SmiApp {
             app_amdgpu_top,
             index: self.index,
-            gfxoff_monitor,
             fdinfo_view: self.fdinfo_view.clone(),
             info_text: self.info_text.clone(),
         })
@@ -276,13 +271,17 @@ pub fn run_smi(
     UiArgs {
         device_path_list,
         update_process_index,
+        session_stats,
+        gfxoff_residency,
         ..
     }: UiArgs,
 ) {
     let sample = Sampling::low();
+    let session_stats: Option<SharedSessionStats> = session_stats
+        .then(|| Arc::new(Mutex::new(Vec::new())));
     let (vec_app, suspended) = AppAmdgpuTop::create_app_and_suspended_list(
         &device_path_list,
-        &Default::default(),
+        &AppOption { gfxoff_residency, ..Default::default() },
     );
     let mut vec_app: Vec<_> = vec_app
         .into_iter()
@@ -293,7 +292,7 @@ pub fn run_smi(
     let mut sus_app_devices: Vec<_> = suspended
         .into_iter()
         .enumerate()
-        .map(|(i, device_path)| SuspendedSmiApp::new(device_path.clone(), app_len+i))
+        .map(|(i, device_path)| SuspendedSmiApp::new(device_path.clone(), app_len+i, gfxoff_residency))
         .collect();
 
     let mut siv = cursive::default();
@@ -350,14 +349,30 @@ pub fn run_smi(
     siv.set_theme(cursive::theme::Theme::terminal_default());
 
     let cb_sink = siv.cb_sink().clone();
+    let shared_stats = session_stats.clone();
+
+    // Quit via cursive on SIGINT/SIGTERM, to stop the residency logging of GFXOFF.
+    if gfxoff_residency { signal::catch_exit_signals() }
 
     std::thread::spawn(move || loop {
         std::thread::sleep(sample.to_duration()); // 1s
 
+        if signal::exit_requested() {
+            let _ = cb_sink.send(Box::new(cursive::Cursive::quit));
+        }
+
         for app in vec_app.iter_mut() {
             app.update(&sample);
         }
 
+        if let Some(shared_stats) = &shared_stats {
+            let mut list = shared_stats.lock().unwrap();
+
+            for app in &vec_app {
+                update_session_stats(&mut list, &app.app_amdgpu_top);
+            }
+        }
+
         sus_app_devices.retain(|sus_app| {
             let is_active = sus_app.device_path.check_if_device_is_active();
 
@@ -373,4 +388,9 @@ pub fn run_smi(
     });
 
     siv.run();
+    stat::stop_gfxoff_residency_logging();
+
+    if let Some(session_stats) = &session_stats {
+        print_session_stats(session_stats);
+    }
 }
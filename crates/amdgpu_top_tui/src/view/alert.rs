@@ -0,0 +1,31 @@
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
+use libamdgpu_top::alert::AlertEvent;
+
+use crate::AppTextView;
+
+impl AppTextView {
+    pub fn print_alerts(&mut self, alerts: &[AlertEvent]) {
+        let mut s = StyledString::new();
+
+        if alerts.is_empty() {
+            s.append_plain(" No alerts");
+        }
+
+        for (i, alert) in alerts.iter().enumerate() {
+            if i != 0 {
+                s.append_plain("\n");
+            }
+
+            let line = format!(" [{}] {}", alert.rule, alert.message);
+
+            if alert.highlight {
+                s.append_styled(line, Style::from(Color::Light(BaseColor::Red)).combine(Effect::Bold));
+            } else {
+                s.append_plain(line);
+            }
+        }
+
+        self.text.content.set_content(s);
+    }
+}
@@ -0,0 +1,23 @@
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
+use libamdgpu_top::stat::DevCoredumpMonitor;
+
+use crate::AppTextView;
+
+impl AppTextView {
+    /// The banner of the last GPU hang, empty (no lines) until a device coredump or a reset is found
+    pub fn print_devcoredump(&mut self, monitor: &DevCoredumpMonitor) {
+        let Some(report) = monitor.last() else { return };
+        let mut s = StyledString::new();
+        let style = Style::from(Color::Light(BaseColor::Red)).combine(Effect::Bold);
+        let resets = monitor.resets.map(|n| format!(", GPU reset: {n}")).unwrap_or_default();
+
+        s.append_styled(format!(" {report} (devcoredump: {}{resets})", monitor.count), style);
+
+        if let Some(path) = &report.path {
+            s.append_plain(format!("\n Saved to {}", path.display()));
+        }
+
+        self.text.content.set_content(s);
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::{self, Write};
+use libamdgpu_top::stat::DpmStat;
+
+use crate::AppTextView;
+
+const BAR_WIDTH: usize = 30;
+
+impl AppTextView {
+    pub fn print_dpm(&mut self, dpm: &DpmStat) -> Result<(), fmt::Error> {
+        self.text.clear();
+
+        for clock in &dpm.clocks {
+            writeln!(
+                self.text.buf,
+                " {} ({}, {:.1}s)",
+                clock.clock,
+                if clock.fine_grained { "Fine-grained" } else { "Levels" },
+                clock.tracked.as_secs_f64(),
+            )?;
+
+            for r in &clock.residency {
+                let per = clock.residency_percent(r);
+                let len = (per * BAR_WIDTH as f64 / 100.0).round() as usize;
+
+                writeln!(
+                    self.text.buf,
+                    " {mark}{name:<14}|{bar:<BAR_WIDTH$}|{per:>5.1}%",
+                    mark = if r.active { '*' } else { ' ' },
+                    name = r.name,
+                    bar = "#".repeat(len.min(BAR_WIDTH)),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt::{self, Write};
 use crate::Opt;
 
-use libamdgpu_top::stat::{FdInfoStat, FdInfoSortType};
+use libamdgpu_top::stat::{FdInfoStat, FdInfoSortType, FdInfoUsage};
 
 // ref: drivers/gpu/drm/amd/amdgpu/amdgpu_fdinfo.c
 
@@ -19,8 +19,39 @@ const ENC_LABEL: &str = "ENC";
 const VCN_LABEL: &str = "VCN";
 const VPE_LABEL: &str = "VPE";
 const KFD_LABEL: &str = "KFD";
-// const UVD_ENC_LABEL: &str = "UVD (ENC)";
-// const JPEG_LABEL: &str = "JPEG";
+const UVD_ENC_LABEL: &str = "UENC";
+const JPEG_LABEL: &str = "JPEG";
+const ENGINE_LEN: usize = 6; // "100.0%"
+const ENERGY_LABEL: &str = "Energy";
+const ENERGY_LEN: usize = 9; // "   1.23Wh"
+
+/// Each engine separately, for the header and the usage columns
+fn engine_columns(stat: &FdInfoStat, usage: &FdInfoUsage) -> Vec<(&'static str, f64)> {
+    let mut columns = vec![
+        (GFX_LABEL, usage.gfx),
+        (COMPUTE_LABEL, usage.compute),
+        (DMA_LABEL, usage.dma),
+    ];
+
+    if stat.has_vcn_unified {
+        columns.push((VCN_LABEL, usage.enc));
+    } else {
+        columns.push((DEC_LABEL, usage.dec));
+        columns.push((ENC_LABEL, usage.enc));
+    }
+
+    if stat.has_vcn {
+        columns.push((JPEG_LABEL, usage.vcn_jpeg));
+    } else {
+        columns.push((UVD_ENC_LABEL, usage.uvd_enc));
+    }
+
+    if stat.has_vpe {
+        columns.push((VPE_LABEL, usage.vpe));
+    }
+
+    columns
+}
 
 use crate::AppTextView;
 
@@ -32,36 +63,33 @@ impl AppTextView {
         stat: &mut FdInfoStat,
         sort: FdInfoSortType,
         reverse: bool,
+        show_clients: bool,
     ) -> Result<(), fmt::Error> {
         self.text.clear();
 
         write!(
             self.text.buf,
-            " {proc_name:<PROC_NAME_LEN$}|{pid:^PID_MAX_LEN$}|{KFD_LABEL}|{VRAM_LABEL:^6}|{GTT_LABEL:^6}|{CPU_LABEL:^4}|{GFX_LABEL:^4}|{COMPUTE_LABEL:^4}|{DMA_LABEL:^4}",
+            " {proc_name:<PROC_NAME_LEN$}|{pid:^PID_MAX_LEN$}|{KFD_LABEL}|{VRAM_LABEL:^6}|{GTT_LABEL:^6}|{CPU_LABEL:^4}|",
             proc_name = "Name",
             pid = "PID",
         )?;
 
-        if stat.has_vcn_unified {
-            write!(self.text.buf, "|{VCN_LABEL:^4}|")?;
-        } else {
-            write!(self.text.buf, "|{DEC_LABEL:^4}|{ENC_LABEL:^4}|")?;
+        for (label, _) in engine_columns(stat, &FdInfoUsage::default()) {
+            write!(self.text.buf, "{label:^ENGINE_LEN$}|")?;
         }
 
-        if stat.has_vpe {
-            write!(self.text.buf, "|{VPE_LABEL:^4}|")?;
-        }
+        write!(self.text.buf, "{ENERGY_LABEL:^ENERGY_LEN$}|")?;
 
         writeln!(self.text.buf)?;
 
         stat.sort_proc_usage(sort, reverse);
 
-        self.print_fdinfo_usage(stat)?;
+        self.print_fdinfo_usage(stat, show_clients)?;
 
         Ok(())
     }
 
-    pub fn print_fdinfo_usage(&mut self, stat: &FdInfoStat) -> Result<(), fmt::Error> {
+    pub fn print_fdinfo_usage(&mut self, stat: &FdInfoStat, show_clients: bool) -> Result<(), fmt::Error> {
         for pu in &stat.proc_usage {
             let utf16_count = pu.name.encode_utf16().count();
             let name_len = if pu.name.len() != utf16_count {
@@ -71,38 +99,69 @@ impl AppTextView {
             };
             write!(
                 self.text.buf,
-                " {name:name_len$}|{pid:>PID_MAX_LEN$}|{kfd:^3}|{vram:>5}M|{gtt:>5}M|",
+                " {name:name_len$}|{pid:>PID_MAX_LEN$}|{kfd:^3}|",
                 name = pu.name,
                 pid = pu.pid,
                 kfd = if pu.is_kfd_process { "Y" } else { "" },
-                vram = pu.usage.vram_usage >> 10,
-                gtt = pu.usage.gtt_usage >> 10,
             )?;
 
-            write!(self.text.buf, "{:>3}%|", pu.cpu_usage)?;
+            self.print_engine_usage(stat, &pu.usage, Some(pu.cpu_usage), Some(pu.energy))?;
 
-            for (usage, label_len) in [
-                (pu.usage.gfx, GFX_LABEL.len()),
-                (pu.usage.compute, COMPUTE_LABEL.len()-1),
-                (pu.usage.dma, DMA_LABEL.len()),
-            ] {
-                write!(self.text.buf, "{usage:>label_len$}%|")?;
-            }
+            // per DRM client
+            if !show_clients || pu.clients.len() < 2 { continue }
 
-            if stat.has_vcn_unified {
-                write!(self.text.buf, "{:>3}%|", pu.usage.media)?;
-            } else {
-                write!(self.text.buf, "{:>3}%|", pu.usage.total_dec)?;
-                write!(self.text.buf, "{:>3}%|", pu.usage.total_enc)?;
-            }
+            for client in &pu.clients {
+                let name = client.fdinfo.client_name.clone()
+                    .unwrap_or_else(|| format!("id {}", client.client_id));
+                let name: String = name.chars().take(PROC_NAME_LEN-3).collect();
 
-            if stat.has_vpe {
-                write!(self.text.buf, "{:>3}%|", pu.usage.vpe)?;
+                write!(
+                    self.text.buf,
+                    "  - {name:<width$}|{fd:>PID_MAX_LEN$}|{kfd:^3}|",
+                    width = PROC_NAME_LEN-3,
+                    fd = format!("fd {}", client.fd),
+                    kfd = "",
+                )?;
+
+                self.print_engine_usage(stat, &client.usage, None, None)?;
             }
+        }
+
+        Ok(())
+    }
+
+    fn print_engine_usage(
+        &mut self,
+        stat: &FdInfoStat,
+        usage: &FdInfoUsage,
+        cpu_usage: Option<i64>,
+        energy: Option<f64>, // J
+    ) -> Result<(), fmt::Error> {
+        write!(
+            self.text.buf,
+            "{vram:>5}M|{gtt:>5}M|",
+            vram = usage.vram_usage >> 10,
+            gtt = usage.gtt_usage >> 10,
+        )?;
+
+        if let Some(cpu_usage) = cpu_usage {
+            write!(self.text.buf, "{cpu_usage:>3}%|")?;
+        } else {
+            write!(self.text.buf, "{:>4}|", "")?;
+        }
+
+        for (_, usage) in engine_columns(stat, usage) {
+            write!(self.text.buf, "{usage:>width$.1}%|", width = ENGINE_LEN-1)?;
+        }
 
-            writeln!(self.text.buf)?;
+        if let Some(energy) = energy {
+            write!(self.text.buf, "{wh:>width$.2}Wh|", wh = energy / 3600.0, width = ENERGY_LEN-2)?;
+        } else {
+            write!(self.text.buf, "{:>ENERGY_LEN$}|", "")?;
         }
 
+        writeln!(self.text.buf)?;
+
         Ok(())
     }
 
@@ -134,6 +193,13 @@ impl AppTextView {
         }
     }
 
+    pub fn cb_fdinfo_clients(siv: &mut cursive::Cursive) {
+        {
+            let mut opt = siv.user_data::<Opt>().unwrap().lock().unwrap();
+            opt.fdinfo_clients ^= true;
+        }
+    }
+
     pub fn cb_reverse_sort(siv: &mut cursive::Cursive) {
         {
             let mut opt = siv.user_data::<Opt>().unwrap().lock().unwrap();
@@ -24,7 +24,15 @@ pub use vram::*;
 mod activity;
 pub use activity::*;
 
-mod memory_error_count;
+mod ras;
+
+mod throttling;
+
+mod dpm;
+
+mod alert;
+
+mod devcoredump;
 
 #[derive(Clone, Default)]
 pub(crate) struct AppTextView {
@@ -0,0 +1,33 @@
+use std::fmt::{self, Write};
+use std::time::SystemTime;
+use libamdgpu_top::stat::RasStat;
+
+use crate::AppTextView;
+
+impl AppTextView {
+    pub fn print_ras(&mut self, ras: &RasStat) -> Result<(), fmt::Error> {
+        self.text.clear();
+
+        writeln!(self.text.buf, " {:<12}|{:^16}|{:^16}|", "Block", "Corrected", "UnCorrected")?;
+
+        for block in &ras.blocks {
+            write!(
+                self.text.buf,
+                " {:<12}|{:>7} (+{:>5})|{:>7} (+{:>5})|",
+                block.name,
+                block.count.corrected,
+                block.delta.corrected,
+                block.count.uncorrected,
+                block.delta.uncorrected,
+            )?;
+
+            if let Some(ago) = block.last_increased.and_then(|t| SystemTime::now().duration_since(t).ok()) {
+                write!(self.text.buf, " last: {}s ago", ago.as_secs())?;
+            }
+
+            writeln!(self.text.buf)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use super::PANEL_WIDTH;
 use std::fmt::{self, Write};
 
-use libamdgpu_top::stat::{Sensors, PcieBw};
+use libamdgpu_top::stat::{EnergyStat, GfxoffMonitor, Sensors, PcieBw, PcieBwSource, PcieLinkMonitor};
 
 const WIDTH: usize = PANEL_WIDTH / 2;
 
@@ -81,6 +81,20 @@ impl AppTextView {
             writeln!(self.text.buf)?;
         }
 
+        if let Some(fan) = &sensors.fan {
+            write!(self.text.buf, " Fan Control => ")?;
+            if let Some(mode) = fan.mode {
+                write!(self.text.buf, "{mode}")?;
+            }
+            if let Some(per) = fan.pwm_percent() {
+                write!(self.text.buf, ", PWM {per:>3}%")?;
+            }
+            if let Some(target) = fan.target_rpm {
+                write!(self.text.buf, " (Target {target} RPM)")?;
+            }
+            writeln!(self.text.buf)?;
+        }
+
         if let Some(cur) = sensors.current_link {
             write!(self.text.buf, " PCIe Link Speed => Gen{}x{:<2}", cur.gen, cur.width)?;
 
@@ -112,9 +126,16 @@ impl AppTextView {
     }
 
     pub fn print_pcie_bw(&mut self, pcie_bw: &PcieBw) -> Result<(), fmt::Error> {
-        let Some(mps) = pcie_bw.max_payload_size else { return Ok(()) };
-        let Some(sent) = pcie_bw.sent.map(|v| (v * mps as u64) >> 20) else { return Ok(()) };
-        let Some(rec) = pcie_bw.received.map(|v| (v * mps as u64) >> 20) else { return Ok(()) };
+        if pcie_bw.source == PcieBwSource::GpuMetrics {
+            let Some(total) = pcie_bw.total_mib() else { return Ok(()) };
+
+            writeln!(self.text.buf, " PCIe Bandwidth Usage => Total: {total:6} MiB/s (gpu_metrics)")?;
+
+            return Ok(());
+        }
+
+        let Some(sent) = pcie_bw.sent_mib() else { return Ok(()) };
+        let Some(rec) = pcie_bw.received_mib() else { return Ok(()) };
 
         writeln!(
             self.text.buf,
@@ -124,6 +145,54 @@ impl AppTextView {
         Ok(())
     }
 
+    pub fn print_energy(&mut self, energy: &EnergyStat) -> Result<(), fmt::Error> {
+        let Some(source) = energy.source else { return Ok(()) };
+
+        writeln!(
+            self.text.buf,
+            " Energy => {:.2} Wh ({:.0} J, {source})",
+            energy.wh(),
+            energy.total,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn print_pcie_link_monitor(&mut self, monitor: &PcieLinkMonitor) -> Result<(), fmt::Error> {
+        let (Some(cur), Some(exp)) = (monitor.downtrained, monitor.expected) else { return Ok(()) };
+
+        writeln!(
+            self.text.buf,
+            " PCIe Link Downtrained => Gen{}x{} (Expected: Gen{}x{})",
+            cur.gen,
+            cur.width,
+            exp.gen,
+            exp.width,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn print_gfxoff(&mut self, gfxoff: &GfxoffMonitor) -> Result<(), fmt::Error> {
+        write!(self.text.buf, " GFXOFF     => {}", gfxoff.status)?;
+
+        if let Some(per) = gfxoff.residency_percent() {
+            write!(self.text.buf, ", {per:5.1}%")?;
+        }
+
+        write!(self.text.buf, ", {} transitions", gfxoff.transitions)?;
+
+        if gfxoff.mode.is_disabled() {
+            write!(self.text.buf, " (Disabled)")?;
+        } else if gfxoff.blocked_by_pc {
+            write!(self.text.buf, " (kept out by GRBM reads, --no-pc)")?;
+        }
+
+        writeln!(self.text.buf)?;
+
+        Ok(())
+    }
+
     pub fn sensors_name(index: usize) -> String {
         format!("{} {index}", Self::SENSORS_TITLE)
     }
@@ -0,0 +1,43 @@
+use std::fmt::{self, Write};
+use libamdgpu_top::stat::ThrottlingStat;
+
+use crate::AppTextView;
+
+impl AppTextView {
+    pub fn print_throttling(&mut self, thr: &ThrottlingStat) -> Result<(), fmt::Error> {
+        self.text.clear();
+
+        let tracked = thr.tracked.unwrap_or_default().as_secs_f64();
+
+        if thr.reasons.is_empty() {
+            writeln!(self.text.buf, " No throttling in {tracked:.1}s")?;
+            return Ok(());
+        }
+
+        writeln!(self.text.buf, " {:<16}|{:^18}|{:^11}|", "Throttler", "Residency", "Transitions")?;
+
+        for r in &thr.reasons {
+            writeln!(
+                self.text.buf,
+                " {name:<16}|{sec:>9.1}s ({per:>5.1}%)|{transitions:>11}|{active}",
+                name = r.name,
+                sec = r.residency.as_secs_f64(),
+                per = thr.residency_percent(r),
+                transitions = r.transitions,
+                active = if r.active { " Active" } else { "" },
+            )?;
+        }
+
+        if let Some(ev) = thr.events.last() {
+            writeln!(
+                self.text.buf,
+                " Last: {} {} at {:.1}s (tracked {tracked:.1}s)",
+                ev.name,
+                if ev.active { "started" } else { "stopped" },
+                ev.time.as_secs_f64(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
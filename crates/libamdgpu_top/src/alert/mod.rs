@@ -0,0 +1,29 @@
+//! Threshold alerts over the collected metrics.
+//!
+//! The rules are read from a file, e.g.:
+//!
+//! ```text
+//! # Junction temperature is above 95 C for 5 seconds
+//! [junction_hot]
+//! if = "junction_temp > 95"
+//! for = 5
+//! exec = "notify-send 'GPU is hot'"
+//! log = "/var/log/amdgpu_top_alert.log"
+//! highlight = true
+//!
+//! # A process keeps getting its VRAM evicted
+//! [eviction]
+//! if = "proc.evicted_vram increases"
+//! process = "blender"
+//! stderr = true
+//! ```
+//!
+//! [`AlertMonitor`] evaluates the rules for a device on every update,
+//! runs the `exec` and `log` actions, and returns [`AlertEvent`]s for the frontends
+//! to handle the `stderr` and `highlight` actions.
+
+mod rule;
+pub use rule::*;
+
+mod monitor;
+pub use monitor::*;
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::PCI;
+use crate::app::AppAmdgpuTop;
+use super::{AlertAction, AlertRule, AlertRules, Metric};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Firing => "firing",
+            Self::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub state: AlertState,
+    pub pci: PCI::BUS_INFO,
+    pub pid: Option<i32>,
+    pub process: Option<String>,
+    pub value: f64,
+    pub message: String,
+    pub time: SystemTime,
+    pub stderr: bool,
+    pub highlight: bool,
+}
+
+impl AlertEvent {
+    fn new(
+        rule: &AlertRule,
+        state: AlertState,
+        pci: PCI::BUS_INFO,
+        target: &Target,
+        pid: Option<i32>,
+    ) -> Self {
+        let value = target.value;
+        let mut message = format!("{} = {value:.1} ({})", rule.condition.metric, rule.condition.comparison);
+
+        if let Some(name) = &target.process {
+            message = format!("{name} ({}): {message}", pid.unwrap_or_default());
+        }
+
+        Self {
+            rule: rule.name.clone(),
+            state,
+            pci,
+            pid,
+            process: target.process.clone(),
+            value,
+            message,
+            time: SystemTime::now(),
+            stderr: rule.has_action(&AlertAction::Stderr),
+            highlight: rule.has_action(&AlertAction::Highlight),
+        }
+    }
+
+    pub fn unix_time(&self) -> u64 {
+        self.time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+
+    fn log_line(&self) -> String {
+        format!(
+            "{} {} {} [{}] {}\n",
+            self.unix_time(),
+            self.pci,
+            self.state.as_str(),
+            self.rule,
+            self.message,
+        )
+    }
+
+    fn exec(&self, cmd: &str) {
+        let mut command = Command::new("sh");
+
+        command
+            .arg("-c")
+            .arg(cmd)
+            .env("AGT_ALERT_RULE", &self.rule)
+            .env("AGT_ALERT_STATE", self.state.as_str())
+            .env("AGT_ALERT_PCI", self.pci.to_string())
+            .env("AGT_ALERT_VALUE", self.value.to_string())
+            .env("AGT_ALERT_MESSAGE", &self.message);
+
+        if let Some(pid) = self.pid {
+            command.env("AGT_ALERT_PID", pid.to_string());
+        }
+
+        let Ok(mut child) = command.spawn() else { return };
+
+        // reap the child without blocking the sampling
+        std::thread::spawn(move || child.wait());
+    }
+
+    fn run_actions(&self, rule: &AlertRule) {
+        for action in &rule.actions {
+            match action {
+                AlertAction::Exec(cmd) if self.state == AlertState::Firing => self.exec(cmd),
+                AlertAction::Log(path) => {
+                    let Ok(mut f) = fs::OpenOptions::new().create(true).append(true).open(path)
+                        else { continue };
+                    let _ = f.write_all(self.log_line().as_bytes());
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Target {
+    process: Option<String>,
+    value: f64,
+    pre: Option<f64>,
+    since: Option<Instant>,
+    firing: bool,
+}
+
+/// Keyed by PID for the process metrics, `None` for the device metrics.
+type RuleState = HashMap<Option<i32>, Target>;
+
+/// Evaluates the alert rules for a device.
+pub struct AlertMonitor {
+    rules: Arc<AlertRules>,
+    states: Vec<RuleState>,
+    active: Vec<AlertEvent>,
+}
+
+impl AlertMonitor {
+    pub fn new(rules: Arc<AlertRules>) -> Self {
+        let states = rules.rules.iter().map(|_| HashMap::new()).collect();
+
+        Self { rules, states, active: Vec::new() }
+    }
+
+    /// Evaluate the rules with the current values, run the `exec` and `log` actions,
+    /// and return the alerts that fired or resolved.
+    pub fn check(&mut self, app: &AppAmdgpuTop) -> Vec<AlertEvent> {
+        let now = Instant::now();
+        let pci = app.device_info.pci_bus;
+        let mut events = Vec::new();
+
+        for (rule, state) in self.rules.rules.iter().zip(self.states.iter_mut()) {
+            let values: Vec<(Option<i32>, Option<&str>, f64)> = match &rule.condition.metric {
+                Metric::Proc(m) => app.stat.fdinfo.proc_usage
+                    .iter()
+                    .filter(|p| rule.process.as_ref().is_none_or(|name| *name == p.name))
+                    .map(|p| (Some(p.pid), Some(p.name.as_str()), m.value(p)))
+                    .collect(),
+                metric => metric.value(&app.stat).map(|v| (None, None, v)).into_iter().collect(),
+            };
+
+            // the process has exited, or the value is no longer available
+            state.retain(|key, target| {
+                let exists = values.iter().any(|(k, _, _)| k == key);
+
+                if !exists && target.firing {
+                    events.push(AlertEvent::new(rule, AlertState::Resolved, pci, target, *key));
+                }
+
+                exists
+            });
+
+            for (key, process, value) in values {
+                let target = state.entry(key).or_default();
+                let met = rule.condition.comparison.check(value, target.pre);
+
+                target.pre = Some(value);
+                target.value = value;
+                target.process = process.map(str::to_string);
+
+                if !met {
+                    target.since = None;
+
+                    if target.firing {
+                        target.firing = false;
+                        events.push(AlertEvent::new(rule, AlertState::Resolved, pci, target, key));
+                    }
+
+                    continue;
+                }
+
+                let since = *target.since.get_or_insert(now);
+
+                if !target.firing && rule.duration <= now.duration_since(since) {
+                    target.firing = true;
+                    events.push(AlertEvent::new(rule, AlertState::Firing, pci, target, key));
+                }
+            }
+        }
+
+        for event in &events {
+            if let Some(rule) = self.rules.rules.iter().find(|rule| rule.name == event.rule) {
+                event.run_actions(rule);
+            }
+
+            self.active.retain(|a| a.rule != event.rule || a.pid != event.pid);
+
+            if event.state == AlertState::Firing {
+                self.active.push(event.clone());
+            }
+        }
+
+        events
+    }
+
+    /// Firing alerts
+    pub fn active(&self) -> &[AlertEvent] {
+        &self.active
+    }
+
+    /// Firing alerts with the `highlight` action
+    pub fn highlighted(&self) -> Vec<AlertEvent> {
+        self.active.iter().filter(|a| a.highlight).cloned().collect()
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+
+use crate::app::AppAmdgpuTopStat;
+use crate::stat::ProcUsage;
+use crate::toml_file;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Metric {
+    EdgeTemp, // C
+    JunctionTemp, // C
+    MemoryTemp, // C
+    Power, // W
+    Sclk, // MHz
+    Mclk, // MHz
+    FanRpm, // RPM
+    Gfx, // %
+    Umc, // %
+    Media, // %
+    Vram, // MiB
+    Gtt, // MiB
+    VramUsage, // %
+    GttUsage, // %
+    RasCorrected,
+    RasUncorrected,
+    /// 1 while the PCIe link is downtrained under load
+    PcieDowntrained,
+    /// 1 while the throttler is active, e.g. "throttle:PPT0"
+    Throttle(String),
+    /// Evaluated for each process
+    Proc(ProcMetric),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcMetric {
+    Vram, // MiB
+    Gtt, // MiB
+    EvictedVram, // MiB
+    Gfx, // %
+    Compute, // %
+    Dma, // %
+    Media, // %
+    Cpu, // %
+}
+
+const METRIC_NAMES: &[(&str, Metric)] = &[
+    ("edge_temp", Metric::EdgeTemp),
+    ("junction_temp", Metric::JunctionTemp),
+    ("memory_temp", Metric::MemoryTemp),
+    ("power", Metric::Power),
+    ("sclk", Metric::Sclk),
+    ("mclk", Metric::Mclk),
+    ("fan_rpm", Metric::FanRpm),
+    ("gfx", Metric::Gfx),
+    ("umc", Metric::Umc),
+    ("media", Metric::Media),
+    ("vram", Metric::Vram),
+    ("gtt", Metric::Gtt),
+    ("vram_usage", Metric::VramUsage),
+    ("gtt_usage", Metric::GttUsage),
+    ("ras_corrected", Metric::RasCorrected),
+    ("ras_uncorrected", Metric::RasUncorrected),
+    ("pcie_downtrained", Metric::PcieDowntrained),
+    ("proc.vram", Metric::Proc(ProcMetric::Vram)),
+    ("proc.gtt", Metric::Proc(ProcMetric::Gtt)),
+    ("proc.evicted_vram", Metric::Proc(ProcMetric::EvictedVram)),
+    ("proc.gfx", Metric::Proc(ProcMetric::Gfx)),
+    ("proc.compute", Metric::Proc(ProcMetric::Compute)),
+    ("proc.dma", Metric::Proc(ProcMetric::Dma)),
+    ("proc.media", Metric::Proc(ProcMetric::Media)),
+    ("proc.cpu", Metric::Proc(ProcMetric::Cpu)),
+];
+
+const THROTTLE_PREFIX: &str = "throttle:";
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = s.strip_prefix(THROTTLE_PREFIX) {
+            if name.is_empty() { bail!("missing throttler name: {s:?}") }
+
+            return Ok(Self::Throttle(name.to_string()));
+        }
+
+        METRIC_NAMES
+            .iter()
+            .find_map(|(name, metric)| (*name == s).then(|| metric.clone()))
+            .ok_or_else(|| anyhow!("unknown metric: {s:?}"))
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Self::Throttle(name) = self {
+            return write!(f, "{THROTTLE_PREFIX}{name}");
+        }
+
+        let name = METRIC_NAMES
+            .iter()
+            .find_map(|(name, metric)| (metric == self).then_some(*name))
+            .unwrap_or_default();
+
+        write!(f, "{name}")
+    }
+}
+
+impl Metric {
+    /// The scalar metrics of the device
+    pub const DEVICE_METRICS: &'static [Self] = &[
+        Self::Gfx,
+        Self::Umc,
+        Self::Media,
+        Self::Sclk,
+        Self::Mclk,
+        Self::Power,
+        Self::EdgeTemp,
+        Self::JunctionTemp,
+        Self::MemoryTemp,
+        Self::FanRpm,
+        Self::Vram,
+        Self::Gtt,
+        Self::VramUsage,
+        Self::GttUsage,
+    ];
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::EdgeTemp | Self::JunctionTemp | Self::MemoryTemp => "C",
+            Self::Power => "W",
+            Self::Sclk | Self::Mclk => "MHz",
+            Self::FanRpm => "RPM",
+            Self::Gfx | Self::Umc | Self::Media | Self::VramUsage | Self::GttUsage => "%",
+            Self::Vram | Self::Gtt => "MiB",
+            Self::RasCorrected | Self::RasUncorrected | Self::PcieDowntrained | Self::Throttle(_) => "",
+            Self::Proc(m) => m.unit(),
+        }
+    }
+
+    pub fn is_proc(&self) -> bool {
+        matches!(self, Self::Proc(_))
+    }
+
+    /// Returns `None` if the metric is not available on the device, or for [`Metric::Proc`].
+    pub fn value(&self, stat: &AppAmdgpuTopStat) -> Option<f64> {
+        let sensors = stat.sensors.as_ref();
+        let percent = |usage: u64, total: u64| (total != 0).then(|| usage as f64 * 100.0 / total as f64);
+
+        let v = match self {
+            Self::EdgeTemp => sensors?.edge_temp.as_ref()?.current as f64,
+            Self::JunctionTemp => sensors?.junction_temp.as_ref()?.current as f64,
+            Self::MemoryTemp => sensors?.memory_temp.as_ref()?.current as f64,
+            Self::Power => sensors?.any_hwmon_power()?.value as f64,
+            Self::Sclk => sensors?.sclk? as f64,
+            Self::Mclk => sensors?.mclk? as f64,
+            Self::FanRpm => sensors?.fan_rpm? as f64,
+            Self::Gfx => stat.activity.gfx? as f64,
+            Self::Umc => stat.activity.umc? as f64,
+            Self::Media => stat.activity.media? as f64,
+            Self::Vram => (stat.vram_usage.0.vram.heap_usage >> 20) as f64,
+            Self::Gtt => (stat.vram_usage.0.gtt.heap_usage >> 20) as f64,
+            Self::VramUsage => {
+                let vram = &stat.vram_usage.0.vram;
+                percent(vram.heap_usage, vram.total_heap_size)?
+            },
+            Self::GttUsage => {
+                let gtt = &stat.vram_usage.0.gtt;
+                percent(gtt.heap_usage, gtt.total_heap_size)?
+            },
+            Self::RasCorrected => stat.ras.as_ref()?.total().corrected as f64,
+            Self::RasUncorrected => stat.ras.as_ref()?.total().uncorrected as f64,
+            Self::PcieDowntrained => {
+                stat.pcie_link_monitor.expected?;
+                if stat.pcie_link_monitor.is_downtrained() { 1.0 } else { 0.0 }
+            },
+            Self::Throttle(name) => {
+                let thr = stat.metrics.as_ref()?.get_throttle_status_info()?;
+                let active = thr
+                    .get_all_throttler()
+                    .into_iter()
+                    .any(|t| t.to_string().eq_ignore_ascii_case(name));
+
+                if active { 1.0 } else { 0.0 }
+            },
+            Self::Proc(_) => return None,
+        };
+
+        Some(v)
+    }
+}
+
+impl ProcMetric {
+    pub const ALL: &'static [Self] = &[
+        Self::Gfx,
+        Self::Compute,
+        Self::Dma,
+        Self::Media,
+        Self::Cpu,
+        Self::Vram,
+        Self::Gtt,
+        Self::EvictedVram,
+    ];
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Vram | Self::Gtt | Self::EvictedVram => "MiB",
+            Self::Gfx | Self::Compute | Self::Dma | Self::Media | Self::Cpu => "%",
+        }
+    }
+
+    pub fn value(&self, proc_usage: &ProcUsage) -> f64 {
+        let usage = &proc_usage.usage;
+
+        match self {
+            Self::Vram => (usage.vram_usage >> 10) as f64,
+            Self::Gtt => (usage.gtt_usage >> 10) as f64,
+            Self::EvictedVram => (usage.amd_evicted_vram >> 10) as f64,
+            Self::Gfx => usage.gfx,
+            Self::Compute => usage.compute,
+            Self::Dma => usage.dma,
+            Self::Media => usage.media,
+            Self::Cpu => proc_usage.cpu_usage as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Gt(f64),
+    Ge(f64),
+    Lt(f64),
+    Le(f64),
+    /// The value is larger than at the previous check
+    Increases,
+}
+
+impl Comparison {
+    pub fn check(&self, value: f64, pre: Option<f64>) -> bool {
+        match *self {
+            Self::Gt(v) => value > v,
+            Self::Ge(v) => value >= v,
+            Self::Lt(v) => value < v,
+            Self::Le(v) => value <= v,
+            Self::Increases => pre.is_some_and(|pre| value > pre),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Gt(v) => write!(f, "> {v}"),
+            Self::Ge(v) => write!(f, ">= {v}"),
+            Self::Lt(v) => write!(f, "< {v}"),
+            Self::Le(v) => write!(f, "<= {v}"),
+            Self::Increases => write!(f, "increases"),
+        }
+    }
+}
+
+/// "<metric> <op> <value>" or "<metric> increases"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub metric: Metric,
+    pub comparison: Comparison,
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+
+        let comparison = match tokens[..] {
+            [_, "increases"] => Comparison::Increases,
+            [_, op, v] => {
+                let v: f64 = v.parse().map_err(|_| anyhow!("invalid value: {v:?}"))?;
+
+                match op {
+                    ">" => Comparison::Gt(v),
+                    ">=" => Comparison::Ge(v),
+                    "<" => Comparison::Lt(v),
+                    "<=" => Comparison::Le(v),
+                    _ => bail!("unknown operator: {op:?}"),
+                }
+            },
+            _ => bail!("invalid condition: {s:?}, expected \"<metric> <op> <value>\" or \"<metric> increases\""),
+        };
+
+        Ok(Self { metric: tokens[0].parse()?, comparison })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.metric, self.comparison)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Run the command with `sh -c` when the alert fires
+    Exec(String),
+    /// Append a line to the file when the alert fires and resolves
+    Log(PathBuf),
+    /// Print the events to stderr (JSON mode)
+    Stderr,
+    /// Highlight the firing alert (TUI/GUI)
+    Highlight,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub name: String,
+    pub condition: Condition,
+    /// The condition must hold for this duration before the alert fires
+    pub duration: Duration,
+    /// Only for the process metrics, matched against the process name
+    pub process: Option<String>,
+    pub actions: Vec<AlertAction>,
+}
+
+impl AlertRule {
+    pub fn has_action(&self, action: &AlertAction) -> bool {
+        self.actions.contains(action)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertRules {
+    pub rules: Vec<AlertRule>,
+}
+
+/// A table of the alert rules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleTable {
+    #[serde(default, rename = "if", deserialize_with = "toml_file::from_str")]
+    condition: Option<Condition>,
+    #[serde(default, rename = "for", deserialize_with = "toml_file::secs")]
+    duration: Option<Duration>,
+    #[serde(default)]
+    process: Option<String>,
+    #[serde(default)]
+    exec: Option<String>,
+    #[serde(default)]
+    log: Option<PathBuf>,
+    #[serde(default)]
+    stderr: bool,
+    #[serde(default)]
+    highlight: bool,
+}
+
+impl RuleTable {
+    fn build(self, name: String) -> anyhow::Result<AlertRule> {
+        let condition = self.condition.ok_or_else(|| anyhow!("[{name}]: missing \"if\""))?;
+
+        if self.process.is_some() && !condition.metric.is_proc() {
+            bail!("[{name}]: \"process\" is only for the \"proc.*\" metrics");
+        }
+
+        let actions: Vec<AlertAction> = [
+            self.exec.map(AlertAction::Exec),
+            self.log.map(AlertAction::Log),
+            self.stderr.then_some(AlertAction::Stderr),
+            self.highlight.then_some(AlertAction::Highlight),
+        ].into_iter().flatten().collect();
+
+        if actions.is_empty() {
+            bail!("[{name}]: no actions (exec, log, stderr, highlight)");
+        }
+
+        Ok(AlertRule {
+            name,
+            condition,
+            duration: self.duration.unwrap_or_default(),
+            process: self.process,
+            actions,
+        })
+    }
+}
+
+impl AlertRules {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+
+        Self::parse(&s).with_context(|| format!("Invalid alert rules: {path:?}"))
+    }
+
+    /// Each table is a rule, in the order of the file.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let rules = toml_file::parse_ordered(s)?
+            .into_iter()
+            .map(|(name, table)| {
+                if !table.is_table() {
+                    bail!("\"{name}\" outside of a rule");
+                }
+
+                let rule: RuleTable = table.try_into().with_context(|| format!("[{name}]"))?;
+
+                rule.build(name)
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alert_rules() {
+        let s = concat!(
+            "# comment\n",
+            "[junction_hot]\n",
+            "if = \"junction_temp > 95\"\n",
+            "for = 5 # seconds\n",
+            "exec = \"notify-send \\\"GPU is hot\\\"\"\n",
+            "highlight = true\n",
+            "\n",
+            "[eviction]\n",
+            "if = \"proc.evicted_vram increases\"\n",
+            "process = \"blender\"\n",
+            "log = \"/tmp/alert.log\"\n",
+            "[ppt]\n",
+            "if = \"throttle:PPT0 >= 1\"\n",
+            "for = 2.5\n",
+            "stderr = true\n",
+        );
+        let rules = AlertRules::parse(s).unwrap().rules;
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].condition.metric, Metric::JunctionTemp);
+        assert_eq!(rules[0].condition.comparison, Comparison::Gt(95.0));
+        assert_eq!(rules[0].duration, Duration::from_secs(5));
+        assert_eq!(
+            rules[0].actions,
+            vec![AlertAction::Exec("notify-send \"GPU is hot\"".to_string()), AlertAction::Highlight],
+        );
+        assert_eq!(rules[1].condition.to_string(), "proc.evicted_vram increases");
+        assert_eq!(rules[1].process.as_deref(), Some("blender"));
+        assert_eq!(rules[1].actions, vec![AlertAction::Log(PathBuf::from("/tmp/alert.log"))]);
+        assert_eq!(rules[2].condition.metric, Metric::Throttle("PPT0".to_string()));
+        assert_eq!(rules[2].duration, Duration::from_millis(2500));
+
+        assert!(AlertRules::parse("[a]\nif = \"gfx > 1\"\n").is_err());
+        assert!(AlertRules::parse("[a]\nif = \"gfx > 1\"\nprocess = \"a\"\nstderr = true\n").is_err());
+        assert!(AlertRules::parse("[a]\nif = \"foo > 1\"\nstderr = true\n").is_err());
+        assert!(AlertRules::parse("[a]\nif = \"gfx > 1\"\nlog = /tmp/alert.log\n").is_err());
+        assert!(AlertRules::parse("[a]\nif = \"gfx > 1\"\nfor = 2.5s\nstderr = true\n").is_err());
+    }
+}
@@ -1,14 +1,17 @@
 use crate::drmVersion;
-use crate::AMDGPU::{DeviceHandle, GPU_INFO, GpuMetrics, RasBlock, RasErrorCount};
+use crate::AMDGPU::{GPU_INFO, GpuMetrics, RasErrorCount};
+use crate::backend::DeviceBackend;
 use crate::{AppDeviceInfo, DevicePath, stat, xdna, VramUsage, has_vcn, has_vcn_unified, has_vpe};
-use stat::{FdInfoStat, GpuActivity, Sensors, PcieBw, PerfCounter, ProcInfo};
+use stat::{DevCoredumpMonitor, DpmStat, EnergyStat, FdInfoStat, ThrottlingStat, GfxoffMonitor, GpuActivity, Sensors, PcieBw, PcieBwSource, PcieLinkMonitor, PerfCounter, ProcInfo, RasStat};
 use xdna::XdnaFdInfoStat;
+use crate::record::RecordedDevice;
+use serde::{Deserialize, Serialize};
 use std::mem::ManuallyDrop;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 pub struct AppAmdgpuTop {
-    amdgpu_dev: ManuallyDrop<Option<DeviceHandle>>,
+    amdgpu_dev: ManuallyDrop<Option<Box<dyn DeviceBackend>>>,
     pub device_info: AppDeviceInfo,
     pub device_path: DevicePath,
     pub xdna_device_path: Option<DevicePath>,
@@ -17,26 +20,41 @@ pub struct AppAmdgpuTop {
     buf_interval: Duration,
     no_drop_device_handle: bool,
     dynamic_no_pc: bool, // to transition the APU into GFXOFF state
+    pc_read: bool, // the performance counters are read since the last update
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AppAmdgpuTopStat {
     pub grbm: PerfCounter,
     pub grbm2: PerfCounter,
     pub vram_usage: VramUsage,
     pub sensors: Option<Sensors>,
+    #[serde(with = "crate::record::remote::gpu_metrics")]
     pub metrics: Option<GpuMetrics>,
     pub activity: GpuActivity,
     pub fdinfo: FdInfoStat,
     pub xdna_fdinfo: XdnaFdInfoStat,
+    #[serde(skip)]
     pub arc_proc_index: Arc<Mutex<Vec<ProcInfo>>>,
+    #[serde(skip)]
     pub arc_xdna_proc_index: Arc<Mutex<Vec<ProcInfo>>>,
     pub arc_pcie_bw: Option<Arc<Mutex<PcieBw>>>,
+    /// UMC block of `ras`
+    #[serde(with = "crate::record::remote::option")]
     pub memory_error_count: Option<RasErrorCount>,
+    pub ras: Option<RasStat>,
+    pub energy: EnergyStat,
+    pub throttling: ThrottlingStat,
+    pub pcie_link_monitor: PcieLinkMonitor,
+    pub dpm: DpmStat,
+    pub gfxoff: Option<GfxoffMonitor>,
+    pub devcoredump: DevCoredumpMonitor,
 }
 
 pub struct AppOption {
     pub pcie_bw: bool,
+    /// Start the residency logging of GFXOFF (`amdgpu_gfxoff_residency`)
+    pub gfxoff_residency: bool,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -44,6 +62,7 @@ impl Default for AppOption {
     fn default() -> Self {
         Self {
             pcie_bw: false,
+            gfxoff_residency: false,
         }
     }
 }
@@ -62,8 +81,8 @@ impl AppAmdgpuTop {
                 continue;
             }
 
-            let Ok(amdgpu_dev) = device_path.init() else { continue };
-            let Some(app) = Self::new(amdgpu_dev, device_path.clone(), opt) else {
+            let Ok(amdgpu_dev) = device_path.init_backend() else { continue };
+            let Some(app) = Self::new_with_backend(amdgpu_dev, device_path.clone(), opt) else {
                 continue
             };
             apps.push(app);
@@ -72,8 +91,8 @@ impl AppAmdgpuTop {
         if apps.is_empty() && !suspended_devices.is_empty() {
             let (device_path, other_sus_devs) = suspended_devices.split_first().unwrap();
             // wake up
-            let amdgpu_dev = device_path.init().unwrap();
-            let app = AppAmdgpuTop::new(
+            let amdgpu_dev = device_path.init_backend().unwrap();
+            let app = AppAmdgpuTop::new_with_backend(
                 amdgpu_dev,
                 device_path.clone(),
                 &Default::default(),
@@ -90,15 +109,27 @@ impl AppAmdgpuTop {
         opt: T,
     ) -> Vec<Self> {
         let vec_json_device: Vec<Self> = device_path_list.iter().filter_map(|device_path| {
-            let amdgpu_dev = device_path.init().ok()?;
+            let amdgpu_dev = device_path.init_backend().ok()?;
 
-            Self::new(amdgpu_dev, device_path.clone(), opt.as_ref())
+            Self::new_with_backend(amdgpu_dev, device_path.clone(), opt.as_ref())
         }).collect();
 
         vec_json_device
     }
 
-    pub fn new(amdgpu_dev: DeviceHandle, device_path: DevicePath, opt: &AppOption) -> Option<Self> {
+    pub fn new<B: DeviceBackend + 'static>(
+        amdgpu_dev: B,
+        device_path: DevicePath,
+        opt: &AppOption,
+    ) -> Option<Self> {
+        Self::new_with_backend(Box::new(amdgpu_dev), device_path, opt)
+    }
+
+    pub fn new_with_backend(
+        amdgpu_dev: Box<dyn DeviceBackend>,
+        device_path: DevicePath,
+        opt: &AppOption,
+    ) -> Option<Self> {
         let pci_bus = device_path.pci;
         let sysfs_path = device_path.sysfs_path.clone();
         let ext_info = amdgpu_dev.device_info().ok()?;
@@ -120,34 +151,43 @@ impl AppAmdgpuTop {
         };
 
         let vram_usage = VramUsage::new(&memory_info);
-        let memory_error_count = RasErrorCount::get_from_sysfs_with_ras_block(&sysfs_path, RasBlock::UMC).ok();
+        let gfxoff = GfxoffMonitor::new(pci_bus).ok().map(|mut gfxoff| {
+            if opt.gfxoff_residency {
+                let _ = gfxoff.start_residency_logging();
+            }
+
+            gfxoff
+        });
+        let ras = RasStat::from_sysfs(&sysfs_path);
+        let memory_error_count = ras.as_ref().and_then(|ras| ras.get("umc")).map(|umc| umc.count.into());
 
-        let sensors = Sensors::new(&amdgpu_dev, &pci_bus, &ext_info);
+        let sensors = Sensors::new(amdgpu_dev.as_ref(), &pci_bus, &ext_info);
         let metrics = GpuMetrics::get_from_sysfs_path(&device_path.sysfs_path).ok();
         let activity = GpuActivity::get(&sysfs_path, asic_name);
 
-        let arc_pcie_bw = if opt.pcie_bw {
+        let arc_pcie_bw = {
             let pcie_bw = PcieBw::new(&sysfs_path);
 
-            if pcie_bw.check_pcie_bw_support(&ext_info) {
+            if opt.pcie_bw && pcie_bw.check_pcie_bw_support(&ext_info) {
                 Some(pcie_bw.spawn_update_thread())
+            } else if metrics.as_ref().is_some_and(PcieBw::check_gpu_metrics_support) {
+                // gpu_metrics is read in `update` anyway, so this does not need the option
+                Some(Arc::new(Mutex::new(PcieBw::new_with_gpu_metrics(&sysfs_path))))
             } else {
                 None
             }
-        } else {
-            None
         };
 
         let fdinfo = FdInfoStat {
-            has_vcn: has_vcn(&amdgpu_dev),
-            has_vcn_unified: has_vcn_unified(&amdgpu_dev),
-            has_vpe: has_vpe(&amdgpu_dev),
+            has_vcn: has_vcn(amdgpu_dev.as_ref()),
+            has_vcn_unified: has_vcn_unified(amdgpu_dev.as_ref()),
+            has_vpe: has_vpe(amdgpu_dev.as_ref()),
             ..Default::default()
         };
         let xdna_fdinfo = XdnaFdInfoStat::default();
 
         let mut device_info = AppDeviceInfo::new(
-            &amdgpu_dev,
+            amdgpu_dev.as_ref(),
             &ext_info,
             &memory_info,
             &sensors,
@@ -212,10 +252,36 @@ impl AppAmdgpuTop {
                 arc_xdna_proc_index,
                 arc_pcie_bw,
                 memory_error_count,
+                ras,
+                energy: Default::default(),
+                throttling: Default::default(),
+                pcie_link_monitor: Default::default(),
+                dpm: Default::default(),
+                gfxoff,
+                devcoredump: DevCoredumpMonitor::new(pci_bus),
             },
             buf_interval: Duration::ZERO,
             no_drop_device_handle,
             dynamic_no_pc: false,
+            pc_read: false,
+        })
+    }
+
+    /// Create an app without a device handle from a recorded device.
+    pub fn from_recorded_device(device: &RecordedDevice) -> Option<Self> {
+        let (_, stat) = device.frames.first()?;
+
+        Some(Self {
+            amdgpu_dev: ManuallyDrop::new(None),
+            device_info: device.device_info.clone(),
+            device_path: device.device_path.clone(),
+            xdna_device_path: device.xdna_device_path.clone(),
+            xdna_fw_version: device.xdna_fw_version.clone(),
+            stat: stat.clone(),
+            buf_interval: Duration::ZERO,
+            no_drop_device_handle: true,
+            dynamic_no_pc: false,
+            pc_read: false,
         })
     }
 
@@ -244,7 +310,7 @@ impl AppAmdgpuTop {
             // running GPU process is only "amdgpu_top"
             // TODO: those checks may not be enough
             if proc_len == 1
-                && self.amdgpu_dev.is_some()
+                && self.amdgpu_dev.as_deref().is_some_and(|dev| dev.is_reopenable())
                 && !self.no_drop_device_handle
                 && !self.device_info.is_apu
                 && pre_activity.is_all_idling()
@@ -255,16 +321,22 @@ impl AppAmdgpuTop {
                 && self.amdgpu_dev.is_none()
                 && stat::check_if_device_is_active(&self.device_info.sysfs_path)
             {
-                self.amdgpu_dev = ManuallyDrop::new(self.device_path.init().ok());
+                self.amdgpu_dev = ManuallyDrop::new(self.device_path.init_backend().ok());
             }
         }
 
+        self.stat.devcoredump.update();
+
         if self.amdgpu_dev.is_none() {
             if let Some(ref mut sensors) = self.stat.sensors {
                 sensors.update_for_idle();
             }
 
             self.stat.metrics = None;
+            self.stat.throttling.update(None);
+            self.stat.dpm.update(None, None);
+            self.pc_read = false;
+            self.update_energy();
             return;
         };
 
@@ -274,7 +346,7 @@ impl AppAmdgpuTop {
             self.stat.metrics = GpuMetrics::get_from_sysfs_path(&self.device_info.sysfs_path).ok();
         }
 
-        if let Some(dev) = self.amdgpu_dev.as_ref() {
+        if let Some(dev) = self.amdgpu_dev.as_deref() {
             self.stat.vram_usage.update_usage(dev);
             self.stat.vram_usage.update_usable_heap_size(dev);
 
@@ -289,11 +361,21 @@ impl AppAmdgpuTop {
             }
         }
 
-        if self.stat.memory_error_count.is_some() {
-            self.stat.memory_error_count = RasErrorCount::get_from_sysfs_with_ras_block(
-                &self.device_info.sysfs_path,
-                RasBlock::UMC,
-            ).ok();
+        if let (Some(metrics), Some(sensors)) = (&self.stat.metrics, &mut self.stat.sensors) {
+            sensors.update_link_with_gpu_metrics(metrics);
+        }
+
+        if let (Some(metrics), Some(arc_pcie_bw)) = (&self.stat.metrics, &self.stat.arc_pcie_bw) {
+            let mut pcie_bw = arc_pcie_bw.lock().unwrap();
+
+            if pcie_bw.source == PcieBwSource::GpuMetrics {
+                pcie_bw.update_with_gpu_metrics(metrics);
+            }
+        }
+
+        if let Some(ras) = &mut self.stat.ras {
+            ras.update();
+            self.stat.memory_error_count = ras.get("umc").map(|umc| umc.count.into());
         }
 
         self.stat.activity = GpuActivity::get_with_option_gpu_metrics(
@@ -305,16 +387,36 @@ impl AppAmdgpuTop {
         self.dynamic_no_pc = self.device_info.is_apu && self.stat.activity.is_gfx_idling();
 
         if self.stat.activity.media.is_none() || self.stat.activity.media == Some(0) {
-            self.stat.activity.media = self.stat.fdinfo.fold_fdinfo_usage().media.try_into().ok();
+            self.stat.activity.media = Some(self.stat.fdinfo.fold_fdinfo_usage().media.round() as u16);
         }
+
+        self.stat.throttling.update(self.stat.metrics.as_ref());
+        self.stat.pcie_link_monitor.update(self.stat.sensors.as_ref(), &self.stat.activity);
+        self.stat.dpm.update(Some(&self.device_info.sysfs_path), self.stat.sensors.as_ref());
+
+        if let Some(gfxoff) = &mut self.stat.gfxoff {
+            let _ = gfxoff.update();
+            gfxoff.blocked_by_pc = self.pc_read;
+        }
+
+        self.pc_read = false;
+        self.update_energy();
+    }
+
+    /// Accumulate the energy of the device, and attribute it to the processes.
+    fn update_energy(&mut self) {
+        let energy = self.stat.energy.update(self.stat.sensors.as_ref(), self.stat.metrics.as_ref());
+
+        self.stat.fdinfo.attribute_energy(energy);
     }
 
     pub fn update_pc(&mut self) {
         if self.dynamic_no_pc { return }
 
-        if let Some(dev) = self.amdgpu_dev.as_ref() {
+        if let Some(dev) = self.amdgpu_dev.as_deref() {
             self.stat.grbm.read_reg(dev);
             self.stat.grbm2.read_reg(dev);
+            self.pc_read = true;
         }
     }
 
@@ -330,7 +432,7 @@ impl AppAmdgpuTop {
 
     pub fn get_drm_version_struct(&mut self) -> Option<drmVersion> {
         self.amdgpu_dev
-            .as_ref()
+            .as_deref()
             .and_then(|dev| dev.get_drm_version_struct().ok())
     }
 }
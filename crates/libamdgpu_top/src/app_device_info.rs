@@ -1,6 +1,5 @@
 use crate::AMDGPU::{
     ASIC_NAME,
-    DeviceHandle,
     drm_amdgpu_info_device,
     drm_amdgpu_memory_info,
     GPU_INFO,
@@ -14,42 +13,62 @@ use crate::AMDGPU::{
     VBIOS::VbiosInfo,
     VIDEO_CAPS::{CAP_TYPE, VideoCapsInfo},
 };
-use crate::{DevicePath, get_hw_ip_info_list, PCI, stat::Sensors};
+use crate::{DevicePath, get_hw_ip_info_list, PCI, backend::DeviceBackend, stat::Sensors};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppDeviceInfo {
+    #[serde(with = "crate::record::remote")]
     pub ext_info: drm_amdgpu_info_device,
+    #[serde(with = "crate::record::remote")]
     pub memory_info: drm_amdgpu_memory_info,
     pub is_apu: bool,
     pub resizable_bar: bool,
+    #[serde(with = "crate::record::remote::option")]
     pub min_dpm_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_dpm_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_gpu_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_system_link: Option<PCI::LINK>,
     pub min_gpu_clk: u32,
     pub max_gpu_clk: u32,
     pub min_mem_clk: u32,
     pub max_mem_clk: u32,
     pub marketing_name: String,
+    #[serde(with = "crate::record::remote")]
     pub asic_name: ASIC_NAME,
+    #[serde(with = "crate::record::remote")]
     pub pci_bus: PCI::BUS_INFO,
     pub sysfs_path: PathBuf,
+    #[serde(with = "crate::record::remote::edge_temp")]
     pub edge_temp: Option<HwmonTemp>,
+    #[serde(with = "crate::record::remote::junction_temp")]
     pub junction_temp: Option<HwmonTemp>,
+    #[serde(with = "crate::record::remote::memory_temp")]
     pub memory_temp: Option<HwmonTemp>,
+    #[serde(with = "crate::record::remote::option")]
     pub power_cap: Option<PowerCap>,
     pub fan_max_rpm: Option<u32>,
+    #[serde(with = "crate::record::remote::option")]
     pub decode: Option<VideoCapsInfo>,
+    #[serde(with = "crate::record::remote::option")]
     pub encode: Option<VideoCapsInfo>,
+    #[serde(with = "crate::record::remote::option")]
     pub vbios: Option<VbiosInfo>,
     pub l1_cache_size_kib_per_cu: u32,
     pub actual_num_tcc_blocks: u32,
     pub gl1_cache_size_kib_per_sa: u32,
     pub total_l2_cache_size_kib: u32,
     pub total_l3_cache_size_mib: u32,
+    #[serde(with = "crate::record::remote::vec")]
     pub hw_ip_info_list: Vec<HwIpInfo>,
+    // IP discovery table is not recorded
+    #[serde(skip)]
     pub ip_die_entries: Vec<IpDieEntry>,
+    #[serde(with = "crate::record::remote::vec")]
     pub power_profiles: Vec<PowerProfile>,
     pub gfx_target_version: Option<String>,
     pub ecc_memory: bool,
@@ -58,7 +77,7 @@ pub struct AppDeviceInfo {
 
 impl AppDeviceInfo {
     pub fn new(
-        amdgpu_dev: &DeviceHandle,
+        amdgpu_dev: &dyn DeviceBackend,
         ext_info: &drm_amdgpu_info_device,
         memory_info: &drm_amdgpu_memory_info,
         sensors: &Option<Sensors>,
@@ -0,0 +1,126 @@
+//! Backends of the device queries used by [`AppAmdgpuTop`](crate::app::AppAmdgpuTop) and the stat modules.
+//!
+//! [`DeviceHandle`] of libdrm_amdgpu_sys is the default backend,
+//! and [`SyntheticBackend`] returns the values of a [`SyntheticState`] instead of querying a GPU.
+
+use crate::drmVersion;
+use crate::AMDGPU::{
+    DeviceHandle,
+    drm_amdgpu_info_device,
+    drm_amdgpu_info_vram_gtt,
+    drm_amdgpu_memory_info,
+    HW_IP::{HW_IP_TYPE, HwIpInfo},
+    SENSOR_INFO::SENSOR_TYPE,
+    VBIOS::VbiosInfo,
+    VIDEO_CAPS::{CAP_TYPE, VideoCapsInfo},
+};
+
+mod synthetic;
+pub use synthetic::*;
+
+use crate::PCI;
+use std::sync::{Arc, Mutex};
+
+pub type BackendFactory = Arc<dyn Fn() -> Box<dyn DeviceBackend> + Send + Sync>;
+
+static SYNTHETIC_DEVICES: Mutex<Vec<(PCI::BUS_INFO, BackendFactory)>> = Mutex::new(Vec::new());
+
+/// Register a backend for the device at `pci`,
+/// [`DevicePath::init_backend`](crate::DevicePath::init_backend) uses it instead of opening the device.
+pub fn register_synthetic_device(pci: PCI::BUS_INFO, factory: BackendFactory) {
+    let mut devices = SYNTHETIC_DEVICES.lock().unwrap();
+
+    devices.retain(|(p, _)| *p != pci);
+    devices.push((pci, factory));
+}
+
+pub(crate) fn init_synthetic_device(pci: &PCI::BUS_INFO) -> Option<Box<dyn DeviceBackend>> {
+    let devices = SYNTHETIC_DEVICES.lock().unwrap();
+
+    devices.iter().find(|(p, _)| p == pci).map(|(_, factory)| factory())
+}
+
+/// The errors are negative errno values, as returned by libdrm.
+pub trait DeviceBackend: Send {
+    fn device_info(&self) -> Result<drm_amdgpu_info_device, i32>;
+    fn memory_info(&self) -> Result<drm_amdgpu_memory_info, i32>;
+    fn vram_gtt_info(&self) -> Result<drm_amdgpu_info_vram_gtt, i32>;
+    fn vram_usage_info(&self) -> Result<u64, i32>;
+    fn vis_vram_usage_info(&self) -> Result<u64, i32>;
+    fn gtt_usage_info(&self) -> Result<u64, i32>;
+    fn read_mm_registers(&self, offset: u32) -> Result<u32, i32>;
+    fn sensor_info(&self, sensor_type: SENSOR_TYPE) -> Result<u32, i32>;
+    fn get_hw_ip_info(&self, ip_type: HW_IP_TYPE) -> Result<HwIpInfo, i32>;
+    fn get_min_max_gpu_clock(&self) -> Option<(u32, u32)>;
+    fn get_min_max_memory_clock(&self) -> Option<(u32, u32)>;
+    fn get_video_caps_info(&self, cap_type: CAP_TYPE) -> Result<VideoCapsInfo, i32>;
+    fn get_vbios_info(&self) -> Result<VbiosInfo, i32>;
+    fn get_drm_version_struct(&self) -> Result<drmVersion, i32>;
+
+    /// Whether the backend can be dropped while the device is idle and reopened with [`DevicePath::init`](crate::DevicePath::init).
+    fn is_reopenable(&self) -> bool {
+        false
+    }
+}
+
+impl DeviceBackend for DeviceHandle {
+    fn device_info(&self) -> Result<drm_amdgpu_info_device, i32> {
+        DeviceHandle::device_info(self)
+    }
+
+    fn memory_info(&self) -> Result<drm_amdgpu_memory_info, i32> {
+        DeviceHandle::memory_info(self)
+    }
+
+    fn vram_gtt_info(&self) -> Result<drm_amdgpu_info_vram_gtt, i32> {
+        DeviceHandle::vram_gtt_info(self)
+    }
+
+    fn vram_usage_info(&self) -> Result<u64, i32> {
+        DeviceHandle::vram_usage_info(self)
+    }
+
+    fn vis_vram_usage_info(&self) -> Result<u64, i32> {
+        DeviceHandle::vis_vram_usage_info(self)
+    }
+
+    fn gtt_usage_info(&self) -> Result<u64, i32> {
+        DeviceHandle::gtt_usage_info(self)
+    }
+
+    fn read_mm_registers(&self, offset: u32) -> Result<u32, i32> {
+        DeviceHandle::read_mm_registers(self, offset)
+    }
+
+    fn sensor_info(&self, sensor_type: SENSOR_TYPE) -> Result<u32, i32> {
+        DeviceHandle::sensor_info(self, sensor_type)
+    }
+
+    fn get_hw_ip_info(&self, ip_type: HW_IP_TYPE) -> Result<HwIpInfo, i32> {
+        DeviceHandle::get_hw_ip_info(self, ip_type)
+    }
+
+    fn get_min_max_gpu_clock(&self) -> Option<(u32, u32)> {
+        DeviceHandle::get_min_max_gpu_clock(self)
+    }
+
+    fn get_min_max_memory_clock(&self) -> Option<(u32, u32)> {
+        DeviceHandle::get_min_max_memory_clock(self)
+    }
+
+    fn get_video_caps_info(&self, cap_type: CAP_TYPE) -> Result<VideoCapsInfo, i32> {
+        DeviceHandle::get_video_caps_info(self, cap_type)
+    }
+
+    fn get_vbios_info(&self) -> Result<VbiosInfo, i32> {
+        DeviceHandle::get_vbios_info(self)
+    }
+
+    fn get_drm_version_struct(&self) -> Result<drmVersion, i32> {
+        DeviceHandle::get_drm_version_struct(self)
+    }
+
+    fn is_reopenable(&self) -> bool {
+        true
+    }
+}
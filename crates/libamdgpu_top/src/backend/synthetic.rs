@@ -0,0 +1,205 @@
+use crate::drmVersion;
+use crate::AMDGPU::{
+    drm_amdgpu_info_device,
+    drm_amdgpu_info_vram_gtt,
+    drm_amdgpu_memory_info,
+    HW_IP::{HW_IP_TYPE, HwIpInfo},
+    SENSOR_INFO::SENSOR_TYPE,
+    VBIOS::VbiosInfo,
+    VIDEO_CAPS::{CAP_TYPE, VideoCapsInfo},
+};
+use super::DeviceBackend;
+use std::sync::{Arc, Mutex};
+
+const EINVAL: i32 = 22;
+
+/// Values returned by [`SyntheticBackend`].
+#[derive(Clone)]
+pub struct SyntheticState {
+    pub device_info: drm_amdgpu_info_device,
+    pub memory_info: drm_amdgpu_memory_info,
+    /// (offset, value)
+    pub registers: Vec<(u32, u32)>,
+    pub sclk: Option<u32>, // MHz
+    pub mclk: Option<u32>, // MHz
+    pub vddnb: Option<u32>, // mV
+    pub vddgfx: Option<u32>, // mV
+    pub hw_ip_info_list: Vec<HwIpInfo>,
+    pub min_max_gpu_clock: Option<(u32, u32)>,
+    pub min_max_memory_clock: Option<(u32, u32)>,
+    pub decode: Option<VideoCapsInfo>,
+    pub encode: Option<VideoCapsInfo>,
+    pub vbios: Option<VbiosInfo>,
+}
+
+impl SyntheticState {
+    pub fn new(device_info: drm_amdgpu_info_device, memory_info: drm_amdgpu_memory_info) -> Self {
+        Self {
+            device_info,
+            memory_info,
+            registers: Vec::new(),
+            sclk: None,
+            mclk: None,
+            vddnb: None,
+            vddgfx: None,
+            hw_ip_info_list: Vec::new(),
+            min_max_gpu_clock: None,
+            min_max_memory_clock: None,
+            decode: None,
+            encode: None,
+            vbios: None,
+        }
+    }
+
+    pub fn set_register(&mut self, offset: u32, value: u32) {
+        if let Some((_, v)) = self.registers.iter_mut().find(|(o, _)| *o == offset) {
+            *v = value;
+        } else {
+            self.registers.push((offset, value));
+        }
+    }
+}
+
+pub type SyntheticScript = Box<dyn FnMut(&mut SyntheticState) + Send>;
+
+/// A backend without a GPU.
+/// The script is called before every query, so the state can evolve over time.
+pub struct SyntheticBackend {
+    state: Arc<Mutex<SyntheticState>>,
+    script: Mutex<Option<SyntheticScript>>,
+}
+
+impl SyntheticBackend {
+    pub fn new(state: SyntheticState) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            script: Mutex::new(None),
+        }
+    }
+
+    pub fn with_script<F: FnMut(&mut SyntheticState) + Send + 'static>(self, script: F) -> Self {
+        *self.script.lock().unwrap() = Some(Box::new(script));
+
+        self
+    }
+
+    /// Returns the shared state, to change the values from outside.
+    pub fn state(&self) -> Arc<Mutex<SyntheticState>> {
+        self.state.clone()
+    }
+
+    fn query<T, F: FnOnce(&SyntheticState) -> Option<T>>(&self, f: F) -> Result<T, i32> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(script) = self.script.lock().unwrap().as_mut() {
+            script(&mut state);
+        }
+
+        f(&state).ok_or(-EINVAL)
+    }
+}
+
+impl DeviceBackend for SyntheticBackend {
+    fn device_info(&self) -> Result<drm_amdgpu_info_device, i32> {
+        self.query(|s| Some(s.device_info))
+    }
+
+    fn memory_info(&self) -> Result<drm_amdgpu_memory_info, i32> {
+        self.query(|s| Some(s.memory_info))
+    }
+
+    fn vram_gtt_info(&self) -> Result<drm_amdgpu_info_vram_gtt, i32> {
+        self.query(|s| Some(drm_amdgpu_info_vram_gtt {
+            vram_size: s.memory_info.vram.usable_heap_size,
+            vram_cpu_accessible_size: s.memory_info.cpu_accessible_vram.usable_heap_size,
+            gtt_size: s.memory_info.gtt.usable_heap_size,
+        }))
+    }
+
+    fn vram_usage_info(&self) -> Result<u64, i32> {
+        self.query(|s| Some(s.memory_info.vram.heap_usage))
+    }
+
+    fn vis_vram_usage_info(&self) -> Result<u64, i32> {
+        self.query(|s| Some(s.memory_info.cpu_accessible_vram.heap_usage))
+    }
+
+    fn gtt_usage_info(&self) -> Result<u64, i32> {
+        self.query(|s| Some(s.memory_info.gtt.heap_usage))
+    }
+
+    fn read_mm_registers(&self, offset: u32) -> Result<u32, i32> {
+        self.query(|s| s.registers.iter().find(|(o, _)| *o == offset).map(|(_, v)| *v))
+    }
+
+    fn sensor_info(&self, sensor_type: SENSOR_TYPE) -> Result<u32, i32> {
+        self.query(|s| match sensor_type {
+            SENSOR_TYPE::GFX_SCLK => s.sclk,
+            SENSOR_TYPE::GFX_MCLK => s.mclk,
+            SENSOR_TYPE::VDDNB => s.vddnb,
+            SENSOR_TYPE::VDDGFX => s.vddgfx,
+            _ => None,
+        })
+    }
+
+    fn get_hw_ip_info(&self, ip_type: HW_IP_TYPE) -> Result<HwIpInfo, i32> {
+        self.query(|s| s.hw_ip_info_list
+            .iter()
+            .find(|ip| ip.ip_type as u32 == ip_type as u32)
+            .cloned()
+        )
+    }
+
+    fn get_min_max_gpu_clock(&self) -> Option<(u32, u32)> {
+        self.query(|s| s.min_max_gpu_clock).ok()
+    }
+
+    fn get_min_max_memory_clock(&self) -> Option<(u32, u32)> {
+        self.query(|s| s.min_max_memory_clock).ok()
+    }
+
+    fn get_video_caps_info(&self, cap_type: CAP_TYPE) -> Result<VideoCapsInfo, i32> {
+        self.query(|s| match cap_type {
+            CAP_TYPE::DECODE => s.decode,
+            CAP_TYPE::ENCODE => s.encode,
+            #[allow(unreachable_patterns)]
+            _ => None,
+        })
+    }
+
+    fn get_vbios_info(&self) -> Result<VbiosInfo, i32> {
+        self.query(|s| s.vbios.clone())
+    }
+
+    fn get_drm_version_struct(&self) -> Result<drmVersion, i32> {
+        Err(-EINVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AMDGPU::CHIP_CLASS;
+    use crate::stat::{PCType, PerfCounter};
+
+    #[test]
+    fn test_synthetic_perf_counter() {
+        // SAFETY: both are plain C structs of integers
+        let state = unsafe { SyntheticState::new(std::mem::zeroed(), std::mem::zeroed()) };
+        let mut count = 0u32;
+        let backend = SyntheticBackend::new(state).with_script(move |s| {
+            // busy on every other read
+            count += 1;
+            s.set_register(PCType::GRBM.offset(), if count % 2 == 0 { u32::MAX } else { 0 });
+        });
+        let mut grbm = PerfCounter::new_with_chip_class(PCType::GRBM, CHIP_CLASS::GFX10);
+
+        for _ in 0..100 {
+            grbm.read_reg(&backend);
+        }
+
+        grbm.update_pc_usage();
+
+        assert!(grbm.pc_index.iter().all(|pc| pc.usage == 50));
+    }
+}
@@ -0,0 +1,373 @@
+//! Persistent settings for all frontends.
+//!
+//! The config file is read from `$XDG_CONFIG_HOME/amdgpu_top/config.toml`
+//! (`~/.config/amdgpu_top/config.toml`), e.g.:
+//!
+//! ```text
+//! theme = "dark"
+//! update_process_index = 10
+//! fdinfo_sort = "gfx"
+//! gpu_metrics = false
+//!
+//! # only when the device is selected
+//! [device."0000:03:00.0"]
+//! no_pc = true
+//! ```
+//!
+//! The top-level settings apply to all devices, and the `[device."<PCI bus>"]` sections
+//! override them for the selected device. The command-line flags override both.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context};
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{GuiWgpuBackend, PCI};
+use crate::stat::FdInfoSortType;
+
+const CONFIG_DIR: &str = "amdgpu_top";
+const CONFIG_FILE: &str = "config.toml";
+const DEVICE_TABLE: &str = "device";
+
+const SORT_NAMES: &[(&str, FdInfoSortType)] = &[
+    ("pid", FdInfoSortType::PID),
+    ("kfd", FdInfoSortType::KFD),
+    ("vram", FdInfoSortType::VRAM),
+    ("gtt", FdInfoSortType::GTT),
+    ("cpu", FdInfoSortType::CPU),
+    ("gfx", FdInfoSortType::GFX),
+    ("compute", FdInfoSortType::Compute),
+    ("dma", FdInfoSortType::DMA),
+    ("decode", FdInfoSortType::Decode),
+    ("encode", FdInfoSortType::Encode),
+    ("uvd_enc", FdInfoSortType::UvdEnc),
+    ("jpeg", FdInfoSortType::JPEG),
+    ("media", FdInfoSortType::MediaEngine),
+    ("vpe", FdInfoSortType::VPE),
+    ("energy", FdInfoSortType::Energy),
+];
+
+/// Each field is `None` if it is not set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    #[serde(deserialize_with = "non_zero")]
+    pub refresh_period: Option<u64>, // ms, JSON
+    #[serde(deserialize_with = "non_zero")]
+    pub update_process_index: Option<u64>, // sec
+    #[serde(rename = "theme", deserialize_with = "theme")]
+    pub is_dark_mode: Option<bool>, // "theme", TUI, GUI
+    pub no_pc: Option<bool>,
+    pub hide_fdinfo: Option<bool>, // TUI
+    #[serde(rename = "gui_backend", deserialize_with = "gui_backend")]
+    pub gui_wgpu_backend: Option<GuiWgpuBackend>, // "gui_backend"
+    #[serde(deserialize_with = "fdinfo_sort")]
+    pub fdinfo_sort: Option<FdInfoSortType>, // TUI, GUI
+    pub reverse_sort: Option<bool>, // TUI, GUI
+    pub fdinfo_clients: Option<bool>, // TUI
+    pub high_freq: Option<bool>, // TUI
+    pub grbm: Option<bool>, // TUI
+    pub grbm2: Option<bool>, // TUI
+    pub vram: Option<bool>, // TUI
+    pub activity: Option<bool>, // TUI
+    pub sensors: Option<bool>, // TUI
+    pub gpu_metrics: Option<bool>, // TUI
+    pub side_panel: Option<bool>, // GUI
+    pub full_fdinfo_list: Option<bool>, // GUI
+    pub session_stats: Option<bool>,
+}
+
+/// 0 is ignored as with the command-line options
+fn non_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(Some(u64::deserialize(deserializer)?).filter(|v| *v != 0))
+}
+
+fn theme<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "dark" => Ok(Some(true)),
+        "light" => Ok(Some(false)),
+        s => Err(de::Error::custom(format!("expected \"dark\" or \"light\": {s:?}"))),
+    }
+}
+
+fn gui_backend<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<GuiWgpuBackend>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "gl" | "opengl" => Ok(Some(GuiWgpuBackend::Gl)),
+        "vk" | "vulkan" => Ok(Some(GuiWgpuBackend::Vulkan)),
+        s => Err(de::Error::custom(format!("expected \"gl\" or \"vulkan\": {s:?}"))),
+    }
+}
+
+fn fdinfo_sort<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<FdInfoSortType>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+
+    SORT_NAMES
+        .iter()
+        .find_map(|(name, sort)| (*name == s).then_some(Some(*sort)))
+        .ok_or_else(|| de::Error::custom(format!("unknown fdinfo sort: {s:?}")))
+}
+
+macro_rules! bool_keys {
+    ($m:ident) => {
+        $m!(no_pc, hide_fdinfo, reverse_sort, fdinfo_clients, high_freq,
+            grbm, grbm2, vram, activity, sensors, gpu_metrics, side_panel, full_fdinfo_list,
+            session_stats)
+    };
+}
+
+impl Settings {
+    /// The settings of `other` take precedence.
+    pub fn merge(&self, other: &Self) -> Self {
+        macro_rules! merge {
+            ($($key:ident),*) => {
+                Self { $($key: other.$key.or(self.$key),)* }
+            };
+        }
+
+        merge!(
+            refresh_period, update_process_index, is_dark_mode, gui_wgpu_backend, fdinfo_sort,
+            no_pc, hide_fdinfo, reverse_sort, fdinfo_clients, high_freq,
+            grbm, grbm2, vram, activity, sensors, gpu_metrics, side_panel, full_fdinfo_list,
+            session_stats
+        )
+    }
+
+    /// `key = value` pairs of the set fields
+    fn entries(&self) -> Vec<(&'static str, toml_edit::Value)> {
+        let mut entries = Vec::new();
+
+        if let Some(v) = self.refresh_period {
+            entries.push(("refresh_period", (v as i64).into()));
+        }
+
+        if let Some(v) = self.update_process_index {
+            entries.push(("update_process_index", (v as i64).into()));
+        }
+
+        if let Some(v) = self.is_dark_mode {
+            entries.push(("theme", (if v { "dark" } else { "light" }).into()));
+        }
+
+        if let Some(v) = self.gui_wgpu_backend {
+            let s = match v {
+                GuiWgpuBackend::Gl => "gl",
+                GuiWgpuBackend::Vulkan => "vulkan",
+            };
+            entries.push(("gui_backend", s.into()));
+        }
+
+        if let Some(v) = self.fdinfo_sort {
+            if let Some((name, _)) = SORT_NAMES.iter().find(|(_, sort)| *sort == v) {
+                entries.push(("fdinfo_sort", (*name).into()));
+            }
+        }
+
+        macro_rules! push_flag {
+            ($($key:ident),*) => {
+                $(if let Some(v) = self.$key {
+                    entries.push((stringify!($key), v.into()));
+                })*
+            };
+        }
+
+        bool_keys!(push_flag);
+
+        entries
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub global: Settings,
+    pub devices: Vec<(PCI::BUS_INFO, Settings)>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/amdgpu_top/config.toml` or `$HOME/.config/amdgpu_top/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let var = |key: &str| std::env::var_os(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+        let config_dir = var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))?;
+
+        Some(config_dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Returns the default config if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let s = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+
+        Self::parse(&s).with_context(|| format!("Invalid config file: {path:?}"))
+    }
+
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut table: toml::value::Table = toml::from_str(s)?;
+        let devices = match table.remove(DEVICE_TABLE) {
+            Some(toml::Value::Table(devices)) => devices,
+            Some(_) => bail!("expected [device.\"<PCI bus>\"]"),
+            None => Default::default(),
+        };
+        let mut config = Self {
+            global: toml::Value::Table(table).try_into()?,
+            devices: Vec::new(),
+        };
+
+        for (name, settings) in devices {
+            let pci: PCI::BUS_INFO = name
+                .parse()
+                .map_err(|_| anyhow!("invalid PCI bus: [device.{name:?}]"))?;
+
+            if config.devices.iter().any(|(p, _)| *p == pci) {
+                bail!("duplicate device section: {pci}");
+            }
+
+            let settings = settings.try_into().with_context(|| format!("[device.{name:?}]"))?;
+
+            config.devices.push((pci, settings));
+        }
+
+        Ok(config)
+    }
+
+    /// The top-level settings merged with the device section
+    pub fn settings(&self, pci: Option<PCI::BUS_INFO>) -> Settings {
+        let device = pci.and_then(|pci| self.devices.iter().find(|(p, _)| *p == pci));
+
+        match device {
+            Some((_, settings)) => self.global.merge(settings),
+            None => self.global.clone(),
+        }
+    }
+}
+
+/// Set the fields of `settings` in the config file `s`, keeping the other lines and comments.
+/// They are set in the device section if it exists, otherwise at the top level.
+fn update_settings(
+    s: &str,
+    pci: Option<PCI::BUS_INFO>,
+    settings: &Settings,
+) -> anyhow::Result<String> {
+    Config::parse(s)?;
+
+    let mut doc: toml_edit::DocumentMut = s.parse()?;
+    // the key as it is written, e.g. "0000:03:00.0"
+    let device_key = pci.and_then(|pci| {
+        doc.get(DEVICE_TABLE)?
+            .as_table_like()?
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .find(|key| key.parse::<PCI::BUS_INFO>().is_ok_and(|p| p == pci))
+    });
+    let table: &mut dyn toml_edit::TableLike = match &device_key {
+        Some(key) => doc
+            .get_mut(DEVICE_TABLE)
+            .and_then(|devices| devices.get_mut(key.as_str()))
+            .and_then(|item| item.as_table_like_mut())
+            .ok_or_else(|| anyhow!("expected [device.{key:?}]"))?,
+        None => doc.as_table_mut(),
+    };
+
+    for (key, val) in settings.entries() {
+        table.insert(key, toml_edit::Item::Value(val));
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Write the set fields of `settings` to the config file, keeping the other lines.
+/// They are written to the device section if it exists, otherwise to the top level.
+pub fn save_settings<P: AsRef<Path>>(
+    path: P,
+    pci: Option<PCI::BUS_INFO>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let s = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?
+    } else {
+        String::new()
+    };
+    let s = update_settings(&s, pci, settings).with_context(|| format!("Invalid config file: {path:?}"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {dir:?}"))?;
+    }
+
+    fs::write(path, s).with_context(|| format!("Failed to write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let s = concat!(
+            "theme = \"dark\"\n",
+            "refresh_period = 500\n",
+            "fdinfo_sort = \"gfx\"\n",
+            "gpu_metrics = false\n",
+            "\n",
+            "[device.\"0000:03:00.0\"]\n",
+            "no_pc = true\n",
+            "theme = \"light\"\n",
+        );
+        let config = Config::parse(s).unwrap();
+        let pci: PCI::BUS_INFO = "0000:03:00.0".parse().unwrap();
+
+        assert_eq!(config.global.is_dark_mode, Some(true));
+        assert_eq!(config.global.refresh_period, Some(500));
+        assert_eq!(config.global.fdinfo_sort, Some(FdInfoSortType::GFX));
+
+        let settings = config.settings(Some(pci));
+
+        assert_eq!(settings.is_dark_mode, Some(false));
+        assert_eq!(settings.no_pc, Some(true));
+        assert_eq!(settings.gpu_metrics, Some(false));
+        assert_eq!(config.settings(None).no_pc, None);
+
+        let entries = settings.entries();
+        assert!(entries.iter().any(|(key, v)| *key == "theme" && v.as_str() == Some("light")));
+        assert!(entries.iter().any(|(key, v)| *key == "fdinfo_sort" && v.as_str() == Some("gfx")));
+
+        assert!(Config::parse("foo = 1\n").is_err());
+        assert!(Config::parse("[gui]\n").is_err());
+        assert!(Config::parse("theme = \"blue\"\n").is_err());
+        assert!(Config::parse("refresh_period = 500ms\n").is_err());
+        assert!(Config::parse("theme = dark\n").is_err());
+    }
+
+    #[test]
+    fn test_update_settings() {
+        let s = concat!(
+            "# comment\n",
+            "no_pc = true\n",
+            "\n",
+            "[device.\"0000:03:00.0\"]\n",
+            "theme = \"dark\" # comment\n",
+            "sensors = false\n",
+        );
+        let settings = Settings { is_dark_mode: Some(false), no_pc: Some(false), ..Default::default() };
+        let pci: PCI::BUS_INFO = "0000:03:00.0".parse().unwrap();
+
+        let out = update_settings(s, None, &settings).unwrap();
+        assert!(out.starts_with("# comment\n"));
+
+        let global = Config::parse(&out).unwrap();
+        assert_eq!(global.global.no_pc, Some(false));
+        assert_eq!(global.settings(Some(pci)).is_dark_mode, Some(true));
+
+        let device = Config::parse(&update_settings(s, Some(pci), &settings).unwrap()).unwrap();
+        assert_eq!(device.global.no_pc, Some(true));
+        assert_eq!(device.settings(Some(pci)).is_dark_mode, Some(false));
+        assert_eq!(device.settings(Some(pci)).sensors, Some(false));
+
+        let new = Config::parse(&update_settings("", Some(pci), &settings).unwrap()).unwrap();
+        assert_eq!(new.global.is_dark_mode, Some(false));
+        assert!(update_settings("theme = dark\n", None, &settings).is_err());
+    }
+}
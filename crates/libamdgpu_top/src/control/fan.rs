@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use anyhow::{anyhow, bail};
+use crate::PCI;
+use crate::stat::{FanMode, FanStat};
+use super::{hwmon_path, sysfs_path, write_sysfs, OdRange, OverdriveTable};
+
+// ref: https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html#fan-curve
+// ref: drivers/gpu/drm/amd/pm/swsmu/smu13/smu_v13_0_0_ppt.c
+
+const FAN_CTRL: &str = "gpu_od/fan_ctrl";
+const FAN_CURVE: &str = "fan_curve";
+const ACOUSTIC_LIMIT: &str = "acoustic_limit_rpm_threshold";
+const FAN_ZERO_RPM: &str = "fan_zero_rpm_enable";
+
+/// A point of `gpu_od/fan_ctrl/fan_curve`, e.g. "0: 25C 20%"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FanCurvePoint {
+    /// hotspot temperature (C)
+    pub temp: i64,
+    /// fan speed (%)
+    pub speed: i64,
+}
+
+/// `gpu_od/fan_ctrl/fan_curve` (RDNA3 and later)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanCurve {
+    pub points: Vec<FanCurvePoint>,
+    pub temp_range: Option<OdRange>,
+    pub speed_range: Option<OdRange>,
+}
+
+impl FanCurve {
+    fn from_table(table: &OverdriveTable) -> Option<Self> {
+        let points: Vec<FanCurvePoint> = table.section("OD_FAN_CURVE")?.entries
+            .iter()
+            .filter_map(|entry| match entry.values[..] {
+                [temp, speed] => Some(FanCurvePoint { temp: temp.value, speed: speed.value }),
+                _ => None,
+            })
+            .collect();
+
+        if points.is_empty() { return None }
+
+        Some(Self {
+            points,
+            temp_range: table.range("FAN_CURVE(hotspot temp)").cloned(),
+            speed_range: table.range("FAN_CURVE(fan speed)").cloned(),
+        })
+    }
+
+    /// Validate the new points against the number of the points and OD_RANGE.
+    pub fn validate(&self, points: &[FanCurvePoint]) -> anyhow::Result<()> {
+        if points.len() != self.points.len() {
+            bail!("the fan curve has {} points, but {} points are given", self.points.len(), points.len());
+        }
+
+        for (i, point) in points.iter().enumerate() {
+            for (range, v) in [(&self.temp_range, point.temp), (&self.speed_range, point.speed)] {
+                let Some(range) = range else { continue };
+
+                if !range.contains(v) {
+                    bail!("point {i}: {v} is out of range of {} ({} - {})", range.name, range.min, range.max);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The points of the fan curve, e.g. "25:20,45:30,60:45,75:70,90:100" (C:%)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanCurvePoints(pub Vec<FanCurvePoint>);
+
+impl FromStr for FanCurvePoints {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',').map(|point| {
+            let (temp, speed) = point
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid fan curve point: {point:?} (expected <Temp>:<Speed>)"))?;
+            let parse = |v: &str| v.trim().trim_end_matches(['C', '%']).parse::<i64>()
+                .map_err(|_| anyhow!("invalid fan curve point: {point:?}"));
+
+            Ok(FanCurvePoint { temp: parse(temp)?, speed: parse(speed)? })
+        }).collect::<anyhow::Result<_>>().map(Self)
+    }
+}
+
+/// A value of `gpu_od/fan_ctrl/`, e.g. `acoustic_limit_rpm_threshold`, `fan_zero_rpm_enable`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanCtrlValue {
+    pub value: i64,
+    pub range: Option<OdRange>,
+}
+
+impl FanCtrlValue {
+    fn from_table(table: &OverdriveTable) -> Option<Self> {
+        let value = table.sections.first()?.entries.first()?.values.first()?.value;
+
+        Some(Self { value, range: table.ranges.first().cloned() })
+    }
+}
+
+/// Set the fan speed (`pwm1`, `pwm1_enable`), and the fan curve of `gpu_od/fan_ctrl/`.
+#[derive(Debug, Clone)]
+pub struct FanControl {
+    pub pci_bus: PCI::BUS_INFO,
+    hwmon_path: Option<PathBuf>,
+    fan_ctrl_path: PathBuf,
+}
+
+impl FanControl {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> Self {
+        let sysfs_path = sysfs_path(pci_bus);
+        let hwmon_path = hwmon_path(&sysfs_path);
+
+        Self { pci_bus, hwmon_path, fan_ctrl_path: sysfs_path.join(FAN_CTRL) }
+    }
+
+    pub fn fan_stat(&self) -> Option<FanStat> {
+        FanStat::from_hwmon_path(self.hwmon_path.as_ref()?)
+    }
+
+    /// Set `pwm1_enable` to manual, and `pwm1` (`pwm1_min`-`pwm1_max`).
+    /// RDNA3 and later do not support the manual mode, use the fan curve instead.
+    pub fn set_manual_pwm(&self, pwm: u32) -> anyhow::Result<()> {
+        let (Some(hwmon_path), Some(fan)) = (&self.hwmon_path, self.fan_stat()) else {
+            bail!("{}: fan control is not supported", self.pci_bus);
+        };
+
+        if !(fan.pwm_min..=fan.pwm_max).contains(&pwm) {
+            bail!("{}: PWM {pwm} is out of range ({}-{})", self.pci_bus, fan.pwm_min, fan.pwm_max);
+        }
+
+        write_sysfs(hwmon_path.join("pwm1_enable"), &FanMode::Manual.as_pwm1_enable().to_string())?;
+        write_sysfs(hwmon_path.join("pwm1"), &pwm.to_string())
+    }
+
+    /// Set the fan speed in percent of `pwm1_max`.
+    pub fn set_manual_speed(&self, percent: u32) -> anyhow::Result<()> {
+        let Some(fan) = self.fan_stat() else {
+            bail!("{}: fan control is not supported", self.pci_bus);
+        };
+
+        if percent > 100 {
+            bail!("{}: {percent}% is out of range (0-100%)", self.pci_bus);
+        }
+
+        self.set_manual_pwm((fan.pwm_max * percent / 100).max(fan.pwm_min))
+    }
+
+    /// Restore the automatic fan control, and the default fan curve.
+    pub fn set_auto(&self) -> anyhow::Result<()> {
+        let fan_mode = self.fan_stat().and_then(|fan| fan.mode);
+
+        if fan_mode.is_none() && self.fan_curve().is_none() {
+            bail!("{}: fan control is not supported", self.pci_bus);
+        }
+
+        if let (Some(hwmon_path), Some(mode)) = (&self.hwmon_path, fan_mode) {
+            if mode != FanMode::Auto {
+                write_sysfs(hwmon_path.join("pwm1_enable"), &FanMode::Auto.as_pwm1_enable().to_string())?;
+            }
+        }
+
+        if self.fan_curve().is_some() {
+            self.reset_fan_curve()?;
+        }
+
+        Ok(())
+    }
+
+    fn read_table(&self, name: &str) -> Option<OverdriveTable> {
+        let s = fs::read_to_string(self.fan_ctrl_path.join(name)).ok()?;
+
+        Some(OverdriveTable::parse(&s))
+    }
+
+    /// Write the commands, and commit them with "c".
+    fn write_commands(&self, name: &str, commands: &[String]) -> anyhow::Result<()> {
+        let path = self.fan_ctrl_path.join(name);
+
+        for cmd in commands {
+            write_sysfs(&path, cmd)?;
+        }
+
+        write_sysfs(&path, "c")
+    }
+
+    pub fn fan_curve(&self) -> Option<FanCurve> {
+        FanCurve::from_table(&self.read_table(FAN_CURVE)?)
+    }
+
+    /// Write the points of the fan curve.
+    /// If the driver rejects a point, the fan curve is restored to the state before.
+    pub fn set_fan_curve(&self, points: &[FanCurvePoint]) -> anyhow::Result<()> {
+        let Some(curve) = self.fan_curve() else {
+            bail!("{}: fan curve is not supported", self.pci_bus);
+        };
+
+        curve.validate(points).map_err(|err| anyhow!("{}: {err}", self.pci_bus))?;
+
+        let commands = |points: &[FanCurvePoint]| -> Vec<String> {
+            points.iter().enumerate().map(|(i, p)| format!("{i} {} {}", p.temp, p.speed)).collect()
+        };
+
+        if let Err(err) = self.write_commands(FAN_CURVE, &commands(points)) {
+            let _ = self.write_commands(FAN_CURVE, &commands(&curve.points));
+
+            return Err(err.context(format!("{}: the fan curve is rolled back", self.pci_bus)));
+        }
+
+        Ok(())
+    }
+
+    pub fn reset_fan_curve(&self) -> anyhow::Result<()> {
+        self.write_commands(FAN_CURVE, &["r".to_string()])
+    }
+
+    /// `acoustic_limit_rpm_threshold` (RPM)
+    pub fn acoustic_limit(&self) -> Option<FanCtrlValue> {
+        FanCtrlValue::from_table(&self.read_table(ACOUSTIC_LIMIT)?)
+    }
+
+    pub fn set_acoustic_limit(&self, rpm: u32) -> anyhow::Result<()> {
+        self.set_value(ACOUSTIC_LIMIT, self.acoustic_limit(), rpm as i64)
+    }
+
+    /// `fan_zero_rpm_enable`
+    pub fn zero_rpm(&self) -> Option<FanCtrlValue> {
+        FanCtrlValue::from_table(&self.read_table(FAN_ZERO_RPM)?)
+    }
+
+    pub fn set_zero_rpm(&self, enable: bool) -> anyhow::Result<()> {
+        self.set_value(FAN_ZERO_RPM, self.zero_rpm(), enable as i64)
+    }
+
+    fn set_value(&self, name: &str, current: Option<FanCtrlValue>, value: i64) -> anyhow::Result<()> {
+        let Some(current) = current else {
+            bail!("{}: {name} is not supported", self.pci_bus);
+        };
+
+        if let Some(range) = current.range.filter(|range| !range.contains(value)) {
+            bail!("{}: {value} is out of range of {} ({} - {})", self.pci_bus, range.name, range.min, range.max);
+        }
+
+        self.write_commands(name, &[value.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fan_curve() {
+        let table = OverdriveTable::parse("\
+OD_FAN_CURVE:
+0: 25C 15%
+1: 45C 30%
+2: 60C 45%
+OD_RANGE:
+FAN_CURVE(hotspot temp): 25C 100C
+FAN_CURVE(fan speed): 15% 100%
+");
+        let curve = FanCurve::from_table(&table).unwrap();
+        let points: FanCurvePoints = "25:20, 50C:35%, 70:60".parse().unwrap();
+
+        assert_eq!(curve.points[1], FanCurvePoint { temp: 45, speed: 30 });
+        assert_eq!(curve.speed_range.as_ref().map(|r| r.min.value), Some(15));
+        assert!(curve.validate(&points.0).is_ok());
+        assert!(curve.validate(&points.0[..2]).is_err());
+        assert!(curve.validate(&[FanCurvePoint { temp: 25, speed: 10 }; 3]).is_err());
+        assert!("25-20".parse::<FanCurvePoints>().is_err());
+    }
+}
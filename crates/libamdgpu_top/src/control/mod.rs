@@ -0,0 +1,41 @@
+//! Write access to the power management interfaces of amdgpu (sysfs, hwmon).
+//!
+//! Writing to them requires root (or the write permission of the files),
+//! and the frontends only expose it with `--enable-control`.
+//! ref: <https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html>
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use crate::{root, PCI};
+
+mod power;
+pub use power::*;
+
+mod overdrive;
+pub use overdrive::*;
+
+mod fan;
+pub use fan::*;
+
+fn sysfs_path(pci_bus: PCI::BUS_INFO) -> PathBuf {
+    root::sys_path(format!("bus/pci/devices/{pci_bus}"))
+}
+
+fn hwmon_path(sysfs_path: &Path) -> Option<PathBuf> {
+    fs::read_dir(sysfs_path.join("hwmon")).ok()?
+        .find_map(|dir_entry| Some(dir_entry.ok()?.path()))
+}
+
+/// Write the value to the sysfs file, with a readable error for the common failures.
+pub(crate) fn write_sysfs<P: AsRef<Path>>(path: P, value: &str) -> anyhow::Result<()> {
+    let path = path.as_ref();
+
+    fs::write(path, value).map_err(|err| match err.kind() {
+        io::ErrorKind::PermissionDenied => anyhow!("permission denied: writing to {path:?} requires root"),
+        io::ErrorKind::NotFound => anyhow!("not supported: {path:?} does not exist"),
+        io::ErrorKind::InvalidInput => anyhow!("the driver rejected {value:?} for {path:?}"),
+        _ => anyhow!("failed to write {value:?} to {path:?}: {err}"),
+    })
+}
@@ -0,0 +1,555 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Context};
+use crate::{PCI, PpFeatureMask};
+use super::{sysfs_path, write_sysfs};
+
+// ref: https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html#pp-od-clk-voltage
+// ref: drivers/gpu/drm/amd/pm/swsmu/smu13/smu_v13_0_0_ppt.c (gpu_od/fan_ctrl)
+
+const PP_OD_CLK_VOLTAGE: &str = "pp_od_clk_voltage";
+const GPU_OD: &str = "gpu_od";
+const OD_RANGE: &str = "OD_RANGE";
+
+/// The sections of `pp_od_clk_voltage` that can be edited, and the command to edit them
+const EDITABLE_SECTIONS: &[(&str, &str)] = &[
+    ("OD_SCLK", "s"),
+    ("OD_MCLK", "m"),
+    ("OD_VDDC_CURVE", "vc"),
+    ("OD_VDDGFX_OFFSET", "vo"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OdUnit {
+    MHz,
+    MilliVolt,
+    Celsius,
+    Percent,
+    None,
+}
+
+impl OdUnit {
+    fn from_suffix(s: &str) -> Option<Self> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            "mhz" => Self::MHz,
+            "mv" => Self::MilliVolt,
+            "c" => Self::Celsius,
+            "%" => Self::Percent,
+            "" => Self::None,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::MHz => "MHz",
+            Self::MilliVolt => "mV",
+            Self::Celsius => "C",
+            Self::Percent => "%",
+            Self::None => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OdValue {
+    pub value: i64,
+    pub unit: OdUnit,
+}
+
+impl FromStr for OdValue {
+    type Err = ();
+
+    /// e.g. "2100Mhz", "800mV", "-450mv", "25C", "20%", "2900"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = s
+            .char_indices()
+            .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+            .map_or(s.len(), |(i, _)| i);
+        let (value, unit) = s.split_at(pos);
+
+        Ok(Self {
+            value: value.parse().map_err(|_| ())?,
+            unit: OdUnit::from_suffix(unit).ok_or(())?,
+        })
+    }
+}
+
+impl fmt::Display for OdValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self.unit {
+            OdUnit::None => self.value.to_string(),
+            unit => format!("{} {}", self.value, unit.as_str()),
+        };
+
+        f.pad(&s)
+    }
+}
+
+/// e.g. "1: 2100Mhz", "0: 800MHz 712mV", "0mV" (OD_VDDGFX_OFFSET)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OdEntry {
+    pub index: Option<u32>,
+    pub values: Vec<OdValue>,
+}
+
+/// e.g. "OD_SCLK", "OD_VDDC_CURVE", "OD_FAN_CURVE"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OdSection {
+    pub name: String,
+    pub entries: Vec<OdEntry>,
+}
+
+impl OdSection {
+    /// The command to edit the section, e.g. "s" for OD_SCLK
+    pub fn command(&self) -> Option<&'static str> {
+        EDITABLE_SECTIONS.iter().find_map(|(name, cmd)| (*name == self.name).then_some(*cmd))
+    }
+
+    pub fn entry(&self, index: Option<u32>) -> Option<&OdEntry> {
+        self.entries.iter().find(|entry| entry.index == index)
+    }
+}
+
+/// e.g. "SCLK: 800Mhz 2150Mhz", "VDDC_CURVE_VOLT[0]: 750mV 1200mV"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OdRange {
+    pub name: String,
+    pub min: OdValue,
+    pub max: OdValue,
+}
+
+impl OdRange {
+    pub fn contains(&self, value: i64) -> bool {
+        (self.min.value..=self.max.value).contains(&value)
+    }
+}
+
+/// The overdrive table read from `pp_od_clk_voltage` or a file in `gpu_od/`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverdriveTable {
+    pub sections: Vec<OdSection>,
+    /// OD_RANGE
+    pub ranges: Vec<OdRange>,
+}
+
+impl OverdriveTable {
+    /// Returns `None` if overdrive is not enabled (`amdgpu.ppfeaturemask`) or not supported.
+    pub fn from_sysfs<P: AsRef<Path>>(sysfs_path: P) -> Option<Self> {
+        let s = fs::read_to_string(sysfs_path.as_ref().join(PP_OD_CLK_VOLTAGE)).ok()?;
+
+        Some(Self::parse(&s)).filter(|table| !table.sections.is_empty())
+    }
+
+    pub fn parse(s: &str) -> Self {
+        let mut table = Self::default();
+        let mut in_range = false;
+
+        for line in s.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some(name) = line.strip_suffix(':').filter(|name| is_section_name(name)) {
+                in_range = name == OD_RANGE;
+
+                if !in_range {
+                    table.sections.push(OdSection { name: name.to_string(), entries: Vec::new() });
+                }
+
+                continue;
+            }
+
+            if in_range {
+                let Some((name, values)) = line.split_once(':') else { continue };
+                let values: Vec<OdValue> = values.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+
+                if let [min, max] = values[..] {
+                    table.ranges.push(OdRange { name: name.trim().to_string(), min, max });
+                }
+
+                continue;
+            }
+
+            let Some(section) = table.sections.last_mut() else { continue };
+            let (index, values) = match line.split_once(':') {
+                Some((index, values)) => (index.trim().parse().ok(), values),
+                None => (None, line),
+            };
+            let values: Vec<OdValue> = values.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+
+            if !values.is_empty() {
+                section.entries.push(OdEntry { index, values });
+            }
+        }
+
+        table
+    }
+
+    pub fn section(&self, name: &str) -> Option<&OdSection> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    pub fn range(&self, name: &str) -> Option<&OdRange> {
+        self.ranges.iter().find(|range| range.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The range for the `pos`-th value of the entry
+    pub fn range_for(&self, section: &str, index: Option<u32>, pos: usize) -> Option<&OdRange> {
+        let i = index.unwrap_or(0);
+        let name = match (section, pos) {
+            ("OD_SCLK", 0) => "SCLK".to_string(),
+            ("OD_MCLK", 0) => "MCLK".to_string(),
+            // Polaris and older: "<level>: <clock> <voltage>"
+            ("OD_SCLK" | "OD_MCLK", 1) => "VDDC".to_string(),
+            ("OD_VDDC_CURVE", 0) => format!("VDDC_CURVE_SCLK[{i}]"),
+            ("OD_VDDC_CURVE", 1) => format!("VDDC_CURVE_VOLT[{i}]"),
+            ("OD_VDDGFX_OFFSET", 0) => "VDDGFX_OFFSET".to_string(),
+            _ => return None,
+        };
+
+        self.range(&name)
+    }
+
+    /// The commands to restore the entries changed in `self` to `other`
+    fn diff_commands(&self, other: &Self) -> Vec<String> {
+        other.sections
+            .iter()
+            .filter_map(|section| Some((section, section.command()?)))
+            .flat_map(|(section, cmd)| section.entries.iter().filter_map(move |entry| {
+                let changed = self.section(&section.name)?.entry(entry.index)? != entry;
+
+                changed.then(|| OdEdit {
+                    section: section.name.clone(),
+                    index: entry.index,
+                    values: entry.values.iter().map(|v| v.value).collect(),
+                }.command_with(cmd))
+            }))
+            .collect()
+    }
+}
+
+impl fmt::Display for OverdriveTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for section in &self.sections {
+            writeln!(f, "{}:", section.name)?;
+
+            for entry in &section.entries {
+                let values: Vec<String> = entry.values.iter().map(|v| format!("{v:>9}")).collect();
+
+                match entry.index {
+                    Some(i) => writeln!(f, "  {i:>2}: {}", values.join(" "))?,
+                    None => writeln!(f, "      {}", values.join(" "))?,
+                }
+            }
+        }
+
+        if !self.ranges.is_empty() {
+            writeln!(f, "{OD_RANGE}:")?;
+        }
+
+        for range in &self.ranges {
+            writeln!(f, "  {:<24} {:>9} - {:>9}", format!("{}:", range.name), range.min, range.max)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// e.g. "OD_SCLK", "FAN_ZERO_RPM_ENABLE" (`gpu_od/fan_ctrl/fan_zero_rpm_enable`)
+fn is_section_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The tables in `gpu_od/` (RDNA3 and later), e.g. "fan_ctrl/fan_curve"
+pub fn gpu_od_tables<P: AsRef<Path>>(sysfs_path: P) -> Vec<(String, OverdriveTable)> {
+    let base = sysfs_path.as_ref().join(GPU_OD);
+    let mut tables = Vec::new();
+    let Ok(dirs) = fs::read_dir(&base) else { return tables };
+
+    for dir in dirs.filter_map(|d| Some(d.ok()?.path())) {
+        let Ok(files) = fs::read_dir(&dir) else { continue };
+
+        for path in files.filter_map(|f| Some(f.ok()?.path())) {
+            let Ok(s) = fs::read_to_string(&path) else { continue };
+            let table = OverdriveTable::parse(&s);
+            let Ok(name) = path.strip_prefix(&base) else { continue };
+
+            if !table.sections.is_empty() {
+                tables.push((name.to_string_lossy().to_string(), table));
+            }
+        }
+    }
+
+    tables.sort_by(|a, b| a.0.cmp(&b.0));
+
+    tables
+}
+
+/// A change of an entry, e.g. "s 1 2500", "vc 2 2100 1100", "vo -50"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OdEdit {
+    pub section: String,
+    pub index: Option<u32>,
+    pub values: Vec<i64>,
+}
+
+impl OdEdit {
+    fn command_with(&self, cmd: &str) -> String {
+        let mut s = cmd.to_string();
+
+        if let Some(index) = self.index {
+            s.push_str(&format!(" {index}"));
+        }
+
+        for v in &self.values {
+            s.push_str(&format!(" {v}"));
+        }
+
+        s
+    }
+}
+
+/// e.g. "OD_SCLK 1: 2500"
+impl fmt::Display for OdEdit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.section)?;
+
+        if let Some(index) = self.index {
+            write!(f, " {index}")?;
+        }
+
+        write!(f, ":")?;
+
+        for v in &self.values {
+            write!(f, " {v}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for OdEdit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let cmd = tokens.next().ok_or_else(|| anyhow!("empty overdrive command"))?;
+        let (section, _) = EDITABLE_SECTIONS
+            .iter()
+            .find(|(_, c)| *c == cmd)
+            .ok_or_else(|| anyhow!("unknown overdrive command: {cmd:?} (expected s, m, vc or vo)"))?;
+        let mut values: Vec<i64> = tokens
+            .map(|v| v.parse().map_err(|_| anyhow!("invalid value: {v:?}")))
+            .collect::<anyhow::Result<_>>()?;
+        let index = if *section == "OD_VDDGFX_OFFSET" {
+            None
+        } else if values.is_empty() {
+            bail!("missing index: {s:?}");
+        } else {
+            Some(u32::try_from(values.remove(0)).map_err(|_| anyhow!("invalid index: {s:?}"))?)
+        };
+
+        if values.is_empty() { bail!("missing value: {s:?}") }
+
+        Ok(Self { section: section.to_string(), index, values })
+    }
+}
+
+/// Stage changes of `pp_od_clk_voltage`, validate them against OD_RANGE and commit them.
+/// The table before the last commit is kept to restore it.
+#[derive(Debug, Clone)]
+pub struct OverdriveEditor {
+    pub pci_bus: PCI::BUS_INFO,
+    path: PathBuf,
+    pub table: OverdriveTable,
+    pub staged: Vec<OdEdit>,
+    /// The table before the last commit
+    pub previous: Option<OverdriveTable>,
+}
+
+impl OverdriveEditor {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> anyhow::Result<Self> {
+        let sysfs_path = sysfs_path(pci_bus);
+        let table = OverdriveTable::from_sysfs(&sysfs_path).ok_or_else(|| {
+            if PpFeatureMask::is_overdrive_enabled() {
+                anyhow!("{pci_bus}: overdrive is not supported")
+            } else {
+                anyhow!("{pci_bus}: overdrive is not enabled (PP_OVERDRIVE_MASK of amdgpu.ppfeaturemask)")
+            }
+        })?;
+
+        Ok(Self {
+            pci_bus,
+            path: sysfs_path.join(PP_OD_CLK_VOLTAGE),
+            table,
+            staged: Vec::new(),
+            previous: None,
+        })
+    }
+
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        let s = fs::read_to_string(&self.path).with_context(|| format!("failed to read {:?}", self.path))?;
+        self.table = OverdriveTable::parse(&s);
+
+        Ok(())
+    }
+
+    /// Validate the change, and replace the staged change of the same entry.
+    pub fn stage(&mut self, edit: OdEdit) -> anyhow::Result<()> {
+        let section = self.table
+            .section(&edit.section)
+            .filter(|section| section.command().is_some())
+            .ok_or_else(|| anyhow!("{}: {} is not supported", self.pci_bus, edit.section))?;
+        let entry = section
+            .entry(edit.index)
+            .ok_or_else(|| anyhow!("{}: {} has no entry {:?}", self.pci_bus, edit.section, edit.index))?;
+
+        if edit.values.len() != entry.values.len() {
+            bail!("{}: {} expects {} value(s)", self.pci_bus, edit.section, entry.values.len());
+        }
+
+        for (pos, v) in edit.values.iter().enumerate() {
+            let Some(range) = self.table.range_for(&edit.section, edit.index, pos) else { continue };
+
+            if !range.contains(*v) {
+                bail!("{}: {v} is out of range of {} ({} - {})", self.pci_bus, range.name, range.min, range.max);
+            }
+        }
+
+        self.staged.retain(|e| !(e.section == edit.section && e.index == edit.index));
+        self.staged.push(edit);
+
+        Ok(())
+    }
+
+    pub fn discard(&mut self) {
+        self.staged.clear();
+    }
+
+    /// Write the staged changes and commit them.
+    /// If the driver rejects a change, the table is restored to the state before the commit.
+    pub fn commit(&mut self) -> anyhow::Result<()> {
+        if self.staged.is_empty() { return Ok(()) }
+
+        let before = self.table.clone();
+        let r = self.staged
+            .iter()
+            .try_for_each(|edit| {
+                let cmd = self.table.section(&edit.section).and_then(|s| s.command()).unwrap_or_default();
+                write_sysfs(&self.path, &edit.command_with(cmd))
+            })
+            .and_then(|_| write_sysfs(&self.path, "c"));
+
+        self.staged.clear();
+        self.reload()?;
+
+        if let Err(err) = r {
+            let _ = self.write_commands(&self.table.diff_commands(&before));
+            self.reload()?;
+
+            return Err(err.context(format!("{}: the changes are rolled back", self.pci_bus)));
+        }
+
+        self.previous = Some(before);
+
+        Ok(())
+    }
+
+    /// Restore the table before the last commit.
+    pub fn restore(&mut self) -> anyhow::Result<()> {
+        let Some(previous) = self.previous.take() else {
+            bail!("{}: there are no committed changes to restore", self.pci_bus);
+        };
+        let r = self.write_commands(&self.table.diff_commands(&previous));
+
+        self.reload()?;
+
+        r
+    }
+
+    /// Reset the table to the defaults.
+    pub fn reset(&mut self) -> anyhow::Result<()> {
+        let before = self.table.clone();
+        let r = self.write_commands(&["r".to_string()]);
+
+        self.staged.clear();
+        self.reload()?;
+        r?;
+        self.previous = Some(before);
+
+        Ok(())
+    }
+
+    fn write_commands(&self, commands: &[String]) -> anyhow::Result<()> {
+        for cmd in commands {
+            write_sysfs(&self.path, cmd)?;
+        }
+
+        write_sysfs(&self.path, "c")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAVI10: &str = "\
+OD_SCLK:
+0: 800Mhz
+1: 2100Mhz
+OD_MCLK:
+1: 875MHz
+OD_VDDC_CURVE:
+0: 800MHz 712mV
+1: 1450MHz 787mV
+2: 2100MHz 1200mV
+OD_RANGE:
+SCLK:     800Mhz       2150Mhz
+MCLK:     625Mhz        950Mhz
+VDDC_CURVE_SCLK[0]:     800Mhz       2150Mhz
+VDDC_CURVE_VOLT[0]:     750mV        1200mV
+VDDC_CURVE_SCLK[2]:     800Mhz       2150Mhz
+VDDC_CURVE_VOLT[2]:     750mV        1200mV
+";
+
+    #[test]
+    fn test_parse_overdrive_table() {
+        let table = OverdriveTable::parse(NAVI10);
+        let curve = table.section("OD_VDDC_CURVE").unwrap();
+
+        assert_eq!(table.sections.len(), 3);
+        assert_eq!(curve.entries[2].values, [
+            OdValue { value: 2100, unit: OdUnit::MHz },
+            OdValue { value: 1200, unit: OdUnit::MilliVolt },
+        ]);
+        assert_eq!(table.range_for("OD_VDDC_CURVE", Some(2), 1).map(|r| r.max.value), Some(1200));
+
+        let navi31 = OverdriveTable::parse("OD_VDDGFX_OFFSET:\n0mV\nOD_RANGE:\nVDDGFX_OFFSET:    -450mv         0mv\n");
+        let offset = navi31.section("OD_VDDGFX_OFFSET").unwrap();
+
+        assert_eq!(offset.entries[0].index, None);
+        assert_eq!(navi31.range("VDDGFX_OFFSET").map(|r| r.min.value), Some(-450));
+
+        let zero_rpm = OverdriveTable::parse("FAN_ZERO_RPM_ENABLE:\n1\nOD_RANGE:\nZERO_RPM_ENABLE: 0 1\n");
+
+        assert_eq!(zero_rpm.sections[0].entries[0].values[0].value, 1);
+        assert_eq!(zero_rpm.ranges[0].max.value, 1);
+    }
+
+    #[test]
+    fn test_overdrive_edit() {
+        let table = OverdriveTable::parse(NAVI10);
+        let edit: OdEdit = "vc 2 2050 1150".parse().unwrap();
+
+        assert_eq!(edit.index, Some(2));
+        assert_eq!(edit.command_with("vc"), "vc 2 2050 1150");
+        assert_eq!("vo -50".parse::<OdEdit>().unwrap().index, None);
+        assert!("s 1".parse::<OdEdit>().is_err());
+        assert!("x 1 100".parse::<OdEdit>().is_err());
+
+        let mut changed = table.clone();
+        changed.sections[0].entries[1].values[0].value = 2000;
+
+        assert_eq!(changed.diff_commands(&table), ["s 1 2100"]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use anyhow::{anyhow, bail};
+use crate::AMDGPU::{PowerCap, PowerProfile};
+use crate::PCI;
+use super::{hwmon_path, sysfs_path, write_sysfs};
+
+const PERFORMANCE_LEVEL: &str = "power_dpm_force_performance_level";
+const POWER_PROFILE_MODE: &str = "pp_power_profile_mode";
+
+/// `power_dpm_force_performance_level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerformanceLevel {
+    Auto,
+    Low,
+    High,
+    Manual,
+    ProfileStandard,
+    ProfileMinSclk,
+    ProfileMinMclk,
+    ProfilePeak,
+    PerfDeterminism,
+}
+
+impl PerformanceLevel {
+    pub const ALL: [Self; 9] = [
+        Self::Auto,
+        Self::Low,
+        Self::High,
+        Self::Manual,
+        Self::ProfileStandard,
+        Self::ProfileMinSclk,
+        Self::ProfileMinMclk,
+        Self::ProfilePeak,
+        Self::PerfDeterminism,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Low => "low",
+            Self::High => "high",
+            Self::Manual => "manual",
+            Self::ProfileStandard => "profile_standard",
+            Self::ProfileMinSclk => "profile_min_sclk",
+            Self::ProfileMinMclk => "profile_min_mclk",
+            Self::ProfilePeak => "profile_peak",
+            Self::PerfDeterminism => "perf_determinism",
+        }
+    }
+}
+
+impl fmt::Display for PerformanceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PerformanceLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let list: Vec<&str> = Self::ALL.iter().map(|level| level.as_str()).collect();
+                anyhow!("unknown performance level: {s:?} (expected one of {})", list.join(", "))
+            })
+    }
+}
+
+/// A profile advertised in `pp_power_profile_mode`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerProfileMode {
+    pub index: u32,
+    /// e.g. "BOOTUP_DEFAULT", "3D_FULL_SCREEN", "COMPUTE"
+    pub name: String,
+    pub is_current: bool,
+}
+
+impl PowerProfileMode {
+    /// Match the profile name (case-insensitive) or the index.
+    pub fn matches(&self, profile: &str) -> bool {
+        let profile = profile.trim();
+
+        self.name.eq_ignore_ascii_case(profile) || self.index.to_string() == profile
+    }
+}
+
+/// Set the power cap, the performance level and the power profile of the device.
+#[derive(Debug, Clone)]
+pub struct PowerControl {
+    pub pci_bus: PCI::BUS_INFO,
+    sysfs_path: PathBuf,
+    hwmon_path: Option<PathBuf>,
+}
+
+impl PowerControl {
+    pub fn new(pci_bus: PCI::BUS_INFO) -> Self {
+        Self::from_sysfs_path(pci_bus, sysfs_path(pci_bus))
+    }
+
+    fn from_sysfs_path(pci_bus: PCI::BUS_INFO, sysfs_path: PathBuf) -> Self {
+        let hwmon_path = hwmon_path(&sysfs_path);
+
+        Self { pci_bus, sysfs_path, hwmon_path }
+    }
+
+    pub fn power_cap(&self) -> Option<PowerCap> {
+        PowerCap::from_hwmon_path(self.hwmon_path.as_ref()?)
+    }
+
+    /// Set `power1_cap` in watts, within the range of `power1_cap_min` and `power1_cap_max`.
+    pub fn set_power_cap(&self, watts: u32) -> anyhow::Result<()> {
+        let (Some(hwmon_path), Some(cap)) = (&self.hwmon_path, self.power_cap()) else {
+            bail!("{}: power cap is not supported", self.pci_bus);
+        };
+
+        if !(cap.min..=cap.max).contains(&watts) {
+            bail!("{}: {watts} W is out of range ({}-{} W)", self.pci_bus, cap.min, cap.max);
+        }
+
+        write_sysfs(hwmon_path.join("power1_cap"), &(watts as u64 * 1_000_000).to_string())
+    }
+
+    /// Restore `power1_cap` to the default.
+    pub fn reset_power_cap(&self) -> anyhow::Result<()> {
+        let Some(cap) = self.power_cap() else {
+            bail!("{}: power cap is not supported", self.pci_bus);
+        };
+
+        self.set_power_cap(cap.default)
+    }
+
+    pub fn performance_level(&self) -> Option<PerformanceLevel> {
+        fs::read_to_string(self.sysfs_path.join(PERFORMANCE_LEVEL)).ok()?.parse().ok()
+    }
+
+    pub fn set_performance_level(&self, level: PerformanceLevel) -> anyhow::Result<()> {
+        write_sysfs(self.sysfs_path.join(PERFORMANCE_LEVEL), level.as_str())
+    }
+
+    /// The current profile of `pp_power_profile_mode`
+    pub fn power_profile(&self) -> Option<PowerProfile> {
+        PowerProfile::get_current_profile_from_sysfs(&self.sysfs_path)
+    }
+
+    /// The profiles advertised by the device
+    pub fn power_profiles(&self) -> Vec<PowerProfileMode> {
+        let current = self.power_profile();
+
+        PowerProfile::get_all_supported_profiles_from_sysfs(&self.sysfs_path)
+            .into_iter()
+            .map(|profile| PowerProfileMode {
+                index: profile as u32,
+                name: profile.to_string(),
+                is_current: current == Some(profile),
+            })
+            .collect()
+    }
+
+    /// Set `pp_power_profile_mode` by the profile name (e.g. "COMPUTE") or the index.
+    pub fn set_power_profile(&self, profile: &str) -> anyhow::Result<()> {
+        let profiles = self.power_profiles();
+
+        if profiles.is_empty() {
+            bail!("{}: power profile is not supported", self.pci_bus);
+        }
+
+        let Some(mode) = profiles.iter().find(|mode| mode.matches(profile)) else {
+            let list: Vec<&str> = profiles.iter().map(|mode| mode.name.as_str()).collect();
+            bail!("{}: unknown power profile: {profile:?} (supported: {})", self.pci_bus, list.join(", "));
+        };
+
+        write_sysfs(self.sysfs_path.join(POWER_PROFILE_MODE), &mode.index.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_profiles() {
+        // SMU13 (RDNA3) prints all the profiles in the header line
+        let rdna3 = concat!(
+            "                             0 BOOTUP_DEFAULT*  1 3D_FULL_SCREEN    2   POWER_SAVING    3          VIDEO",
+            "    4             VR    5        COMPUTE    6         CUSTOM    7      WINDOW_3D\n",
+            "0(       GFXCLK)       0       1       0       0       0       0       0       0       0       0       0\n",
+            "1(      FCLK)          0       1       0       0       0       0       0       0       0       0       0\n",
+        );
+        let dir = std::env::temp_dir().join(format!("amdgpu_top-test-power-{}", std::process::id()));
+        let pci: PCI::BUS_INFO = "0000:03:00.0".parse().unwrap();
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(POWER_PROFILE_MODE), rdna3).unwrap();
+
+        let profiles = PowerControl::from_sysfs_path(pci, dir.clone()).power_profiles();
+        let current: Vec<u32> = profiles.iter().filter(|mode| mode.is_current).map(|mode| mode.index).collect();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(profiles.iter().any(|mode| mode.index == PowerProfile::COMPUTE as u32));
+        assert_eq!(current, [PowerProfile::BOOTUP_DEFAULT as u32]);
+        assert_eq!("Profile_Peak".parse::<PerformanceLevel>().ok(), Some(PerformanceLevel::ProfilePeak));
+        assert!("turbo".parse::<PerformanceLevel>().is_err());
+    }
+}
@@ -0,0 +1,561 @@
+//! Simulated devices for `--demo`.
+//!
+//! [`setup`] creates a sysfs/procfs tree of a dGPU and an APU with an NPU in a temporary directory,
+//! switches the [`root`](crate::root) to it, and registers a [`SyntheticBackend`] for each device.
+//! The tree is removed when the returned [`DemoDir`] is dropped.
+//! A background thread keeps updating hwmon, gpu_metrics and the fdinfo of the simulated processes,
+//! so every frontend works unchanged on top of it.
+
+use crate::backend::{self, SyntheticBackend, SyntheticState};
+use crate::root::{self, Root};
+use crate::stat::PCType;
+use std::collections::HashMap;
+use std::ffi::{CString, OsString};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+mod model;
+use model::*;
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Small xorshift PRNG, the demo does not need a good one.
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct Demo {
+    dir: PathBuf,
+    start: Instant,
+    devices: Vec<DemoDevice>,
+    /// Set when the tree is removed, the update thread must not recreate it.
+    stopped: Mutex<bool>,
+}
+
+/// The sysfs/procfs tree of the demo, removed on drop.
+pub struct DemoDir {
+    demo: Arc<Demo>,
+}
+
+impl DemoDir {
+    pub fn path(&self) -> &Path {
+        &self.demo.dir
+    }
+}
+
+impl Drop for DemoDir {
+    fn drop(&mut self) {
+        let mut stopped = self.demo.stopped.lock().unwrap_or_else(|err| err.into_inner());
+
+        *stopped = true;
+        let _ = fs::remove_dir_all(&self.demo.dir);
+    }
+}
+
+/// Create a new directory only accessible by the user with a unique name (`mkdtemp(3)`),
+/// so that another user cannot replace it in the shared temporary directory.
+fn create_temp_dir() -> io::Result<PathBuf> {
+    let template = std::env::temp_dir().join("amdgpu_top-demo-XXXXXX");
+    let mut template = CString::new(template.into_os_string().into_vec())?.into_bytes_with_nul();
+
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    template.pop();
+
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+/// Set up the simulated devices, returns the sysfs/procfs tree to be kept until exit.
+/// This must be called before any device is enumerated.
+pub fn setup() -> io::Result<DemoDir> {
+    let dir = create_temp_dir()?;
+    let demo = Arc::new(Demo {
+        dir: dir.clone(),
+        start: Instant::now(),
+        devices: demo_devices(),
+        stopped: Mutex::new(false),
+    });
+    let demo_dir = DemoDir { demo: demo.clone() };
+
+    root::set(Root {
+        sysfs: dir.join("sys"),
+        procfs: dir.join("proc"),
+        devfs: dir.join("dev"),
+    }).map_err(|_| io::Error::other("The root directories are already in use"))?;
+
+    for (i, device) in demo.devices.iter().enumerate() {
+        demo.create_device_tree(device, i)?;
+        register_backend(demo.clone(), i);
+    }
+
+    let mut counters = HashMap::new();
+    demo.update(&mut counters, Duration::ZERO)?;
+
+    {
+        let demo = demo.clone();
+
+        std::thread::spawn(move || {
+            let mut last = Instant::now();
+
+            loop {
+                std::thread::sleep(UPDATE_INTERVAL);
+                let now = Instant::now();
+                let stopped = demo.stopped.lock().unwrap();
+
+                if *stopped { break }
+
+                let _ = demo.update(&mut counters, now.duration_since(last));
+                last = now;
+            }
+        });
+    }
+
+    Ok(demo_dir)
+}
+
+fn register_backend(demo: Arc<Demo>, index: usize) {
+    let factory = {
+        let demo = demo.clone();
+
+        Arc::new(move || -> Box<dyn backend::DeviceBackend> {
+            let device = &demo.devices[index];
+            let mut state = SyntheticState::new(device.ext_info(), device.memory_info());
+
+            state.hw_ip_info_list = device.hw_ip_info_list();
+            state.min_max_gpu_clock = Some(device.min_max_gpu_clock);
+            state.min_max_memory_clock = Some(device.min_max_memory_clock);
+
+            let demo = demo.clone();
+            let mut rng = Rng(0x2545_F491_4F6C_DD1D ^ (index as u64 + 1));
+
+            Box::new(SyntheticBackend::new(state).with_script(move |state| {
+                let device = &demo.devices[index];
+                let load = device.load(demo.start);
+
+                // each bit of GRBM/GRBM2 is busy with a probability around the GFX load
+                for pc_type in [PCType::GRBM, PCType::GRBM2] {
+                    let reg = (0..32).fold(0u32, |reg, i| {
+                        let p = load.gfx * (0.6 + 0.4 * ((i * 7 % 11) as f64 / 10.0));
+
+                        if rng.next_f64() < p { reg | (1 << i) } else { reg }
+                    });
+
+                    state.set_register(pc_type.offset(), reg);
+                }
+
+                state.sclk = Some(device.sclk(&load));
+                state.mclk = Some(device.mclk(&load));
+                state.vddgfx = Some(700 + (450.0 * load.gfx) as u32);
+                state.vddnb = device.is_apu.then_some(750 + (200.0 * load.mem) as u32);
+                state.memory_info.vram.heap_usage = device.vram_usage(demo.start);
+                state.memory_info.cpu_accessible_vram.heap_usage = state.memory_info.vram.heap_usage;
+                state.memory_info.gtt.heap_usage = device.gtt_usage();
+            }))
+        })
+    };
+
+    backend::register_synthetic_device(demo.devices[index].pci, factory);
+}
+
+fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let path = path.as_ref();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, contents)
+}
+
+/// Replace the file at once, so that readers never see a partially written file.
+fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+fn link<P: AsRef<Path>, Q: AsRef<Path>>(target: P, path: Q) -> io::Result<()> {
+    let path = path.as_ref();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    symlink(target, path)
+}
+
+impl Demo {
+    fn sys(&self) -> PathBuf {
+        self.dir.join("sys")
+    }
+
+    fn proc(&self) -> PathBuf {
+        self.dir.join("proc")
+    }
+
+    fn device_dir(&self, device: &DemoDevice) -> PathBuf {
+        self.sys().join(format!("devices/pci0000:00/{}/{}", device.port, device.pci))
+    }
+
+    fn create_device_tree(&self, device: &DemoDevice, index: usize) -> io::Result<()> {
+        let sys = self.sys();
+        let port_dir = self.sys().join(format!("devices/pci0000:00/{}", device.port));
+        let dev_dir = self.device_dir(device);
+        let rel_dev_dir = format!("../../../devices/pci0000:00/{}/{}", device.port, device.pci);
+
+        fs::create_dir_all(&dev_dir)?;
+        link(&rel_dev_dir, sys.join(format!("bus/pci/devices/{}", device.pci)))?;
+        link(&rel_dev_dir, sys.join(format!("bus/pci/drivers/amdgpu/{}", device.pci)))?;
+
+        write(port_dir.join("vendor"), "0x1022\n")?;
+        write(port_dir.join("max_link_speed"), "16.0 GT/s PCIe\n")?;
+        write(port_dir.join("max_link_width"), "16\n")?;
+
+        write(dev_dir.join("vendor"), "0x1002\n")?;
+        write(dev_dir.join("device"), format!("{:#06x}\n", device.device_id))?;
+        write(dev_dir.join("revision"), format!("{:#04x}\n", device.revision_id))?;
+        write(dev_dir.join("class"), "0x030000\n")?;
+        write(dev_dir.join("power/runtime_status"), "active\n")?;
+        write(dev_dir.join("power_state"), "D0\n")?;
+        fs::create_dir_all(dev_dir.join(format!("drm/renderD{}", device.render_minor)))?;
+        fs::create_dir_all(dev_dir.join(format!("drm/card{}", device.card_minor)))?;
+
+        write(
+            dev_dir.join("pp_power_profile_mode"),
+            concat!(
+                " 0 BOOTUP_DEFAULT*:\n",
+                " 1 3D_FULL_SCREEN :\n",
+                " 2 POWER_SAVING   :\n",
+                " 3 VIDEO          :\n",
+                " 4 VR             :\n",
+                " 5 COMPUTE        :\n",
+                " 6 CUSTOM         :\n",
+            ),
+        )?;
+
+        if !device.is_apu {
+            write(dev_dir.join("current_link_speed"), "16.0 GT/s PCIe\n")?;
+            write(dev_dir.join("current_link_width"), "16\n")?;
+            write(dev_dir.join("max_link_speed"), "16.0 GT/s PCIe\n")?;
+            write(dev_dir.join("max_link_width"), "16\n")?;
+            write(dev_dir.join("pcie_bw"), "0 0 128\n")?;
+        }
+
+        let hwmon = dev_dir.join(format!("hwmon/hwmon{index}"));
+
+        write(hwmon.join("name"), "amdgpu\n")?;
+        write(hwmon.join("temp1_label"), "edge\n")?;
+        write(hwmon.join("temp1_crit"), "100000\n")?;
+
+        if !device.is_apu {
+            write(hwmon.join("temp1_emergency"), "105000\n")?;
+            write(hwmon.join("temp2_label"), "junction\n")?;
+            write(hwmon.join("temp2_crit"), "110000\n")?;
+            write(hwmon.join("temp2_emergency"), "115000\n")?;
+            write(hwmon.join("temp3_label"), "mem\n")?;
+            write(hwmon.join("temp3_crit"), "100000\n")?;
+            write(hwmon.join("temp3_emergency"), "105000\n")?;
+            write(hwmon.join("fan1_max"), "3300\n")?;
+            write(hwmon.join("fan1_min"), "0\n")?;
+        }
+
+        let cap = device.power_cap as u64 * 1_000_000;
+        write(hwmon.join("power1_cap"), format!("{cap}\n"))?;
+        write(hwmon.join("power1_cap_default"), format!("{cap}\n"))?;
+        write(hwmon.join("power1_cap_min"), "0\n")?;
+        write(hwmon.join("power1_cap_max"), format!("{}\n", cap * 11 / 10))?;
+
+        if device.is_apu {
+            let debug_dri = sys.join(format!("kernel/debug/dri/{}", device.card_minor));
+
+            write(debug_dri.join("name"), format!("amdgpu dev={} unique={}\n", device.pci, device.pci))?;
+            write(debug_dri.join("amdgpu_gfxoff"), 1u32.to_le_bytes())?;
+        }
+
+        if let Some(npu) = &device.npu {
+            let npu_dir = sys.join(format!("devices/pci0000:00/{}/{}", device.port, npu.pci));
+
+            fs::create_dir_all(npu_dir.join("accel/accel0"))?;
+            link(
+                format!("../../../devices/pci0000:00/{}/{}", device.port, npu.pci),
+                sys.join(format!("bus/pci/devices/{}", npu.pci)),
+            )?;
+            write(npu_dir.join("vendor"), "0x1022\n")?;
+            write(npu_dir.join("device"), format!("{:#06x}\n", npu.device_id))?;
+            write(npu_dir.join("revision"), "0x00\n")?;
+            write(npu_dir.join("class"), "0x118000\n")?;
+            write(npu_dir.join("fw_version"), format!("{}\n", npu.fw_version))?;
+            write(npu_dir.join("vbnv"), format!("{}\n", npu.vbnv))?;
+        }
+
+        for (i, proc) in device.processes.iter().enumerate() {
+            let proc_dir = self.proc().join(proc.pid.to_string());
+            let mut cmdline = proc.cmdline.replace(' ', "\0");
+            cmdline.push('\0');
+
+            write(proc_dir.join("cmdline"), cmdline)?;
+            write(proc_dir.join("comm"), format!("{}\n", proc.name))?;
+
+            let node = if proc.npu {
+                "/dev/accel/accel0".to_string()
+            } else {
+                format!("/dev/dri/renderD{}", device.render_minor)
+            };
+
+            link(node, proc_dir.join(format!("fd/{}", 20 + i)))?;
+
+            if proc.is_kfd_process {
+                fs::create_dir_all(sys.join(format!("class/kfd/kfd/proc/{}", proc.pid)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, counters: &mut HashMap<(i32, &'static str), u64>, dt: Duration) -> io::Result<()> {
+        for device in &self.devices {
+            let load = device.load(self.start);
+
+            self.update_sensors(device, &load)?;
+
+            for (i, proc) in device.processes.iter().enumerate() {
+                self.update_process(device, proc, 20 + i, &load, counters, dt)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_sensors(&self, device: &DemoDevice, load: &Load) -> io::Result<()> {
+        let dev_dir = self.device_dir(device);
+        let hwmon = fs::read_dir(dev_dir.join("hwmon"))?
+            .find_map(|d| Some(d.ok()?.path()))
+            .ok_or(io::Error::from(io::ErrorKind::NotFound))?;
+        let edge = device.edge_temp(load);
+        let power = device.power(load) as u64 * 1_000_000;
+
+        write_atomic(hwmon.join("temp1_input"), format!("{}\n", edge * 1000))?;
+
+        if device.is_apu {
+            write_atomic(hwmon.join("power1_input"), format!("{power}\n"))?;
+            write_atomic(dev_dir.join("gpu_busy_percent"), format!("{}\n", (load.gfx * 100.0) as u32))?;
+            write_atomic(dev_dir.join("mem_busy_percent"), format!("{}\n", (load.mem * 100.0) as u32))?;
+
+            let debug_dri = self.sys().join(format!("kernel/debug/dri/{}", device.card_minor));
+            // GfxoffStatus: 0 = InGFXOFF, 2 = NotInGFXOFF
+            let status: u32 = if load.gfx < 0.1 { 0 } else { 2 };
+
+            write_atomic(debug_dri.join("amdgpu_gfxoff_status"), status.to_le_bytes())?;
+            // an entry into GFXOFF every 2 seconds or so,
+            // `amdgpu_gfxoff_residency` is not written, the demo can't respond to the logging control.
+            let count = self.start.elapsed().as_secs() / 2;
+
+            write_atomic(debug_dri.join("amdgpu_gfxoff_count"), count.to_le_bytes())?;
+        } else {
+            let junction = edge + 8 + (10.0 * load.gfx) as u32;
+            let mem = 50 + (20.0 * load.mem) as u32;
+            let fan = 800 + (2000.0 * load.gfx) as u32;
+            let gpu_metrics = self.gpu_metrics_v1_3(device, load, [edge, junction, mem], fan);
+
+            write_atomic(hwmon.join("temp2_input"), format!("{}\n", junction * 1000))?;
+            write_atomic(hwmon.join("temp3_input"), format!("{}\n", mem * 1000))?;
+            write_atomic(hwmon.join("power1_average"), format!("{power}\n"))?;
+            write_atomic(hwmon.join("fan1_input"), format!("{fan}\n"))?;
+            write_atomic(dev_dir.join("gpu_metrics"), gpu_metrics)?;
+            write_atomic(
+                dev_dir.join("pp_dpm_pcie"),
+                if load.gfx < 0.1 {
+                    "0: 2.5GT/s, x1 619Mhz *\n1: 16.0GT/s, x16 1000Mhz \n"
+                } else {
+                    "0: 2.5GT/s, x1 619Mhz \n1: 16.0GT/s, x16 1000Mhz *\n"
+                },
+            )?;
+
+            // fine-grained SCLK lists the min, the current and the max clock
+            let (min_sclk, max_sclk) = device.min_max_gpu_clock;
+            let sclk = device.sclk(load);
+            let sclk_levels = if sclk == min_sclk {
+                format!("0: {min_sclk}Mhz *\n1: {max_sclk}Mhz \n")
+            } else if sclk == max_sclk {
+                format!("0: {min_sclk}Mhz \n1: {max_sclk}Mhz *\n")
+            } else {
+                format!("0: {min_sclk}Mhz \n1: {sclk}Mhz *\n2: {max_sclk}Mhz \n")
+            };
+            let (min_mclk, max_mclk) = device.min_max_memory_clock;
+            let mclk_levels: String = [min_mclk, 456, 673, max_mclk]
+                .iter()
+                .enumerate()
+                .map(|(i, mclk)| {
+                    let active = if *mclk == device.mclk(load) { "*" } else { "" };
+                    format!("{i}: {mclk}Mhz {active}\n")
+                })
+                .collect();
+
+            write_atomic(dev_dir.join("pp_dpm_sclk"), sclk_levels)?;
+            write_atomic(dev_dir.join("pp_dpm_mclk"), mclk_levels)?;
+        }
+
+        Ok(())
+    }
+
+    /// ref: drivers/gpu/drm/amd/include/kgd_pp_interface.h
+    fn gpu_metrics_v1_3(
+        &self,
+        device: &DemoDevice,
+        load: &Load,
+        [edge, junction, mem]: [u32; 3],
+        fan: u32,
+    ) -> Vec<u8> {
+        const SIZE: usize = 120;
+        let mut buf = vec![0u8; SIZE];
+        let mut put = |offset: usize, bytes: &[u8]| buf[offset..offset+bytes.len()].copy_from_slice(bytes);
+        let le16 = |v: u32| (v as u16).to_le_bytes();
+        let sclk = device.sclk(load);
+        let mclk = device.mclk(load);
+        let [vclk, dclk] = if load.media < 0.05 { [0, 0] } else { [1300, 1100] };
+
+        // metrics_table_header
+        put(0, &(SIZE as u16).to_le_bytes());
+        put(2, &[1, 3]);
+        // temperature_{edge,hotspot,mem,vrgfx,vrsoc,vrmem}
+        for (i, temp) in [edge, junction, mem, edge + 4, edge + 2, mem + 3].into_iter().enumerate() {
+            put(4 + i * 2, &le16(temp));
+        }
+        // average_{gfx,umc,mm}_activity
+        put(16, &le16((load.gfx * 100.0) as u32));
+        put(18, &le16((load.mem * 100.0) as u32));
+        put(20, &le16((load.media * 100.0) as u32));
+        // average_socket_power
+        put(22, &le16(device.power(load)));
+        // system_clock_counter
+        put(32, &(self.start.elapsed().as_nanos() as u64 / 10).to_le_bytes());
+        // average_{gfx,soc,u,vclk0,dclk0,vclk1,dclk1}_frequency
+        for (i, clk) in [sclk, 1200, mclk, vclk, dclk, 0, 0].into_iter().enumerate() {
+            put(40 + i * 2, &le16(clk));
+        }
+        // current_{gfx,soc,u,vclk0,dclk0,vclk1,dclk1}
+        for (i, clk) in [sclk, 1200, mclk, vclk, dclk, 0, 0].into_iter().enumerate() {
+            put(54 + i * 2, &le16(clk));
+        }
+        // current_fan_speed
+        put(72, &le16(fan));
+        // pcie_link_{width,speed}
+        put(74, &le16(16));
+        put(76, &le16(4));
+        // voltage_{soc,gfx,mem}
+        put(104, &le16(900));
+        put(106, &le16(700 + (450.0 * load.gfx) as u32));
+        put(108, &le16(1350));
+
+        buf
+    }
+
+    fn update_process(
+        &self,
+        device: &DemoDevice,
+        proc: &DemoProcess,
+        fd: usize,
+        load: &Load,
+        counters: &mut HashMap<(i32, &'static str), u64>,
+        dt: Duration,
+    ) -> io::Result<()> {
+        let proc_dir = self.proc().join(proc.pid.to_string());
+        let client_id = proc.pid as usize * 10 + fd;
+        let mut engines = String::new();
+        let mut busy = 0.0;
+
+        for (engine, share) in proc.engines {
+            let ns = (dt.as_nanos() as f64 * load.engine(*engine) * share) as u64;
+            let key = engine.fdinfo_key();
+            let counter = counters.entry((proc.pid, key)).or_default();
+            *counter += ns;
+            busy += load.engine(*engine) * share;
+
+            engines.push_str(&format!("drm-engine-{key}:\t{counter} ns\n"));
+        }
+
+        let fdinfo = if proc.npu {
+            format!(
+                concat!(
+                    "pos:\t0\nflags:\t02100002\nmnt_id:\t26\nino:\t{fd}\n",
+                    "drm-driver:\tamdxdna_accel_driver\n",
+                    "drm-client-id:\t{client_id}\n",
+                    "drm-pdev:\t{pci}\n",
+                    "drm-total-memory:\t{total} KiB\n",
+                    "drm-shared-memory:\t{shared} KiB\n",
+                    "drm-active-memory:\t{active} KiB\n",
+                    "{engines}",
+                ),
+                fd = fd,
+                client_id = client_id,
+                pci = device.npu.as_ref().map(|npu| npu.pci).unwrap_or(device.pci),
+                total = proc.gtt >> 10,
+                shared = proc.gtt >> 12,
+                active = ((proc.gtt >> 10) as f64 * load.npu) as u64,
+                engines = engines,
+            )
+        } else {
+            format!(
+                concat!(
+                    "pos:\t0\nflags:\t02100002\nmnt_id:\t24\nino:\t{fd}\n",
+                    "drm-driver:\tamdgpu\n",
+                    "drm-client-id:\t{client_id}\n",
+                    "drm-pdev:\t{pci}\n",
+                    "pasid:\t{pasid}\n",
+                    "drm-memory-vram:\t{vram} KiB\n",
+                    "drm-memory-gtt: \t{gtt} KiB\n",
+                    "drm-memory-cpu: \t0 KiB\n",
+                    "amd-memory-visible-vram:\t{vram} KiB\n",
+                    "amd-evicted-vram:\t0 KiB\n",
+                    "amd-evicted-visible-vram:\t0 KiB\n",
+                    "amd-requested-vram:\t{vram} KiB\n",
+                    "amd-requested-visible-vram:\t0 KiB\n",
+                    "amd-requested-gtt:\t{gtt} KiB\n",
+                    "{engines}",
+                ),
+                fd = fd,
+                client_id = client_id,
+                pci = device.pci,
+                pasid = 32768 + proc.pid,
+                vram = proc.vram >> 10,
+                gtt = proc.gtt >> 10,
+                engines = engines,
+            )
+        };
+
+        write_atomic(proc_dir.join(format!("fdinfo/{fd}")), fdinfo)?;
+
+        // CPU time in clock ticks (100 Hz), a GPU-bound process uses some CPU time too
+        let cpu_time = counters.entry((proc.pid, "cpu")).or_default();
+        *cpu_time += (dt.as_secs_f64() * 100.0 * (0.05 + busy * 0.3)) as u64;
+
+        write_atomic(
+            proc_dir.join("stat"),
+            format!(
+                "{pid} ({name}) S 1 {pid} {pid} 0 -1 4194560 0 0 0 0 {utime} {stime} 0 0 20 0 8 0 0 0 0\n",
+                pid = proc.pid,
+                name = proc.name,
+                utime = *cpu_time * 4 / 5,
+                stime = *cpu_time / 5,
+            ),
+        )
+    }
+}
@@ -0,0 +1,379 @@
+use crate::AMDGPU::{
+    drm_amdgpu_info_device,
+    drm_amdgpu_memory_info,
+    HW_IP::{HW_IP_TYPE, HwIpInfo},
+};
+use crate::PCI;
+use std::time::Instant;
+
+const MIB: u64 = 1 << 20;
+const GIB: u64 = 1 << 30;
+
+// ref: include/uapi/drm/amdgpu_drm.h
+const AMDGPU_FAMILY_NV: u32 = 143;
+const AMDGPU_FAMILY_GC_11_0_1: u32 = 148;
+const AMDGPU_VRAM_TYPE_GDDR6: u32 = 9;
+const AMDGPU_VRAM_TYPE_LPDDR5: u32 = 12;
+const AMDGPU_IDS_FLAGS_FUSION: u64 = 0x1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Engine {
+    Gfx,
+    Compute,
+    Dma,
+    Dec,
+    Enc,
+    Npu,
+}
+
+impl Engine {
+    pub fn fdinfo_key(&self) -> &'static str {
+        match self {
+            Self::Gfx => "gfx",
+            Self::Compute => "compute",
+            Self::Dma => "dma",
+            Self::Dec => "dec",
+            Self::Enc => "enc",
+            Self::Npu => "npu-amdxdna",
+        }
+    }
+}
+
+pub(crate) struct DemoProcess {
+    pub pid: i32,
+    pub name: &'static str,
+    pub cmdline: &'static str,
+    pub vram: u64, // bytes
+    pub gtt: u64, // bytes
+    /// (engine, share of the engine load)
+    pub engines: &'static [(Engine, f64)],
+    pub is_kfd_process: bool,
+    /// Use the NPU instead of the GPU
+    pub npu: bool,
+}
+
+pub(crate) struct DemoNpu {
+    pub pci: PCI::BUS_INFO,
+    pub device_id: u32,
+    pub fw_version: &'static str,
+    pub vbnv: &'static str,
+}
+
+pub(crate) struct DemoDevice {
+    pub pci: PCI::BUS_INFO,
+    pub port: PCI::BUS_INFO,
+    pub device_id: u32,
+    pub revision_id: u32,
+    pub render_minor: u32,
+    pub card_minor: u32,
+    pub is_apu: bool,
+    pub vram_size: u64,
+    pub gtt_size: u64,
+    pub min_max_gpu_clock: (u32, u32), // MHz
+    pub min_max_memory_clock: (u32, u32), // MHz
+    pub power_cap: u32, // W
+    /// Phase shift of the load, so that the devices do not look alike
+    pub phase: f64,
+    pub processes: Vec<DemoProcess>,
+    pub npu: Option<DemoNpu>,
+}
+
+/// Utilization of each block, 0.0 ..= 1.0
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Load {
+    pub gfx: f64,
+    pub mem: f64,
+    pub media: f64,
+    pub npu: f64,
+}
+
+impl Load {
+    pub fn engine(&self, engine: Engine) -> f64 {
+        match engine {
+            Engine::Gfx | Engine::Compute => self.gfx,
+            Engine::Dma => self.mem * 0.5,
+            Engine::Dec | Engine::Enc => self.media,
+            Engine::Npu => self.npu,
+        }
+    }
+}
+
+fn wave(t: f64, period: f64, phase: f64) -> f64 {
+    (t / period * std::f64::consts::TAU + phase).sin()
+}
+
+impl DemoDevice {
+    pub fn load(&self, start: Instant) -> Load {
+        let t = start.elapsed().as_secs_f64();
+        let p = self.phase;
+        // a busy scene every 40s, on top of the slow waves
+        let burst = if (t + p * 10.0) % 40.0 < 8.0 { 0.3 } else { 0.0 };
+
+        Load {
+            gfx: (0.45 + 0.3 * wave(t, 23.0, p) + 0.1 * wave(t, 3.0, p) + burst).clamp(0.0, 1.0),
+            mem: (0.3 + 0.2 * wave(t, 17.0, p + 1.0) + burst * 0.5).clamp(0.0, 1.0),
+            media: (0.2 + 0.2 * wave(t, 31.0, p + 2.0)).clamp(0.0, 1.0),
+            npu: (0.4 + 0.4 * wave(t, 13.0, p + 3.0)).clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn vram_usage(&self, start: Instant) -> u64 {
+        let base = if self.is_apu { 64 * MIB } else { 512 * MIB };
+        let procs: u64 = self.processes.iter().filter(|p| !p.npu).map(|p| p.vram).sum();
+        let t = start.elapsed().as_secs_f64();
+        let wiggle = ((1.0 + wave(t, 29.0, self.phase)) * 128.0) as u64 * MIB;
+
+        (base + procs + wiggle).min(self.vram_size)
+    }
+
+    pub fn gtt_usage(&self) -> u64 {
+        let procs: u64 = self.processes.iter().map(|p| p.gtt).sum();
+
+        (32 * MIB + procs).min(self.gtt_size)
+    }
+
+    pub fn sclk(&self, load: &Load) -> u32 {
+        let (min, max) = self.min_max_gpu_clock;
+
+        min + ((max - min) as f64 * load.gfx.sqrt()) as u32
+    }
+
+    pub fn mclk(&self, load: &Load) -> u32 {
+        let (min, max) = self.min_max_memory_clock;
+
+        if load.mem < 0.05 { min } else { max }
+    }
+
+    pub fn power(&self, load: &Load) -> u32 {
+        let idle = if self.is_apu { 3.0 } else { 12.0 };
+
+        (idle + (self.power_cap as f64 - idle) * (0.8 * load.gfx + 0.2 * load.mem)) as u32
+    }
+
+    pub fn edge_temp(&self, load: &Load) -> u32 {
+        38 + (40.0 * load.gfx) as u32
+    }
+
+    pub fn ext_info(&self) -> drm_amdgpu_info_device {
+        // SAFETY: drm_amdgpu_info_device is a plain C struct of integers
+        let mut ext_info: drm_amdgpu_info_device = unsafe { std::mem::zeroed() };
+
+        ext_info.device_id = self.device_id;
+        ext_info.pci_rev = self.revision_id;
+        ext_info.max_engine_clock = self.min_max_gpu_clock.1 as u64 * 1000;
+        ext_info.min_engine_clock = self.min_max_gpu_clock.0 as u64 * 1000;
+        ext_info.max_memory_clock = self.min_max_memory_clock.1 as u64 * 1000;
+        ext_info.min_memory_clock = self.min_max_memory_clock.0 as u64 * 1000;
+        ext_info.wave_front_size = 32;
+
+        if self.is_apu {
+            // Phoenix (GFX1103_R1)
+            ext_info.family = AMDGPU_FAMILY_GC_11_0_1;
+            ext_info.external_rev = 0x01;
+            ext_info.ids_flags = AMDGPU_IDS_FLAGS_FUSION;
+            ext_info.num_shader_engines = 1;
+            ext_info.num_shader_arrays_per_engine = 2;
+            ext_info.num_cu_per_sh = 6;
+            ext_info.cu_active_number = 12;
+            ext_info.num_rb_pipes = 4;
+            ext_info.num_tcc_blocks = 4;
+            ext_info.vram_type = AMDGPU_VRAM_TYPE_LPDDR5;
+            ext_info.vram_bit_width = 128;
+        } else {
+            // Navi22
+            ext_info.family = AMDGPU_FAMILY_NV;
+            ext_info.external_rev = 0x32;
+            ext_info.num_shader_engines = 2;
+            ext_info.num_shader_arrays_per_engine = 2;
+            ext_info.num_cu_per_sh = 10;
+            ext_info.cu_active_number = 40;
+            ext_info.num_rb_pipes = 8;
+            ext_info.num_tcc_blocks = 12;
+            ext_info.vram_type = AMDGPU_VRAM_TYPE_GDDR6;
+            ext_info.vram_bit_width = 192;
+            ext_info.pcie_gen = 4;
+            ext_info.pcie_num_lanes = 16;
+        }
+
+        ext_info
+    }
+
+    pub fn memory_info(&self) -> drm_amdgpu_memory_info {
+        // SAFETY: drm_amdgpu_memory_info is a plain C struct of integers
+        let mut memory_info: drm_amdgpu_memory_info = unsafe { std::mem::zeroed() };
+
+        memory_info.vram.total_heap_size = self.vram_size;
+        memory_info.vram.usable_heap_size = self.vram_size - 64 * MIB;
+        memory_info.vram.max_allocation = memory_info.vram.usable_heap_size * 3 / 4;
+        // Resizable BAR
+        memory_info.cpu_accessible_vram = memory_info.vram;
+        memory_info.gtt.total_heap_size = self.gtt_size;
+        memory_info.gtt.usable_heap_size = self.gtt_size - 16 * MIB;
+        memory_info.gtt.max_allocation = memory_info.gtt.usable_heap_size * 3 / 4;
+
+        memory_info
+    }
+
+    pub fn hw_ip_info_list(&self) -> Vec<HwIpInfo> {
+        let list: &[(HW_IP_TYPE, u32, u32, u32)] = if self.is_apu {
+            &[
+                (HW_IP_TYPE::GFX, 11, 0, 1),
+                (HW_IP_TYPE::COMPUTE, 11, 0, 4),
+                (HW_IP_TYPE::DMA, 6, 0, 1),
+                (HW_IP_TYPE::VCN_DEC, 4, 0, 1),
+                (HW_IP_TYPE::VCN_ENC, 4, 0, 1),
+                (HW_IP_TYPE::VCN_JPEG, 4, 0, 1),
+            ]
+        } else {
+            &[
+                (HW_IP_TYPE::GFX, 10, 3, 1),
+                (HW_IP_TYPE::COMPUTE, 10, 3, 4),
+                (HW_IP_TYPE::DMA, 5, 2, 2),
+                (HW_IP_TYPE::VCN_DEC, 3, 0, 1),
+                (HW_IP_TYPE::VCN_ENC, 3, 0, 1),
+                (HW_IP_TYPE::VCN_JPEG, 3, 0, 1),
+            ]
+        };
+
+        list.iter().map(|(ip_type, major, minor, count)| {
+            // SAFETY: HwIpInfo is a plain struct of integers, and `HW_IP_TYPE::GFX` is 0
+            let mut ip: HwIpInfo = unsafe { std::mem::zeroed() };
+
+            ip.ip_type = *ip_type;
+            ip.info.hw_ip_version_major = *major;
+            ip.info.hw_ip_version_minor = *minor;
+            ip.info.available_rings = (1 << count) - 1;
+            ip.count = 1;
+
+            ip
+        }).collect()
+    }
+}
+
+pub(crate) fn demo_devices() -> Vec<DemoDevice> {
+    let pci = |s: &str| s.parse::<PCI::BUS_INFO>().unwrap();
+
+    vec![
+        DemoDevice {
+            pci: pci("0000:03:00.0"),
+            port: pci("0000:00:01.1"),
+            device_id: 0x73DF,
+            revision_id: 0xC1,
+            render_minor: 128,
+            card_minor: 1,
+            is_apu: false,
+            vram_size: 12 * GIB,
+            gtt_size: 16 * GIB,
+            min_max_gpu_clock: (500, 2581),
+            min_max_memory_clock: (96, 1000),
+            power_cap: 186,
+            phase: 0.0,
+            processes: vec![
+                DemoProcess {
+                    pid: 1201,
+                    name: "Xorg",
+                    cmdline: "/usr/lib/xorg/Xorg vt2 -displayfd 3",
+                    vram: 180 * MIB,
+                    gtt: 24 * MIB,
+                    engines: &[(Engine::Gfx, 0.03)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 1402,
+                    name: "gnome-shell",
+                    cmdline: "/usr/bin/gnome-shell --wayland",
+                    vram: 260 * MIB,
+                    gtt: 48 * MIB,
+                    engines: &[(Engine::Gfx, 0.1), (Engine::Dma, 0.2)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 2301,
+                    name: "Game.exe",
+                    cmdline: "Z:\\games\\Game\\Game.exe -dx12",
+                    vram: 5 * GIB,
+                    gtt: 512 * MIB,
+                    engines: &[(Engine::Gfx, 0.72), (Engine::Compute, 0.1), (Engine::Dma, 0.6)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 2402,
+                    name: "mpv",
+                    cmdline: "/usr/bin/mpv --hwdec=vaapi video.mkv",
+                    vram: 220 * MIB,
+                    gtt: 16 * MIB,
+                    engines: &[(Engine::Gfx, 0.05), (Engine::Dec, 1.0)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 2501,
+                    name: "python3",
+                    cmdline: "/usr/bin/python3 train.py --device cuda",
+                    vram: 2 * GIB,
+                    gtt: 256 * MIB,
+                    engines: &[(Engine::Compute, 0.6)],
+                    is_kfd_process: true,
+                    npu: false,
+                },
+            ],
+            npu: None,
+        },
+        DemoDevice {
+            pci: pci("0000:c4:00.0"),
+            port: pci("0000:00:08.1"),
+            device_id: 0x15BF,
+            revision_id: 0xC4,
+            render_minor: 129,
+            card_minor: 2,
+            is_apu: true,
+            vram_size: 512 * MIB,
+            gtt_size: 16 * GIB,
+            min_max_gpu_clock: (800, 2700),
+            min_max_memory_clock: (400, 2800),
+            power_cap: 54,
+            phase: 1.7,
+            processes: vec![
+                DemoProcess {
+                    pid: 3101,
+                    name: "firefox",
+                    cmdline: "/usr/lib/firefox/firefox -contentproc",
+                    vram: 120 * MIB,
+                    gtt: 380 * MIB,
+                    engines: &[(Engine::Gfx, 0.3), (Engine::Enc, 0.4), (Engine::Dec, 0.6)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 3202,
+                    name: "obs",
+                    cmdline: "/usr/bin/obs --startrecording",
+                    vram: 96 * MIB,
+                    gtt: 220 * MIB,
+                    engines: &[(Engine::Gfx, 0.2), (Engine::Enc, 0.6)],
+                    is_kfd_process: false,
+                    npu: false,
+                },
+                DemoProcess {
+                    pid: 3301,
+                    name: "llm-server",
+                    cmdline: "/opt/ryzenai/bin/llm-server --model phi-3",
+                    vram: 0,
+                    gtt: 2 * GIB,
+                    engines: &[(Engine::Npu, 0.9)],
+                    is_kfd_process: false,
+                    npu: true,
+                },
+            ],
+            npu: Some(DemoNpu {
+                pci: pci("0000:c5:00.1"),
+                device_id: 0x1502,
+                fw_version: "1.5.2.380",
+                vbnv: "RyzenAI-npu1",
+            }),
+        },
+    ]
+}
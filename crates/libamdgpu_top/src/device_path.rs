@@ -8,22 +8,28 @@ use crate::{
     },
     PCI,
 };
+use crate::backend::{self, DeviceBackend};
 use crate::stat::ProcInfo;
-use std::path::PathBuf;
-use std::fs;
+use crate::root;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
 use std::fmt;
 use std::sync::{Arc, Mutex};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DevicePath {
+    #[serde(skip)]
     pub libdrm_amdgpu: Option<LibDrmAmdgpu>,
     pub render: PathBuf,
     pub card: PathBuf,
+    #[serde(with = "crate::record::remote")]
     pub pci: PCI::BUS_INFO,
     pub sysfs_path: PathBuf,
     pub device_id: Option<u32>,
     pub revision_id: Option<u32>,
     pub device_name: String,
+    #[serde(skip)]
     pub arc_proc_index: Arc<Mutex<Vec<ProcInfo>>>,
 }
 
@@ -38,7 +44,7 @@ impl DevicePath {
                 .map_err(|v| anyhow!(v))?;
             // need write option for GUI context
             // https://gitlab.freedesktop.org/mesa/mesa/-/issues/2424
-            let f = fs::OpenOptions::new().read(true).write(true).open(&self.render)?;
+            let f = fs::OpenOptions::new().read(true).write(true).open(root::dev_path(&self.render))?;
 
             libdrm_amdgpu.init_device_handle(f.into_raw_fd())
                 .map_err(|v| anyhow!(v))
@@ -48,9 +54,18 @@ impl DevicePath {
         Ok(amdgpu_dev)
     }
 
+    /// Returns the registered synthetic backend for the device, or the libdrm device handle.
+    pub fn init_backend(&self) -> anyhow::Result<Box<dyn DeviceBackend>> {
+        if let Some(backend) = backend::init_synthetic_device(&self.pci) {
+            return Ok(backend);
+        }
+
+        Ok(Box::new(self.init()?))
+    }
+
     pub fn get_device_path_list() -> Vec<Self> {
         let libdrm_amdgpu = LibDrmAmdgpu::new().ok();
-        let amdgpu_devices = fs::read_dir("/sys/bus/pci/drivers/amdgpu/").unwrap_or_else(|_| {
+        let amdgpu_devices = fs::read_dir(root::sys_path("bus/pci/drivers/amdgpu/")).unwrap_or_else(|_| {
             eprintln!("The AMDGPU driver is not loaded.");
             panic!();
         });
@@ -89,7 +104,7 @@ impl DevicePath {
             format!("drm_render_minor {}", &render.get(PRE_LEN..)?)
         };
 
-        let dirs = fs::read_dir("/sys/class/kfd/kfd/topology/nodes/").ok()?;
+        let dirs = fs::read_dir(root::sys_path("class/kfd/kfd/topology/nodes/")).ok()?;
         let mut gfx_target_version = String::new();
 
         'node: for dir_entry in dirs.flatten() {
@@ -128,10 +143,12 @@ impl TryFrom<PCI::BUS_INFO> for DevicePath {
     type Error = std::io::Error;
 
     fn try_from(pci: PCI::BUS_INFO) -> Result<Self, Self::Error> {
-        let render = pci.get_drm_render_path()?;
-        let card = pci.get_drm_card_path()?;
-        let sysfs_path = pci.get_sysfs_path();
-        let [device_id, revision_id] = [pci.get_device_id(), pci.get_revision_id()];
+        let sysfs_path = root::sys_path(format!("bus/pci/devices/{pci}"));
+        let [render, card] = get_drm_node_paths(&sysfs_path)?;
+        let [device_id, revision_id] = [
+            parse_sysfs_hex(sysfs_path.join("device")),
+            parse_sysfs_hex(sysfs_path.join("revision")),
+        ];
         let device_name = String::new();
         let arc_proc_index = Arc::new(Mutex::new(Vec::new()));
 
@@ -149,6 +166,40 @@ impl TryFrom<PCI::BUS_INFO> for DevicePath {
     }
 }
 
+/// Returns the paths of the render node and the primary node (e.g. `/dev/dri/renderD128`, `/dev/dri/card0`)
+/// as they appear on the target system, not mapped to the devfs root.
+/// Accel devices (e.g. `/dev/accel/accel0`) only have one node.
+fn get_drm_node_paths(sysfs_path: &Path) -> io::Result<[PathBuf; 2]> {
+    let find_node = |dir: &str, prefix: &str| -> Option<PathBuf> {
+        fs::read_dir(sysfs_path.join(dir)).ok()?.find_map(|dir_entry| {
+            let name = dir_entry.ok()?.file_name().into_string().ok()?;
+
+            name.starts_with(prefix).then(|| PathBuf::from(format!("/dev/{dir}/{name}")))
+        })
+    };
+
+    if let [Some(render), Some(card)] = [find_node("drm", "renderD"), find_node("drm", "card")] {
+        return Ok([render, card]);
+    }
+
+    if let Some(accel) = find_node("accel", "accel") {
+        return Ok([accel.clone(), accel]);
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("DRM node not found: {sysfs_path:?}"),
+    ))
+}
+
+/// Parse sysfs files like `device`, `vendor` (e.g. "0x1002\n").
+pub(crate) fn parse_sysfs_hex<P: AsRef<Path>>(path: P) -> Option<u32> {
+    let s = fs::read_to_string(path.as_ref()).ok()?;
+    let s = s.trim_end();
+
+    u32::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+}
+
 impl fmt::Debug for DevicePath {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("DevicePath")
@@ -9,7 +9,7 @@ use crate::{
     drmModeModeInfo,
     drm_mode_property_enum,
 };
-use crate::DevicePath;
+use crate::{root, DevicePath};
 
 #[derive(Debug, Clone)]
 pub struct ConnectorInfo {
@@ -64,7 +64,7 @@ pub fn connector_info(device_path: &DevicePath) -> Vec<ConnectorInfo> {
     let fd = {
         use std::os::fd::IntoRawFd;
 
-        let Some(f) = File::open(&device_path.card).ok() else { return Vec::new() };
+        let Some(f) = File::open(root::dev_path(&device_path.card)).ok() else { return Vec::new() };
 
         f.into_raw_fd()
     };
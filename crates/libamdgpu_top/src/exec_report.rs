@@ -0,0 +1,210 @@
+//! GPU usage of a launched command and its descendants (`--exec`).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::PCI;
+use crate::app::AppAmdgpuTop;
+use crate::stat::{self, EngineUsage};
+
+#[derive(Debug, Clone, Default)]
+pub struct DeviceReport {
+    pub pci: PCI::BUS_INFO,
+    pub device_name: String,
+    /// Total busy time of the clients opened by the command, `usage` is not used
+    pub engines: Vec<EngineUsage>,
+    pub peak_vram: u64, // KiB
+    pub avg_vram: u64, // KiB
+    pub peak_gtt: u64, // KiB
+    pub avg_gtt: u64, // KiB
+    pub peak_evicted_vram: u64, // KiB
+    pub energy: Option<f64>, // J, consumed by the device during the run
+    pub job_energy: Option<f64>, // J, attributed to the command by the engine usage
+    pub peak_temp: Option<i64>, // C, edge or junction
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecReport {
+    pub command: Vec<String>,
+    pub wall_time: Duration,
+    pub exit_code: i32,
+    /// Devices used by the command
+    pub devices: Vec<DeviceReport>,
+}
+
+struct DeviceTracker {
+    app: AppAmdgpuTop,
+    clients: HashMap<usize, Vec<EngineUsage>>,
+    proc_energy: HashMap<i32, f64>, // J
+    samples: u64,
+    sum_vram: u64,
+    sum_gtt: u64,
+    report: DeviceReport,
+}
+
+impl DeviceTracker {
+    fn new(app: AppAmdgpuTop) -> Self {
+        let report = DeviceReport {
+            pci: app.device_info.pci_bus,
+            device_name: app.device_info.marketing_name.clone(),
+            ..Default::default()
+        };
+
+        Self {
+            app,
+            clients: HashMap::new(),
+            proc_energy: HashMap::new(),
+            samples: 0,
+            sum_vram: 0,
+            sum_gtt: 0,
+            report,
+        }
+    }
+
+    fn update(&mut self, pids: &[i32], elapsed: Duration) {
+        // All processes on the device are indexed, the energy is shared with the other processes.
+        stat::update_index(&mut self.app.stat.arc_proc_index.lock().unwrap(), &self.app.device_path);
+
+        self.app.update(elapsed);
+
+        let stat = &self.app.stat;
+        let job = || stat.fdinfo.proc_usage.iter().filter(|pu| pids.contains(&pu.pid));
+
+        // The busy time of a client only increases, the last value is the total.
+        for client in job().flat_map(|pu| &pu.clients) {
+            self.clients.insert(client.client_id, client.engines.clone());
+        }
+
+        // Keep the energy of the exited processes.
+        for pu in job() {
+            self.proc_energy.insert(pu.pid, pu.energy);
+        }
+
+        let usage = job().fold(stat::FdInfoUsage::default(), |acc, pu| acc + pu.usage);
+        let report = &mut self.report;
+
+        self.samples += 1;
+        self.sum_vram += usage.vram_usage;
+        self.sum_gtt += usage.gtt_usage;
+        report.peak_vram = report.peak_vram.max(usage.vram_usage);
+        report.peak_gtt = report.peak_gtt.max(usage.gtt_usage);
+        report.peak_evicted_vram = report.peak_evicted_vram.max(usage.amd_evicted_vram);
+        report.avg_vram = self.sum_vram / self.samples;
+        report.avg_gtt = self.sum_gtt / self.samples;
+
+        if stat.energy.source.is_some() {
+            report.energy = Some(stat.energy.total);
+            report.job_energy = Some(self.proc_energy.values().sum());
+        }
+
+        let Some(sensors) = &stat.sensors else { return };
+
+        let temp = [&sensors.edge_temp, &sensors.junction_temp]
+            .into_iter()
+            .filter_map(|t| t.as_ref().map(|t| t.current as i64))
+            .max();
+
+        report.peak_temp = report.peak_temp.max(temp);
+    }
+
+    fn report(&self) -> DeviceReport {
+        let mut engines = Vec::new();
+
+        for client_engines in self.clients.values() {
+            EngineUsage::merge(&mut engines, client_engines);
+        }
+
+        engines.sort_by(|a, b| a.name.cmp(&b.name));
+
+        DeviceReport { engines, ..self.report.clone() }
+    }
+}
+
+/// Track a process and its descendants through fdinfo.
+/// The usage is sampled on [`ExecTracker::update`], so the usage of a client after the last
+/// update before it is closed is not counted.
+pub struct ExecTracker {
+    pid: i32,
+    start: Instant,
+    last: Instant,
+    devices: Vec<DeviceTracker>,
+}
+
+impl ExecTracker {
+    pub fn new(pid: i32, apps: Vec<AppAmdgpuTop>) -> Self {
+        let now = Instant::now();
+
+        Self {
+            pid,
+            start: now,
+            last: now,
+            devices: apps.into_iter().map(DeviceTracker::new).collect(),
+        }
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last);
+        let pids = stat::list_descendants(self.pid);
+
+        self.last = now;
+
+        for device in self.devices.iter_mut() {
+            device.update(&pids, elapsed);
+        }
+    }
+
+    pub fn report(&self, command: &[String], exit_code: i32) -> ExecReport {
+        ExecReport {
+            command: command.to_vec(),
+            wall_time: self.start.elapsed(),
+            exit_code,
+            devices: self.devices
+                .iter()
+                .filter(|d| !d.clients.is_empty())
+                .map(|d| d.report())
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for ExecReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mib = |kib: u64| kib >> 10;
+
+        writeln!(f, "command   {}", self.command.join(" "))?;
+        writeln!(f, "exit code {}", self.exit_code)?;
+        writeln!(f, "real      {:.3}s", self.wall_time.as_secs_f64())?;
+
+        if self.devices.is_empty() {
+            writeln!(f, "No AMD GPU was used.")?;
+        }
+
+        for d in &self.devices {
+            writeln!(f, "{} ({})", d.device_name, d.pci)?;
+
+            for e in &d.engines {
+                writeln!(f, "  {:<16}{:>10.3}s", e.name, e.busy as f64 / 1_000_000_000.0)?;
+            }
+
+            writeln!(f, "  {:<16}{:>7} MiB (peak), {} MiB (avg)", "VRAM", mib(d.peak_vram), mib(d.avg_vram))?;
+            writeln!(f, "  {:<16}{:>7} MiB (peak), {} MiB (avg)", "GTT", mib(d.peak_gtt), mib(d.avg_gtt))?;
+            writeln!(f, "  {:<16}{:>7} MiB (peak)", "Evicted VRAM", mib(d.peak_evicted_vram))?;
+
+            if let Some(energy) = d.energy {
+                writeln!(f, "  {:<16}{:>9.1} J (device)", "Energy", energy)?;
+            }
+
+            if let Some(energy) = d.job_energy {
+                writeln!(f, "  {:<16}{:>9.1} J ({:.6} kWh)", "Job Energy", energy, energy / 3_600_000.0)?;
+            }
+
+            if let Some(temp) = d.peak_temp {
+                writeln!(f, "  {:<16}{:>7} C (peak)", "Temperature", temp)?;
+            }
+        }
+
+        Ok(())
+    }
+}
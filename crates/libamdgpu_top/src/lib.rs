@@ -1,9 +1,10 @@
 use std::time::Duration;
 use std::path::PathBuf;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
 pub use libdrm_amdgpu_sys::*;
 use libdrm_amdgpu_sys::AMDGPU::{
     CHIP_CLASS,
-    DeviceHandle,
     drm_amdgpu_memory_info,
     HW_IP::{HW_IP_TYPE, HwIpInfo},
 };
@@ -13,7 +14,21 @@ pub use app_device_info::*;
 
 pub mod stat;
 pub mod app;
+pub mod backend;
+use backend::DeviceBackend;
 pub mod xdna;
+pub mod record;
+pub mod demo;
+pub mod alert;
+pub mod session_stats;
+pub mod exec_report;
+pub mod config;
+pub mod control;
+pub mod profile_daemon;
+pub mod signal;
+mod toml_file;
+
+pub mod root;
 
 mod device_path;
 pub use device_path::DevicePath;
@@ -39,6 +54,14 @@ pub struct UiArgs {
     pub is_dark_mode: Option<bool>, // TUI, GUI
     pub hide_fdinfo: bool, // TUI
     pub gui_wgpu_backend: GuiWgpuBackend,
+    pub record: Option<PathBuf>, // TUI, GUI
+    pub replay: Option<Arc<record::Recording>>, // TUI, GUI
+    pub alert_rules: Option<Arc<alert::AlertRules>>, // TUI, GUI
+    pub settings: config::Settings, // TUI, GUI
+    pub config_path: Option<PathBuf>, // TUI, GUI
+    pub session_stats: bool, // TUI, SMI, GUI
+    pub enable_control: bool, // TUI, GUI
+    pub gfxoff_residency: bool, // TUI, SMI, GUI
 }
 
 pub struct Sampling {
@@ -72,15 +95,15 @@ impl Sampling {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct VramUsage(pub drm_amdgpu_memory_info);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VramUsage(#[serde(with = "crate::record::remote")] pub drm_amdgpu_memory_info);
 
 impl VramUsage {
     pub fn new(memory_info: &drm_amdgpu_memory_info) -> Self {
         Self(*memory_info)
     }
 
-    pub fn update_usage(&mut self, amdgpu_dev: &DeviceHandle) {
+    pub fn update_usage(&mut self, amdgpu_dev: &dyn DeviceBackend) {
         if let [Ok(vram), Ok(vis_vram), Ok(gtt)] = [
             amdgpu_dev.vram_usage_info(),
             amdgpu_dev.vis_vram_usage_info(),
@@ -92,7 +115,7 @@ impl VramUsage {
         }
     }
 
-    pub fn update_usable_heap_size(&mut self, amdgpu_dev: &DeviceHandle) {
+    pub fn update_usable_heap_size(&mut self, amdgpu_dev: &dyn DeviceBackend) {
         let Ok(info) = amdgpu_dev.vram_gtt_info() else { return };
 
         self.0.vram.usable_heap_size = info.vram_size;
@@ -101,22 +124,22 @@ impl VramUsage {
     }
 }
 
-pub fn has_vcn(amdgpu_dev: &DeviceHandle) -> bool {
+pub fn has_vcn(amdgpu_dev: &dyn DeviceBackend) -> bool {
     amdgpu_dev.get_hw_ip_info(HW_IP_TYPE::VCN_DEC).is_ok()
 }
 
-pub fn has_vcn_unified(amdgpu_dev: &DeviceHandle) -> bool {
+pub fn has_vcn_unified(amdgpu_dev: &dyn DeviceBackend) -> bool {
     let Ok(ip) = amdgpu_dev.get_hw_ip_info(HW_IP_TYPE::VCN_ENC) else { return false };
 
     4 <= ip.info.hw_ip_version_major
 }
 
-pub fn has_vpe(amdgpu_dev: &DeviceHandle) -> bool {
+pub fn has_vpe(amdgpu_dev: &dyn DeviceBackend) -> bool {
     amdgpu_dev.get_hw_ip_info(HW_IP_TYPE::VPE).is_ok()
 }
 
 pub fn get_hw_ip_info_list(
-    amdgpu_dev: &DeviceHandle,
+    amdgpu_dev: &dyn DeviceBackend,
     chip_class: CHIP_CLASS,
 ) -> Vec<HwIpInfo> {
     const HW_IP_LIST: &[HW_IP_TYPE] = &[
@@ -93,7 +93,7 @@ impl fmt::Display for PpFeatureMask {
 
 impl PpFeatureMask {
     pub fn get_param_u32() -> Option<u32> {
-        let s = std::fs::read_to_string("/sys/module/amdgpu/parameters/ppfeaturemask").ok()?;
+        let s = std::fs::read_to_string(crate::root::sys_path("module/amdgpu/parameters/ppfeaturemask")).ok()?;
         let len = s.len();
 
         s.get(if s.starts_with("0x") {
@@ -103,6 +103,10 @@ impl PpFeatureMask {
         }).and_then(|param| u32::from_str_radix(param, 16).ok())
     }
 
+    pub fn is_overdrive_enabled() -> bool {
+        Self::get_param_u32().is_some_and(|n| (n & MaskValue::PP_OVERDRIVE_MASK) != 0)
+    }
+
     pub fn get_all_enabled_feature() -> Vec<Self> {
         let Some(mut n) = Self::get_param_u32() else { return Vec::new() };
         let mut vec: Vec<Self> = Vec::with_capacity(32);
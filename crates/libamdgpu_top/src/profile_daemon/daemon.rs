@@ -0,0 +1,274 @@
+use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::PCI;
+use crate::app::AppAmdgpuTop;
+use crate::control::PowerControl;
+use super::{ProfileRules, ProfileSettings};
+
+const DEFAULT_RULE: &str = "default";
+const EXIT_RULE: &str = "exit";
+
+/// A change of a setting, or a failed attempt
+#[derive(Debug, Clone)]
+pub struct ProfileChange {
+    pub pci: PCI::BUS_INFO,
+    /// The rule name, "default" or "exit"
+    pub rule: String,
+    /// The process that matched the rule, e.g. "python3 (1234)"
+    pub reason: Option<String>,
+    /// "power_profile", "perf_level" or "power_cap"
+    pub setting: &'static str,
+    pub from: Option<String>,
+    pub to: String,
+    pub error: Option<String>,
+    pub time: SystemTime,
+}
+
+impl ProfileChange {
+    pub fn unix_time(&self) -> u64 {
+        self.time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+
+    fn log_line(&self) -> String {
+        format!("{} {self}\n", self.unix_time())
+    }
+}
+
+impl std::fmt::Display for ProfileChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}: {} -> {}",
+            self.pci,
+            self.rule,
+            self.setting,
+            self.from.as_deref().unwrap_or("-"),
+            self.to,
+        )?;
+
+        if let Some(reason) = &self.reason {
+            write!(f, " ({reason})")?;
+        }
+
+        if let Some(err) = &self.error {
+            write!(f, " failed: {err}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Switch to the matched rule after it has been matched for the delay.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Hysteresis {
+    /// The index of the applied rule, `None` for the default
+    active: Option<usize>,
+    /// The matched rule, and since when
+    pending: Option<(Option<usize>, Instant)>,
+}
+
+impl Hysteresis {
+    /// Returns the rule to switch to.
+    fn update(
+        &mut self,
+        matched: Option<usize>,
+        now: Instant,
+        start_delay: Duration,
+        stop_delay: Duration,
+    ) -> Option<Option<usize>> {
+        if matched == self.active {
+            self.pending = None;
+            return None;
+        }
+
+        let since = match self.pending {
+            Some((m, since)) if m == matched => since,
+            _ => {
+                self.pending = Some((matched, now));
+                now
+            },
+        };
+        let delay = if matched.is_some() { start_delay } else { stop_delay };
+
+        if now.duration_since(since) < delay { return None }
+
+        self.active = matched;
+        self.pending = None;
+
+        Some(matched)
+    }
+}
+
+struct DeviceState {
+    control: PowerControl,
+    /// The settings before the daemon started
+    original: ProfileSettings,
+    hysteresis: Hysteresis,
+}
+
+impl DeviceState {
+    fn new(pci: PCI::BUS_INFO) -> Self {
+        let control = PowerControl::new(pci);
+        let original = current_settings(&control);
+
+        Self { control, original, hysteresis: Hysteresis::default() }
+    }
+}
+
+fn current_settings(control: &PowerControl) -> ProfileSettings {
+    ProfileSettings {
+        power_profile: control.power_profile().map(|profile| profile.to_string()),
+        perf_level: control.performance_level(),
+        power_cap: control.power_cap().map(|cap| cap.current),
+    }
+}
+
+/// Applies the profile rules to the devices.
+pub struct ProfileDaemon {
+    rules: ProfileRules,
+    devices: Vec<DeviceState>,
+}
+
+impl ProfileDaemon {
+    pub fn new(rules: ProfileRules) -> Self {
+        Self { rules, devices: Vec::new() }
+    }
+
+    fn device(&mut self, pci: PCI::BUS_INFO) -> &mut DeviceState {
+        let pos = match self.devices.iter().position(|d| d.control.pci_bus == pci) {
+            Some(pos) => pos,
+            None => {
+                self.devices.push(DeviceState::new(pci));
+                self.devices.len() - 1
+            },
+        };
+
+        &mut self.devices[pos]
+    }
+
+    /// Find the matched rule with the current processes, apply it after the delay,
+    /// and return the changes. The changes are also appended to the `log` file.
+    pub fn check(&mut self, app: &AppAmdgpuTop) -> Vec<ProfileChange> {
+        let pci = app.device_info.pci_bus;
+        let matched = self.rules.find(pci, &app.stat.fdinfo.proc_usage);
+        let reason = matched.map(|(_, pu)| format!("{} ({})", pu.name, pu.pid));
+        let (start_delay, stop_delay) = (self.rules.start_delay, self.rules.stop_delay);
+        let device = self.device(pci);
+
+        let Some(target) = device.hysteresis.update(
+            matched.map(|(i, _)| i),
+            Instant::now(),
+            start_delay,
+            stop_delay,
+        ) else { return Vec::new() };
+
+        let (name, settings) = match target {
+            Some(i) => {
+                let rule = &self.rules.rules[i];
+                (rule.name.clone(), rule.settings.or(&self.rules.default))
+            },
+            None => (DEFAULT_RULE.to_string(), self.rules.default.clone()),
+        };
+        let device = self.device(pci);
+        let settings = settings.or(&device.original);
+        let changes = apply(&device.control, &settings, &name, reason);
+
+        self.log(&changes);
+
+        changes
+    }
+
+    /// Restore the settings before the daemon started.
+    pub fn restore(&mut self) -> Vec<ProfileChange> {
+        let changes: Vec<ProfileChange> = self.devices
+            .iter()
+            .flat_map(|device| apply(&device.control, &device.original, EXIT_RULE, None))
+            .collect();
+
+        self.log(&changes);
+
+        changes
+    }
+
+    fn log(&self, changes: &[ProfileChange]) {
+        let Some(path) = &self.rules.log else { return };
+        let Ok(mut f) = fs::OpenOptions::new().create(true).append(true).open(path) else { return };
+
+        for change in changes {
+            let _ = f.write_all(change.log_line().as_bytes());
+        }
+    }
+}
+
+/// Write the settings that differ from the current values.
+fn apply(
+    control: &PowerControl,
+    settings: &ProfileSettings,
+    rule: &str,
+    reason: Option<String>,
+) -> Vec<ProfileChange> {
+    let current = current_settings(control);
+    let mut changes = Vec::new();
+    let mut push = |setting, from: Option<String>, to: String, r: anyhow::Result<()>| {
+        changes.push(ProfileChange {
+            pci: control.pci_bus,
+            rule: rule.to_string(),
+            reason: reason.clone(),
+            setting,
+            from,
+            to,
+            error: r.err().map(|err| format!("{err:#}")),
+            time: SystemTime::now(),
+        });
+    };
+
+    // The performance level first, some devices accept the power profile only in "manual".
+    if let Some(level) = settings.perf_level.filter(|level| Some(*level) != current.perf_level) {
+        let r = control.set_performance_level(level);
+        push("perf_level", current.perf_level.map(|l| l.to_string()), level.to_string(), r);
+    }
+
+    if let Some(profile) = settings.power_profile.as_ref().filter(|p| {
+        !control.power_profiles().iter().any(|mode| mode.is_current && mode.matches(p))
+    }) {
+        let r = control.set_power_profile(profile);
+        push("power_profile", current.power_profile.clone(), profile.clone(), r);
+    }
+
+    if let Some(watts) = settings.power_cap.filter(|w| Some(*w) != current.power_cap) {
+        let r = control.set_power_cap(watts);
+        push("power_cap", current.power_cap.map(|w| format!("{w} W")), format!("{watts} W"), r);
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis() {
+        let mut h = Hysteresis::default();
+        let t = Instant::now();
+        let s = |secs: u64| t + Duration::from_secs(secs);
+        let (start, stop) = (Duration::from_secs(2), Duration::from_secs(10));
+
+        assert_eq!(h.update(Some(0), t, start, stop), None);
+        assert_eq!(h.update(Some(0), s(1), start, stop), None);
+        assert_eq!(h.update(Some(0), s(2), start, stop), Some(Some(0)));
+        assert_eq!(h.update(Some(0), s(3), start, stop), None);
+        // the process stopped for a moment
+        assert_eq!(h.update(None, s(4), start, stop), None);
+        assert_eq!(h.update(Some(0), s(5), start, stop), None);
+        assert_eq!(h.update(None, s(6), start, stop), None);
+        assert_eq!(h.update(None, s(15), start, stop), None);
+        assert_eq!(h.update(None, s(16), start, stop), Some(None));
+        // a rule with the higher priority
+        assert_eq!(h.update(Some(1), s(17), start, stop), None);
+        assert_eq!(h.update(Some(0), s(18), start, stop), None);
+        assert_eq!(h.update(Some(0), s(20), start, stop), Some(Some(0)));
+    }
+}
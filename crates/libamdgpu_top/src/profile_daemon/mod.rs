@@ -0,0 +1,38 @@
+//! Switch the power profile, the performance level and the power cap of the devices
+//! when the configured processes start or stop.
+//!
+//! The rules are read from a file, e.g.:
+//!
+//! ```text
+//! # A rule must match for `start_delay` seconds to be applied,
+//! # and no rule must match for `stop_delay` seconds to restore the default.
+//! start_delay = 2
+//! stop_delay = 10
+//! log = "/var/log/amdgpu_top_profile.log"
+//!
+//! # ROCm/HIP (KFD) processes
+//! [rocm]
+//! kfd = true
+//! power_profile = "COMPUTE"
+//!
+//! # ffmpeg while it uses the media engine
+//! [ffmpeg]
+//! process = "ffmpeg"
+//! if = "proc.media > 0"
+//! power_profile = "VIDEO"
+//! power_cap = 150
+//!
+//! # When no rule matches
+//! [default]
+//! power_profile = "BOOTUP_DEFAULT"
+//! ```
+//!
+//! The first rule that matches a process of the device is applied.
+//! The settings not set by the rule or `[default]` are kept at the values
+//! before [`ProfileDaemon`] started, and they are restored by [`ProfileDaemon::restore`].
+
+mod rule;
+pub use rule::*;
+
+mod daemon;
+pub use daemon::*;
@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+
+use crate::PCI;
+use crate::alert::{Comparison, Condition, Metric};
+use crate::control::PerformanceLevel;
+use crate::stat::ProcUsage;
+use crate::toml_file;
+
+const DEFAULT_SECTION: &str = "default";
+const DEFAULT_START_DELAY: Duration = Duration::from_secs(2);
+const DEFAULT_STOP_DELAY: Duration = Duration::from_secs(10);
+
+/// Each field is `None` if it is not changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileSettings {
+    /// The name or the index in `pp_power_profile_mode`
+    pub power_profile: Option<String>,
+    #[serde(deserialize_with = "toml_file::from_str")]
+    pub perf_level: Option<PerformanceLevel>,
+    pub power_cap: Option<u32>, // W
+}
+
+impl ProfileSettings {
+    /// The settings of `self`, and the settings of `other` for the unset fields
+    pub fn or(&self, other: &Self) -> Self {
+        Self {
+            power_profile: self.power_profile.clone().or_else(|| other.power_profile.clone()),
+            perf_level: self.perf_level.or(other.perf_level),
+            power_cap: self.power_cap.or(other.power_cap),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileRule {
+    pub name: String,
+    /// Only for the device
+    pub pci: Option<PCI::BUS_INFO>,
+    /// Matched against the process name
+    pub process: Option<String>,
+    /// Only for the processes using KFD (ROCm/HIP)
+    pub kfd: bool,
+    /// The usage of the process, e.g. "proc.media > 0"
+    pub condition: Option<Condition>,
+    pub settings: ProfileSettings,
+}
+
+impl ProfileRule {
+    pub fn matches(&self, proc_usage: &ProcUsage) -> bool {
+        if self.kfd && !proc_usage.is_kfd_process {
+            return false;
+        }
+
+        if self.process.as_ref().is_some_and(|name| *name != proc_usage.name) {
+            return false;
+        }
+
+        match &self.condition {
+            Some(Condition { metric: Metric::Proc(m), comparison }) => comparison.check(m.value(proc_usage), None),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileRules {
+    pub rules: Vec<ProfileRule>,
+    /// `[default]`, applied when no rule matches
+    pub default: ProfileSettings,
+    /// A rule must match for this duration to be applied
+    pub start_delay: Duration,
+    /// No rule must match for this duration to restore the default
+    pub stop_delay: Duration,
+    /// Append a line to the file for every change
+    pub log: Option<PathBuf>,
+}
+
+impl Default for ProfileRules {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            default: ProfileSettings::default(),
+            start_delay: DEFAULT_START_DELAY,
+            stop_delay: DEFAULT_STOP_DELAY,
+            log: None,
+        }
+    }
+}
+
+/// A table of the profile rules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleTable {
+    #[serde(default)]
+    pci: Option<String>,
+    #[serde(default)]
+    process: Option<String>,
+    #[serde(default)]
+    kfd: bool,
+    #[serde(default, rename = "if", deserialize_with = "toml_file::from_str")]
+    condition: Option<Condition>,
+    #[serde(default)]
+    power_profile: Option<String>,
+    #[serde(default, deserialize_with = "toml_file::from_str")]
+    perf_level: Option<PerformanceLevel>,
+    #[serde(default)]
+    power_cap: Option<u32>,
+}
+
+impl RuleTable {
+    fn build(self, name: String) -> anyhow::Result<ProfileRule> {
+        let pci = self.pci
+            .map(|pci| pci.parse().map_err(|_| anyhow!("[{name}]: invalid PCI bus: {pci:?}")))
+            .transpose()?;
+
+        if let Some(condition) = &self.condition {
+            if !condition.metric.is_proc() || condition.comparison == Comparison::Increases {
+                bail!("[{name}]: expected \"proc.<metric> <op> <value>\": \"{condition}\"");
+            }
+        }
+
+        Ok(ProfileRule {
+            name,
+            pci,
+            process: self.process,
+            kfd: self.kfd,
+            condition: self.condition,
+            settings: ProfileSettings {
+                power_profile: self.power_profile,
+                perf_level: self.perf_level,
+                power_cap: self.power_cap,
+            },
+        })
+    }
+}
+
+impl ProfileRules {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+
+        Self::parse(&s).with_context(|| format!("Invalid profile rules: {path:?}"))
+    }
+
+    /// The top-level keys are the options, `[default]` is the default settings,
+    /// and the other tables are the rules, in the order of the file.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut rules = Self::default();
+
+        for (name, val) in toml_file::parse_ordered(s)? {
+            let secs = |val: toml::Value| -> anyhow::Result<Duration> {
+                let secs: f64 = val.try_into().with_context(|| format!("\"{name}\""))?;
+
+                Ok(Duration::try_from_secs_f64(secs)?)
+            };
+
+            match name.as_str() {
+                "start_delay" => rules.start_delay = secs(val)?,
+                "stop_delay" => rules.stop_delay = secs(val)?,
+                "log" => rules.log = Some(val.try_into().context("\"log\"")?),
+                DEFAULT_SECTION => rules.default = val.try_into().with_context(|| format!("[{name}]"))?,
+                _ if val.is_table() => {
+                    let rule: RuleTable = val.try_into().with_context(|| format!("[{name}]"))?;
+
+                    rules.push(rule.build(name)?)?;
+                },
+                _ => bail!("unknown key: {name:?}"),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    fn push(&mut self, rule: ProfileRule) -> anyhow::Result<()> {
+        let name = &rule.name;
+
+        if rule.settings.is_empty() {
+            bail!("[{name}]: no settings (power_profile, perf_level, power_cap)");
+        }
+
+        if !rule.kfd && rule.process.is_none() && rule.condition.is_none() {
+            bail!("[{name}]: no conditions (process, kfd, if)");
+        }
+
+        self.rules.push(rule);
+
+        Ok(())
+    }
+
+    /// The first rule that matches a process of the device, and the process
+    pub fn find<'a>(
+        &self,
+        pci: PCI::BUS_INFO,
+        proc_usage: &'a [ProcUsage],
+    ) -> Option<(usize, &'a ProcUsage)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.pci.is_none_or(|p| p == pci))
+            .find_map(|(i, rule)| Some((i, proc_usage.iter().find(|pu| rule.matches(pu))?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stat::FdInfoUsage;
+
+    #[test]
+    fn test_parse_profile_rules() {
+        let s = concat!(
+            "start_delay = 1.5\n",
+            "log = \"/tmp/profile.log\"\n",
+            "[rocm]\n",
+            "kfd = true\n",
+            "power_profile = \"COMPUTE\"\n",
+            "[ffmpeg]\n",
+            "process = \"ffmpeg\"\n",
+            "if = \"proc.media > 0\"\n",
+            "power_profile = \"VIDEO\"\n",
+            "power_cap = 150\n",
+            "[default]\n",
+            "power_profile = \"BOOTUP_DEFAULT\"\n",
+            "perf_level = \"auto\"\n",
+        );
+        let rules = ProfileRules::parse(s).unwrap();
+
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.start_delay, Duration::from_millis(1500));
+        assert_eq!(rules.stop_delay, DEFAULT_STOP_DELAY);
+        assert_eq!(rules.rules[1].settings.power_cap, Some(150));
+        assert_eq!(rules.default.perf_level, Some(PerformanceLevel::Auto));
+        assert_eq!(
+            rules.rules[0].settings.or(&rules.default),
+            ProfileSettings {
+                power_profile: Some("COMPUTE".to_string()),
+                perf_level: Some(PerformanceLevel::Auto),
+                power_cap: None,
+            },
+        );
+
+        let ffmpeg = |media: f64| ProcUsage {
+            pid: 100,
+            name: "ffmpeg".to_string(),
+            usage: FdInfoUsage { media, ..Default::default() },
+            ..Default::default()
+        };
+        let python = ProcUsage { pid: 200, name: "python3".to_string(), is_kfd_process: true, ..Default::default() };
+        let pci: PCI::BUS_INFO = "0000:03:00.0".parse().unwrap();
+
+        assert_eq!(rules.find(pci, &[ffmpeg(0.0)]).map(|(i, _)| i), None);
+        assert_eq!(rules.find(pci, &[ffmpeg(10.0)]).map(|(i, _)| i), Some(1));
+        assert_eq!(rules.find(pci, &[ffmpeg(10.0), python.clone()]).map(|(i, pu)| (i, pu.pid)), Some((0, 200)));
+
+        assert!(ProfileRules::parse("[a]\nkfd = true\n").is_err());
+        assert!(ProfileRules::parse("[a]\npower_profile = \"COMPUTE\"\n").is_err());
+        assert!(ProfileRules::parse("[a]\nif = \"gfx > 1\"\npower_profile = \"COMPUTE\"\n").is_err());
+        assert!(ProfileRules::parse("[a]\nkfd = true\npower_profile = COMPUTE\n").is_err());
+        assert!(ProfileRules::parse("[default]\nprocess = \"a\"\n").is_err());
+        assert!(ProfileRules::parse("start_delay = 1.5s\n").is_err());
+    }
+}
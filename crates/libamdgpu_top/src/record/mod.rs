@@ -0,0 +1,584 @@
+//! Recording and replaying of sampling sessions.
+//!
+//! A recording starts with the magic and the format version,
+//! followed by the version of amdgpu_top and a sequence of [`Record`]s encoded with `bincode`.
+//! Device records hold the static [`AppDeviceInfo`],
+//! and frame records hold an [`AppAmdgpuTopStat`] snapshot with a timestamp.
+
+use anyhow::{bail, Context};
+use crate::app::{AppAmdgpuTop, AppAmdgpuTopStat};
+use crate::{AppDeviceInfo, DevicePath, PCI};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub(crate) mod remote;
+
+const MAGIC: &[u8; 8] = b"AGTREC\0\0";
+/// Bump when the layout of the recorded types changes, including an update of libdrm_amdgpu_sys.
+const FORMAT_VERSION: u32 = 1;
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Serialize, Deserialize)]
+enum Record<'a> {
+    Device {
+        index: usize,
+        device_path: Cow<'a, DevicePath>,
+        device_info: Cow<'a, AppDeviceInfo>,
+        xdna_device_path: Cow<'a, Option<DevicePath>>,
+        xdna_fw_version: Cow<'a, Option<String>>,
+    },
+    Frame {
+        index: usize,
+        time: Duration,
+        stat: Cow<'a, AppAmdgpuTopStat>,
+    },
+}
+
+fn to_io_error(err: bincode::Error) -> io::Error {
+    match *err {
+        bincode::ErrorKind::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
+
+pub struct Recorder {
+    writer: BufWriter<File>,
+    base_time: Instant,
+    devices: Vec<PCI::BUS_INFO>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, VERSION).map_err(to_io_error)?;
+
+        Ok(Self {
+            writer,
+            base_time: Instant::now(),
+            devices: Vec::new(),
+        })
+    }
+
+    fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        bincode::serialize_into(&mut self.writer, record).map_err(to_io_error)
+    }
+
+    /// Append the current stat of `app`.
+    /// The device info is written on the first call for each device.
+    pub fn record(&mut self, app: &AppAmdgpuTop) -> io::Result<()> {
+        let pci_bus = app.device_info.pci_bus;
+        let index = match self.devices.iter().position(|pci| *pci == pci_bus) {
+            Some(index) => index,
+            None => {
+                let index = self.devices.len();
+
+                self.write_record(&Record::Device {
+                    index,
+                    device_path: Cow::Borrowed(&app.device_path),
+                    device_info: Cow::Borrowed(&app.device_info),
+                    xdna_device_path: Cow::Borrowed(&app.xdna_device_path),
+                    xdna_fw_version: Cow::Borrowed(&app.xdna_fw_version),
+                })?;
+                self.devices.push(pci_bus);
+
+                index
+            },
+        };
+
+        self.write_record(&Record::Frame {
+            index,
+            time: self.base_time.elapsed(),
+            stat: Cow::Borrowed(&app.stat),
+        })?;
+        // flush every frame, so that an interrupted session can still be replayed
+        self.writer.flush()
+    }
+}
+
+#[derive(Clone)]
+pub struct RecordedDevice {
+    pub device_path: DevicePath,
+    pub device_info: AppDeviceInfo,
+    pub xdna_device_path: Option<DevicePath>,
+    pub xdna_fw_version: Option<String>,
+    pub frames: Vec<(Duration, AppAmdgpuTopStat)>,
+}
+
+impl RecordedDevice {
+    /// Returns the latest frame at `position`.
+    pub fn stat_at(&self, position: Duration) -> Option<&AppAmdgpuTopStat> {
+        let idx = self.frames.partition_point(|(time, _)| *time <= position);
+
+        self.frames.get(idx.saturating_sub(1)).map(|(_, stat)| stat)
+    }
+}
+
+pub struct Recording {
+    pub version: String,
+    pub devices: Vec<RecordedDevice>,
+}
+
+impl fmt::Debug for Recording {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Recording")
+            .field("version", &self.version)
+            .field("devices", &self.device_path_list())
+            .field("duration", &self.duration())
+            .finish()
+    }
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))?;
+
+        let Some(r) = bytes.strip_prefix(MAGIC) else {
+            bail!("{path:?} is not a recording of amdgpu_top");
+        };
+        let Some((format_version, mut r)) = r.split_first_chunk::<4>() else {
+            bail!("Invalid header: {path:?}");
+        };
+        let format_version = u32::from_le_bytes(*format_version);
+
+        if format_version != FORMAT_VERSION {
+            bail!("{path:?} is recording format v{format_version}, and cannot be replayed by amdgpu_top v{VERSION} (format v{FORMAT_VERSION})");
+        }
+
+        let version: String = bincode::deserialize_from(&mut r).with_context(|| format!("Invalid header: {path:?}"))?;
+        let mut devices: Vec<RecordedDevice> = Vec::new();
+
+        while !r.is_empty() {
+            let record = match bincode::deserialize_from(&mut r) {
+                Ok(record) => record,
+                // the last record may be truncated if the session was interrupted
+                Err(err) if matches!(&*err, bincode::ErrorKind::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof) => break,
+                Err(err) => return Err(err).with_context(|| format!("Invalid record: {path:?}")),
+            };
+
+            match record {
+                Record::Device { index, device_path, device_info, xdna_device_path, xdna_fw_version } => {
+                    if index != devices.len() {
+                        bail!("Invalid device index: {index}");
+                    }
+
+                    devices.push(RecordedDevice {
+                        device_path: device_path.into_owned(),
+                        device_info: device_info.into_owned(),
+                        xdna_device_path: xdna_device_path.into_owned(),
+                        xdna_fw_version: xdna_fw_version.into_owned(),
+                        frames: Vec::new(),
+                    });
+                },
+                Record::Frame { index, time, stat } => {
+                    let device = devices.get_mut(index).with_context(|| format!("Invalid device index: {index}"))?;
+
+                    device.frames.push((time, stat.into_owned()));
+                },
+            }
+        }
+
+        devices.retain(|device| !device.frames.is_empty());
+
+        if devices.is_empty() {
+            bail!("{path:?} has no recorded frames");
+        }
+
+        Ok(Self { version, devices })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.devices
+            .iter()
+            .filter_map(|device| device.frames.last().map(|(time, _)| *time))
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn device_path_list(&self) -> Vec<DevicePath> {
+        self.devices.iter().map(|device| device.device_path.clone()).collect()
+    }
+
+    pub fn create_app_list(&self) -> Vec<AppAmdgpuTop> {
+        self.devices.iter().filter_map(AppAmdgpuTop::from_recorded_device).collect()
+    }
+}
+
+/// Playback state of a [`Recording`], shared between the frontend and its update thread.
+pub struct Player {
+    pub recording: Arc<Recording>,
+    position: Duration,
+    speed: f64,
+    paused: bool,
+    seeked: bool,
+    last_tick: Instant,
+}
+
+impl Player {
+    const MIN_SPEED: f64 = 0.125;
+    const MAX_SPEED: f64 = 32.0;
+
+    pub fn new(recording: Arc<Recording>) -> Self {
+        Self {
+            recording,
+            position: Duration::ZERO,
+            speed: 1.0,
+            paused: false,
+            seeked: false,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Advance the position by the elapsed time since the last call.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+
+        if !self.paused {
+            let duration = self.duration();
+
+            self.position += now.duration_since(self.last_tick).mul_f64(self.speed);
+
+            if duration <= self.position {
+                self.position = duration;
+                self.paused = true;
+            }
+        }
+
+        self.last_tick = now;
+    }
+
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.recording.duration()
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused && self.duration() <= self.position {
+            self.seek(Duration::ZERO);
+        }
+
+        self.paused ^= true;
+        self.last_tick = Instant::now();
+    }
+
+    pub fn seek(&mut self, position: Duration) {
+        self.position = position.min(self.duration());
+        self.seeked = true;
+    }
+
+    pub fn seek_relative(&mut self, secs: f64) {
+        let position = (self.position.as_secs_f64() + secs).max(0.0);
+
+        self.seek(Duration::from_secs_f64(position));
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(Self::MIN_SPEED, Self::MAX_SPEED);
+    }
+
+    pub fn faster(&mut self) {
+        self.set_speed(self.speed * 2.0);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_speed(self.speed / 2.0);
+    }
+
+    /// Returns `true` once after the position is changed by seeking.
+    pub fn take_seeked(&mut self) -> bool {
+        std::mem::take(&mut self.seeked)
+    }
+
+    pub fn stat(&self, pci_bus: &PCI::BUS_INFO) -> Option<&AppAmdgpuTopStat> {
+        self.recording.devices
+            .iter()
+            .find(|device| device.device_info.pci_bus == *pci_bus)
+            .and_then(|device| device.stat_at(self.position))
+    }
+
+    /// Replace the stat of `app` with the recorded one at the current position.
+    pub fn apply(&self, app: &mut AppAmdgpuTop) {
+        if let Some(stat) = self.stat(&app.device_info.pci_bus) {
+            app.stat = stat.clone();
+        }
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "Replay: {:.1}s / {:.1}s, x{}{}",
+            self.position.as_secs_f64(),
+            self.duration().as_secs_f64(),
+            self.speed,
+            if self.paused { " (Paused)" } else { "" },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AMDGPU::{GpuMetrics, HwmonTemp, HwmonTempType, PowerCap, PowerProfile, RasErrorCount};
+    use crate::stat::{
+        DevCoredumpMonitor,
+        DevCoredumpReport,
+        DpmClockType,
+        DpmLevel,
+        DpmLevels,
+        DpmResidency,
+        DpmStat,
+        DrmEngineStats,
+        DrmFdInfo,
+        DrmMemoryStats,
+        EnergySource,
+        EnergyStat,
+        EngineUsage,
+        FanMode,
+        FanStat,
+        FdInfoClient,
+        FdInfoStat,
+        FdInfoUsage,
+        GfxoffMode,
+        GfxoffMonitor,
+        GfxoffStatus,
+        GpuActivity,
+        HwmonPower,
+        PCIndex,
+        PcieBw,
+        PcieBwSource,
+        PcieDowntrainEvent,
+        PcieLinkMonitor,
+        PerfCounter,
+        PCType,
+        PowerType,
+        ProcUsage,
+        RasBlockStat,
+        RasCount,
+        RasEvent,
+        RasStat,
+        Sensors,
+        ThrottleEvent,
+        ThrottleResidency,
+        ThrottlingStat,
+    };
+    use crate::xdna::{XdnaFdInfoStat, XdnaFdInfoUsage, XdnaProcUsage};
+    use crate::VramUsage;
+    use std::sync::Mutex;
+    use std::time::UNIX_EPOCH;
+
+    fn link(gen: u8, width: u8) -> PCI::LINK {
+        PCI::LINK { gen, width }
+    }
+
+    fn proc_usage() -> ProcUsage {
+        let engines = vec![EngineUsage { name: "gfx".to_string(), busy: 1_000_000, capacity: 1, usage: 12.5 }];
+        let fdinfo = DrmFdInfo {
+            driver: Some("amdgpu".to_string()),
+            pdev: Some("0000:03:00.0".to_string()),
+            client_id: Some(42),
+            client_name: Some("vkcube".to_string()),
+            memory: vec![("vram".to_string(), DrmMemoryStats { total: Some(1024), resident: Some(1024), ..Default::default() })],
+            engines: vec![("gfx".to_string(), DrmEngineStats { busy: Some(1), capacity: Some(1), cycles: None, total_cycles: None, maxfreq: None })],
+            driver_memory: vec![("amd-memory-visible-vram".to_string(), 1024)],
+            others: vec![("pasid".to_string(), "32794".to_string())],
+        };
+        let usage = FdInfoUsage { vram_usage: 1024, gtt_usage: 2048, gfx: 12.5, ..Default::default() };
+
+        ProcUsage {
+            pid: 1234,
+            name: "vkcube".to_string(),
+            ids_count: 1,
+            usage,
+            engines: engines.clone(),
+            cpu_usage: 3,
+            is_kfd_process: true,
+            clients: vec![FdInfoClient { client_id: 42, fd: 5, usage, engines, fdinfo }],
+            energy: 1.5,
+        }
+    }
+
+    fn populated_stat() -> AppAmdgpuTopStat {
+        let pci: PCI::BUS_INFO = "0000:03:00.0".parse().unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let pc = |pc_type| PerfCounter {
+            pc_type,
+            bits: Default::default(),
+            pc_index: vec![PCIndex { name: "Graphics Pipe".to_string(), index: 31, usage: 50 }],
+        };
+        let temp = |type_| Some(HwmonTemp { type_, current: 45, critical: Some(100), emergency: None });
+        let sensors = Sensors {
+            hwmon_path: "/sys/class/hwmon/hwmon3".into(),
+            gpu_port_path: "/sys/bus/pci/devices/0000:03:00.0".into(),
+            sysfs_path: "/sys/bus/pci/devices/0000:03:00.0".into(),
+            is_apu: false,
+            vega10_and_later: true,
+            current_link: Some(link(4, 16)),
+            min_dpm_link: Some(link(1, 16)),
+            max_dpm_link: Some(link(4, 16)),
+            max_gpu_link: Some(link(4, 16)),
+            max_system_link: Some(link(4, 16)),
+            bus_info: pci,
+            sclk: Some(2500),
+            mclk: Some(1000),
+            vddnb: Some(900),
+            vddgfx: Some(1100),
+            edge_temp: temp(HwmonTempType::Edge),
+            junction_temp: temp(HwmonTempType::Junction),
+            memory_temp: temp(HwmonTempType::Memory),
+            average_power: Some(HwmonPower { type_: PowerType::Average, value: 150 }),
+            input_power: Some(HwmonPower { type_: PowerType::Input, value: 160 }),
+            // SAFETY: all fields are integers or a fieldless enum
+            power_cap: Some(PowerCap { current: 200, default: 200, min: 100, max: 250, ..unsafe { std::mem::zeroed() } }),
+            fan_rpm: Some(1500),
+            fan_max_rpm: Some(3000),
+            pci_power_state: Some("D0".to_string()),
+            power_profile: Some(PowerProfile::COMPUTE),
+            fan: Some(FanStat { mode: Some(FanMode::Auto), pwm: Some(128), pwm_min: 0, pwm_max: 255, target_rpm: Some(1500) }),
+        };
+        let dpm_levels = DpmLevels {
+            clock: DpmClockType::Sclk,
+            levels: vec![DpmLevel { label: "1".to_string(), mhz: Some(2500), link: None, active: true }],
+            fine_grained: true,
+            residency: vec![DpmResidency { name: "1".to_string(), residency: Duration::from_secs(3), active: true }],
+            tracked: Duration::from_secs(3),
+        };
+        let report = DevCoredumpReport {
+            pci: Some(pci),
+            time: Some(time),
+            kernel: Some("6.10.0".to_string()),
+            uptime: Some("45.084775181".to_string()),
+            process_name: Some("vkcube".to_string()),
+            pid: Some(1234),
+            ring: Some("gfx_0.0.0".to_string()),
+            page_fault: Some(0x800100200000),
+            ibs: vec![0x800100123000],
+            ip_dumps: vec!["gfx_v11_0".to_string()],
+            vram_lost: true,
+            reset: true,
+            path: Some("/tmp/amdgpu_devcoredump.txt".into()),
+        };
+
+        let mut energy = EnergyStat::default();
+        let mut throttling = ThrottlingStat::default();
+        let mut dpm = DpmStat::default();
+        let mut devcoredump = DevCoredumpMonitor::new(pci);
+
+        energy.total = 100.0;
+        energy.source = Some(EnergySource::GpuMetrics);
+        throttling.tracked = Some(Duration::from_secs(3));
+        throttling.reasons = vec![ThrottleResidency { name: "PPT0".to_string(), active: true, residency: Duration::from_secs(1), transitions: 2 }];
+        throttling.events = vec![ThrottleEvent { time: Duration::from_secs(2), name: "PPT0".to_string(), active: true }];
+        dpm.clocks = vec![dpm_levels];
+        devcoredump.count = 1;
+        devcoredump.resets = Some(1);
+        devcoredump.reports = vec![report];
+
+        AppAmdgpuTopStat {
+            grbm: pc(PCType::GRBM),
+            grbm2: pc(PCType::GRBM2),
+            // SAFETY: a plain C struct of integers
+            vram_usage: VramUsage(unsafe { std::mem::zeroed() }),
+            sensors: Some(sensors),
+            // SAFETY: a plain C struct of integers
+            metrics: Some(GpuMetrics::V1_3(unsafe { std::mem::zeroed() })),
+            activity: GpuActivity { gfx: Some(90), umc: Some(30), media: Some(0) },
+            fdinfo: FdInfoStat {
+                proc_usage: vec![proc_usage()],
+                interval: Duration::from_secs(1),
+                has_vcn: true,
+                has_vcn_unified: true,
+                has_vpe: false,
+                ..Default::default()
+            },
+            xdna_fdinfo: XdnaFdInfoStat {
+                proc_usage: vec![XdnaProcUsage {
+                    pid: 5678,
+                    name: "npu".to_string(),
+                    ids_count: 1,
+                    usage: XdnaFdInfoUsage { total_memory: 1, shared_memory: 2, active_memory: 3, npu: 4 },
+                }],
+                interval: Duration::from_secs(1),
+                ..Default::default()
+            },
+            arc_proc_index: Default::default(),
+            arc_xdna_proc_index: Default::default(),
+            arc_pcie_bw: Some(Arc::new(Mutex::new(PcieBw {
+                path: "/sys/bus/pci/devices/0000:03:00.0/pcie_bw".into(),
+                sent: Some(1),
+                received: Some(2),
+                max_payload_size: Some(256),
+                total: Some(3),
+                source: PcieBwSource::GpuMetrics,
+                pre_acc: None,
+            }))),
+            memory_error_count: Some(RasErrorCount { corrected: 1, uncorrected: 0 }),
+            ras: Some(RasStat {
+                path: "/sys/bus/pci/devices/0000:03:00.0/ras".into(),
+                blocks: vec![RasBlockStat {
+                    name: "umc".to_string(),
+                    count: RasCount { corrected: 1, uncorrected: 0 },
+                    delta: RasCount { corrected: 1, uncorrected: 0 },
+                    last_increased: Some(time),
+                }],
+                events: vec![RasEvent { time, block: "umc".to_string(), delta: RasCount { corrected: 1, uncorrected: 0 } }],
+            }),
+            energy,
+            throttling,
+            pcie_link_monitor: PcieLinkMonitor {
+                expected: Some(link(4, 16)),
+                downtrained: Some(link(3, 4)),
+                events: vec![PcieDowntrainEvent { time, current: link(3, 4), expected: link(4, 16) }],
+            },
+            dpm,
+            gfxoff: Some(GfxoffMonitor {
+                debug_dri_path: "/sys/kernel/debug/dri/0".into(),
+                mode: GfxoffMode::Enable,
+                status: GfxoffStatus::NotInGFXOFF,
+                residency: Some(42.0),
+                count: Some(10),
+                transitions: 3,
+                samples: (5, 10),
+                blocked_by_pc: true,
+                residency_logging: false,
+            }),
+            devcoredump,
+        }
+    }
+
+    #[test]
+    fn test_stat_round_trip() {
+        let stat = populated_stat();
+        let buf = bincode::serialize(&stat).unwrap();
+        let decoded: AppAmdgpuTopStat = bincode::deserialize(&buf).unwrap();
+
+        assert_eq!(buf, bincode::serialize(&decoded).unwrap());
+
+        let sensors = decoded.sensors.as_ref().unwrap();
+        assert!(sensors.junction_temp.is_some() && sensors.fan.is_some());
+        assert_eq!(decoded.fdinfo.proc_usage, vec![proc_usage()]);
+        assert_eq!(decoded.throttling.events, stat.throttling.events);
+        assert_eq!(decoded.devcoredump.reports, stat.devcoredump.reports);
+        assert_eq!(decoded.dpm.clocks[0].residency, stat.dpm.clocks[0].residency);
+        assert!(matches!(decoded.metrics, Some(GpuMetrics::V1_3(_))));
+        assert!(decoded.arc_pcie_bw.is_some_and(|bw| bw.lock().unwrap().total == Some(3)));
+    }
+}
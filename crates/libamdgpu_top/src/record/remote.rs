@@ -0,0 +1,245 @@
+//! Serialization of the types of libdrm_amdgpu_sys, for `#[serde(with = "..")]`.
+//!
+//! The structures of the kernel UAPI and `gpu_metrics` are plain data,
+//! so they are stored as they are in memory.
+//! [`super::FORMAT_VERSION`] must be bumped when their layout changes.
+
+use crate::AMDGPU::{
+    drm_amdgpu_info_device,
+    drm_amdgpu_memory_info,
+    ASIC_NAME,
+    GpuMetrics,
+    HW_IP::HwIpInfo,
+    HwmonTemp,
+    HwmonTempType,
+    PowerCap,
+    PowerProfile,
+    RasErrorCount,
+    VBIOS::VbiosInfo,
+    VIDEO_CAPS::VideoCapsInfo,
+};
+use crate::PCI;
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+pub(crate) trait Remote: Sized {
+    type Repr: Serialize + DeserializeOwned;
+
+    fn to_repr(&self) -> Self::Repr;
+    fn from_repr(repr: Self::Repr) -> Option<Self>;
+}
+
+fn from_repr<'de, T: Remote, D: Deserializer<'de>>(repr: T::Repr) -> Result<T, D::Error> {
+    T::from_repr(repr).ok_or_else(|| de::Error::custom(format!("invalid {}", std::any::type_name::<T>())))
+}
+
+pub(crate) fn serialize<T: Remote, S: Serializer>(v: &T, s: S) -> Result<S::Ok, S::Error> {
+    v.to_repr().serialize(s)
+}
+
+pub(crate) fn deserialize<'de, T: Remote, D: Deserializer<'de>>(d: D) -> Result<T, D::Error> {
+    from_repr::<T, D>(T::Repr::deserialize(d)?)
+}
+
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<T: Remote, S: Serializer>(v: &Option<T>, s: S) -> Result<S::Ok, S::Error> {
+        v.as_ref().map(T::to_repr).serialize(s)
+    }
+
+    pub(crate) fn deserialize<'de, T: Remote, D: Deserializer<'de>>(d: D) -> Result<Option<T>, D::Error> {
+        Option::<T::Repr>::deserialize(d)?.map(from_repr::<T, D>).transpose()
+    }
+}
+
+pub(crate) mod vec {
+    use super::*;
+
+    pub(crate) fn serialize<T: Remote, S: Serializer>(v: &[T], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(v.iter().map(T::to_repr))
+    }
+
+    pub(crate) fn deserialize<'de, T: Remote, D: Deserializer<'de>>(d: D) -> Result<Vec<T>, D::Error> {
+        Vec::<T::Repr>::deserialize(d)?.into_iter().map(from_repr::<T, D>).collect()
+    }
+}
+
+fn to_bytes<T: Copy>(v: &T) -> Vec<u8> {
+    // SAFETY: `T` is a plain data type, and the slice does not outlive `v`
+    unsafe { std::slice::from_raw_parts((v as *const T).cast::<u8>(), size_of::<T>()) }.to_vec()
+}
+
+fn from_bytes<T: Copy>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() != size_of::<T>() { return None }
+
+    // SAFETY: the length is checked, and the layout is fixed by FORMAT_VERSION
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<T>()) })
+}
+
+macro_rules! impl_remote_pod {
+    ($($ty:ty),* $(,)?) => {$(
+        impl Remote for $ty {
+            type Repr = Vec<u8>;
+
+            fn to_repr(&self) -> Self::Repr {
+                to_bytes(self)
+            }
+
+            fn from_repr(repr: Self::Repr) -> Option<Self> {
+                from_bytes(&repr)
+            }
+        }
+    )*};
+}
+
+impl_remote_pod!(
+    drm_amdgpu_info_device,
+    drm_amdgpu_memory_info,
+    ASIC_NAME,
+    PCI::BUS_INFO,
+    PCI::LINK,
+    PowerProfile,
+    VideoCapsInfo,
+);
+
+impl Remote for HwIpInfo {
+    type Repr = (Vec<u8>, Vec<u8>, u64);
+
+    fn to_repr(&self) -> Self::Repr {
+        (to_bytes(&self.ip_type), to_bytes(&self.info), self.count as u64)
+    }
+
+    fn from_repr((ip_type, info, count): Self::Repr) -> Option<Self> {
+        Some(Self { ip_type: from_bytes(&ip_type)?, info: from_bytes(&info)?, count: count as _ })
+    }
+}
+
+impl Remote for VbiosInfo {
+    type Repr = (String, String, String, String, u64);
+
+    fn to_repr(&self) -> Self::Repr {
+        (self.name.clone(), self.pn.clone(), self.ver.clone(), self.date.clone(), self.size as u64)
+    }
+
+    fn from_repr((name, pn, ver, date, size): Self::Repr) -> Option<Self> {
+        Some(Self { name, pn, ver, date, size: size as _ })
+    }
+}
+
+impl Remote for PowerCap {
+    type Repr = (Vec<u8>, u64, u64, u64, u64);
+
+    fn to_repr(&self) -> Self::Repr {
+        (
+            to_bytes(&self.type_),
+            self.current as u64,
+            self.default as u64,
+            self.min as u64,
+            self.max as u64,
+        )
+    }
+
+    fn from_repr((type_, current, default, min, max): Self::Repr) -> Option<Self> {
+        Some(Self {
+            type_: from_bytes(&type_)?,
+            current: current as _,
+            default: default as _,
+            min: min as _,
+            max: max as _,
+        })
+    }
+}
+
+impl Remote for RasErrorCount {
+    type Repr = (u64, u64);
+
+    fn to_repr(&self) -> Self::Repr {
+        (self.corrected as u64, self.uncorrected as u64)
+    }
+
+    fn from_repr((corrected, uncorrected): Self::Repr) -> Option<Self> {
+        Some(Self { corrected: corrected as _, uncorrected: uncorrected as _ })
+    }
+}
+
+// The type of temperature is implied by the field that holds it.
+macro_rules! hwmon_temp {
+    ($($name:ident => $type_:expr),* $(,)?) => {$(
+        pub(crate) mod $name {
+            use super::*;
+
+            pub(crate) fn serialize<S: Serializer>(temp: &Option<HwmonTemp>, s: S) -> Result<S::Ok, S::Error> {
+                temp.as_ref().map(|t| (
+                    t.current as i64,
+                    t.critical.map(|v| v as i64),
+                    t.emergency.map(|v| v as i64),
+                )).serialize(s)
+            }
+
+            pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<HwmonTemp>, D::Error> {
+                let temp: Option<(i64, Option<i64>, Option<i64>)> = Deserialize::deserialize(d)?;
+
+                Ok(temp.map(|(current, critical, emergency)| HwmonTemp {
+                    type_: $type_,
+                    current: current as _,
+                    critical: critical.map(|v| v as _),
+                    emergency: emergency.map(|v| v as _),
+                }))
+            }
+        }
+    )*};
+}
+
+hwmon_temp!(
+    edge_temp => HwmonTempType::Edge,
+    junction_temp => HwmonTempType::Junction,
+    memory_temp => HwmonTempType::Memory,
+);
+
+/// Unsupported versions of `gpu_metrics` are dropped instead of failing the whole frame.
+pub(crate) mod gpu_metrics {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(metrics: &Option<GpuMetrics>, s: S) -> Result<S::Ok, S::Error> {
+        let metrics = metrics.as_ref().and_then(|m| Some(match m {
+            GpuMetrics::V1_0(m) => (0x10u8, to_bytes(m)),
+            GpuMetrics::V1_1(m) => (0x11, to_bytes(m)),
+            GpuMetrics::V1_2(m) => (0x12, to_bytes(m)),
+            GpuMetrics::V1_3(m) => (0x13, to_bytes(m)),
+            GpuMetrics::V1_4(m) => (0x14, to_bytes(m)),
+            GpuMetrics::V1_5(m) => (0x15, to_bytes(m)),
+            GpuMetrics::V2_0(m) => (0x20, to_bytes(m)),
+            GpuMetrics::V2_1(m) => (0x21, to_bytes(m)),
+            GpuMetrics::V2_2(m) => (0x22, to_bytes(m)),
+            GpuMetrics::V2_3(m) => (0x23, to_bytes(m)),
+            GpuMetrics::V2_4(m) => (0x24, to_bytes(m)),
+            GpuMetrics::V3_0(m) => (0x30, to_bytes(m)),
+            _ => return None,
+        }));
+
+        metrics.serialize(s)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<GpuMetrics>, D::Error> {
+        let Some((version, bytes)) = Option::<(u8, Vec<u8>)>::deserialize(d)? else { return Ok(None) };
+        let m = match version {
+            0x10 => from_bytes(&bytes).map(GpuMetrics::V1_0),
+            0x11 => from_bytes(&bytes).map(GpuMetrics::V1_1),
+            0x12 => from_bytes(&bytes).map(GpuMetrics::V1_2),
+            0x13 => from_bytes(&bytes).map(GpuMetrics::V1_3),
+            0x14 => from_bytes(&bytes).map(GpuMetrics::V1_4),
+            0x15 => from_bytes(&bytes).map(GpuMetrics::V1_5),
+            0x20 => from_bytes(&bytes).map(GpuMetrics::V2_0),
+            0x21 => from_bytes(&bytes).map(GpuMetrics::V2_1),
+            0x22 => from_bytes(&bytes).map(GpuMetrics::V2_2),
+            0x23 => from_bytes(&bytes).map(GpuMetrics::V2_3),
+            0x24 => from_bytes(&bytes).map(GpuMetrics::V2_4),
+            0x30 => from_bytes(&bytes).map(GpuMetrics::V3_0),
+            _ => None,
+        };
+
+        m.map(Some).ok_or_else(|| de::Error::custom(format!("invalid gpu_metrics v{version:#x}")))
+    }
+}
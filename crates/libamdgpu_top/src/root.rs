@@ -0,0 +1,115 @@
+//! Root directories of sysfs, procfs and devfs.
+//!
+//! By default, the paths of the running system (`/sys`, `/proc`, `/dev`) are used.
+//! They can be replaced with `AGT_SYSFS_ROOT`, `AGT_PROC_ROOT` and `AGT_DEV_ROOT`,
+//! or with [`set`] before any device is opened,
+//! e.g. to read a captured directory tree or the host filesystem mounted elsewhere.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DEFAULT_SYSFS: &str = "/sys";
+const DEFAULT_PROCFS: &str = "/proc";
+const DEFAULT_DEVFS: &str = "/dev";
+
+#[derive(Debug, Clone)]
+pub struct Root {
+    pub sysfs: PathBuf,
+    pub procfs: PathBuf,
+    pub devfs: PathBuf,
+}
+
+impl Default for Root {
+    fn default() -> Self {
+        Self {
+            sysfs: PathBuf::from(DEFAULT_SYSFS),
+            procfs: PathBuf::from(DEFAULT_PROCFS),
+            devfs: PathBuf::from(DEFAULT_DEVFS),
+        }
+    }
+}
+
+impl Root {
+    pub fn from_env() -> Self {
+        let var = |key: &str, default: &str| {
+            std::env::var_os(key)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or(PathBuf::from(default))
+        };
+
+        Self {
+            sysfs: var("AGT_SYSFS_ROOT", DEFAULT_SYSFS),
+            procfs: var("AGT_PROC_ROOT", DEFAULT_PROCFS),
+            devfs: var("AGT_DEV_ROOT", DEFAULT_DEVFS),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.sysfs == Path::new(DEFAULT_SYSFS)
+        && self.procfs == Path::new(DEFAULT_PROCFS)
+        && self.devfs == Path::new(DEFAULT_DEVFS)
+    }
+}
+
+static ROOT: OnceLock<Root> = OnceLock::new();
+
+/// Set the root directories.
+/// Returns `Err` with the given `root` if the root directories are already in use.
+pub fn set(root: Root) -> Result<(), Root> {
+    ROOT.set(root)
+}
+
+pub fn get() -> &'static Root {
+    ROOT.get_or_init(Root::from_env)
+}
+
+fn join(base: &Path, default: &str, path: &Path) -> PathBuf {
+    match path.strip_prefix(default) {
+        Ok(rel) if rel.as_os_str().is_empty() => base.to_path_buf(),
+        Ok(rel) => base.join(rel),
+        Err(_) => base.join(path.strip_prefix("/").unwrap_or(path)),
+    }
+}
+
+/// Map a path under `/sys` (e.g. `/sys/class/drm`, or `class/drm`) to the configured sysfs root.
+pub fn sys_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    join(&get().sysfs, DEFAULT_SYSFS, path.as_ref())
+}
+
+/// Map a path under `/proc` (e.g. `/proc/1/fdinfo`, or `1/fdinfo`) to the configured procfs root.
+pub fn proc_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    join(&get().procfs, DEFAULT_PROCFS, path.as_ref())
+}
+
+/// Map a path under `/dev` (e.g. `/dev/dri/renderD128`, or `dri/renderD128`) to the configured devfs root.
+pub fn dev_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    join(&get().devfs, DEFAULT_DEVFS, path.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_join() {
+        let base = Path::new("/tmp/capture/sys");
+
+        assert_eq!(
+            join(base, DEFAULT_SYSFS, Path::new("/sys/class/drm")),
+            PathBuf::from("/tmp/capture/sys/class/drm"),
+        );
+        assert_eq!(
+            join(base, DEFAULT_SYSFS, Path::new("class/drm")),
+            PathBuf::from("/tmp/capture/sys/class/drm"),
+        );
+        assert_eq!(
+            join(base, DEFAULT_SYSFS, Path::new("/sys")),
+            PathBuf::from("/tmp/capture/sys"),
+        );
+        assert_eq!(
+            join(Path::new(DEFAULT_SYSFS), DEFAULT_SYSFS, Path::new("/sys/module/amdgpu")),
+            PathBuf::from("/sys/module/amdgpu"),
+        );
+    }
+}
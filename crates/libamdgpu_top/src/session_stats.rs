@@ -0,0 +1,306 @@
+//! Distribution of the metrics over a session (min/max/avg/percentiles).
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::{PCI, root};
+use crate::alert::{Metric, ProcMetric};
+use crate::app::{AppAmdgpuTop, AppAmdgpuTopStat};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Relative error of the percentiles
+pub const RELATIVE_ERROR: f64 = 0.01;
+const GAMMA: f64 = (1.0 + RELATIVE_ERROR) / (1.0 - RELATIVE_ERROR);
+/// The samples below it are counted as 0
+const MIN_VALUE: f64 = 1e-3;
+
+/// Samples of a metric in a histogram of logarithmic buckets (DDSketch),
+/// so the memory does not grow with the length of the session.
+/// min/max/avg are exact, the percentiles are within [`RELATIVE_ERROR`].
+/// ref: <https://arxiv.org/abs/1908.10693>
+#[derive(Debug, Clone, Default)]
+pub struct Series {
+    /// Count of the samples by the bucket index, see [`Series::index`]
+    buckets: BTreeMap<i32, usize>,
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Series {
+    /// The bucket `i` (> 0) holds `(MIN_VALUE * GAMMA^(i-2), MIN_VALUE * GAMMA^(i-1)]`,
+    /// the negative values are in the mirrored buckets.
+    fn index(v: f64) -> i32 {
+        if v.abs() < MIN_VALUE { return 0 }
+
+        let i = (v.abs() / MIN_VALUE).log(GAMMA).ceil() as i32 + 1;
+
+        if v < 0.0 { -i } else { i }
+    }
+
+    fn value(i: i32) -> f64 {
+        if i == 0 { return 0.0 }
+
+        let v = MIN_VALUE * GAMMA.powi(i.abs() - 1) * 2.0 / (1.0 + GAMMA);
+
+        if i < 0 { -v } else { v }
+    }
+
+    pub fn push(&mut self, v: f64) {
+        if !v.is_finite() { return }
+
+        if self.count == 0 {
+            (self.min, self.max) = (v, v);
+        } else {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+
+        *self.buckets.entry(Self::index(v)).or_default() += 1;
+        self.count += 1;
+        self.sum += v;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn summary(&self) -> Option<Summary> {
+        if self.count == 0 { return None }
+
+        let count = self.count;
+        // nearest-rank method
+        let percentile = |p: usize| {
+            let rank = (p * count).div_ceil(100).max(1);
+            let mut acc = 0;
+            let i = self.buckets
+                .iter()
+                .find_map(|(i, n)| { acc += n; (rank <= acc).then_some(*i) })
+                .unwrap_or_default();
+
+            Self::value(i).clamp(self.min, self.max)
+        };
+
+        Some(Summary {
+            count,
+            min: self.min,
+            max: self.max,
+            avg: self.sum / count as f64,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcSeries {
+    pub pid: i32,
+    pub name: String,
+    pub metrics: Vec<(ProcMetric, Series)>,
+}
+
+impl ProcSeries {
+    /// The metrics that have always been 0 are omitted.
+    pub fn summary(&self) -> ProcSummary {
+        let metrics = self.metrics
+            .iter()
+            .filter_map(|(m, series)| series.summary().map(|sum| (*m, sum)))
+            .filter(|(_, sum)| sum.max != 0.0)
+            .collect();
+
+        ProcSummary { pid: self.pid, name: self.name.clone(), metrics }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcSummary {
+    pub pid: i32,
+    pub name: String,
+    pub metrics: Vec<(ProcMetric, Summary)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    pub pci: PCI::BUS_INFO,
+    pub device_name: String,
+    start: Instant,
+    last: Instant,
+    pub samples: usize,
+    pub device: Vec<(Metric, Series)>,
+    /// Running processes
+    pub procs: Vec<ProcSeries>,
+    /// The samples of a process are dropped when it exits, only the summary is kept.
+    pub exited_procs: Vec<ProcSummary>,
+}
+
+impl SessionStats {
+    pub fn new(app: &AppAmdgpuTop) -> Self {
+        let now = Instant::now();
+
+        Self {
+            pci: app.device_info.pci_bus,
+            device_name: app.device_info.marketing_name.clone(),
+            start: now,
+            last: now,
+            samples: 0,
+            device: Metric::DEVICE_METRICS.iter().map(|m| (m.clone(), Series::default())).collect(),
+            procs: Vec::new(),
+            exited_procs: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, stat: &AppAmdgpuTopStat) {
+        self.samples += 1;
+        self.last = Instant::now();
+
+        for (metric, series) in self.device.iter_mut() {
+            if let Some(v) = metric.value(stat) {
+                series.push(v);
+            }
+        }
+
+        for proc_usage in &stat.fdinfo.proc_usage {
+            let pos = self.procs
+                .iter()
+                .position(|p| p.pid == proc_usage.pid && p.name == proc_usage.name)
+                .unwrap_or_else(|| {
+                    self.procs.push(ProcSeries {
+                        pid: proc_usage.pid,
+                        name: proc_usage.name.clone(),
+                        metrics: ProcMetric::ALL.iter().map(|m| (*m, Series::default())).collect(),
+                    });
+                    self.procs.len() - 1
+                });
+
+            for (metric, series) in self.procs[pos].metrics.iter_mut() {
+                series.push(metric.value(proc_usage));
+            }
+        }
+
+        self.procs.retain(|p| {
+            let exited = !stat.fdinfo.proc_usage.iter().any(|pu| pu.pid == p.pid && pu.name == p.name)
+                && !root::proc_path(p.pid.to_string()).exists();
+
+            if exited {
+                let summary = p.summary();
+
+                if !summary.metrics.is_empty() {
+                    self.exited_procs.push(summary);
+                }
+            }
+
+            !exited
+        });
+    }
+
+    /// Summaries of the exited and running processes that have used the device
+    pub fn proc_summaries(&self) -> Vec<ProcSummary> {
+        self.exited_procs
+            .iter()
+            .cloned()
+            .chain(self.procs.iter().map(ProcSeries::summary))
+            .filter(|p| !p.metrics.is_empty())
+            .collect()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.last.duration_since(self.start)
+    }
+
+    /// Summary table, the process metrics that have always been 0 are omitted.
+    pub fn text(&self) -> Result<String, fmt::Error> {
+        let mut s = String::new();
+
+        writeln!(
+            s,
+            " {} ({}), {:.0}s, {} samples",
+            self.device_name,
+            self.pci,
+            self.duration().as_secs_f64(),
+            self.samples,
+        )?;
+        writeln!(
+            s,
+            " {:<16} {:>4} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "", "", "Min", "Max", "Avg", "p50", "p95", "p99",
+        )?;
+
+        let row = |s: &mut String, name: &str, unit: &str, sum: &Summary| writeln!(
+            s,
+            " {name:<16} {unit:>4} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+            sum.min, sum.max, sum.avg, sum.p50, sum.p95, sum.p99,
+        );
+
+        for (metric, series) in &self.device {
+            let Some(sum) = series.summary() else { continue };
+
+            row(&mut s, &metric.to_string(), metric.unit(), &sum)?;
+        }
+
+        for proc in self.proc_summaries() {
+            writeln!(s, " {} ({})", proc.name, proc.pid)?;
+
+            for (metric, sum) in proc.metrics {
+                let name = Metric::Proc(*metric).to_string();
+
+                row(&mut s, &format!("  {}", name.trim_start_matches("proc.")), metric.unit(), &sum)?;
+            }
+        }
+
+        Ok(s)
+    }
+}
+
+/// Update the stats of the device in `list`, and return it.
+pub fn update_session_stats<'a>(list: &'a mut Vec<SessionStats>, app: &AppAmdgpuTop) -> &'a SessionStats {
+    let pci = app.device_info.pci_bus;
+    let pos = list.iter().position(|s| s.pci == pci).unwrap_or_else(|| {
+        list.push(SessionStats::new(app));
+        list.len() - 1
+    });
+
+    list[pos].update(&app.stat);
+
+    &list[pos]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut series = Series::default();
+
+        for v in (1..=100).rev() {
+            series.push(v as f64);
+        }
+
+        let sum = series.summary().unwrap();
+        let approx = |v: f64, expected: f64| (v - expected).abs() <= expected * RELATIVE_ERROR;
+
+        assert_eq!(sum.count, 100);
+        assert_eq!((sum.min, sum.max, sum.avg), (1.0, 100.0, 50.5));
+        assert!(approx(sum.p50, 50.0) && approx(sum.p95, 95.0) && approx(sum.p99, 99.0), "{sum:?}");
+        assert!(Series::default().summary().is_none());
+
+        for v in 0..100_000 {
+            series.push(v as f64 * 0.01);
+        }
+
+        assert!(series.buckets.len() < 1000);
+    }
+}
@@ -0,0 +1,23 @@
+//! SIGINT/SIGTERM handling for the modes that clean up before exiting.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn exit_handler(_sig: libc::c_int) {
+    EXIT_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Catch SIGINT and SIGTERM instead of exiting, see [`exit_requested`].
+pub fn catch_exit_signals() {
+    let handler = exit_handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+pub fn exit_requested() -> bool {
+    EXIT_REQUESTED.load(Ordering::Relaxed)
+}
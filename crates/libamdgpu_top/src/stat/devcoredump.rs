@@ -0,0 +1,471 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::{root, PCI};
+
+// amdgpu creates a device coredump when a job times out (GPU hang), before the GPU reset.
+// `/sys/class/devcoredump/devcd<N>/data` is removed by the kernel after 5 minutes,
+// so the dump is saved to a file with the decoded report.
+// The GPU resets are also read from the kernel log (`/dev/kmsg`),
+// because the coredump is not created for every reset (e.g. without `CONFIG_DEV_COREDUMP`).
+// ref: drivers/gpu/drm/amd/amdgpu/amdgpu_dev_coredump.c
+// ref: drivers/gpu/drm/amd/amdgpu/amdgpu_device.c (amdgpu_device_gpu_recover)
+
+const BASE: &str = "/sys/class/devcoredump";
+const KMSG: &str = "/dev/kmsg";
+const HEADER: &str = "AMDGPU Device Coredump";
+const MAX_REPORTS: usize = 16;
+const STATE_DIR: &str = "amdgpu_top";
+
+static DEVCOREDUMP_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Set the directory to save the device coredumps,
+/// the default is `$XDG_STATE_HOME/amdgpu_top` (`~/.local/state/amdgpu_top`).
+pub fn set_devcoredump_dir(dir: Option<PathBuf>) {
+    *DEVCOREDUMP_DIR.write().unwrap() = dir;
+}
+
+/// The default directory is created only accessible by the user,
+/// the coredump contains the process name and the addresses of the hung job.
+fn devcoredump_dir() -> Option<PathBuf> {
+    if let Some(dir) = DEVCOREDUMP_DIR.read().unwrap().clone() {
+        return Some(dir);
+    }
+
+    let var = |key: &str| std::env::var_os(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let dir = var("XDG_STATE_HOME")
+        .or_else(|| var("HOME").map(|home| home.join(".local/state")))?
+        .join(STATE_DIR);
+
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir).ok()?;
+
+    Some(dir)
+}
+
+/// The report decoded from an amdgpu device coredump
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DevCoredumpReport {
+    /// The failing device, `None` if it is decoded from a file
+    #[serde(with = "crate::record::remote::option")]
+    pub pci: Option<PCI::BUS_INFO>,
+    /// When the coredump was found
+    pub time: Option<SystemTime>,
+    /// `UTS_RELEASE` of the kernel
+    pub kernel: Option<String>,
+    /// The time of the hang since the boot, e.g. "45.084775181"
+    pub uptime: Option<String>,
+    /// The process that submitted the hung job
+    pub process_name: Option<String>,
+    pub pid: Option<u32>,
+    /// The ring that timed out, e.g. "gfx_0.0.0"
+    pub ring: Option<String>,
+    /// The GPU virtual address of the page fault
+    pub page_fault: Option<u64>,
+    /// The GPU virtual addresses of the IBs (Indirect Buffers) of the hung job
+    pub ibs: Vec<u64>,
+    /// The IP blocks whose registers are dumped, e.g. "gfx_v11_0", "sdma_v6_0"
+    pub ip_dumps: Vec<String>,
+    pub vram_lost: bool,
+    /// The GPU reset is found in the kernel log
+    pub reset: bool,
+    /// The saved file
+    pub path: Option<PathBuf>,
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.trim().strip_prefix("0x")?, 16).ok()
+}
+
+impl DevCoredumpReport {
+    /// Returns `None` if it is not an amdgpu device coredump.
+    pub fn parse(s: &str) -> Option<Self> {
+        if !s.contains(HEADER) { return None }
+
+        let mut report = Self::default();
+        let mut in_ibs = false;
+
+        for line in s.lines().map(|line| line.trim()) {
+            if let Some(v) = line.strip_prefix("kernel:") {
+                report.kernel = Some(v.trim().to_string());
+            } else if let Some(v) = line.strip_prefix("time:") {
+                report.uptime = Some(v.trim().to_string());
+            } else if let Some(v) = line.strip_prefix("process_name:") {
+                // "process_name: gnome-shell PID: 1234"
+                let (name, pid) = v.rsplit_once("PID:").unwrap_or((v, ""));
+
+                report.process_name = Some(name.trim().to_string());
+                report.pid = pid.trim().parse().ok();
+            } else if let Some((_, name)) = line.strip_prefix("IP Type:").and_then(|v| v.split_once("Ring Name:")) {
+                report.ring = Some(name.trim().to_string());
+            } else if let Some(v) = line.strip_prefix("Faulty page starting at address:") {
+                report.page_fault = parse_hex(v);
+            } else if line.starts_with("VRAM is lost") {
+                report.vram_lost = true;
+            } else if let Some(name) = line.strip_prefix("IP:") {
+                report.ip_dumps.push(name.trim().to_string());
+            } else if line.starts_with("IBs") {
+                in_ibs = true;
+            } else if line.is_empty() {
+                in_ibs = false;
+            } else if in_ibs || line.starts_with("IB #") {
+                // e.g. "[0] 0x0000800100123000 (64)", "IB #0 0x800100123000 64 dw"
+                if let Some(addr) = line.split_whitespace().find_map(parse_hex) {
+                    report.ibs.push(addr);
+                }
+            }
+        }
+
+        Some(report)
+    }
+
+    pub fn unix_time(&self) -> Option<u64> {
+        self.time.and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs())
+    }
+
+    /// Save the report and the coredump to `<dir>/amdgpu_devcoredump-<pci>-<unix time>.txt`.
+    /// An existing file (or symlink) is not overwritten.
+    fn save(&mut self, data: &str) {
+        let Some(dir) = devcoredump_dir() else { return };
+        let path = dir.join(format!(
+            "amdgpu_devcoredump-{}-{}.txt",
+            self.pci.map(|pci| pci.to_string()).unwrap_or_default(),
+            self.unix_time().unwrap_or_default(),
+        ));
+        let r = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut f| f.write_all(format!("# {self}\n{data}").as_bytes()));
+
+        if r.is_ok() {
+            self.path = Some(path);
+        }
+    }
+}
+
+impl fmt::Display for DevCoredumpReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Only the reset is known without the coredump and the timeout message
+        if self.reset && self.ring.is_none() {
+            write!(f, "GPU reset")?;
+        } else {
+            write!(f, "GPU hang")?;
+        }
+
+        if let Some(ring) = &self.ring {
+            write!(f, ", ring {ring} timed out")?;
+        }
+
+        if let Some(name) = &self.process_name {
+            write!(f, ", process: {name} ({})", self.pid.unwrap_or_default())?;
+        }
+
+        if let Some(addr) = self.page_fault {
+            write!(f, ", page fault at {addr:#x}")?;
+        }
+
+        if self.vram_lost {
+            write!(f, ", VRAM lost")?;
+        }
+
+        if self.reset && self.ring.is_some() {
+            write!(f, ", GPU reset")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The messages of amdgpu in the kernel log about a GPU hang and the reset
+#[derive(Clone, Debug, PartialEq)]
+enum KernelEvent {
+    /// "ring gfx_0.0.0 timeout, signaled seq=123, emitted seq=125"
+    Timeout(String),
+    /// "Process information: process vkcube pid 1234 thread vkcube:cs0 pid 1235"
+    Process(String, u32),
+    /// "GPU reset(2) succeeded!" or "GPU reset(2) failed"
+    Reset,
+}
+
+/// Parse a record of `/dev/kmsg` ("<prio>,<seq>,<time>,<flags>;<message>"),
+/// the messages of the other devices are skipped.
+fn parse_kmsg(record: &str, pci: &str) -> Option<KernelEvent> {
+    let (_, msg) = record.split_once(';')?;
+
+    // "amdgpu 0000:03:00.0: amdgpu: ...", or " DEVICE=+pci:0000:03:00.0" in the dictionary
+    if !msg.contains(pci) { return None }
+
+    let msg = msg.lines().next()?;
+
+    if let Some(rest) = msg.split_once("GPU reset(").map(|(_, rest)| rest) {
+        return (rest.contains("succeeded") || rest.contains("failed")).then_some(KernelEvent::Reset);
+    }
+
+    if let Some((_, rest)) = msg.split_once("ring ") {
+        let (name, _) = rest.split_once(" timeout")?;
+
+        return (!name.contains(' ')).then(|| KernelEvent::Timeout(name.to_string()));
+    }
+
+    if msg.contains("Process") {
+        let (_, rest) = msg.rsplit_once("rocess ")?;
+        let mut split = rest.split_whitespace();
+        let name = split.next()?;
+
+        if split.next()? != "pid" { return None }
+
+        return Some(KernelEvent::Process(name.to_string(), split.next()?.parse().ok()?));
+    }
+
+    None
+}
+
+/// `/dev/kmsg` opened at the end, reading it requires root if `kernel.dmesg_restrict = 1`.
+fn open_kmsg() -> Option<Arc<fs::File>> {
+    let mut f = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(root::dev_path(KMSG))
+        .ok()?;
+
+    f.seek(SeekFrom::End(0)).ok()?;
+
+    Some(Arc::new(f))
+}
+
+/// A read returns a record, until `EAGAIN`.
+fn read_kmsg(mut f: &fs::File) -> Vec<String> {
+    let mut buf = [0u8; 8192];
+    let mut records = Vec::new();
+
+    loop {
+        match f.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => records.push(String::from_utf8_lossy(&buf[..n]).into_owned()),
+            // The records not read yet were overwritten
+            Err(err) if err.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+
+    records
+}
+
+/// Watch `/sys/class/devcoredump/` for the coredumps of the device,
+/// and the kernel log for the GPU resets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DevCoredumpMonitor {
+    #[serde(with = "crate::record::remote")]
+    pub(crate) pci: PCI::BUS_INFO,
+    /// `devcd<N>` already reported
+    #[serde(skip)]
+    seen: Vec<String>,
+    /// The number of the coredumps since the start
+    pub count: u64,
+    /// The number of the GPU resets since the start, `None` if the kernel log is not readable
+    pub resets: Option<u64>,
+    /// The last reports, in chronological order
+    pub reports: Vec<DevCoredumpReport>,
+    /// The number of the reports found at the last update
+    #[serde(skip)]
+    new: usize,
+    #[serde(skip)]
+    kmsg: Option<Arc<fs::File>>,
+}
+
+impl DevCoredumpMonitor {
+    pub fn new(pci: PCI::BUS_INFO) -> Self {
+        let kmsg = open_kmsg();
+
+        Self { pci, seen: Vec::new(), count: 0, resets: kmsg.as_ref().map(|_| 0), reports: Vec::new(), new: 0, kmsg }
+    }
+
+    /// `/sys/class/devcoredump` is not a part of the device, reading it does not wake up the device.
+    pub fn update(&mut self) {
+        self.new = 0;
+
+        let pci = self.pci.to_string();
+        let dumps: Vec<(String, PathBuf)> = fs::read_dir(root::sys_path(BASE))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+
+                Some((entry.file_name().into_string().ok()?, entry.path()))
+            })
+            .filter(|(_, path)| is_failing_device(path, &pci))
+            .collect();
+
+        // The kernel removes the coredumps
+        self.seen.retain(|name| dumps.iter().any(|(n, _)| n == name));
+
+        for (name, path) in dumps {
+            if self.seen.contains(&name) { continue }
+
+            // `data` is readable only by root
+            let data = fs::read(path.join("data")).map(|v| String::from_utf8_lossy(&v).into_owned());
+            let mut report = data
+                .as_deref()
+                .ok()
+                .and_then(DevCoredumpReport::parse)
+                .unwrap_or_default();
+
+            report.pci = Some(self.pci);
+            report.time = Some(SystemTime::now());
+
+            if let Ok(data) = &data {
+                report.save(data);
+            }
+
+            self.seen.push(name);
+            self.reports.push(report);
+            self.count += 1;
+            self.new += 1;
+        }
+
+        self.update_resets(&pci);
+
+        if self.reports.len() > MAX_REPORTS {
+            self.reports.drain(..self.reports.len() - MAX_REPORTS);
+            self.new = self.new.min(MAX_REPORTS);
+        }
+    }
+
+    /// A reset is reported with the coredump found in the same update,
+    /// or alone with the timed out ring and the process in the kernel log.
+    fn update_resets(&mut self, pci: &str) {
+        let Some(kmsg) = &self.kmsg else { return };
+        let events: Vec<KernelEvent> = read_kmsg(kmsg)
+            .iter()
+            .filter_map(|record| parse_kmsg(record, pci))
+            .collect();
+        let mut hang = DevCoredumpReport::default();
+
+        for event in events {
+            match event {
+                KernelEvent::Timeout(ring) => hang.ring = Some(ring),
+                KernelEvent::Process(name, pid) => {
+                    hang.process_name = Some(name);
+                    hang.pid = Some(pid);
+                },
+                KernelEvent::Reset => {
+                    *self.resets.get_or_insert(0) += 1;
+
+                    let len = self.reports.len();
+
+                    if let Some(report) = self.reports[len - self.new..].iter_mut().find(|r| !r.reset) {
+                        report.reset = true;
+                    } else {
+                        let mut report = std::mem::take(&mut hang);
+
+                        report.pci = Some(self.pci);
+                        report.time = Some(SystemTime::now());
+                        report.reset = true;
+                        self.reports.push(report);
+                        self.new += 1;
+                    }
+
+                    hang = DevCoredumpReport::default();
+                },
+            }
+        }
+    }
+
+    /// The reports found at the last update
+    pub fn new_reports(&self) -> &[DevCoredumpReport] {
+        &self.reports[self.reports.len() - self.new..]
+    }
+
+    pub fn last(&self) -> Option<&DevCoredumpReport> {
+        self.reports.last()
+    }
+}
+
+/// `failing_device` is a symlink to the device, e.g. "../../../0000:03:00.0"
+fn is_failing_device(path: &Path, pci: &str) -> bool {
+    fs::read_link(path.join("failing_device"))
+        .ok()
+        .is_some_and(|link| link.file_name().is_some_and(|name| name == pci))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_devcoredump() {
+        let s = concat!(
+            "**** AMDGPU Device Coredump ****\n",
+            "version: 1\n",
+            "kernel: 6.10.0-amd\n",
+            "module: amdgpu\n",
+            "time: 45.084775181\n",
+            "process_name: vkcube PID: 1234\n",
+            "\n",
+            "Ring timed out details\n",
+            "IP Type: 0 Ring Name: gfx_0.0.0\n",
+            "\n",
+            "[gfxhub] Page fault observed\n",
+            "Faulty page starting at address: 0x0000800100200000\n",
+            "Protection fault status register: 0x301031\n",
+            "\n",
+            "IBs:\n",
+            "\t[0] 0x0000800100123000 (64)\n",
+            "\n",
+            "IP Dump\n",
+            "IP: gfx_v11_0\n",
+            "regGRBM_STATUS 0x00003028\n",
+            "IP: sdma_v6_0\n",
+            "Ring buffer information\n",
+            "ring name: gfx_0.0.0\n",
+            "VRAM is lost due to GPU reset!\n",
+        );
+        let report = DevCoredumpReport::parse(s).unwrap();
+
+        assert_eq!(report.kernel.as_deref(), Some("6.10.0-amd"));
+        assert_eq!(report.process_name.as_deref(), Some("vkcube"));
+        assert_eq!(report.pid, Some(1234));
+        assert_eq!(report.ring.as_deref(), Some("gfx_0.0.0"));
+        assert_eq!(report.page_fault, Some(0x800100200000));
+        assert_eq!(report.ibs, vec![0x800100123000]);
+        assert_eq!(report.ip_dumps, vec!["gfx_v11_0", "sdma_v6_0"]);
+        assert!(report.vram_lost);
+        assert_eq!(
+            report.to_string(),
+            "GPU hang, ring gfx_0.0.0 timed out, process: vkcube (1234), page fault at 0x800100200000, VRAM lost",
+        );
+        assert!(DevCoredumpReport::parse("kernel: 6.10.0\n").is_none());
+    }
+
+    #[test]
+    fn test_parse_kmsg() {
+        let pci = "0000:03:00.0";
+        let parse = |record: &str| parse_kmsg(record, pci);
+
+        assert_eq!(
+            parse("3,1234,45084775,-;amdgpu 0000:03:00.0: amdgpu: ring gfx_0.0.0 timeout, signaled seq=123, emitted seq=125\n"),
+            Some(KernelEvent::Timeout("gfx_0.0.0".to_string())),
+        );
+        assert_eq!(
+            parse("3,1235,45084790,-;amdgpu 0000:03:00.0: amdgpu: Process information: process vkcube pid 1234 thread vkcube:cs0 pid 1235\n"),
+            Some(KernelEvent::Process("vkcube".to_string(), 1234)),
+        );
+        assert_eq!(
+            parse("6,1240,46102030,-;amdgpu 0000:03:00.0: amdgpu: GPU reset(2) succeeded!\n SUBSYSTEM=pci\n DEVICE=+pci:0000:03:00.0\n"),
+            Some(KernelEvent::Reset),
+        );
+        assert_eq!(parse("6,1238,45900000,-;amdgpu 0000:03:00.0: amdgpu: GPU reset begin!\n"), None);
+        assert_eq!(parse("6,1241,46102031,-;amdgpu 0000:04:00.0: amdgpu: GPU reset(1) succeeded!\n"), None);
+
+        let report = DevCoredumpReport { reset: true, ..Default::default() };
+
+        assert_eq!(report.to_string(), "GPU reset");
+    }
+}
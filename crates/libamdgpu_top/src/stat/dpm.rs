@@ -0,0 +1,326 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::PCI;
+use super::{pcie_link, Sensors};
+
+// ref: https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html#pp-dpm
+
+/// The number of the clock ranges for fine-grained DPM
+const BUCKETS: u32 = 10;
+
+/// `pp_dpm_*`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DpmClockType {
+    Sclk,
+    Mclk,
+    Fclk,
+    Socclk,
+    Dcefclk,
+    Pcie,
+}
+
+impl DpmClockType {
+    pub const ALL: [Self; 6] = [
+        Self::Sclk,
+        Self::Mclk,
+        Self::Fclk,
+        Self::Socclk,
+        Self::Dcefclk,
+        Self::Pcie,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Sclk => "pp_dpm_sclk",
+            Self::Mclk => "pp_dpm_mclk",
+            Self::Fclk => "pp_dpm_fclk",
+            Self::Socclk => "pp_dpm_socclk",
+            Self::Dcefclk => "pp_dpm_dcefclk",
+            Self::Pcie => "pp_dpm_pcie",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sclk => "SCLK",
+            Self::Mclk => "MCLK",
+            Self::Fclk => "FCLK",
+            Self::Socclk => "SOCCLK",
+            Self::Dcefclk => "DCEFCLK",
+            Self::Pcie => "PCIe",
+        }
+    }
+}
+
+impl fmt::Display for DpmClockType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// A level of `pp_dpm_*`, e.g. "1: 1000Mhz *", "S: 19Mhz", "1: 16.0GT/s, x16 619Mhz *"
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DpmLevel {
+    /// "0", "1", .. or "S" for the deep sleep state
+    pub label: String,
+    pub mhz: Option<u32>,
+    /// Only for `pp_dpm_pcie`
+    #[serde(with = "crate::record::remote::option")]
+    pub link: Option<PCI::LINK>,
+    pub active: bool,
+}
+
+fn parse_mhz(s: &str) -> Option<u32> {
+    s.trim().to_ascii_lowercase().strip_suffix("mhz")?.trim().parse().ok()
+}
+
+impl DpmLevel {
+    fn parse(clock: DpmClockType, line: &str) -> Option<Self> {
+        let (label, s) = line.split_once(':')?;
+        let s = s.trim_end();
+        let active = s.ends_with('*');
+        let s = s.trim_end_matches('*');
+
+        let (mhz, link) = if clock == DpmClockType::Pcie {
+            let (link, _) = pcie_link::parse_dpm_line(line)?;
+
+            (s.split_whitespace().last().and_then(parse_mhz), Some(link))
+        } else {
+            (Some(parse_mhz(s)?), None)
+        };
+
+        Some(Self { label: label.trim().to_string(), mhz, link, active })
+    }
+}
+
+impl fmt::Display for DpmLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.link, self.mhz) {
+            (Some(link), _) => write!(f, "{}: Gen{} x{}", self.label, link.gen, link.width),
+            (None, Some(mhz)) => write!(f, "{}: {mhz}MHz", self.label),
+            (None, None) => write!(f, "{}", self.label),
+        }
+    }
+}
+
+/// Time spent at a level, or in a range of the clock for fine-grained DPM
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DpmResidency {
+    /// e.g. "1: 1000MHz", "500-708MHz"
+    pub name: String,
+    pub residency: Duration,
+    pub active: bool,
+}
+
+/// The levels of `pp_dpm_*` and the residency since the start.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DpmLevels {
+    pub clock: DpmClockType,
+    /// The levels at the last update
+    pub levels: Vec<DpmLevel>,
+    /// With fine-grained DPM, `pp_dpm_*` lists only the min, the current and the max clock,
+    /// so the time is accumulated in the ranges of the clock instead of the levels.
+    pub fine_grained: bool,
+    pub residency: Vec<DpmResidency>,
+    pub tracked: Duration,
+}
+
+impl DpmLevels {
+    pub fn new(clock: DpmClockType) -> Self {
+        Self {
+            clock,
+            levels: Vec::new(),
+            fine_grained: false,
+            residency: Vec::new(),
+            tracked: Duration::ZERO,
+        }
+    }
+
+    pub fn parse(clock: DpmClockType, s: &str) -> Vec<DpmLevel> {
+        s.lines().filter_map(|line| DpmLevel::parse(clock, line)).collect()
+    }
+
+    pub fn active(&self) -> Option<&DpmLevel> {
+        self.levels.iter().find(|level| level.active)
+    }
+
+    /// The min and max clock, except for the deep sleep state
+    fn min_max_mhz(levels: &[DpmLevel]) -> Option<(u32, u32)> {
+        let mut iter = levels.iter().filter(|level| level.label != "S").filter_map(|level| level.mhz);
+        let min = iter.next()?;
+        let max = iter.next_back()?;
+
+        (min < max).then_some((min, max))
+    }
+
+    fn bucket(min: u32, max: u32, i: u32) -> String {
+        let width = max - min;
+
+        format!("{}-{}MHz", min + width * i / BUCKETS, min + width * (i + 1) / BUCKETS)
+    }
+
+    /// The elapsed time is attributed to the level (or the range) active at this update.
+    /// `current_mhz` is the current clock from the sensors for fine-grained DPM.
+    fn update_with(&mut self, levels: Vec<DpmLevel>, current_mhz: Option<u32>, elapsed: Duration) {
+        let min_max = Self::min_max_mhz(&levels).filter(|_| {
+            matches!(self.clock, DpmClockType::Sclk | DpmClockType::Mclk)
+                && levels.iter().filter(|level| level.label != "S").count() <= 3
+        });
+        let (names, active) = match min_max {
+            Some((min, max)) => {
+                let names: Vec<String> = (0..BUCKETS).map(|i| Self::bucket(min, max, i)).collect();
+                let active = current_mhz
+                    .or_else(|| levels.iter().find(|level| level.active)?.mhz)
+                    .map(|mhz| (mhz.clamp(min, max) - min) * BUCKETS / (max - min))
+                    .map(|i| names[i.min(BUCKETS - 1) as usize].clone());
+
+                (names, active)
+            },
+            None => {
+                let names: Vec<String> = levels.iter().map(|level| level.to_string()).collect();
+                let active = levels.iter().find(|level| level.active).map(|level| level.to_string());
+
+                (names, active)
+            },
+        };
+        let fine_grained = min_max.is_some();
+
+        // The ranges are changed with the clock range (e.g. overdrive)
+        if fine_grained != self.fine_grained
+            || (fine_grained && !self.residency.iter().map(|r| &r.name).eq(names.iter()))
+        {
+            self.residency.clear();
+            self.tracked = Duration::ZERO;
+        }
+
+        for name in names {
+            if !self.residency.iter().any(|r| r.name == name) {
+                self.residency.push(DpmResidency { name, ..Default::default() });
+            }
+        }
+
+        for r in self.residency.iter_mut() {
+            r.active = active.as_ref() == Some(&r.name);
+
+            if r.active {
+                r.residency += elapsed;
+            }
+        }
+
+        self.fine_grained = fine_grained;
+        self.levels = levels;
+        self.tracked += elapsed;
+    }
+
+    /// Residency (%) over the tracked time
+    pub fn residency_percent(&self, r: &DpmResidency) -> f64 {
+        let tracked = self.tracked.as_secs_f64();
+
+        if tracked == 0.0 { return 0.0 }
+
+        r.residency.as_secs_f64() * 100.0 / tracked
+    }
+}
+
+/// The DPM levels and the residency of `pp_dpm_*` supported by the device.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DpmStat {
+    pub clocks: Vec<DpmLevels>,
+    #[serde(skip)]
+    pre_time: Option<Instant>,
+}
+
+impl DpmStat {
+    /// `None` while the device is idle, `pp_dpm_*` is not read to not wake it up.
+    pub fn update(&mut self, sysfs_path: Option<&Path>, sensors: Option<&Sensors>) {
+        let Some(sysfs_path) = sysfs_path else {
+            self.pre_time = None;
+            return;
+        };
+        let now = Instant::now();
+        let elapsed = self.pre_time.replace(now).map(|pre| now.saturating_duration_since(pre));
+
+        for clock in DpmClockType::ALL {
+            let Ok(s) = fs::read_to_string(sysfs_path.join(clock.file_name())) else { continue };
+            let levels = DpmLevels::parse(clock, &s);
+
+            if levels.is_empty() { continue }
+
+            let current_mhz = match clock {
+                DpmClockType::Sclk => sensors.and_then(|s| s.sclk),
+                DpmClockType::Mclk => sensors.and_then(|s| s.mclk),
+                _ => None,
+            };
+            let pos = match self.clocks.iter().position(|c| c.clock == clock) {
+                Some(pos) => pos,
+                None => {
+                    self.clocks.push(DpmLevels::new(clock));
+                    self.clocks.len() - 1
+                },
+            };
+
+            self.clocks[pos].update_with(levels, current_mhz, elapsed.unwrap_or_default());
+        }
+    }
+
+    pub fn get(&self, clock: DpmClockType) -> Option<&DpmLevels> {
+        self.clocks.iter().find(|c| c.clock == clock)
+    }
+
+    pub fn is_supported(&self) -> bool {
+        !self.clocks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dpm_residency() {
+        let sec = Duration::from_secs(1);
+        let mclk = |active: usize| {
+            let s: String = ["0: 96Mhz", "1: 456Mhz", "2: 673Mhz", "3: 1000Mhz"]
+                .iter()
+                .enumerate()
+                .map(|(i, l)| format!("{l} {}\n", if i == active { "*" } else { "" }))
+                .collect();
+
+            DpmLevels::parse(DpmClockType::Mclk, &s)
+        };
+        let mut levels = DpmLevels::new(DpmClockType::Mclk);
+
+        levels.update_with(mclk(0), None, Duration::ZERO);
+        levels.update_with(mclk(3), None, sec);
+        levels.update_with(mclk(3), None, sec);
+        levels.update_with(mclk(1), None, sec);
+
+        assert!(!levels.fine_grained);
+        assert_eq!(levels.active().and_then(|l| l.mhz), Some(456));
+        assert_eq!(levels.residency[3].name, "3: 1000MHz");
+        assert_eq!(levels.residency[3].residency, 2 * sec);
+        assert!(levels.residency[1].active);
+        assert_eq!(levels.residency_percent(&levels.residency[1]), 100.0 / 3.0);
+
+        let sclk = DpmLevels::parse(DpmClockType::Sclk, "S: 19Mhz\n0: 500Mhz\n1: 1800Mhz *\n2: 2500Mhz\n");
+        let mut levels = DpmLevels::new(DpmClockType::Sclk);
+
+        levels.update_with(sclk.clone(), Some(2450), sec);
+        levels.update_with(sclk, None, sec);
+
+        assert!(levels.fine_grained);
+        assert_eq!(levels.residency.len(), BUCKETS as usize);
+        assert_eq!(levels.residency[0].name, "500-700MHz");
+        assert_eq!(levels.residency[9].residency, sec);
+        assert_eq!(levels.residency[6].residency, sec);
+
+        let pcie = DpmLevels::parse(DpmClockType::Pcie, "0: 2.5GT/s, x1 619Mhz *\n1: 16.0GT/s, x16 1000Mhz\n");
+
+        assert_eq!(pcie[1].to_string(), "1: Gen4 x16");
+        assert_eq!(pcie[1].mhz, Some(1000));
+        assert!(pcie[0].active);
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::AMDGPU::{GpuMetrics, MetricsInfo};
+use super::Sensors;
+
+/// 15.259 uJ (2^-16 J), the unit of `energy_accumulator` in gpu_metrics
+const ENERGY_ACCUMULATOR_UNIT: f64 = 1.0 / 65536.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnergySource {
+    /// `energy_accumulator` in gpu_metrics
+    GpuMetrics,
+    /// the power from hwmon integrated over time
+    Hwmon,
+}
+
+impl fmt::Display for EnergySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GpuMetrics => write!(f, "gpu_metrics"),
+            Self::Hwmon => write!(f, "hwmon"),
+        }
+    }
+}
+
+/// Energy consumed by the device since the start.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EnergyStat {
+    pub total: f64, // J
+    pub source: Option<EnergySource>,
+    #[serde(skip)]
+    pre_accumulator: Option<u64>,
+    #[serde(skip)]
+    pre_time: Option<Instant>,
+}
+
+impl EnergyStat {
+    /// Accumulate the energy since the previous update, and return it (J).
+    /// `energy_accumulator` in gpu_metrics is used if it is available,
+    /// otherwise the power from hwmon is integrated over the elapsed time.
+    pub fn update(&mut self, sensors: Option<&Sensors>, metrics: Option<&GpuMetrics>) -> f64 {
+        let now = Instant::now();
+        let elapsed = self.pre_time.replace(now).map(|pre| now.saturating_duration_since(pre));
+        let accumulator = metrics.and_then(|m| m.get_energy_accumulator());
+        let power = sensors.and_then(|s| s.any_hwmon_power()).map(|p| p.value);
+
+        self.accumulate(accumulator, power, elapsed)
+    }
+
+    fn accumulate(&mut self, accumulator: Option<u64>, power: Option<u32>, elapsed: Option<Duration>) -> f64 {
+        // 0 or u64::MAX if it is not supported
+        let accumulator = accumulator.filter(|v| *v != 0 && *v != u64::MAX);
+        let pre_accumulator = std::mem::replace(&mut self.pre_accumulator, accumulator);
+
+        let energy = match (pre_accumulator, accumulator) {
+            // the counter can be reset, e.g. after the suspend
+            (Some(pre), Some(cur)) if pre <= cur => {
+                self.source = Some(EnergySource::GpuMetrics);
+                (cur - pre) as f64 * ENERGY_ACCUMULATOR_UNIT
+            },
+            _ => {
+                let (Some(power), Some(elapsed)) = (power, elapsed) else { return 0.0 };
+                self.source = Some(EnergySource::Hwmon);
+                power as f64 * elapsed.as_secs_f64()
+            },
+        };
+
+        self.total += energy;
+
+        energy
+    }
+
+    pub fn wh(&self) -> f64 {
+        self.total / 3600.0
+    }
+
+    pub fn kwh(&self) -> f64 {
+        self.total / 3_600_000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulate_energy() {
+        let mut energy = EnergyStat::default();
+        let sec = Some(Duration::from_secs(1));
+
+        assert_eq!(energy.accumulate(Some(65536), Some(100), None), 0.0);
+        assert_eq!(energy.accumulate(Some(65536 * 31), Some(100), sec), 30.0);
+        assert_eq!(energy.source, Some(EnergySource::GpuMetrics));
+        // reset
+        assert_eq!(energy.accumulate(Some(65536), Some(100), sec), 100.0);
+        assert_eq!(energy.source, Some(EnergySource::Hwmon));
+        assert_eq!(energy.accumulate(None, Some(50), sec), 50.0);
+        assert_eq!(energy.total, 180.0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use super::parse_hwmon;
+
+// ref: https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html#hwmon-interfaces
+
+/// `pwm1_enable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FanMode {
+    /// No fan speed control (full speed)
+    NoControl,
+    Manual,
+    Auto,
+}
+
+impl FanMode {
+    pub fn from_pwm1_enable(v: u32) -> Option<Self> {
+        Some(match v {
+            0 => Self::NoControl,
+            1 => Self::Manual,
+            2 => Self::Auto,
+            _ => return None,
+        })
+    }
+
+    pub fn as_pwm1_enable(&self) -> u32 {
+        match self {
+            Self::NoControl => 0,
+            Self::Manual => 1,
+            Self::Auto => 2,
+        }
+    }
+}
+
+impl fmt::Display for FanMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::NoControl => "No Control",
+            Self::Manual => "Manual",
+            Self::Auto => "Auto",
+        };
+
+        f.pad(s)
+    }
+}
+
+/// The fan speed control of hwmon (`pwm1`, `pwm1_enable`, `fan1_target`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FanStat {
+    pub mode: Option<FanMode>,
+    /// 0-`pwm_max`
+    pub pwm: Option<u32>,
+    pub pwm_min: u32,
+    pub pwm_max: u32,
+    /// RPM
+    pub target_rpm: Option<u32>,
+}
+
+impl FanStat {
+    pub fn from_hwmon_path<P: AsRef<Path>>(hwmon_path: P) -> Option<Self> {
+        let hwmon_path = hwmon_path.as_ref();
+        let mode = parse_hwmon(hwmon_path.join("pwm1_enable")).and_then(FanMode::from_pwm1_enable);
+        let pwm = parse_hwmon(hwmon_path.join("pwm1"));
+        let target_rpm = parse_hwmon(hwmon_path.join("fan1_target"));
+
+        if mode.is_none() && pwm.is_none() && target_rpm.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            mode,
+            pwm,
+            pwm_min: parse_hwmon(hwmon_path.join("pwm1_min")).unwrap_or(0),
+            pwm_max: parse_hwmon(hwmon_path.join("pwm1_max")).unwrap_or(255),
+            target_rpm,
+        })
+    }
+
+    pub fn pwm_percent(&self) -> Option<u32> {
+        self.pwm?.saturating_mul(100).checked_div(self.pwm_max)
+    }
+}
@@ -1,35 +1,37 @@
 use std::fs;
 use std::io::Read;
 use std::collections::{HashMap, HashSet};
-use std::time::Duration;
-use std::path::Path;
-use super::ProcInfo;
-use crate::stat;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use super::{is_filtered_out, DrmFdInfo, ProcInfo};
+use crate::{root, stat};
 
 const KFD_PROC_PATH: &str = "/sys/class/kfd/kfd/proc/";
 
 // ref: drivers/gpu/drm/amd/amdgpu/amdgpu_fdinfo.c
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct FdInfoUsage {
     // client_id: usize,
     pub vram_usage: u64, // KiB
     pub gtt_usage: u64, // KiB
     pub system_cpu_memory_usage: u64, // KiB, from Linux Kernel v6.4
+    pub amd_visible_vram: u64, // KiB, from Linux Kernel v6.4
     pub amd_evicted_vram: u64, // KiB, from Linux Kernel v6.4
     pub amd_requested_vram: u64, // KiB, from Linux Kernel v6.4
     pub amd_requested_gtt: u64, // KiB, from Linux Kernel v6.4
-    pub gfx: i64, // ns, %
-    pub compute: i64, // ns, %
-    pub dma: i64, // ns, %
-    pub dec: i64, // ns, %
-    pub enc: i64, // ns, %
-    pub uvd_enc: i64, // ns, %
-    pub vcn_jpeg: i64, // ns, %
-    pub media: i64, // ns, %
-    pub total_dec: i64, // ns, %
-    pub total_enc: i64, // ns, %
-    pub vpe: i64, // ns, %
+    pub gfx: f64, // %
+    pub compute: f64, // %
+    pub dma: f64, // %
+    pub dec: f64, // %
+    pub enc: f64, // %
+    pub uvd_enc: f64, // %
+    pub vcn_jpeg: f64, // %
+    pub media: f64, // %
+    pub total_dec: f64, // %
+    pub total_enc: f64, // %
+    pub vpe: f64, // %
 }
 
 impl std::ops::Add for FdInfoUsage {
@@ -40,6 +42,7 @@ impl std::ops::Add for FdInfoUsage {
             vram_usage: self.vram_usage + other.vram_usage,
             gtt_usage: self.gtt_usage + other.gtt_usage,
             system_cpu_memory_usage: self.system_cpu_memory_usage + other.system_cpu_memory_usage,
+            amd_visible_vram: self.amd_visible_vram + other.amd_visible_vram,
             amd_evicted_vram: self.amd_evicted_vram + other.amd_evicted_vram,
             amd_requested_vram: self.amd_requested_vram + other.amd_requested_vram,
             amd_requested_gtt: self.amd_requested_gtt + other.amd_requested_gtt,
@@ -59,202 +62,194 @@ impl std::ops::Add for FdInfoUsage {
 }
 
 impl FdInfoUsage {
-    const KIB: usize = " KiB".len();
+    /// Returns the memory usage, the engine usage is set by [`FdInfoUsage::set_engine_usage`].
+    pub fn from_drm_fdinfo(info: &DrmFdInfo) -> Self {
+        let mem = |region: &str| info.memory_region(region)
+            .and_then(|m| m.resident_or_memory())
+            .unwrap_or(0);
+        let amd = |key: &str| info.driver_memory(key).unwrap_or(0);
 
-    pub fn id_parse(s: &str) -> Option<usize> {
-        const LEN: usize = "drm-client-id:\t".len();
-        s.get(LEN..)?.parse().ok()
+        Self {
+            vram_usage: mem("vram"),
+            gtt_usage: mem("gtt"),
+            system_cpu_memory_usage: mem("cpu"),
+            amd_visible_vram: amd("amd-memory-visible-vram"),
+            amd_evicted_vram: amd("amd-evicted-vram"),
+            amd_requested_vram: amd("amd-requested-vram"),
+            amd_requested_gtt: amd("amd-requested-gtt"),
+            ..Default::default()
+        }
     }
 
-    pub fn mem_usage_parse(&mut self, s: &str) {
-        const PRE: usize = "drm-memory-xxxx:\t".len(); // "vram:" or "gtt: " or "cpu: "
-        const KIB: usize = " KiB".len();
-        const MEM_TYPE: std::ops::Range<usize> = {
-            const PRE_LEN: usize = "drm-memory-".len();
+    pub fn set_engine_usage(
+        &mut self,
+        engines: &[EngineUsage],
+        has_vcn: bool,
+        has_vcn_unified: bool,
+    ) {
+        let usage = |name: &str| engines
+            .iter()
+            .find(|e| e.name == name)
+            .map_or(0.0, |e| e.usage);
+
+        self.gfx = usage("gfx");
+        self.compute = usage("compute");
+        self.dma = usage("dma");
+        self.dec = usage("dec");
+        self.enc = usage("enc");
+        self.uvd_enc = usage("enc_1");
+        self.vcn_jpeg = usage("jpeg");
+        self.vpe = usage("vpe");
+
+        let [dec, enc, uvd_enc, vcn_jpeg] = [self.dec, self.enc, self.uvd_enc, self.vcn_jpeg];
 
-            PRE_LEN..(PRE_LEN+5)
-        };
+        /*
+            From VCN4, the encoding queue and decoding queue have been unified.
+            The AMDGPU driver handles both decoding and encoding as contexts for the encoding engine.
+        */
+        [self.total_dec, self.total_enc, self.media] = if has_vcn_unified {
+            let media = (vcn_jpeg + enc) / 2.0;
 
-        let Some(usage) = s.get(PRE..s.len()-KIB).and_then(|s| s.parse::<u64>().ok()) else { return };
-        let Some(mem_type) = s.get(MEM_TYPE) else { return };
+            [0.0, 0.0, media]
+        } else if has_vcn {
+            let total_dec = (dec + vcn_jpeg) / 2.0;
+            let media = (dec + vcn_jpeg + enc) / 3.0;
 
-        match mem_type {
-            "vram:" => self.vram_usage += usage,
-            "gtt: " => self.gtt_usage += usage,
-            "cpu: " => self.system_cpu_memory_usage += usage, // from Linux Kernel v6.4
-            _ => {},
-        };
-    }
+            [total_dec, enc, media]
+        } else {
+            let total_enc = (enc + uvd_enc) / 2.0;
+            let media = (dec + enc + uvd_enc) / 3.0;
 
-    pub fn engine_parse(&mut self, s: &str) {
-        const PRE: usize = "drm-engine-".len();
-        const NS: usize = " ns".len();
-        let Some(pos) = s.find('\t') else { return };
-
-        let Some(ns) = s.get(pos+1..s.len()-NS).and_then(|s| s.parse::<i64>().ok()) else { return };
-        let Some(s) = s.get(PRE..pos) else { return };
-
-        match s {
-            "gfx:" => self.gfx += ns,
-            "compute:" => self.compute += ns,
-            "dma:" => self.dma += ns,
-            "dec:" => self.dec += ns,
-            "enc:" => self.enc += ns,
-            "enc_1:" => self.uvd_enc += ns,
-            "jpeg:" => self.vcn_jpeg += ns,
-            "vpe:" => self.vpe += ns,
-            _ => {},
+            [dec, total_enc, media]
         };
     }
+}
 
-    pub fn evicted_vram_parse(&mut self, s: &str) {
-        enum EvictedVramType {
-            Vram,
-        }
-
-        impl EvictedVramType {
-            const PRE_LEN: usize = "amd-evicted-".len();
-            const VRAM_TYPE: std::ops::Range<usize> = Self::PRE_LEN..(Self::PRE_LEN+4);
+/// Busy time and usage of an engine in fdinfo, e.g. "gfx", "dec", "jpeg"
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct EngineUsage {
+    pub name: String,
+    pub busy: u64, // ns
+    pub capacity: u32, // `drm-engine-capacity-<engine>`, 1 if the kernel does not report it
+    pub usage: f64, // %, normalized by the capacity
+}
 
-            fn from_line(s: &str) -> Option<Self> {
-                match s.get(Self::VRAM_TYPE)? {
-                    "vram" => Some(Self::Vram),
-                    _ => None
-                }
-            }
+impl EngineUsage {
+    pub fn from_drm_fdinfo(info: &DrmFdInfo) -> Vec<Self> {
+        info.engines.iter().filter_map(|(name, e)| Some(Self {
+            name: name.to_string(),
+            busy: e.busy?,
+            capacity: e.capacity.unwrap_or(1).max(1),
+            usage: 0.0,
+        })).collect()
+    }
 
-            const fn vram_pos(&self) -> usize {
-                match self {
-                    Self::Vram => Self::PRE_LEN + "vram:\t".len(),
-                }
+    /// Accumulate the busy time of the same engines, e.g. for the total of a process.
+    pub fn merge(engines: &mut Vec<Self>, other: &[Self]) {
+        for o in other {
+            if let Some(e) = engines.iter_mut().find(|e| e.name == o.name) {
+                e.busy += o.busy;
+                e.capacity = e.capacity.max(o.capacity);
+            } else {
+                engines.push(Self { usage: 0.0, ..o.clone() });
             }
         }
-
-        let Some(vram_type) = EvictedVramType::from_line(s) else { return };
-        let Some(m) = s.get(vram_type.vram_pos()..s.len()-Self::KIB)
-            .and_then(|m| m.parse::<u64>().ok()) else { return };
-
-        match vram_type {
-            EvictedVramType::Vram => self.amd_evicted_vram += m,
-        }
     }
 
-    pub fn requested_vram_parse(&mut self, s: &str) {
-        enum RequestedVramType {
-            Vram,
-            Gtt,
-        }
-
-        impl RequestedVramType {
-            const PRE_LEN: usize = "amd-requested-".len();
-            const VRAM_TYPE: std::ops::Range<usize> = Self::PRE_LEN..(Self::PRE_LEN+4);
-
-            fn from_line(s: &str) -> Option<Self> {
-                match s.get(Self::VRAM_TYPE)? {
-                    "vram" => Some(Self::Vram),
-                    "gtt:" => Some(Self::Gtt),
-                    _ => None
-                }
-            }
+    fn calc_usage(&mut self, pre: &[Self], elapsed: &Duration) {
+        let Some(pre) = pre.iter().find(|e| e.name == self.name) else { return };
 
-            const fn vram_pos(&self) -> usize {
-                match self {
-                    Self::Vram => Self::PRE_LEN + "vram:\t".len(),
-                    Self::Gtt => Self::PRE_LEN + "gtt:\t".len(),
-                }
-            }
-        }
+        self.usage = stat::diff_usage(pre.busy as i64, self.busy as i64, elapsed) / self.capacity as f64;
+    }
+}
 
-        let Some(vram_type) = RequestedVramType::from_line(s) else { return };
-        let Some(m) = s.get(vram_type.vram_pos()..s.len()-Self::KIB)
-            .and_then(|m| m.parse::<u64>().ok()) else { return };
+/// Engine busy time with the timestamp, to calculate the usage on the next update.
+#[derive(Clone, Debug)]
+pub struct FdInfoSample {
+    pub time: Instant,
+    pub engines: Vec<EngineUsage>,
+}
 
-        match vram_type {
-            RequestedVramType::Vram => self.amd_requested_vram += m,
-            RequestedVramType::Gtt => self.amd_requested_gtt += m,
+/// Calculate the usage (%) from the previous sample of the same key.
+/// The usage is zero for the first time.
+fn calc_engine_usage<K: Eq + Hash>(
+    map: &mut HashMap<K, FdInfoSample>,
+    key: K,
+    time: Instant,
+    engines: &mut [EngineUsage],
+) {
+    if let Some(pre) = map.get(&key) {
+        let elapsed = time.saturating_duration_since(pre.time);
+
+        for e in engines.iter_mut() {
+            e.calc_usage(&pre.engines, &elapsed);
         }
     }
 
-    pub fn calc_usage(
-        &self,
-        pre_stat: &Self,
-        interval: &Duration,
-        has_vcn: bool,
-        has_vcn_unified: bool,
-    ) -> Self {
-        let [gfx, compute, dma, dec, enc, uvd_enc, vcn_jpeg, vpe] = {
-            [
-                (pre_stat.gfx, self.gfx),
-                (pre_stat.compute, self.compute),
-                (pre_stat.dma, self.dma),
-                (pre_stat.dec, self.dec),
-                (pre_stat.enc, self.enc),
-                (pre_stat.uvd_enc, self.uvd_enc),
-                (pre_stat.vcn_jpeg, self.vcn_jpeg),
-                (pre_stat.vpe, self.vpe),
-            ]
-            .map(|(pre, cur)| stat::diff_usage(pre, cur, interval))
-        };
-
-        /*
-            From VCN4, the encoding queue and decoding queue have been unified.
-            The AMDGPU driver handles both decoding and encoding as contexts for the encoding engine.
-        */
-        let [total_dec, total_enc, media] = if has_vcn_unified {
-            let media = (vcn_jpeg + enc) / 2;
-
-            [0, 0, media]
-        } else if has_vcn {
-            let total_dec = (dec + vcn_jpeg) / 2;
-            let media = (dec + vcn_jpeg + enc) / 3;
+    map.insert(key, FdInfoSample { time, engines: engines.to_vec() });
+}
 
-            [total_dec, enc, media]
-        } else {
-            let total_enc = (enc + uvd_enc) / 2;
-            let media = (dec + enc + uvd_enc) / 3;
+/// Usage of a DRM client (an opened DRM file), processes can have several clients.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct FdInfoClient {
+    pub client_id: usize,
+    pub fd: i32,
+    pub usage: FdInfoUsage,
+    pub engines: Vec<EngineUsage>,
+    /// All keys of the fdinfo, including `drm-client-name` (from Linux Kernel v6.15) and unknown keys
+    pub fdinfo: DrmFdInfo,
+}
 
-            [dec, total_enc, media]
-        };
+impl FdInfoClient {
+    /// Parse the fdinfo of a DRM client.
+    /// Returns `None` if it is not a DRM client.
+    pub fn parse(s: &str, fd: i32) -> Option<Self> {
+        let fdinfo = DrmFdInfo::parse(s);
+        let client_id = fdinfo.client_id?;
+        let usage = FdInfoUsage::from_drm_fdinfo(&fdinfo);
+        let engines = EngineUsage::from_drm_fdinfo(&fdinfo);
 
-        Self {
-            vram_usage: self.vram_usage,
-            gtt_usage: self.gtt_usage,
-            system_cpu_memory_usage: self.system_cpu_memory_usage,
-            amd_evicted_vram: self.amd_evicted_vram,
-            amd_requested_vram: self.amd_requested_vram,
-            amd_requested_gtt: self.amd_requested_gtt,
-            gfx,
-            compute,
-            dma,
-            dec,
-            enc,
-            uvd_enc,
-            vcn_jpeg,
-            media,
-            total_dec,
-            total_enc,
-            vpe,
-        }
+        Some(Self { client_id, fd, usage, engines, fdinfo })
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct ProcUsage {
     pub pid: i32,
     pub name: String,
     pub ids_count: usize,
     pub usage: FdInfoUsage,
+    pub engines: Vec<EngineUsage>,
     pub cpu_usage: i64, // %
     pub is_kfd_process: bool,
+    pub clients: Vec<FdInfoClient>,
+    pub energy: f64, // J, attributed by the engine usage, see `FdInfoStat::attribute_energy`
+}
+
+impl ProcUsage {
+    /// Sum of the usage (%) of the engines
+    pub fn engine_usage(&self) -> f64 {
+        self.engines.iter().map(|e| e.usage).sum()
+    }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct FdInfoStat {
-    pub pid_map: HashMap<i32, FdInfoUsage>,
+    #[serde(skip)]
+    pub pid_map: HashMap<i32, FdInfoSample>,
+    #[serde(skip)]
+    pub client_map: HashMap<usize, FdInfoSample>,
+    #[serde(skip)]
     pub drm_client_ids: HashSet<usize>,
     pub proc_usage: Vec<ProcUsage>,
     pub interval: Duration,
+    #[serde(skip)]
     pub cpu_time_map: HashMap<i32, f32>, // sec
+    #[serde(skip)]
+    pub energy_map: HashMap<i32, f64>, // J
+    /// Sum of the engine usage of all processes, including the filtered out ones
+    #[serde(skip)]
+    pub total_engine_usage: f64,
     pub has_vcn: bool,
     pub has_vcn_unified: bool,
     pub has_vpe: bool,
@@ -266,7 +261,7 @@ impl FdInfoStat {
         // ref: https://manpages.org/proc/5
         const UTIME: usize = 14 - OFFSET;
         const HZ: f32 = 100.0;
-        let Ok(s) = std::fs::read_to_string(format!("/proc/{pid}/stat")) else { return 0.0 };
+        let Ok(s) = std::fs::read_to_string(root::proc_path(format!("{pid}/stat"))) else { return 0.0 };
         // for process names with spaces
         let s = s.trim_start_matches(&format!("{pid} ({name}) "));
         let mut split = s.split(' ').skip(UTIME);
@@ -290,92 +285,54 @@ impl FdInfoStat {
 
     pub fn get_proc_usage(&mut self, proc_info: &ProcInfo) {
         let pid = proc_info.pid;
-        let mut stat = FdInfoUsage::default();
+        let mut usage = FdInfoUsage::default();
+        let mut engines: Vec<EngineUsage> = Vec::new();
         let mut buf = String::with_capacity(2048);
         let mut ids_count = 0usize;
+        let mut clients = Vec::new();
+        let time = Instant::now();
 
         for fd in &proc_info.fds {
             buf.clear();
 
             {
-                let path = format!("/proc/{pid}/fdinfo/{fd}");
+                let path = root::proc_path(format!("{pid}/fdinfo/{fd}"));
                 let Ok(mut f) = fs::File::open(&path) else { continue };
                 if f.read_to_string(&mut buf).is_err() { continue }
             }
 
-            let mut lines = buf.lines().skip_while(|l| !l.starts_with("drm-client-id"));
-            if let Some(id) = lines.next().and_then(FdInfoUsage::id_parse) {
-                ids_count += 1;
-                if !self.drm_client_ids.insert(id) { continue }
-            } else {
-                continue;
-            }
+            let client_time = Instant::now();
+            let Some(mut client) = FdInfoClient::parse(&buf, *fd) else { continue };
 
-            for l in lines {
-                let Some(s) = l.get(0..10) else { continue };
+            ids_count += 1;
+            if !self.drm_client_ids.insert(client.client_id) { continue }
 
-                match s {
-                    "drm-memory" => stat.mem_usage_parse(l),
-                    "drm-engine" => stat.engine_parse(l),
-                    "amd-evicte" => stat.evicted_vram_parse(l),
-                    "amd-reques" => stat.requested_vram_parse(l),
-                    _ => {},
-                }
-            }
+            usage = usage + client.usage;
+            EngineUsage::merge(&mut engines, &client.engines);
+
+            calc_engine_usage(&mut self.client_map, client.client_id, client_time, &mut client.engines);
+            client.usage.set_engine_usage(&client.engines, self.has_vcn, self.has_vcn_unified);
+
+            clients.push(client);
         }
 
-        let usage = if let Some(pre_stat) = self.pid_map.get_mut(&pid) {
-            // ns -> %
-            let usage_per = stat.calc_usage(
-                pre_stat,
-                &self.interval,
-                self.has_vcn,
-                self.has_vcn_unified,
-            );
-            *pre_stat = stat;
-
-            usage_per
-        } else {
-            let [
-                vram_usage,
-                gtt_usage,
-                system_cpu_memory_usage,
-                amd_evicted_vram,
-                amd_requested_vram,
-                amd_requested_gtt,
-            ] = [
-                stat.vram_usage,
-                stat.gtt_usage,
-                stat.system_cpu_memory_usage,
-                stat.amd_evicted_vram,
-                stat.amd_requested_vram,
-                stat.amd_requested_gtt,
-            ];
-
-            self.pid_map.insert(pid, stat);
-
-            FdInfoUsage {
-                vram_usage,
-                gtt_usage,
-                system_cpu_memory_usage,
-                amd_evicted_vram,
-                amd_requested_vram,
-                amd_requested_gtt,
-                ..Default::default()
-            }
-        };
+        calc_engine_usage(&mut self.pid_map, pid, time, &mut engines);
+        usage.set_engine_usage(&engines, self.has_vcn, self.has_vcn_unified);
 
         let name = proc_info.name.clone();
         let cpu_usage = self.get_cpu_usage(pid, &name) as i64;
-        let is_kfd_process = Path::new(KFD_PROC_PATH).join(pid.to_string()).exists();
+        let is_kfd_process = root::sys_path(KFD_PROC_PATH).join(pid.to_string()).exists();
 
         self.proc_usage.push(ProcUsage {
             pid,
             name,
             ids_count,
             usage,
+            engines,
             cpu_usage,
             is_kfd_process,
+            clients,
+            energy: self.energy_map.get(&pid).copied().unwrap_or(0.0),
         });
     }
 
@@ -385,9 +342,84 @@ impl FdInfoStat {
         for pu in proc_index {
             self.get_proc_usage(pu);
         }
+        // the filtered out processes also share the energy of the device
+        self.total_engine_usage = self.proc_usage.iter().map(ProcUsage::engine_usage).sum();
+        self.proc_usage.retain(|pu| !is_filtered_out(pu.pid, &pu.name));
+        // remove closed clients
+        self.client_map.retain(|id, _| self.drm_client_ids.contains(id));
+    }
+
+    /// Attribute the energy (J) consumed by the device to the processes
+    /// proportionally to their engine usage, and accumulate it to [`ProcUsage::energy`].
+    /// The energy while no process uses the engines is not attributed.
+    pub fn attribute_energy(&mut self, energy: f64) {
+        let total = self.total_engine_usage;
+
+        for pu in self.proc_usage.iter_mut() {
+            let acc = self.energy_map.entry(pu.pid).or_default();
+
+            if total > 0.0 {
+                *acc += energy * pu.engine_usage() / total;
+            }
+
+            pu.energy = *acc;
+        }
+
+        self.energy_map.retain(|pid, _| self.proc_usage.iter().any(|pu| pu.pid == *pid));
     }
 
     pub fn fold_fdinfo_usage(&self) -> FdInfoUsage {
         self.proc_usage.iter().fold(FdInfoUsage::default(), |acc, pu| acc + pu.usage)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fdinfo_client() {
+        let s = concat!(
+            "pos:\t0\nflags:\t02100002\nmnt_id:\t24\nino:\t1086\n",
+            "drm-driver:\tamdgpu\n",
+            "drm-client-id:\t48\n",
+            "drm-pdev:\t0000:03:00.0\n",
+            "drm-client-name:\tWebGL\n",
+            "pasid:\t32786\n",
+            "drm-memory-vram:\t34816 KiB\n",
+            "drm-memory-gtt: \t2048 KiB\n",
+            "drm-memory-cpu: \t0 KiB\n",
+            "drm-engine-gfx:\t1234567 ns\n",
+            "drm-engine-dma:\t89 ns\n",
+        );
+        let client = FdInfoClient::parse(s, 21).unwrap();
+
+        assert_eq!(client.client_id, 48);
+        assert_eq!(client.fd, 21);
+        assert_eq!(client.fdinfo.pdev.as_deref(), Some("0000:03:00.0"));
+        assert_eq!(client.fdinfo.client_name.as_deref(), Some("WebGL"));
+        assert_eq!((client.usage.vram_usage, client.usage.gtt_usage), (34816, 2048));
+        assert_eq!(
+            client.engines.iter().map(|e| (e.name.as_str(), e.busy)).collect::<Vec<_>>(),
+            [("gfx", 1234567), ("dma", 89)],
+        );
+
+        assert!(FdInfoClient::parse("pos:\t0\nflags:\t02100002\n", 3).is_none());
+    }
+
+    #[test]
+    fn test_engine_usage_capacity() {
+        let mut map = HashMap::new();
+        let time = Instant::now();
+        let mut engines = vec![
+            EngineUsage { name: "gfx".to_string(), busy: 1_000, capacity: 2, usage: 0.0 },
+        ];
+
+        calc_engine_usage(&mut map, 0, time, &mut engines);
+        engines[0].busy += 5_000_000;
+        calc_engine_usage(&mut map, 0, time + Duration::from_millis(1000), &mut engines);
+
+        // 5ms / (1s * 2)
+        assert_eq!(engines[0].usage, 0.25);
+    }
+}
@@ -0,0 +1,236 @@
+// Parser of the DRM client usage stats in fdinfo, shared by amdgpu and amdxdna.
+// ref: https://docs.kernel.org/gpu/drm-usage-stats.html
+
+use serde::{Deserialize, Serialize};
+
+/// Keys of the file, not of the DRM client
+const FILE_KEYS: &[&str] = &["pos", "flags", "mnt_id", "ino"];
+
+/// `drm-{total,shared,resident,purgeable,active,memory}-<region>`
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct DrmMemoryStats {
+    pub total: Option<u64>, // KiB
+    pub shared: Option<u64>, // KiB
+    pub resident: Option<u64>, // KiB
+    pub purgeable: Option<u64>, // KiB
+    pub active: Option<u64>, // KiB
+    pub memory: Option<u64>, // KiB, `drm-memory-<region>`, the legacy key for the resident memory
+}
+
+impl DrmMemoryStats {
+    /// Returns `drm-resident-<region>`, or `drm-memory-<region>` for older kernels.
+    pub fn resident_or_memory(&self) -> Option<u64> {
+        self.resident.or(self.memory)
+    }
+}
+
+/// `drm-{engine,engine-capacity,cycles,total-cycles,maxfreq}-<engine>`
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct DrmEngineStats {
+    pub busy: Option<u64>, // ns
+    pub capacity: Option<u32>,
+    pub cycles: Option<u64>,
+    pub total_cycles: Option<u64>,
+    pub maxfreq: Option<u64>, // Hz
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct DrmFdInfo {
+    pub driver: Option<String>,
+    pub pdev: Option<String>,
+    pub client_id: Option<usize>,
+    pub client_name: Option<String>,
+    /// (region, stats), e.g. "vram", "gtt", "cpu", "memory"
+    pub memory: Vec<(String, DrmMemoryStats)>,
+    /// (engine, stats), e.g. "gfx", "dec", "npu-amdxdna"
+    pub engines: Vec<(String, DrmEngineStats)>,
+    /// Driver-specific keys with a memory unit, e.g. ("amd-memory-visible-vram", KiB)
+    pub driver_memory: Vec<(String, u64)>,
+    /// Other keys as they are, e.g. ("pasid", "32794")
+    pub others: Vec<(String, String)>,
+}
+
+/// "<uint> [KiB|MiB|GiB]" -> KiB, the unit is bytes without a suffix.
+fn parse_memory(s: &str) -> Option<u64> {
+    let (v, unit) = s.split_once(' ').unwrap_or((s, ""));
+    let v: u64 = v.parse().ok()?;
+
+    match unit.trim() {
+        "" => Some(v >> 10),
+        "KiB" => Some(v),
+        "MiB" => Some(v << 10),
+        "GiB" => Some(v << 20),
+        _ => None,
+    }
+}
+
+/// "<uint> [Hz|KHz|MHz]" -> Hz
+fn parse_freq(s: &str) -> Option<u64> {
+    let (v, unit) = s.split_once(' ').unwrap_or((s, ""));
+    let v: u64 = v.parse().ok()?;
+
+    match unit.trim() {
+        "" | "Hz" => Some(v),
+        "KHz" | "kHz" => Some(v * 1_000),
+        "MHz" => Some(v * 1_000_000),
+        _ => None,
+    }
+}
+
+fn parse_ns(s: &str) -> Option<u64> {
+    s.strip_suffix("ns").unwrap_or(s).trim_end().parse().ok()
+}
+
+impl DrmFdInfo {
+    pub fn parse(s: &str) -> Self {
+        let mut info = Self::default();
+
+        for l in s.lines() {
+            let Some((key, val)) = l.split_once(':') else { continue };
+            let (key, val) = (key.trim(), val.trim());
+
+            if FILE_KEYS.contains(&key) { continue }
+
+            if info.parse_key_value(key, val).is_none() {
+                info.others.push((key.to_string(), val.to_string()));
+            }
+        }
+
+        info
+    }
+
+    fn parse_key_value(&mut self, key: &str, val: &str) -> Option<()> {
+        match key {
+            "drm-driver" => self.driver = Some(val.to_string()),
+            "drm-pdev" => self.pdev = Some(val.to_string()),
+            "drm-client-id" => self.client_id = Some(val.parse().ok()?),
+            "drm-client-name" => self.client_name = Some(val.to_string()),
+            _ => if let Some(name) = key.strip_prefix("drm-engine-capacity-") {
+                let v = val.parse().ok()?;
+                self.engine_mut(name).capacity = Some(v);
+            } else if let Some(name) = key.strip_prefix("drm-engine-") {
+                let v = parse_ns(val)?;
+                self.engine_mut(name).busy = Some(v);
+            } else if let Some(name) = key.strip_prefix("drm-total-cycles-") {
+                let v = val.parse().ok()?;
+                self.engine_mut(name).total_cycles = Some(v);
+            } else if let Some(name) = key.strip_prefix("drm-cycles-") {
+                let v = val.parse().ok()?;
+                self.engine_mut(name).cycles = Some(v);
+            } else if let Some(name) = key.strip_prefix("drm-maxfreq-") {
+                let v = parse_freq(val)?;
+                self.engine_mut(name).maxfreq = Some(v);
+            } else if let Some((name, field)) = Self::memory_key(key) {
+                let v = parse_memory(val)?;
+                let stats = self.memory_mut(name);
+
+                *match field {
+                    "total" => &mut stats.total,
+                    "shared" => &mut stats.shared,
+                    "resident" => &mut stats.resident,
+                    "purgeable" => &mut stats.purgeable,
+                    "active" => &mut stats.active,
+                    _ => &mut stats.memory,
+                } = Some(v);
+            } else if !key.starts_with("drm-") && val.ends_with("iB") {
+                let v = parse_memory(val)?;
+                self.driver_memory.push((key.to_string(), v));
+            } else {
+                return None;
+            },
+        }
+
+        Some(())
+    }
+
+    /// "drm-total-vram" -> ("vram", "total")
+    fn memory_key(key: &str) -> Option<(&str, &str)> {
+        let key = key.strip_prefix("drm-")?;
+        let (field, name) = key.split_once('-')?;
+
+        ["total", "shared", "resident", "purgeable", "active", "memory"]
+            .contains(&field)
+            .then_some((name, field))
+    }
+
+    fn engine_mut(&mut self, name: &str) -> &mut DrmEngineStats {
+        let pos = self.engines.iter().position(|(n, _)| n == name).unwrap_or_else(|| {
+            self.engines.push((name.to_string(), Default::default()));
+            self.engines.len() - 1
+        });
+
+        &mut self.engines[pos].1
+    }
+
+    fn memory_mut(&mut self, name: &str) -> &mut DrmMemoryStats {
+        let pos = self.memory.iter().position(|(n, _)| n == name).unwrap_or_else(|| {
+            self.memory.push((name.to_string(), Default::default()));
+            self.memory.len() - 1
+        });
+
+        &mut self.memory[pos].1
+    }
+
+    pub fn engine(&self, name: &str) -> Option<&DrmEngineStats> {
+        self.engines.iter().find_map(|(n, stats)| (n == name).then_some(stats))
+    }
+
+    pub fn memory_region(&self, name: &str) -> Option<&DrmMemoryStats> {
+        self.memory.iter().find_map(|(n, stats)| (n == name).then_some(stats))
+    }
+
+    pub fn driver_memory(&self, key: &str) -> Option<u64> {
+        self.driver_memory.iter().find_map(|(k, v)| (k == key).then_some(*v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drm_fdinfo_parse() {
+        let s = concat!(
+            "pos:\t0\n",
+            "drm-driver:\tamdgpu\n",
+            "drm-client-id:\t48\n",
+            "drm-pdev:\t0000:03:00.0\n",
+            "pasid:\t32786\n",
+            "drm-memory-gtt: \t2048 KiB\n",
+            "drm-total-vram:\t2 MiB\n",
+            "drm-resident-vram:\t1 GiB\n",
+            "drm-purgeable-vram:\t4096\n",
+            "amd-memory-visible-vram:\t1024 KiB\n",
+            "drm-engine-gfx:\t1234567 ns\n",
+            "drm-engine-capacity-gfx:\t2\n",
+            "drm-cycles-gfx:\t100\n",
+            "drm-total-cycles-gfx:\t200\n",
+            "drm-maxfreq-gfx:\t2500 MHz\n",
+            "drm-foo-bar:\tbaz\n",
+        );
+        let info = DrmFdInfo::parse(s);
+
+        assert_eq!(info.driver.as_deref(), Some("amdgpu"));
+        assert_eq!(info.client_id, Some(48));
+        assert_eq!(info.pdev.as_deref(), Some("0000:03:00.0"));
+
+        let vram = info.memory_region("vram").unwrap();
+        assert_eq!((vram.total, vram.resident, vram.purgeable), (Some(2048), Some(1 << 20), Some(4)));
+        assert_eq!(info.memory_region("gtt").unwrap().resident_or_memory(), Some(2048));
+        assert_eq!(info.driver_memory("amd-memory-visible-vram"), Some(1024));
+
+        let gfx = info.engine("gfx").unwrap();
+        assert_eq!(gfx.busy, Some(1234567));
+        assert_eq!(gfx.capacity, Some(2));
+        assert_eq!((gfx.cycles, gfx.total_cycles), (Some(100), Some(200)));
+        assert_eq!(gfx.maxfreq, Some(2_500_000_000));
+
+        assert_eq!(
+            info.others,
+            vec![
+                ("pasid".to_string(), "32786".to_string()),
+                ("drm-foo-bar".to_string(), "baz".to_string()),
+            ],
+        );
+    }
+}
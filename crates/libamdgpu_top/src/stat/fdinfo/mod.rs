@@ -1,3 +1,6 @@
+mod drm_fdinfo;
+pub use drm_fdinfo::*;
+
 mod amdgpu_fdinfo;
 pub use amdgpu_fdinfo::*;
 
@@ -6,3 +9,11 @@ pub use amdgpu_fdinfo_sort::*;
 
 mod proc_info;
 pub use proc_info::*;
+
+mod proc_index;
+pub use proc_index::*;
+
+mod proc_filter;
+pub use proc_filter::*;
+
+mod proc_connector;
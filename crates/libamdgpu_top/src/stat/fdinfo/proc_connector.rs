@@ -0,0 +1,229 @@
+// Process events from the netlink proc connector.
+// Requires CAP_NET_ADMIN and the initial PID namespace.
+// ref: include/uapi/linux/connector.h, include/uapi/linux/cn_proc.h
+
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+const NETLINK_CONNECTOR: libc::c_int = 11;
+const CN_IDX_PROC: u32 = 0x1;
+const CN_VAL_PROC: u32 = 0x1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+const NLMSG_HDR_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+/// `what`, `cpu`, `timestamp_ns` of `struct proc_event`
+const PROC_EVENT_HDR_LEN: usize = 16;
+
+const RECV_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProcEvent {
+    /// A new process, events of threads are ignored
+    Fork(i32),
+    Exec(i32),
+    Exit(i32),
+}
+
+pub(crate) struct ProcConnector {
+    fd: libc::c_int,
+}
+
+impl ProcConnector {
+    /// The kernel only sends the events to listeners in the initial PID namespace,
+    /// the PIDs in other namespaces do not match the events.
+    pub fn is_available() -> bool {
+        let ns = |pid: &str| fs::read_link(format!("/proc/{pid}/ns/pid")).ok();
+
+        matches!((ns("self"), ns("1")), (Some(s), Some(init)) if s == init)
+    }
+
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, NETLINK_CONNECTOR)
+        };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let conn = Self { fd };
+
+        // SAFETY: sockaddr_nl is a plain C struct of integers
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = CN_IDX_PROC;
+
+        let r = unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let tv = libc::timeval {
+            tv_sec: RECV_TIMEOUT.as_secs() as libc::time_t,
+            tv_usec: RECV_TIMEOUT.subsec_micros() as libc::suseconds_t,
+        };
+        let r = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &tv as *const libc::timeval as *const libc::c_void,
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        conn.send_listen()?;
+
+        Ok(conn)
+    }
+
+    fn send_listen(&self) -> io::Result<()> {
+        let mut buf = [0u8; NLMSG_HDR_LEN + CN_MSG_LEN + 4];
+        let len = buf.len() as u32;
+        let pid = std::process::id();
+
+        // struct nlmsghdr
+        buf[0..4].copy_from_slice(&len.to_ne_bytes());
+        buf[4..6].copy_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        buf[12..16].copy_from_slice(&pid.to_ne_bytes());
+        // struct cn_msg
+        buf[16..20].copy_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        buf[20..24].copy_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        buf[32..34].copy_from_slice(&4u16.to_ne_bytes());
+        // enum proc_cn_mcast_op
+        buf[36..40].copy_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+        let r = unsafe { libc::send(self.fd, buf.as_ptr() as *const libc::c_void, buf.len(), 0) };
+
+        if r < 0 { Err(io::Error::last_os_error()) } else { Ok(()) }
+    }
+
+    /// Wait for a message up to `RECV_TIMEOUT` and push the events to `events`.
+    /// Returns `Err` with `ENOBUFS` if some events are lost.
+    pub fn recv(&self, events: &mut Vec<ProcEvent>) -> io::Result<()> {
+        let mut buf = [0u8; 4096];
+        let r = unsafe { libc::recv(self.fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+
+        if r < 0 {
+            let err = io::Error::last_os_error();
+
+            return match err.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => Ok(()),
+                _ => Err(err),
+            };
+        }
+
+        parse_messages(&buf[..r as usize], events);
+
+        Ok(())
+    }
+}
+
+impl Drop for ProcConnector {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_ne_bytes(buf.get(pos..pos+4)?.try_into().ok()?))
+}
+
+fn parse_messages(buf: &[u8], events: &mut Vec<ProcEvent>) {
+    let mut pos = 0;
+
+    while let Some(len) = u32_at(buf, pos) {
+        let len = len as usize;
+
+        if len < NLMSG_HDR_LEN || buf.len() < pos + len { break }
+
+        if let Some(event) = parse_proc_event(&buf[pos+NLMSG_HDR_LEN..pos+len]) {
+            events.push(event);
+        }
+
+        // NLMSG_ALIGN
+        pos += (len + 3) & !3;
+    }
+}
+
+/// Parse `struct cn_msg` with `struct proc_event`.
+fn parse_proc_event(msg: &[u8]) -> Option<ProcEvent> {
+    if u32_at(msg, 0)? != CN_IDX_PROC || u32_at(msg, 4)? != CN_VAL_PROC { return None }
+
+    let event = msg.get(CN_MSG_LEN..)?;
+    let what = u32_at(event, 0)?;
+    let data = event.get(PROC_EVENT_HDR_LEN..)?;
+    let i32_at = |pos: usize| u32_at(data, pos).map(|v| v as i32);
+
+    match what {
+        // parent_pid, parent_tgid, child_pid, child_tgid
+        PROC_EVENT_FORK => {
+            let (pid, tgid) = (i32_at(8)?, i32_at(12)?);
+            (pid == tgid).then_some(ProcEvent::Fork(tgid))
+        },
+        // process_pid, process_tgid
+        PROC_EVENT_EXEC => Some(ProcEvent::Exec(i32_at(4)?)),
+        // process_pid, process_tgid, exit_code, exit_signal, ...
+        PROC_EVENT_EXIT => {
+            let (pid, tgid) = (i32_at(0)?, i32_at(4)?);
+            (pid == tgid).then_some(ProcEvent::Exit(tgid))
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(what: u32, data: &[i32]) -> Vec<u8> {
+        let mut buf = vec![0u8; NLMSG_HDR_LEN + CN_MSG_LEN + PROC_EVENT_HDR_LEN];
+
+        buf[16..20].copy_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        buf[20..24].copy_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        buf[36..40].copy_from_slice(&what.to_ne_bytes());
+
+        for v in data {
+            buf.extend_from_slice(&v.to_ne_bytes());
+        }
+
+        let len = buf.len() as u32;
+        buf[0..4].copy_from_slice(&len.to_ne_bytes());
+
+        buf
+    }
+
+    #[test]
+    fn test_parse_proc_events() {
+        let mut buf = message(PROC_EVENT_FORK, &[100, 100, 200, 200]);
+        buf.extend(message(PROC_EVENT_FORK, &[200, 200, 201, 200])); // thread
+        buf.extend(message(PROC_EVENT_EXEC, &[200, 200]));
+        buf.extend(message(PROC_EVENT_EXIT, &[201, 200, 0, 0]));
+        buf.extend(message(PROC_EVENT_EXIT, &[200, 200, 0, 0]));
+
+        let mut events = Vec::new();
+        parse_messages(&buf, &mut events);
+
+        assert_eq!(
+            events,
+            vec![ProcEvent::Fork(200), ProcEvent::Exec(200), ProcEvent::Exit(200)],
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Filter of the processes in fdinfo by PID, name (regex) and user.
+//! The filter is global like [`crate::root`], and applies to [`FdInfoStat::proc_usage`] of all devices.
+//!
+//! [`FdInfoStat::proc_usage`]: super::FdInfoStat::proc_usage
+
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::sync::RwLock;
+use anyhow::{anyhow, bail, Context};
+use regex::Regex;
+use crate::root;
+
+/// Processes match if they match any of the values for each of the kinds (PID, name, user).
+#[derive(Debug, Clone, Default)]
+pub struct ProcFilter {
+    pub pids: Vec<i32>,
+    pub names: Vec<Regex>,
+    pub uids: Vec<u32>,
+}
+
+impl ProcFilter {
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty() && self.names.is_empty() && self.uids.is_empty()
+    }
+
+    /// Comma separated PIDs
+    pub fn add_pids(&mut self, s: &str) -> anyhow::Result<()> {
+        for pid in s.split(',').map(str::trim).filter(|pid| !pid.is_empty()) {
+            self.pids.push(pid.parse().with_context(|| format!("invalid PID: {pid:?}"))?);
+        }
+
+        Ok(())
+    }
+
+    /// A process name or a regex, it matches a part of the name like `pgrep`.
+    pub fn add_name(&mut self, s: &str) -> anyhow::Result<()> {
+        self.names.push(Regex::new(s)?);
+
+        Ok(())
+    }
+
+    /// A user name or an UID
+    pub fn add_user(&mut self, s: &str) -> anyhow::Result<()> {
+        let uid = match s.parse::<u32>() {
+            Ok(uid) => uid,
+            Err(_) => get_uid(s).ok_or_else(|| anyhow!("unknown user: {s:?}"))?,
+        };
+
+        self.uids.push(uid);
+
+        Ok(())
+    }
+
+    /// Parse the filter for the TUI prompt, e.g. `pid:1234,1235 user:foo firefox`.
+    /// The words without the prefix are the process names.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut filter = Self::default();
+
+        for word in s.split_whitespace() {
+            if let Some(pids) = word.strip_prefix("pid:") {
+                filter.add_pids(pids)?;
+            } else if let Some(user) = word.strip_prefix("user:") {
+                filter.add_user(user)?;
+            } else if word.contains(':') && !word.starts_with("name:") {
+                bail!("unknown filter: {word:?}");
+            } else {
+                filter.add_name(word.strip_prefix("name:").unwrap_or(word))?;
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, pid: i32, name: &str) -> bool {
+        (self.pids.is_empty() || self.pids.contains(&pid))
+        && (self.names.is_empty() || self.names.iter().any(|re| re.is_match(name)))
+        && (self.uids.is_empty() || read_uid(pid).is_some_and(|uid| self.uids.contains(&uid)))
+    }
+}
+
+impl fmt::Display for ProcFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words: Vec<String> = Vec::new();
+
+        if !self.pids.is_empty() {
+            let pids: Vec<String> = self.pids.iter().map(|pid| pid.to_string()).collect();
+            words.push(format!("pid:{}", pids.join(",")));
+        }
+
+        words.extend(self.uids.iter().map(|uid| format!("user:{uid}")));
+        words.extend(self.names.iter().map(|re| re.as_str().to_string()));
+
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+fn get_uid(user: &str) -> Option<u32> {
+    let name = CString::new(user).ok()?;
+    let pw = unsafe { libc::getpwnam(name.as_ptr()) };
+
+    if pw.is_null() { return None }
+
+    Some(unsafe { (*pw).pw_uid })
+}
+
+/// The effective UID of the process
+fn read_uid(pid: i32) -> Option<u32> {
+    let s = fs::read_to_string(root::proc_path(format!("{pid}/status"))).ok()?;
+    let uids = s.lines().find_map(|line| line.strip_prefix("Uid:"))?;
+
+    uids.split_whitespace().nth(1)?.parse().ok()
+}
+
+static PROC_FILTER: RwLock<Option<ProcFilter>> = RwLock::new(None);
+
+/// Set the process filter, an empty filter removes it.
+pub fn set_proc_filter(filter: ProcFilter) {
+    *PROC_FILTER.write().unwrap() = (!filter.is_empty()).then_some(filter);
+}
+
+pub fn get_proc_filter() -> Option<ProcFilter> {
+    PROC_FILTER.read().unwrap().clone()
+}
+
+pub(crate) fn is_filtered_out(pid: i32, name: &str) -> bool {
+    PROC_FILTER.read().unwrap().as_ref().is_some_and(|f| !f.matches(pid, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_filter() {
+        let filter = ProcFilter::parse("pid:10,20 fire.*x name:Xorg").unwrap();
+
+        assert_eq!(filter.pids, [10, 20]);
+        assert_eq!(filter.to_string(), "pid:10,20 fire.*x Xorg");
+        assert!(filter.matches(10, "firefox"));
+        assert!(filter.matches(20, "Xorg"));
+        assert!(!filter.matches(30, "firefox"));
+        assert!(!filter.matches(10, "gnome-shell"));
+        assert!(ProcFilter::parse("foo:1").is_err());
+        assert!(ProcFilter::parse("").unwrap().is_empty());
+    }
+}
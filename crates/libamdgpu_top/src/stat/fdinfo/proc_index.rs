@@ -0,0 +1,285 @@
+// Incremental process index.
+// A full scan reads the links of every fd of every process, which is expensive on hosts with
+// many processes and misses short-lived processes between the scans.
+// `ProcIndexer` caches the fd links, and only checks new processes at a short interval,
+// using the netlink proc connector if permitted, or the diff of the PID list of procfs.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::{root, DevicePath};
+use super::proc_connector::{ProcConnector, ProcEvent};
+use super::{ProcInfo, is_fdinfo_target, list_pids, read_comm};
+
+/// Interval to check new processes and publish the index
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+/// A new process opens the device some time after it started,
+/// so the fds of new processes are checked every `WATCH_INTERVAL` for this period.
+const WATCH_PERIOD: Duration = Duration::from_secs(30);
+/// A closed fd can be reused for the device, but the cached link is only read again in a full rescan.
+const FULL_RESCAN_PERIOD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rescan {
+    /// Read the links of new fds
+    New,
+    /// Read the links of new fds and fds opening the devices
+    Device,
+    /// Read the links of all fds
+    All,
+}
+
+#[derive(Debug)]
+struct ProcEntry {
+    /// fd -> index of the device opened by the fd
+    fds: HashMap<i32, Option<usize>>,
+    /// (fdinfo target, comm), read when the process opens the device
+    info: Option<(bool, String)>,
+    watch_until: Instant,
+}
+
+impl ProcEntry {
+    fn new(watch_until: Instant) -> Self {
+        Self { fds: HashMap::new(), info: None, watch_until }
+    }
+
+    fn has_device_fds(&self) -> bool {
+        self.fds.values().any(|dev| dev.is_some())
+    }
+}
+
+pub struct ProcIndexer {
+    device_paths: Vec<DevicePath>,
+    procs: HashMap<i32, ProcEntry>,
+    /// published index for each device
+    index: Vec<Vec<ProcInfo>>,
+    buf_path: PathBuf,
+}
+
+impl ProcIndexer {
+    pub fn new(device_paths: Vec<DevicePath>) -> Self {
+        let index = device_paths
+            .iter()
+            .map(|device_path| device_path.arc_proc_index.lock().map(|v| v.clone()).unwrap_or_default())
+            .collect();
+
+        Self {
+            device_paths,
+            procs: HashMap::new(),
+            index,
+            buf_path: PathBuf::with_capacity(32),
+        }
+    }
+
+    /// Check the fds of the process, returns `false` if the process has exited.
+    fn scan_fds(&mut self, pid: i32, rescan: Rescan) -> bool {
+        let Some(entry) = self.procs.get_mut(&pid) else { return false };
+
+        self.buf_path.clear();
+        self.buf_path.push(&root::get().procfs);
+        self.buf_path.push(pid.to_string());
+        self.buf_path.push("fd");
+
+        let Ok(fd_list) = fs::read_dir(&self.buf_path) else { return false };
+        let mut fds = HashMap::with_capacity(entry.fds.len());
+
+        for dir_entry in fd_list {
+            let Ok(dir_entry) = dir_entry else { continue };
+            let Some(fd) = dir_entry.file_name().to_str().and_then(|s| s.parse::<i32>().ok())
+                else { continue };
+            let cached = entry.fds.get(&fd).copied();
+            let dev = match (cached, rescan) {
+                (Some(dev), Rescan::New) | (Some(dev @ None), Rescan::Device) => dev,
+                _ => {
+                    let Ok(link) = fs::read_link(dir_entry.path()) else { continue };
+
+                    // e.g. "/dev/dri/renderD128" or "/dev/dri/card0"
+                    self.device_paths.iter().position(|device_path| {
+                        link.starts_with(&device_path.render) || link.starts_with(&device_path.card)
+                    })
+                },
+            };
+
+            fds.insert(fd, dev);
+        }
+
+        entry.fds = fds;
+
+        true
+    }
+
+    fn add_proc(&mut self, pid: i32, watch_until: Instant) {
+        self.procs.insert(pid, ProcEntry::new(watch_until));
+
+        if !self.scan_fds(pid, Rescan::All) {
+            self.procs.remove(&pid);
+        }
+    }
+
+    /// Add new processes and remove exited processes.
+    fn diff_pids(&mut self, now: Instant, watch: bool) {
+        let pids: HashSet<i32> = list_pids().into_iter().collect();
+        let watch_until = if watch { now + WATCH_PERIOD } else { now };
+
+        self.procs.retain(|pid, _| pids.contains(pid));
+
+        for pid in pids {
+            if !self.procs.contains_key(&pid) {
+                self.add_proc(pid, watch_until);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: ProcEvent, now: Instant) {
+        match event {
+            // exec closes fds with O_CLOEXEC and changes comm
+            ProcEvent::Fork(pid) | ProcEvent::Exec(pid) => self.add_proc(pid, now + WATCH_PERIOD),
+            ProcEvent::Exit(pid) => { self.procs.remove(&pid); },
+        }
+    }
+
+    fn rescan_watched(&mut self, now: Instant) {
+        let pids: Vec<i32> = self.procs
+            .iter()
+            .filter_map(|(pid, entry)| (now < entry.watch_until).then_some(*pid))
+            .collect();
+
+        for pid in pids {
+            if !self.scan_fds(pid, Rescan::New) {
+                self.procs.remove(&pid);
+            }
+        }
+    }
+
+    fn rescan_all(&mut self, rescan: Rescan) {
+        let pids: Vec<i32> = self.procs.keys().copied().collect();
+
+        for pid in pids {
+            if !self.scan_fds(pid, rescan) {
+                self.procs.remove(&pid);
+                continue;
+            }
+
+            // comm can be changed
+            if let Some(entry) = self.procs.get_mut(&pid) {
+                entry.info = None;
+            }
+        }
+    }
+
+    /// Build the index for each device, and update `DevicePath::arc_proc_index` if changed.
+    fn publish(&mut self) {
+        let mut index = vec![Vec::new(); self.device_paths.len()];
+
+        for (pid, entry) in self.procs.iter_mut() {
+            if !entry.has_device_fds() { continue }
+
+            if entry.info.is_none() {
+                let target = is_fdinfo_target(*pid);
+                let Some(name) = read_comm(*pid) else { continue };
+
+                entry.info = Some((target, name));
+            }
+
+            let Some((true, name)) = &entry.info else { continue };
+
+            for (i, vec_info) in index.iter_mut().enumerate() {
+                let mut fds: Vec<i32> = entry.fds
+                    .iter()
+                    .filter_map(|(fd, dev)| (*dev == Some(i)).then_some(*fd))
+                    .collect();
+
+                if fds.is_empty() { continue }
+
+                fds.sort_unstable();
+                vec_info.push(ProcInfo { pid: *pid, name: name.clone(), fds });
+            }
+        }
+
+        for ((vec_info, pre), device_path) in index.iter_mut().zip(&self.index).zip(&self.device_paths) {
+            vec_info.sort_unstable_by_key(|info| info.pid);
+
+            if *vec_info == *pre { continue }
+
+            if let Ok(mut lock) = device_path.arc_proc_index.lock() {
+                lock.clone_from(vec_info);
+            }
+        }
+
+        self.index = index;
+    }
+
+    /// Keep the index up to date.
+    /// All processes are rechecked every `interval`, new processes every `WATCH_INTERVAL`.
+    pub fn run(mut self, interval: Duration) {
+        // the PIDs of the events do not match a procfs other than the running system
+        let mut connector = if root::get().procfs == Path::new("/proc")
+            && ProcConnector::is_available()
+        {
+            ProcConnector::new().ok()
+        } else {
+            None
+        };
+        let mut events = Vec::new();
+
+        self.diff_pids(Instant::now(), false);
+        self.publish();
+
+        let mut last_recheck = Instant::now();
+        let mut last_full_rescan = last_recheck;
+
+        loop {
+            let start = Instant::now();
+            let mut lost_events = connector.is_none();
+            let mut closed = false;
+
+            if let Some(conn) = &connector {
+                while start.elapsed() < WATCH_INTERVAL {
+                    let Err(err) = conn.recv(&mut events) else { continue };
+
+                    lost_events = true;
+
+                    if err.raw_os_error() != Some(libc::ENOBUFS) {
+                        closed = true;
+                        break;
+                    }
+                }
+            } else {
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+
+            if closed {
+                connector = None;
+            }
+
+            let now = Instant::now();
+
+            for event in events.drain(..) {
+                self.handle_event(event, now);
+            }
+
+            let rescan = if FULL_RESCAN_PERIOD.max(interval) <= now.duration_since(last_full_rescan) {
+                last_full_rescan = now;
+                last_recheck = now;
+                Some(Rescan::All)
+            } else if interval <= now.duration_since(last_recheck) {
+                last_recheck = now;
+                Some(Rescan::Device)
+            } else {
+                None
+            };
+
+            if lost_events || rescan.is_some() {
+                self.diff_pids(now, true);
+            }
+
+            if let Some(rescan) = rescan {
+                self.rescan_all(rescan);
+            }
+
+            self.rescan_watched(now);
+            self.publish();
+        }
+    }
+}
@@ -2,9 +2,10 @@ use std::io::Read;
 use std::fs;
 use std::time::Duration;
 use std::path::{Path, PathBuf};
-use crate::DevicePath;
+use crate::{root, DevicePath};
+use super::ProcIndexer;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ProcInfo {
     pub pid: i32,
     pub name: String,
@@ -32,36 +33,71 @@ fn get_fds<T: AsRef<Path>>(fd_dir_path: &mut PathBuf, device_path: &[T]) -> Vec<
     }).collect()
 }
 
-pub fn get_process_list() -> Vec<i32> {
+/// PIDs in procfs, without any filter
+pub(crate) fn list_pids() -> Vec<i32> {
+    let Ok(proc_dir) = fs::read_dir(root::get().procfs.as_path()) else { return Vec::new() };
+
+    proc_dir.filter_map(|dir_entry| {
+        let dir_entry = dir_entry.ok()?;
+
+        if !dir_entry.file_type().ok()?.is_dir() { return None }
+
+        dir_entry.file_name().to_str()?.parse::<i32>().ok()
+    }).collect()
+}
+
+pub(crate) fn is_fdinfo_target(pid: i32) -> bool {
     const SYSTEMD_CMDLINE: &[&[u8]] = &[ b"/lib/systemd", b"/usr/lib/systemd" ];
 
-    let Ok(proc_dir) = fs::read_dir("/proc") else { return Vec::new() };
+    if pid == 1 { return false } // init process, systemd
+
+    // filter systemd processes from fdinfo target
+    // gnome-shell share the AMDGPU driver context with systemd processes
     let mut buf_cmdline = [0u8; 16];
+    let Ok(mut f) = fs::File::open(root::proc_path(format!("{pid}/cmdline"))) else { return false };
 
-    proc_dir.filter_map(|dir_entry| {
-        let dir_entry = dir_entry.ok()?;
-        let metadata = dir_entry.metadata().ok()?;
+    // cmdline can be shorter than the buffer
+    if f.read(&mut buf_cmdline).is_err() { return false }
 
-        if !metadata.is_dir() { return None }
+    !SYSTEMD_CMDLINE.iter().any(|path| buf_cmdline.starts_with(path))
+}
 
-        let pid = dir_entry.file_name().to_str()?.parse::<i32>().ok()?;
+/// Maximum 16 characters
+/// https://www.kernel.org/doc/html/latest/filesystems/proc.html#proc-pid-comm-proc-pid-task-tid-comm
+pub(crate) fn read_comm(pid: i32) -> Option<String> {
+    let mut name = fs::read_to_string(root::proc_path(format!("{pid}/comm"))).ok()?;
+    name.pop(); // trim '\n'
 
-        if pid == 1 { return None } // init process, systemd
+    Some(name)
+}
 
-        // filter systemd processes from fdinfo target
-        // gnome-shell share the AMDGPU driver context with systemd processes
-        {
-            buf_cmdline = Default::default();
-            let mut f = fs::File::open(format!("/proc/{pid}/cmdline")).ok()?;
-            f.read_exact(&mut buf_cmdline).ok()?;
+/// Parse the parent PID from `/proc/<pid>/stat`, the name can contain spaces and parentheses.
+fn parse_ppid(stat: &str) -> Option<i32> {
+    let (_, rest) = stat.rsplit_once(')')?;
 
-            if SYSTEMD_CMDLINE.iter().any(|path| buf_cmdline.starts_with(path)) {
-                return None;
-            }
-        }
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
 
-        Some(pid)
-    }).collect()
+/// `pid` and its descendants
+pub fn list_descendants(pid: i32) -> Vec<i32> {
+    let parents: Vec<(i32, i32)> = list_pids().into_iter().filter_map(|p| {
+        let s = fs::read_to_string(root::proc_path(format!("{p}/stat"))).ok()?;
+
+        Some((p, parse_ppid(&s)?))
+    }).collect();
+    let mut pids = vec![pid];
+    let mut i = 0;
+
+    while let Some(&parent) = pids.get(i) {
+        pids.extend(parents.iter().filter(|(_, ppid)| *ppid == parent).map(|(p, _)| *p));
+        i += 1;
+    }
+
+    pids
+}
+
+pub fn get_process_list() -> Vec<i32> {
+    list_pids().into_iter().filter(|pid| is_fdinfo_target(*pid)).collect()
 }
 
 pub fn update_index_by_all_proc<T: AsRef<Path>>(
@@ -80,7 +116,7 @@ pub fn update_index_by_all_proc<T: AsRef<Path>>(
 
         let pid = *p;
 
-        buf_path.push("/proc");
+        buf_path.push(&root::get().procfs);
         buf_path.push(pid.to_string());
 
         let fds = get_fds(&mut buf_path.join("fd/"), device_path);
@@ -108,42 +144,37 @@ pub fn update_index(vec_info: &mut Vec<ProcInfo>, device_path: &DevicePath) {
     );
 }
 
+/// Spawn a thread to keep the process index of `device_paths` up to date.
+/// New processes are indexed immediately, and all processes are rechecked every `interval` seconds.
+/// See [`ProcIndexer`].
 pub fn spawn_update_index_thread(
     device_paths: Vec<DevicePath>,
     interval: u64,
 ) {
-    let mut buf_index: Vec<ProcInfo> = Vec::new();
     let interval = Duration::from_secs(interval);
 
-    std::thread::spawn(move || loop {
-        let all_proc = get_process_list();
+    std::thread::spawn(move || ProcIndexer::new(device_paths).run(interval));
+}
 
-        for device_path in &device_paths {
-            update_index_by_all_proc(
-                &mut buf_index,
-                &[&device_path.render, &device_path.card],
-                &all_proc,
-            );
+// Calculate usage (%) from previous and current usage (ns)
+pub fn diff_usage(pre_usage_ns: i64, cur_usage_ns: i64, interval: &Duration) -> f64 {
+    if pre_usage_ns == 0 || cur_usage_ns < pre_usage_ns || interval.is_zero() {
+        return 0.0;
+    }
 
-            let lock = device_path.arc_proc_index.lock();
-            if let Ok(mut index) = lock {
-                index.clone_from(&buf_index);
-            }
-        }
+    let diff_ns = cur_usage_ns.saturating_sub(pre_usage_ns) as f64;
 
-        std::thread::sleep(interval);
-    });
+    diff_ns * 100.0 / interval.as_nanos() as f64
 }
 
-// Calculate usage (%) from previous and current usage (ns)
-pub fn diff_usage(pre_usage_ns: i64, cur_usage_ns: i64, interval: &Duration) -> i64 {
-    let diff_ns = if pre_usage_ns == 0 || cur_usage_ns < pre_usage_ns {
-        return 0;
-    } else {
-        cur_usage_ns.saturating_sub(pre_usage_ns) as u128
-    };
-
-    (diff_ns * 100)
-        .checked_div(interval.as_nanos())
-        .unwrap_or(0) as i64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ppid() {
+        assert_eq!(parse_ppid("1234 (cat) R 1000 1234 1000 34816 1234 4194304"), Some(1000));
+        assert_eq!(parse_ppid("42 (a (b) c) S 7 42 42 0 -1"), Some(7));
+        assert_eq!(parse_ppid("42 (a"), None);
+    }
 }
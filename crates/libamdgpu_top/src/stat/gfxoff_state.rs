@@ -1,32 +1,73 @@
 // ref: https://www.kernel.org/doc/html/latest/gpu/amdgpu/thermal.html#gfxoff
 
 // AMD APU/GPU exits GFXOFF state by reading the performance counter (GRBM, GRBM2),  
-// so useful only in SMI mode.
+// so the state is only meaningful with `--no-pc`, in SMI mode or while the APU is idle.
+// `blocked_by_pc` marks the updates in which the registers were read.
 
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::fs;
-use crate::PCI;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::{root, PCI};
 
 const BASE: &str = "/sys/kernel/debug/dri";
 
-#[derive(Debug, Clone)]
+/// The devices whose residency logging is started, to stop it on exit.
+/// The monitors of TUI/SMI/GUI live in the update threads, which are not joined.
+static RESIDENCY_LOGGING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Stop the residency logging started by the monitors, on exit or SIGINT/SIGTERM.
+pub fn stop_gfxoff_residency_logging() {
+    for path in RESIDENCY_LOGGING.lock().unwrap().drain(..) {
+        let _ = write_gfxoff(path.join("amdgpu_gfxoff_residency"), 0);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GfxoffMonitor {
-    debug_dri_path: PathBuf,
+    pub(crate) debug_dri_path: PathBuf,
     pub mode: GfxoffMode,
     pub status: GfxoffStatus,
+    /// `amdgpu_gfxoff_residency` (%) in the last sampling period, if the SMU supports it
+    pub residency: Option<f64>,
+    /// `amdgpu_gfxoff_count`, the number of the entries into GFXOFF since the boot
+    pub count: Option<u64>,
+    /// The number of the entries into GFXOFF since the start
+    pub transitions: u64,
+    /// The number of the updates in GFXOFF, and of all the updates
+    pub samples: (u64, u64),
+    /// The performance counters (GRBM, GRBM2) were read since the last update,
+    /// which keeps the GPU out of GFXOFF.
+    pub blocked_by_pc: bool,
+    #[serde(skip)]
+    pub(crate) residency_logging: bool,
 }
 
 impl GfxoffMonitor {
     pub fn new(pci_bus: PCI::BUS_INFO) -> io::Result<Self> {
-        let debug_dri_path = pci_bus.get_debug_dri_path()?;
+        let debug_dri_path = find_debug_dri_path(pci_bus)?;
         let mode = GfxoffMode::get_with_debug_dri_path(&debug_dri_path)?;
         let status = GfxoffStatus::get_with_debug_dri_path(&debug_dri_path)?;
+        let count = read_gfxoff_count(&debug_dri_path).ok();
 
-        Ok(Self { debug_dri_path, mode, status })
+        Ok(Self {
+            debug_dri_path,
+            mode,
+            status,
+            residency: None,
+            count,
+            transitions: 0,
+            samples: (0, 0),
+            blocked_by_pc: false,
+            residency_logging: false,
+        })
     }
 
     pub fn update(&mut self) -> io::Result<()> {
+        let pre_status = self.status;
+
         self.mode = GfxoffMode::get_with_debug_dri_path(&self.debug_dri_path)?;
         self.status = if self.mode.is_disabled() {
             GfxoffStatus::NotInGFXOFF
@@ -34,11 +75,135 @@ impl GfxoffMonitor {
             GfxoffStatus::get_with_debug_dri_path(&self.debug_dri_path)?
         };
 
+        // Count the entries observed by the updates if `amdgpu_gfxoff_count` is not supported.
+        match read_gfxoff_count(&self.debug_dri_path).ok() {
+            Some(count) => {
+                self.transitions += count.saturating_sub(self.count.unwrap_or(count));
+                self.count = Some(count);
+            },
+            None => if self.status == GfxoffStatus::InGFXOFF && pre_status != GfxoffStatus::InGFXOFF {
+                self.transitions += 1;
+            },
+        }
+
+        self.samples.1 += 1;
+
+        if self.status == GfxoffStatus::InGFXOFF {
+            self.samples.0 += 1;
+        }
+
+        if self.residency_logging {
+            self.sample_residency();
+        }
+
+        Ok(())
+    }
+
+    /// Start logging `amdgpu_gfxoff_residency`, it is sampled at every update.
+    /// The logging is stopped by [`Self::stop_residency_logging`], on drop,
+    /// or by [`stop_gfxoff_residency_logging`].
+    pub fn start_residency_logging(&mut self) -> io::Result<()> {
+        write_gfxoff(self.debug_dri_path.join("amdgpu_gfxoff_residency"), 1)?;
+        self.residency_logging = true;
+
+        let mut list = RESIDENCY_LOGGING.lock().unwrap();
+
+        if !list.contains(&self.debug_dri_path) {
+            list.push(self.debug_dri_path.clone());
+        }
+
         Ok(())
     }
+
+    pub fn stop_residency_logging(&mut self) -> io::Result<()> {
+        self.residency_logging = false;
+        self.residency = None;
+        RESIDENCY_LOGGING.lock().unwrap().retain(|path| *path != self.debug_dri_path);
+
+        write_gfxoff(self.debug_dri_path.join("amdgpu_gfxoff_residency"), 0)
+    }
+
+    /// The SMU reports the residency of a logging period when it is stopped,
+    /// so stop the logging, read the residency, and start the next period.
+    /// The lock is held to not restart the logging stopped on exit by another thread.
+    fn sample_residency(&mut self) {
+        let list = RESIDENCY_LOGGING.lock().unwrap();
+
+        if !list.contains(&self.debug_dri_path) {
+            self.residency_logging = false;
+            self.residency = None;
+            return;
+        }
+
+        let path = self.debug_dri_path.join("amdgpu_gfxoff_residency");
+
+        self.residency = write_gfxoff(&path, 0)
+            .and_then(|_| read_gfxoff(&path))
+            .map(|v| v as f64 / 100.0)
+            .ok();
+
+        if write_gfxoff(&path, 1).is_err() {
+            self.residency = None;
+        }
+    }
+
+    pub fn is_in_gfxoff(&self) -> bool {
+        self.status == GfxoffStatus::InGFXOFF
+    }
+
+    /// `amdgpu_gfxoff_residency` in the last sampling period,
+    /// or the ratio of the updates in GFXOFF since the start.
+    pub fn residency_percent(&self) -> Option<f64> {
+        if self.residency.is_some() { return self.residency }
+
+        let (in_gfxoff, all) = self.samples;
+
+        (all != 0).then(|| in_gfxoff as f64 * 100.0 / all as f64)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// A copy (e.g. the stat sent to the GUI) does not own the logging,
+// dropping it must not stop the logging of the monitor.
+impl Clone for GfxoffMonitor {
+    fn clone(&self) -> Self {
+        Self {
+            debug_dri_path: self.debug_dri_path.clone(),
+            mode: self.mode,
+            status: self.status,
+            residency: self.residency,
+            count: self.count,
+            transitions: self.transitions,
+            samples: self.samples,
+            blocked_by_pc: self.blocked_by_pc,
+            residency_logging: false,
+        }
+    }
+}
+
+impl Drop for GfxoffMonitor {
+    fn drop(&mut self) {
+        if self.residency_logging {
+            let _ = self.stop_residency_logging();
+        }
+    }
+}
+
+/// Find `/sys/kernel/debug/dri/<instance>` whose `name` is "amdgpu dev=<pci_bus> ...".
+fn find_debug_dri_path(pci_bus: PCI::BUS_INFO) -> io::Result<PathBuf> {
+    let dev = format!("dev={pci_bus}");
+
+    fs::read_dir(root::sys_path(BASE))?
+        .filter_map(|dir_entry| dir_entry.ok().map(|d| d.path()))
+        .find(|path| {
+            let Ok(s) = fs::read_to_string(path.join("name")) else { return false };
+
+            s.split_whitespace().any(|v| v == dev)
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("debugfs for {pci_bus} not found")))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
 #[repr(u32)]
 pub enum GfxoffMode {
     Disable,
@@ -48,7 +213,7 @@ pub enum GfxoffMode {
 
 impl GfxoffMode {
     pub fn get(instance: u32) -> io::Result<Self> {
-        Self::get_with_debug_dri_path(format!("{BASE}/{instance}/"))
+        Self::get_with_debug_dri_path(root::sys_path(BASE).join(instance.to_string()))
     }
 
     pub fn get_with_debug_dri_path<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
@@ -66,6 +231,16 @@ impl GfxoffMode {
     }
 }
 
+impl From<GfxoffMode> for u32 {
+    fn from(mode: GfxoffMode) -> Self {
+        match mode {
+            GfxoffMode::Disable => 0,
+            GfxoffMode::Enable => 1,
+            GfxoffMode::Unknown(val) => val,
+        }
+    }
+}
+
 impl From<u32> for GfxoffMode {
     fn from(val: u32) -> Self {
         match val {
@@ -76,7 +251,8 @@ impl From<u32> for GfxoffMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
 #[repr(u32)]
 pub enum GfxoffStatus {
     InGFXOFF = 0, // GPU is in GFXOFF state, the gfx engine is powered down.
@@ -88,7 +264,7 @@ pub enum GfxoffStatus {
 
 impl GfxoffStatus {
     pub fn get(instance: u32) -> io::Result<Self> {
-        Self::get_with_debug_dri_path(format!("{BASE}/{instance}/"))
+        Self::get_with_debug_dri_path(root::sys_path(BASE).join(instance.to_string()))
     }
 
     pub fn get_with_debug_dri_path<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
@@ -98,6 +274,30 @@ impl GfxoffStatus {
     }
 }
 
+impl fmt::Display for GfxoffStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InGFXOFF => write!(f, "In GFXOFF"),
+            Self::OutGFXOFF => write!(f, "Out of GFXOFF"),
+            Self::NotInGFXOFF => write!(f, "Not in GFXOFF"),
+            Self::IntoGFXOFF => write!(f, "Into GFXOFF"),
+            Self::Unknown(val) => write!(f, "Unknown ({val})"),
+        }
+    }
+}
+
+impl From<GfxoffStatus> for u32 {
+    fn from(status: GfxoffStatus) -> Self {
+        match status {
+            GfxoffStatus::InGFXOFF => 0,
+            GfxoffStatus::OutGFXOFF => 1,
+            GfxoffStatus::NotInGFXOFF => 2,
+            GfxoffStatus::IntoGFXOFF => 3,
+            GfxoffStatus::Unknown(val) => val,
+        }
+    }
+}
+
 impl From<u32> for GfxoffStatus {
     fn from(val: u32) -> Self {
         match val {
@@ -118,3 +318,17 @@ fn read_gfxoff<P: Into<PathBuf>>(path: P) -> io::Result<u32> {
 
     Ok(u32::from_le_bytes(buf))
 }
+
+fn read_gfxoff_count(debug_dri_path: &Path) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+
+    let mut f = fs::File::open(debug_dri_path.join("amdgpu_gfxoff_count"))?;
+    f.read_exact(&mut buf)?;
+
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// The debugfs files of GFXOFF take a u32 value.
+fn write_gfxoff<P: Into<PathBuf>>(path: P, val: u32) -> io::Result<()> {
+    fs::OpenOptions::new().write(true).open(path.into())?.write_all(&val.to_le_bytes())
+}
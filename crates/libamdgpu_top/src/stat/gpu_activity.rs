@@ -1,7 +1,8 @@
 use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 use crate::AMDGPU::{ASIC_NAME, GpuMetrics, MetricsInfo};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct GpuActivity {
     pub gfx: Option<u16>, // %
     pub umc: Option<u16>, // %
@@ -1,10 +1,11 @@
 use std::fmt;
 use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 
 const POWER1_AVG: &str = "power1_average";
 const POWER1_INPUT: &str = "power1_input";
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
 pub enum PowerType {
     Input,
     Average,
@@ -25,7 +26,7 @@ impl fmt::Display for PowerType {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HwmonPower {
     pub type_: PowerType,
     pub value: u32, // W
@@ -99,15 +99,38 @@ pub use sensors::*;
 mod hwmon_power;
 pub(crate) use hwmon_power::*;
 
+mod fan;
+pub use fan::*;
+
 mod pcie_bw;
 pub use pcie_bw::*;
 
+mod pcie_link;
+
+mod pcie_link_monitor;
+pub use pcie_link_monitor::*;
+
+mod ras;
+pub use ras::*;
+
 mod gfxoff_state;
 pub use gfxoff_state::*;
 
 mod gpu_activity;
 pub use gpu_activity::*;
 
+mod energy;
+pub use energy::*;
+
+mod throttling;
+pub use throttling::*;
+
+mod dpm;
+pub use dpm::*;
+
+mod devcoredump;
+pub use devcoredump::*;
+
 pub mod gpu_metrics_util;
 
 pub(crate) fn parse_hwmon<T: std::str::FromStr, P: Into<std::path::PathBuf>>(path: P) -> Option<T> {
@@ -1,8 +1,9 @@
-use crate::AMDGPU::{drm_amdgpu_info_device, GPU_INFO, ASIC_NAME};
+use crate::AMDGPU::{drm_amdgpu_info_device, GPU_INFO, ASIC_NAME, GpuMetrics, MetricsInfo};
 use std::fs;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 
 // PCIe bandwidth (throughput) available from `pcie_bw` sysfs
 // `pcie_bw` is supported on dGPUs only
@@ -16,13 +17,34 @@ use std::path::PathBuf;
 //    * `drivers/gpu/drm/amd/amdgpu_pm.c`
 //    * `drivers/gpu/drm/amd/amdgpu/{cik,si,vi,soc15}.c`
 //      * `{cik,si,vi,soc15}_get_pcie_usage`
+//
+// For APUs and RDNA GPUs, `pcie_bandwidth_inst`/`pcie_bandwidth_acc` in gpu_metrics
+// (v1.4 and later) are used instead, if the SMU reports them.
+// They are the total of both directions.
+//  * `drivers/gpu/drm/amd/pm/swsmu/smu13/smu_v13_0_6_ppt.c`
 
-#[derive(Clone, Debug)]
+const GB_TO_MIB: u64 = 1_000_000_000 >> 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PcieBwSource {
+    /// `pcie_bw` sysfs
+    #[default]
+    Sysfs,
+    /// `pcie_bandwidth_{inst,acc}` in gpu_metrics
+    GpuMetrics,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PcieBw {
-    path: PathBuf,
+    pub(crate) path: PathBuf,
     pub sent: Option<u64>,
     pub received: Option<u64>,
     pub max_payload_size: Option<i32>,
+    /// Total bandwidth (MiB/s) from gpu_metrics, the direction is not reported
+    pub total: Option<u64>,
+    pub source: PcieBwSource,
+    #[serde(skip)]
+    pub(crate) pre_acc: Option<(u64, Instant)>,
 }
 
 impl PcieBw {
@@ -34,6 +56,34 @@ impl PcieBw {
             sent: None,
             received: None,
             max_payload_size: None,
+            total: None,
+            source: PcieBwSource::Sysfs,
+            pre_acc: None,
+        }
+    }
+
+    pub fn new_with_gpu_metrics<P: Into<PathBuf>>(sysfs_path: P) -> Self {
+        Self {
+            source: PcieBwSource::GpuMetrics,
+            ..Self::new(sysfs_path)
+        }
+    }
+
+    /// Sent (MiB/s), `pcie_bw` only
+    pub fn sent_mib(&self) -> Option<u64> {
+        Some((self.sent? * self.max_payload_size? as u64) >> 20)
+    }
+
+    /// Received (MiB/s), `pcie_bw` only
+    pub fn received_mib(&self) -> Option<u64> {
+        Some((self.received? * self.max_payload_size? as u64) >> 20)
+    }
+
+    /// Sent + Received (MiB/s)
+    pub fn total_mib(&self) -> Option<u64> {
+        match self.source {
+            PcieBwSource::Sysfs => Some(self.sent_mib()? + self.received_mib()?),
+            PcieBwSource::GpuMetrics => self.total,
         }
     }
 
@@ -71,6 +121,29 @@ impl PcieBw {
         arc_pcie_bw
     }
 
+    /// `pcie_bandwidth_inst` is preferred, otherwise the delta of `pcie_bandwidth_acc` (GB) is used.
+    pub fn update_with_gpu_metrics(&mut self, metrics: &GpuMetrics) {
+        let now = Instant::now();
+        let acc = metrics.get_pcie_bandwidth_acc().filter(|v| *v != u64::MAX);
+        let pre_acc = std::mem::replace(&mut self.pre_acc, acc.map(|acc| (acc, now)));
+
+        self.total = if let Some(inst) = get_bandwidth_inst(metrics) {
+            Some(inst * GB_TO_MIB)
+        } else if let (Some((pre, pre_time)), Some(acc)) = (pre_acc, acc) {
+            let secs = now.saturating_duration_since(pre_time).as_secs_f64();
+
+            (pre <= acc && secs > 0.0)
+                .then(|| ((acc - pre) as f64 * GB_TO_MIB as f64 / secs) as u64)
+        } else {
+            None
+        };
+    }
+
+    pub fn check_gpu_metrics_support(metrics: &GpuMetrics) -> bool {
+        get_bandwidth_inst(metrics).is_some()
+        || metrics.get_pcie_bandwidth_acc().is_some_and(|v| v != u64::MAX)
+    }
+
     pub fn check_pcie_bw_support(&self, ext_info: &drm_amdgpu_info_device) -> bool {
         // APU and RDNA GPU dose not support `pcie_bw`.
         // ref: https://lists.freedesktop.org/archives/amd-gfx/2020-May/049649.html
@@ -79,3 +152,7 @@ impl PcieBw {
         && ext_info.get_asic_name() < ASIC_NAME::CHIP_NAVI10
     }
 }
+
+fn get_bandwidth_inst(metrics: &GpuMetrics) -> Option<u64> {
+    metrics.get_pcie_bandwidth_inst().filter(|v| *v != u64::MAX)
+}
@@ -0,0 +1,124 @@
+// PCIe link info from sysfs.
+// These work on the paths of the configured sysfs root, unlike the methods of `PCI::BUS_INFO`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::PCI;
+use crate::AMDGPU::{GpuMetrics, MetricsInfo};
+use crate::device_path::parse_sysfs_hex;
+
+const VENDOR_ATI: u32 = 0x1002;
+
+fn speed_to_gen(speed: &str) -> Option<u8> {
+    let speed: f32 = speed.trim().trim_end_matches("PCIe").trim_end().trim_end_matches("GT/s").trim().parse().ok()?;
+
+    Some(gts_to_gen(speed))
+}
+
+fn gts_to_gen(speed: f32) -> u8 {
+    match speed {
+        s if s < 3.0 => 1,
+        s if s < 6.0 => 2,
+        s if s < 10.0 => 3,
+        s if s < 20.0 => 4,
+        s if s < 40.0 => 5,
+        _ => 6,
+    }
+}
+
+/// Read `{current,max}_link_{speed,width}`.
+fn read_link(sysfs_path: &Path, prefix: &str) -> Option<PCI::LINK> {
+    let speed = fs::read_to_string(sysfs_path.join(format!("{prefix}_link_speed"))).ok()?;
+    let width = fs::read_to_string(sysfs_path.join(format!("{prefix}_link_width"))).ok()?;
+
+    Some(PCI::LINK {
+        gen: speed_to_gen(&speed)?,
+        width: width.trim().parse().ok()?,
+    })
+}
+
+pub(crate) fn current_link(sysfs_path: &Path) -> Option<PCI::LINK> {
+    read_link(sysfs_path, "current")
+}
+
+pub(crate) fn max_link(sysfs_path: &Path) -> Option<PCI::LINK> {
+    read_link(sysfs_path, "max")
+}
+
+/// `pcie_link_{speed,width}` in gpu_metrics.
+/// Some SMUs report the speed in 0.1 GT/s, others report the index of the link speed (0: Gen1).
+pub(crate) fn link_from_gpu_metrics(metrics: &GpuMetrics) -> Option<PCI::LINK> {
+    let speed = metrics.get_pcie_link_speed().filter(|v| *v != u16::MAX)?;
+    let width = metrics.get_pcie_link_width().filter(|v| *v != 0 && *v != u16::MAX)?;
+    let gen = if speed < 10 { speed as u8 + 1 } else { gts_to_gen(speed as f32 / 10.0) };
+
+    Some(PCI::LINK { gen, width: width.try_into().ok()? })
+}
+
+/// Parse a line of `pp_dpm_pcie`, e.g. "1: 8.0GT/s, x16 619Mhz *".
+pub(super) fn parse_dpm_line(line: &str) -> Option<(PCI::LINK, bool)> {
+    let (_, s) = line.split_once(':')?;
+    let (speed, s) = s.split_once(',')?;
+    let width = s.trim_start().strip_prefix('x')?;
+    let width = width.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()?;
+
+    Some((PCI::LINK { gen: speed_to_gen(speed)?, width }, line.trim_end().ends_with('*')))
+}
+
+fn dpm_links(sysfs_path: &Path) -> Option<Vec<(PCI::LINK, bool)>> {
+    let s = fs::read_to_string(sysfs_path.join("pp_dpm_pcie")).ok()?;
+    let links: Vec<_> = s.lines().filter_map(parse_dpm_line).collect();
+
+    (!links.is_empty()).then_some(links)
+}
+
+pub(crate) fn min_max_link_from_dpm(sysfs_path: &Path) -> Option<[PCI::LINK; 2]> {
+    let links = dpm_links(sysfs_path)?;
+
+    Some([links.first()?.0, links.last()?.0])
+}
+
+pub(crate) fn current_link_from_dpm(sysfs_path: &Path) -> Option<PCI::LINK> {
+    dpm_links(sysfs_path)?.into_iter().find_map(|(link, cur)| cur.then_some(link))
+}
+
+fn pci_port_path(path: &Path) -> Option<&Path> {
+    let parent = path.parent()?;
+    let name = parent.file_name()?.to_str()?;
+
+    name.parse::<PCI::BUS_INFO>().is_ok().then_some(parent)
+}
+
+/// Returns the sysfs paths of the PCIe port of the GPU and the upstream port of the system.
+/// Recent AMD dGPUs have PCIe switches (upstream/downstream ports) inside the GPU package,
+/// so the link between the GPU port and the system is the actual link.
+pub(crate) fn gpu_and_system_port_path(sysfs_path: &Path) -> (PathBuf, Option<PathBuf>) {
+    let Ok(path) = fs::canonicalize(sysfs_path) else { return (sysfs_path.to_path_buf(), None) };
+    let mut gpu_port = path.as_path();
+
+    while let Some(port) = pci_port_path(gpu_port) {
+        if parse_sysfs_hex(port.join("vendor")) != Some(VENDOR_ATI) { break }
+
+        gpu_port = port;
+    }
+
+    let system_port = pci_port_path(gpu_port).map(|p| p.to_path_buf());
+
+    (gpu_port.to_path_buf(), system_port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dpm_pcie_line() {
+        let (link, cur) = parse_dpm_line("1: 16.0GT/s, x16 619Mhz *").unwrap();
+
+        assert_eq!((link.gen, link.width, cur), (4, 16, true));
+
+        let (link, cur) = parse_dpm_line("0: 2.5GT/s, x1 ").unwrap();
+
+        assert_eq!((link.gen, link.width, cur), (1, 1, false));
+    }
+}
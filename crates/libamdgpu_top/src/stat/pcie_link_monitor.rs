@@ -0,0 +1,111 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::PCI;
+use super::{GpuActivity, Sensors};
+
+/// GFX activity (%) regarded as under load.
+/// The link speed/width can be lowered by PCIe DPM while the GPU is idle.
+const LOAD_THRESHOLD: u16 = 50;
+const MAX_EVENTS: usize = 64;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PcieDowntrainEvent {
+    pub time: SystemTime,
+    #[serde(with = "crate::record::remote")]
+    pub current: PCI::LINK,
+    #[serde(with = "crate::record::remote")]
+    pub expected: PCI::LINK,
+}
+
+impl PcieDowntrainEvent {
+    pub fn unix_time(&self) -> u64 {
+        self.time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+}
+
+/// Detect the PCIe link trained down below the expected link under load,
+/// e.g. a riser cable that trains down to Gen3x4.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PcieLinkMonitor {
+    /// The lower of the max link of the GPU (or PCIe DPM) and the max link of the system port
+    #[serde(with = "crate::record::remote::option")]
+    pub expected: Option<PCI::LINK>,
+    /// The current link while it is downtrained
+    #[serde(with = "crate::record::remote::option")]
+    pub downtrained: Option<PCI::LINK>,
+    /// Downtraining events, in chronological order
+    pub events: Vec<PcieDowntrainEvent>,
+}
+
+impl PcieLinkMonitor {
+    pub fn update(&mut self, sensors: Option<&Sensors>, activity: &GpuActivity) {
+        let Some(sensors) = sensors else { return };
+        let Some(expected) = expected_link(sensors) else { return };
+        let under_load = activity.gfx.is_some_and(|gfx| gfx >= LOAD_THRESHOLD);
+
+        self.expected = Some(expected);
+
+        // keep the state while the GPU is idle
+        if !under_load { return }
+        let Some(current) = sensors.current_link else { return };
+
+        self.check(current, expected);
+    }
+
+    fn check(&mut self, current: PCI::LINK, expected: PCI::LINK) {
+        let is_downtrained = current.gen < expected.gen || current.width < expected.width;
+
+        if !is_downtrained {
+            self.downtrained = None;
+            return;
+        }
+
+        if self.downtrained.is_some_and(|pre| pre.gen == current.gen && pre.width == current.width) {
+            return;
+        }
+
+        self.downtrained = Some(current);
+        self.events.push(PcieDowntrainEvent { time: SystemTime::now(), current, expected });
+
+        if self.events.len() > MAX_EVENTS {
+            self.events.remove(0);
+        }
+    }
+
+    pub fn is_downtrained(&self) -> bool {
+        self.downtrained.is_some()
+    }
+}
+
+fn expected_link(sensors: &Sensors) -> Option<PCI::LINK> {
+    let gpu = sensors.max_dpm_link.or(sensors.max_gpu_link)?;
+    let Some(system) = sensors.max_system_link else { return Some(gpu) };
+
+    Some(PCI::LINK {
+        gen: gpu.gen.min(system.gen),
+        width: gpu.width.min(system.width),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcie_downtrain() {
+        let mut monitor = PcieLinkMonitor::default();
+        let expected = PCI::LINK { gen: 4, width: 16 };
+        let x4 = PCI::LINK { gen: 3, width: 4 };
+
+        monitor.check(expected, expected);
+        assert!(!monitor.is_downtrained());
+        monitor.check(x4, expected);
+        monitor.check(x4, expected);
+        assert_eq!(monitor.downtrained.map(|l| (l.gen, l.width)), Some((3, 4)));
+        assert_eq!(monitor.events.len(), 1);
+        monitor.check(expected, expected);
+        assert!(!monitor.is_downtrained());
+        monitor.check(PCI::LINK { gen: 4, width: 8 }, expected);
+        assert_eq!(monitor.events.len(), 2);
+    }
+}
@@ -1,22 +1,24 @@
 use libdrm_amdgpu_sys::AMDGPU::{
     CHIP_CLASS,
-    DeviceHandle,
     GRBM_OFFSET,
     GRBM2_OFFSET,
 };
+use crate::backend::DeviceBackend;
 use crate::stat;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PCIndex {
     pub name: String,
-    index: usize,
+    pub(crate) index: usize,
     pub usage: u8, // %
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PerfCounter {
     pub pc_type: PCType,
-    bits: PCAcc,
+    #[serde(skip)]
+    pub(crate) bits: PCAcc,
     pub pc_index: Vec<PCIndex>,
 }
 
@@ -67,7 +69,7 @@ impl PerfCounter {
         }
     }
 
-    pub fn read_reg(&mut self, amdgpu_dev: &DeviceHandle) {
+    pub fn read_reg(&mut self, amdgpu_dev: &dyn DeviceBackend) {
         if let Ok(out) = amdgpu_dev.read_mm_registers(self.pc_type.offset()) {
             self.bits.acc(out);
         }
@@ -84,7 +86,7 @@ impl PerfCounter {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 #[allow(clippy::upper_case_acronyms)]
 pub enum PCType {
@@ -107,7 +109,7 @@ impl PCType {
         }
     }
 
-    pub fn check_reg_offset(&self, amdgpu_dev: &DeviceHandle) -> bool {
+    pub fn check_reg_offset(&self, amdgpu_dev: &dyn DeviceBackend) -> bool {
         let offset = self.offset();
         let reg_name = match self {
             Self::GRBM => "GRBM_STATUS",
@@ -122,7 +124,7 @@ impl PCType {
 }
 
 #[derive(Clone, Default, Debug)]
-struct PCAcc([u8; 32]);
+pub(crate) struct PCAcc([u8; 32]);
 
 impl PCAcc {
     pub fn clear(&mut self) {
@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::AMDGPU::RasErrorCount;
+
+// RAS (Reliability, Availability, Serviceability) error counts of each block,
+// read from `ras/<block>_err_count`, e.g. "ue: 0\nce: 0\n".
+// ref: drivers/gpu/drm/amd/amdgpu/amdgpu_ras.c
+
+const ERR_COUNT_SUFFIX: &str = "_err_count";
+const MAX_EVENTS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RasCount {
+    pub corrected: u64,
+    pub uncorrected: u64,
+}
+
+impl RasCount {
+    pub fn is_zero(&self) -> bool {
+        self.corrected == 0 && self.uncorrected == 0
+    }
+}
+
+impl From<RasCount> for RasErrorCount {
+    fn from(count: RasCount) -> Self {
+        Self { corrected: count.corrected as _, uncorrected: count.uncorrected as _ }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RasBlockStat {
+    /// e.g. "UMC", "SDMA", "XGMI_WAFL"
+    pub name: String,
+    pub count: RasCount,
+    /// The increase at the last update
+    pub delta: RasCount,
+    /// The time when the count increased last
+    pub last_increased: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RasEvent {
+    pub time: SystemTime,
+    pub block: String,
+    pub delta: RasCount,
+}
+
+impl RasEvent {
+    pub fn unix_time(&self) -> u64 {
+        self.time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+}
+
+/// Error counts of all RAS blocks the device exposes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RasStat {
+    pub(crate) path: PathBuf,
+    pub blocks: Vec<RasBlockStat>,
+    /// The increases of the error counts, in chronological order
+    pub events: Vec<RasEvent>,
+}
+
+impl RasStat {
+    /// Returns `None` if the device does not support RAS.
+    pub fn from_sysfs<P: Into<PathBuf>>(sysfs_path: P) -> Option<Self> {
+        let path = sysfs_path.into().join("ras");
+        let mut blocks: Vec<RasBlockStat> = fs::read_dir(&path).ok()?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let block = name.strip_suffix(ERR_COUNT_SUFFIX)?;
+                let count = read_err_count(&path, block)?;
+
+                Some(RasBlockStat {
+                    name: block.to_uppercase(),
+                    count,
+                    delta: RasCount::default(),
+                    last_increased: None,
+                })
+            })
+            .collect();
+
+        if blocks.is_empty() { return None }
+
+        // UMC first, for ECC memory
+        blocks.sort_by(|a, b| (a.name != "UMC", &a.name).cmp(&(b.name != "UMC", &b.name)));
+
+        Some(Self { path, blocks, events: Vec::new() })
+    }
+
+    pub fn update(&mut self) {
+        let now = SystemTime::now();
+
+        for block in self.blocks.iter_mut() {
+            let Some(count) = read_err_count(&self.path, &block.name.to_lowercase()) else { continue };
+            let delta = RasCount {
+                corrected: count.corrected.saturating_sub(block.count.corrected),
+                uncorrected: count.uncorrected.saturating_sub(block.count.uncorrected),
+            };
+
+            if !delta.is_zero() {
+                block.last_increased = Some(now);
+                self.events.push(RasEvent { time: now, block: block.name.clone(), delta });
+            }
+
+            block.count = count;
+            block.delta = delta;
+        }
+
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RasBlockStat> {
+        self.blocks.iter().find(|block| block.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Total of all blocks
+    pub fn total(&self) -> RasCount {
+        self.blocks.iter().fold(RasCount::default(), |acc, block| RasCount {
+            corrected: acc.corrected + block.count.corrected,
+            uncorrected: acc.uncorrected + block.count.uncorrected,
+        })
+    }
+}
+
+fn read_err_count(ras_path: &Path, block: &str) -> Option<RasCount> {
+    let s = fs::read_to_string(ras_path.join(format!("{block}{ERR_COUNT_SUFFIX}"))).ok()?;
+
+    parse_err_count(&s)
+}
+
+fn parse_err_count(s: &str) -> Option<RasCount> {
+    let mut count = RasCount::default();
+    let [mut ue, mut ce] = [false; 2];
+
+    for line in s.lines() {
+        let Some((key, val)) = line.split_once(':') else { continue };
+        let val = val.trim().parse().ok()?;
+
+        match key.trim() {
+            "ue" => {
+                count.uncorrected = val;
+                ue = true;
+            },
+            "ce" => {
+                count.corrected = val;
+                ce = true;
+            },
+            _ => {},
+        }
+    }
+
+    (ue && ce).then_some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_err_count() {
+        let count = parse_err_count("ue: 1\nce: 23\n").unwrap();
+
+        assert_eq!((count.corrected, count.uncorrected), (23, 1));
+        // deferred errors on recent kernels
+        assert!(parse_err_count("ue: 0\nce: 0\nde: 0\n").is_some());
+        assert!(parse_err_count("ue: 0\n").is_none());
+        assert!(parse_err_count("").is_none());
+    }
+}
@@ -5,8 +5,8 @@ use libdrm_amdgpu_sys::{
     AMDGPU::{
         drm_amdgpu_info_device,
         GPU_INFO,
+        GpuMetrics,
         ASIC_NAME,
-        DeviceHandle,
         HwmonTemp,
         HwmonTempType,
         SENSOR_INFO::SENSOR_TYPE,
@@ -14,45 +14,61 @@ use libdrm_amdgpu_sys::{
         PowerProfile,
     },
 };
-use super::{parse_hwmon, HwmonPower, PowerType};
+use super::{parse_hwmon, pcie_link, FanStat, HwmonPower, PowerType};
+use crate::{backend::DeviceBackend, root};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sensors {
     pub hwmon_path: PathBuf,
     pub gpu_port_path: PathBuf,
     pub sysfs_path: PathBuf,
     pub is_apu: bool,
     pub vega10_and_later: bool,
+    #[serde(with = "crate::record::remote::option")]
     pub current_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub min_dpm_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_dpm_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_gpu_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote::option")]
     pub max_system_link: Option<PCI::LINK>,
+    #[serde(with = "crate::record::remote")]
     pub bus_info: PCI::BUS_INFO,
     pub sclk: Option<u32>,
     pub mclk: Option<u32>,
     pub vddnb: Option<u32>,
     pub vddgfx: Option<u32>,
+    #[serde(with = "crate::record::remote::edge_temp")]
     pub edge_temp: Option<HwmonTemp>,
+    #[serde(with = "crate::record::remote::junction_temp")]
     pub junction_temp: Option<HwmonTemp>,
+    #[serde(with = "crate::record::remote::memory_temp")]
     pub memory_temp: Option<HwmonTemp>,
     pub average_power: Option<HwmonPower>,
     pub input_power: Option<HwmonPower>,
+    #[serde(with = "crate::record::remote::option")]
     pub power_cap: Option<PowerCap>,
     pub fan_rpm: Option<u32>,
     pub fan_max_rpm: Option<u32>,
     pub pci_power_state: Option<String>,
+    #[serde(with = "crate::record::remote::option")]
     pub power_profile: Option<PowerProfile>,
+    pub fan: Option<FanStat>,
 }
 
 impl Sensors {
     pub fn new(
-        amdgpu_dev: &DeviceHandle,
+        amdgpu_dev: &dyn DeviceBackend,
         pci_bus: &PCI::BUS_INFO,
         ext_info: &drm_amdgpu_info_device,
     ) -> Option<Self> {
-        let hwmon_path = pci_bus.get_hwmon_path()?;
-        let sysfs_path = pci_bus.get_sysfs_path();
+        let sysfs_path = root::sys_path(format!("bus/pci/devices/{pci_bus}"));
+        let hwmon_path = fs::read_dir(sysfs_path.join("hwmon")).ok()?
+            .find_map(|dir_entry| Some(dir_entry.ok()?.path()))?;
+        let (gpu_port_path, system_port_path) = pcie_link::gpu_and_system_port_path(&sysfs_path);
         let asic_name = ext_info.get_asic_name();
         let is_apu = ext_info.is_apu();
         let vega10_and_later = ASIC_NAME::CHIP_VEGA10 <= asic_name;
@@ -67,28 +83,28 @@ impl Sensors {
         let [current_link, min_dpm_link, max_dpm_link, max_gpu_link, max_system_link] = if is_apu {
             [None; 5]
         } else if vega10_and_later {
-            let [min, max] = match pci_bus.get_min_max_link_info_from_dpm() {
+            let [min, max] = match pcie_link::min_max_link_from_dpm(&sysfs_path) {
                 Some([min, max]) => [Some(min), Some(max)],
                 None => [None, None],
             };
 
             [
-                pci_bus.get_current_link_info_from_dpm(),
+                pcie_link::current_link_from_dpm(&sysfs_path),
                 min,
                 max,
-                pci_bus.get_max_gpu_link(),
-                pci_bus.get_max_system_link(),
+                pcie_link::max_link(&gpu_port_path),
+                system_port_path.as_deref().and_then(pcie_link::max_link),
             ]
         } else {
-            let min = pci_bus.get_min_max_link_info_from_dpm().map(|[min, _]| min);
-            let max = pci_bus.get_max_link_info();
+            let min = pcie_link::min_max_link_from_dpm(&sysfs_path).map(|[min, _]| min);
+            let max = pcie_link::max_link(&sysfs_path);
 
             [
-                pci_bus.get_current_link_info(),
+                pcie_link::current_link(&sysfs_path),
                 min,
                 max,
                 max,
-                pci_bus.get_max_system_link(),
+                system_port_path.as_deref().and_then(pcie_link::max_link),
             ]
         };
 
@@ -107,7 +123,6 @@ impl Sensors {
 
         let fan_rpm = parse_hwmon(hwmon_path.join("fan1_input"));
         let fan_max_rpm = parse_hwmon(hwmon_path.join("fan1_max"));
-        let gpu_port_path = pci_bus.get_gpu_pcie_port_bus().get_sysfs_path();
         let pci_power_state = fs::read_to_string(gpu_port_path.join("power_state"))
             .ok()
             .map(|mut s| {
@@ -115,6 +130,7 @@ impl Sensors {
                 s
             });
         let power_profile = PowerProfile::get_current_profile_from_sysfs(&sysfs_path);
+        let fan = FanStat::from_hwmon_path(&hwmon_path);
 
         Some(Self {
             hwmon_path,
@@ -142,6 +158,7 @@ impl Sensors {
             gpu_port_path,
             pci_power_state,
             power_profile,
+            fan,
         })
     }
 
@@ -149,9 +166,9 @@ impl Sensors {
         self.current_link = if self.is_apu {
             None
         } else if self.vega10_and_later {
-            self.bus_info.get_current_link_info_from_dpm()
+            pcie_link::current_link_from_dpm(&self.sysfs_path)
         } else {
-            self.bus_info.get_current_link_info()
+            pcie_link::current_link(&self.sysfs_path)
         };
 
         for temp in [&mut self.edge_temp, &mut self.junction_temp, &mut self.memory_temp] {
@@ -174,10 +191,19 @@ impl Sensors {
         }
 
         self.fan_rpm = parse_hwmon(self.hwmon_path.join("fan1_input"));
+        self.fan = FanStat::from_hwmon_path(&self.hwmon_path);
+
         self.power_profile = PowerProfile::get_current_profile_from_sysfs(&self.sysfs_path);
         self.update_pci_power_state();
     }
 
+    /// Use `pcie_link_{speed,width}` in gpu_metrics if the current link is not available from sysfs.
+    pub fn update_link_with_gpu_metrics(&mut self, metrics: &GpuMetrics) {
+        if self.is_apu || self.current_link.is_some() { return }
+
+        self.current_link = pcie_link::link_from_gpu_metrics(metrics);
+    }
+
     pub fn update_for_idle(&mut self) {
         self.current_link = None;
         self.edge_temp = None;
@@ -190,6 +216,7 @@ impl Sensors {
         self.vddnb = None;
         self.vddgfx = None;
         self.fan_rpm = None;
+        self.fan = None;
         self.power_profile = None;
 
         self.update_pci_power_state();
@@ -204,7 +231,7 @@ impl Sensors {
             });
     }
 
-    pub fn update(&mut self, amdgpu_dev: &DeviceHandle) {
+    pub fn update(&mut self, amdgpu_dev: &dyn DeviceBackend) {
         self.update_without_device_handle();
         self.sclk = amdgpu_dev.sensor_info(SENSOR_TYPE::GFX_SCLK).ok();
         self.mclk = amdgpu_dev.sensor_info(SENSOR_TYPE::GFX_MCLK).ok();
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::AMDGPU::{GpuMetrics, MetricsInfo};
+
+/// The number of transitions kept for the timeline
+const MAX_EVENTS: usize = 512;
+
+/// Residency of a throttler decoded from `indep_throttle_status` or `throttle_status`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleResidency {
+    pub name: String,
+    pub active: bool,
+    /// Total time while the throttler is active
+    pub residency: Duration,
+    /// The number of times the throttler became active
+    pub transitions: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleEvent {
+    /// Time since the start of the tracking
+    pub time: Duration,
+    pub name: String,
+    pub active: bool,
+}
+
+/// Throttling residency and transitions since the start.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ThrottlingStat {
+    /// Total time tracked, `None` if gpu_metrics does not report the throttle status
+    pub tracked: Option<Duration>,
+    /// Throttlers that have been active at least once
+    pub reasons: Vec<ThrottleResidency>,
+    /// Recent transitions, in chronological order
+    pub events: Vec<ThrottleEvent>,
+    #[serde(skip)]
+    pre_time: Option<Instant>,
+}
+
+impl ThrottlingStat {
+    pub fn update(&mut self, metrics: Option<&GpuMetrics>) {
+        let Some(thr) = metrics.and_then(|m| m.get_throttle_status_info()) else {
+            self.pre_time = None;
+            return;
+        };
+        let now = Instant::now();
+        let elapsed = self.pre_time.replace(now).map(|pre| now.saturating_duration_since(pre));
+        let active: Vec<String> = thr.get_all_throttler().into_iter().map(|t| t.to_string()).collect();
+
+        self.update_with(&active, elapsed.unwrap_or_default());
+    }
+
+    /// The elapsed time is attributed to the throttlers active at this update.
+    fn update_with(&mut self, active: &[String], elapsed: Duration) {
+        let time = self.tracked.unwrap_or_default() + elapsed;
+
+        self.tracked = Some(time);
+
+        for name in active {
+            if !self.reasons.iter().any(|r| &r.name == name) {
+                self.reasons.push(ThrottleResidency { name: name.clone(), ..Default::default() });
+            }
+        }
+
+        for r in self.reasons.iter_mut() {
+            let is_active = active.contains(&r.name);
+
+            if is_active {
+                r.residency += elapsed;
+            }
+
+            if is_active != r.active {
+                r.active = is_active;
+
+                if is_active {
+                    r.transitions += 1;
+                }
+
+                self.events.push(ThrottleEvent { time, name: r.name.clone(), active: is_active });
+            }
+        }
+
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.tracked.is_some()
+    }
+
+    /// Residency (%) of the throttler over the tracked time
+    pub fn residency_percent(&self, r: &ThrottleResidency) -> f64 {
+        let tracked = self.tracked.unwrap_or_default().as_secs_f64();
+
+        if tracked == 0.0 { return 0.0 }
+
+        r.residency.as_secs_f64() * 100.0 / tracked
+    }
+
+    /// Active periods (sec) of each throttler for the timeline, from the recent transitions
+    pub fn active_periods(&self) -> Vec<(String, Vec<[f64; 2]>)> {
+        let end = self.tracked.unwrap_or_default().as_secs_f64();
+
+        self.reasons.iter().map(|r| {
+            let mut periods = Vec::new();
+            let mut start = None;
+
+            for ev in self.events.iter().filter(|ev| ev.name == r.name) {
+                let time = ev.time.as_secs_f64();
+
+                match (ev.active, start) {
+                    (true, _) => start = Some(time),
+                    (false, Some(s)) => {
+                        periods.push([s, time]);
+                        start = None;
+                    },
+                    (false, None) => {},
+                }
+            }
+
+            if let Some(s) = start {
+                periods.push([s, end]);
+            }
+
+            (r.name.clone(), periods)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttling_residency() {
+        let mut thr = ThrottlingStat::default();
+        let sec = Duration::from_secs(1);
+        let ppt = vec!["PPT0".to_string()];
+        let both = vec!["PPT0".to_string(), "TEMP_HOTSPOT".to_string()];
+
+        thr.update_with(&[], Duration::ZERO);
+        thr.update_with(&ppt, sec);
+        thr.update_with(&both, sec);
+        thr.update_with(&[], sec);
+        thr.update_with(&ppt, sec);
+
+        assert_eq!(thr.tracked, Some(Duration::from_secs(4)));
+        assert_eq!(thr.reasons[0].residency, Duration::from_secs(3));
+        assert_eq!(thr.reasons[0].transitions, 2);
+        assert!(thr.reasons[0].active);
+        assert_eq!(thr.reasons[1].residency, sec);
+        assert_eq!(thr.residency_percent(&thr.reasons[1]), 25.0);
+        assert_eq!(thr.events.len(), 5);
+        assert_eq!(
+            thr.active_periods(),
+            [
+                ("PPT0".to_string(), vec![[1.0, 3.0], [4.0, 4.0]]),
+                ("TEMP_HOTSPOT".to_string(), vec![[2.0, 3.0]]),
+            ],
+        );
+    }
+}
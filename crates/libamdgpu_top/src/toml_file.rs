@@ -0,0 +1,52 @@
+//! Helpers to read the TOML files (the config file, the alert rules and the profile rules)
+//! with `toml` and `serde`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::de::{self, Deserialize, Deserializer};
+
+/// Parse the TOML document, and returns the top-level keys and tables in the order of the file.
+/// The rules are evaluated in the order of the file, `toml::Table` keeps it with `preserve_order`.
+pub(crate) fn parse_ordered(s: &str) -> anyhow::Result<Vec<(String, toml::Value)>> {
+    Ok(toml::from_str::<toml::Table>(s)?.into_iter().collect())
+}
+
+/// `deserialize_with` for a string parsed with `FromStr`, e.g. `if = "junction_temp > 95"`
+pub(crate) fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+
+    s.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// `deserialize_with` for a duration in seconds, e.g. `for = 5`, `start_delay = 1.5`
+pub(crate) fn secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let secs = f64::deserialize(deserializer)?;
+
+    Duration::try_from_secs_f64(secs).map(Some).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ordered() {
+        let s = concat!(
+            "a = 1\n",
+            "[z]\n",
+            "b = \"c\"\n",
+            "[y]\n",
+        );
+        let keys: Vec<String> = parse_ordered(s).unwrap().into_iter().map(|(k, _)| k).collect();
+
+        assert_eq!(keys, ["a", "z", "y"]);
+        assert!(parse_ordered("a = 500ms\n").is_err());
+        assert!(parse_ordered("log = /tmp/alert.log\n").is_err());
+    }
+}
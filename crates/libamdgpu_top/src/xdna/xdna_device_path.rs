@@ -2,8 +2,8 @@
 // ref: https://github.com/amd/xdna-driver/blob/main/src/driver/amdxdna/amdxdna_pci_drv.c
 
 use std::{fs, io};
-use std::path::Path;
-use crate::{DevicePath, PCI};
+use crate::{root, DevicePath, PCI};
+use crate::device_path::parse_sysfs_hex;
 
 /*
 const DRIVER_NAME_1: &str = "/sys/bus/pci/drivers/amdxdna_accel_driver";
@@ -34,19 +34,13 @@ const XDNA_NPU3_DEVICES: &[(u32, u32)] = &[
     (VENDOR_ATI, 0x1640),
 ];
 
-fn parse_sysfs_hex<P: AsRef<Path>>(path: P) -> Option<u32> {
-    let s = fs::read_to_string(path.as_ref()).ok()?;
-
-    u32::from_str_radix(s.get(2..s.len()-1)?, 16).ok()
-}
-
 fn is_amd_signal_processing(vendor: u32, class: u32) -> bool {
     // 0x11: Signal Processing Controller, 0x80: Other
     vendor == 0x1022 && class == 0x118000
 }
 
 pub fn find_xdna_device() -> Option<DevicePath> {
-    fs::read_dir(PCI_DEVICES_DIR).ok()?.find_map(|dir_entry| {
+    fs::read_dir(root::sys_path(PCI_DEVICES_DIR)).ok()?.find_map(|dir_entry| {
         let path = dir_entry.ok()?.path();
 
         {
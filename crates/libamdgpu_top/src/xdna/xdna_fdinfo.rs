@@ -1,9 +1,10 @@
 use std::time::Duration;
-use crate::stat;
+use serde::{Deserialize, Serialize};
+use crate::stat::{self, DrmFdInfo};
 
 // ref: https://github.com/amd/xdna-driver
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct XdnaFdInfoUsage {
     pub total_memory: u64, // KiB
     pub shared_memory: u64, // KiB
@@ -28,22 +29,8 @@ impl std::ops::Add for XdnaFdInfoUsage {
 
 #[test]
 fn test_xdna_fdinfo_parse() {
-    let mut usage = XdnaFdInfoUsage::default();
     let s = std::fs::read_to_string("src/xdna/fdinfo_sample.txt").unwrap();
-
-    let lines = s.lines().skip_while(|l| !l.starts_with("drm-client-id"));
-
-    for l in lines {
-        let Some(s) = l.get(0..13) else { continue };
-
-        match s {
-            "drm-total-mem" => usage.total_memory_usage_parse(l),
-            "drm-shared-me" => usage.shared_memory_usage_parse(l),
-            "drm-active-me" => usage.active_memory_usage_parse(l),
-            "drm-engine-np" => usage.engine_usage_parse(l),
-            _ => {},
-        }
-    }
+    let usage = XdnaFdInfoUsage::from_drm_fdinfo(&DrmFdInfo::parse(&s));
 
     const RESULT: XdnaFdInfoUsage = XdnaFdInfoUsage {
         total_memory: 8192,
@@ -56,51 +43,16 @@ fn test_xdna_fdinfo_parse() {
 }
 
 impl XdnaFdInfoUsage {
-    const UNIT_LEN: usize = " KiB".len();
-
-    pub fn id_parse(s: &str) -> Option<usize> {
-        const LEN: usize = "drm-client-id:\t".len();
-        s.get(LEN..)?.parse().ok()
-    }
-
-    fn memory_usage_parse(s: &str, prefix_len: usize) -> Option<u64> {
-        let shift = if s.ends_with("MiB") { 10 } else { 0 };
-
-        s.get(prefix_len..s.len()-Self::UNIT_LEN)
-            .and_then(|s| s.parse::<u64>().ok())
-            .map(|v| v << shift)
-    }
-
-    pub fn total_memory_usage_parse(&mut self, s: &str) {
-        const TOTAL_MEM_LEN: usize = "drm-total-memory:\t".len();
-        if let Some(usage) = Self::memory_usage_parse(s, TOTAL_MEM_LEN) {
-            self.total_memory = usage;
-        }
-    }
-
-    pub fn shared_memory_usage_parse(&mut self, s: &str) {
-        const SHARED_MEM_LEN: usize = "drm-shared-memory:\t".len();
-        if let Some(usage) = Self::memory_usage_parse(s, SHARED_MEM_LEN) {
-            self.shared_memory = usage;
-        }
-    }
-
-    pub fn active_memory_usage_parse(&mut self, s: &str) {
-        const ACTIVE_MEM_LEN: usize = "drm-active-memory:\t".len();
-        if let Some(usage) = Self::memory_usage_parse(s, ACTIVE_MEM_LEN) {
-            self.active_memory = usage;
-        }
-    }
-
-    pub fn engine_usage_parse(&mut self, s: &str) {
-        const ENGINE_USAGE_PREFIX_LEN: usize = "drm-engine-npu-amdxdna:\t".len();
-        const NS: usize = " ns".len();
-
-        let ends = s.len() - NS;
-        let Some(ns) = s.get(ENGINE_USAGE_PREFIX_LEN..ends)
-            .and_then(|s| s.parse::<i64>().ok()) else { return };
-
-        self.npu += ns;
+    pub fn from_drm_fdinfo(info: &DrmFdInfo) -> Self {
+        let mem = info.memory_region("memory");
+        let [total_memory, shared_memory, active_memory] = [
+            mem.and_then(|m| m.total),
+            mem.and_then(|m| m.shared),
+            mem.and_then(|m| m.active),
+        ].map(|v| v.unwrap_or(0));
+        let npu = info.engine("npu-amdxdna").and_then(|e| e.busy).unwrap_or(0) as i64;
+
+        Self { total_memory, shared_memory, active_memory, npu }
     }
 
     pub fn calc_usage(
@@ -109,7 +61,7 @@ impl XdnaFdInfoUsage {
         interval: &Duration,
     ) -> Self {
         // ns -> %
-        let npu = stat::diff_usage(pre_stat.npu, self.npu, interval);
+        let npu = stat::diff_usage(pre_stat.npu, self.npu, interval).round() as i64;
 
         Self {
             total_memory: self.total_memory,
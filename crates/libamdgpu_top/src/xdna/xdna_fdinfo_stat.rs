@@ -3,10 +3,12 @@ use std::fs;
 use std::io::Read;
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
 use super::XdnaFdInfoUsage;
-use crate::stat::ProcInfo;
+use crate::root;
+use crate::stat::{DrmFdInfo, ProcInfo};
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct XdnaProcUsage {
     pub pid: i32,
     pub name: String,
@@ -15,9 +17,11 @@ pub struct XdnaProcUsage {
     // pub cpu_usage: i64, // %
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct XdnaFdInfoStat {
+    #[serde(skip)]
     pub pid_map: HashMap<i32, XdnaFdInfoUsage>,
+    #[serde(skip)]
     pub drm_client_ids: HashSet<usize>,
     pub proc_usage: Vec<XdnaProcUsage>,
     pub interval: Duration,
@@ -36,30 +40,18 @@ impl XdnaFdInfoStat {
             buf.clear();
 
             {
-                let path = format!("/proc/{pid}/fdinfo/{fd}");
+                let path = root::proc_path(format!("{pid}/fdinfo/{fd}"));
                 let Ok(mut f) = fs::File::open(&path) else { continue };
                 if f.read_to_string(&mut buf).is_err() { continue }
             }
 
-            let mut lines = buf.lines().skip_while(|l| !l.starts_with("drm-client-id"));
-            if let Some(id) = lines.next().and_then(XdnaFdInfoUsage::id_parse) {
-                ids_count += 1;
-                if !self.drm_client_ids.insert(id) { continue }
-            } else {
-                continue;
-            }
+            let fdinfo = DrmFdInfo::parse(&buf);
+            let Some(id) = fdinfo.client_id else { continue };
 
-            for l in lines {
-                let Some(s) = l.get(0..13) else { continue };
+            ids_count += 1;
+            if !self.drm_client_ids.insert(id) { continue }
 
-                match s {
-                    "drm-total-mem" => stat.total_memory_usage_parse(l),
-                    "drm-shared-me" => stat.shared_memory_usage_parse(l),
-                    "drm-active-me" => stat.active_memory_usage_parse(l),
-                    "drm-engine-np" => stat.engine_usage_parse(l),
-                    _ => {},
-                }
-            }
+            stat = stat + XdnaFdInfoUsage::from_drm_fdinfo(&fdinfo);
         }
 
         let diff = if let Some(pre_stat) = self.pid_map.get_mut(&pid) {
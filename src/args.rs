@@ -1,4 +1,9 @@
 use libamdgpu_top::{GuiWgpuBackend, PCI};
+use libamdgpu_top::config::{Config, Settings};
+use libamdgpu_top::root::Root;
+use libamdgpu_top::stat::ProcFilter;
+use std::path::PathBuf;
+use crate::control::ControlRequest;
 
 pub struct MainOpt {
     pub instance: Option<usize>, // index
@@ -14,8 +19,25 @@ pub struct MainOpt {
     pub no_pc: bool,
     pub is_dark_mode: Option<bool>,
     pub decode_gpu_metrics: Option<String>,
+    pub decode_devcoredump: Option<String>,
+    pub devcoredump_dir: Option<PathBuf>,
     pub hide_fdinfo: bool,
     pub wgpu_backend: GuiWgpuBackend,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub alert_rules: Option<String>,
+    pub profile_daemon: Option<String>,
+    pub root: Root,
+    pub demo: bool,
+    pub session_stats: bool,
+    pub exec: Vec<String>, // command and arguments
+    pub control: Vec<ControlRequest>,
+    pub enable_control: bool,
+    pub gfxoff_residency: bool,
+    pub proc_filter: ProcFilter,
+    pub config_path: Option<PathBuf>,
+    pub config: Config,
+    pub cli: Settings, // flags overriding the config file
 }
 
 impl Default for MainOpt {
@@ -34,8 +56,25 @@ impl Default for MainOpt {
             no_pc: false,
             is_dark_mode: None,
             decode_gpu_metrics: None,
+            decode_devcoredump: None,
+            devcoredump_dir: None,
             hide_fdinfo: false,
             wgpu_backend: GuiWgpuBackend::Gl,
+            record: None,
+            replay: None,
+            alert_rules: None,
+            profile_daemon: None,
+            root: Root::from_env(),
+            demo: false,
+            session_stats: false,
+            exec: Vec::new(),
+            control: Vec::new(),
+            enable_control: false,
+            gfxoff_residency: false,
+            proc_filter: Default::default(),
+            config_path: Config::default_path(),
+            config: Default::default(),
+            cli: Default::default(),
         }
     }
 }
@@ -51,6 +90,8 @@ pub enum AppMode {
     JSON,
     #[cfg(feature = "json")]
     JSON_FIFO(String),
+    #[cfg(feature = "json")]
+    PROMETHEUS(String),
     #[cfg(feature = "tui")]
     SMI,
 }
@@ -120,6 +161,19 @@ const HELP_MSG: &str = concat!(
     "       Set to the light mode. (TUI/GUI)\n",
     "   --hide-fdinfo\n",
     "       Hide fdinfo panel and launch. (TUI)\n",
+    "   --session-stats\n",
+    "       Print the statistics (min/max/avg/p50/p95/p99) of the metrics over the session\n",
+    "       on exit (TUI/SMI/JSON), and show them in a panel. (TUI/GUI)\n",
+    "   --enable-control\n",
+    "       Enable the controls of the power cap, performance level, power profile,\n",
+    "       overdrive and fan. (TUI/GUI)\n",
+    "       Writing to sysfs requires root.\n",
+    "   --gfxoff-residency\n",
+    "       Log the GFXOFF residency with the SMU (amdgpu_gfxoff_residency),\n",
+    "       and stop it on exit. (TUI/SMI/GUI)\n",
+    "       Writing to debugfs requires root.\n",
+    "   --no-config\n",
+    "       Do not read the config file.\n",
     "   --gl, --opengl\n",
     "       Use OpenGL API to the GUI backend.\n",
     "   --vk, --vulkan\n",
@@ -141,11 +195,78 @@ const HELP_MSG: &str = concat!(
     "       Specifies the maximum number of iteration for JSON mode.\n",
     "       If 0 is specified, it will be an infinite loop. (default: 0)\n",
     "   -u <u64>, --update-process-index <u64>\n",
-    "       Update interval in seconds of the process index for fdinfo. (default: 5s)\n",
+    "       Interval in seconds to recheck all processes for the process index of fdinfo.\n",
+    "       New processes are indexed immediately. (default: 5s)\n",
     "   --json_fifo, --json-fifo <String>\n",
     "       Output JSON formatted data to FIFO (named pipe) for other application and scripts.\n",
+    "   --prometheus <addr:port>\n",
+    "       Serve metrics in the OpenMetrics text format at http://<addr:port>/metrics\n",
+    "       for Prometheus. (e.g. \"--prometheus 127.0.0.1:9101\")\n",
+    "   --pid <PIDs>\n",
+    "       Show only the processes with the PIDs in fdinfo. (comma separated, all modes)\n",
+    "   --name <Name|Regex>\n",
+    "       Show only the processes whose names match the regex in fdinfo. (all modes)\n",
+    "   --user <Name|UID>\n",
+    "       Show only the processes of the user in fdinfo. (all modes)\n",
+    "       These filters can be specified multiple times, and changed with the \"/\" key in TUI.\n",
     "   --decode-gm <Path>, --decode-gpu-metrics <Path>\n",
     "       Decode the specified gpu_metrics file.\n",
+    "   --decode-devcoredump <Path>\n",
+    "       Decode the specified amdgpu device coredump (/sys/class/devcoredump/devcd<N>/data),\n",
+    "       or the file saved by amdgpu_top.\n",
+    "   --devcoredump-dir <Path>\n",
+    "       Save the device coredumps of GPU hangs with the decoded reports to the directory.\n",
+    "       (TUI/GUI/JSON, default: $XDG_STATE_HOME/amdgpu_top)\n",
+    "   --record <Path>\n",
+    "       Record the sampling session to the specified file. (TUI/GUI/JSON)\n",
+    "   --replay <Path>\n",
+    "       Replay the recorded session. (TUI/GUI)\n",
+    "       It does not require the AMD GPU.\n",
+    "   --alert-rules <Path>\n",
+    "       Evaluate the alert rules in the specified file, and run the actions of the fired alerts.\n",
+    "       (run a command, append to a log, write to stderr in JSON mode, highlight in TUI/GUI)\n",
+    "   --config <Path>\n",
+    "       Read the settings from the specified config file, and save the layout of TUI/GUI to it.\n",
+    "       The command-line options override the config file.\n",
+    "       (default: $XDG_CONFIG_HOME/amdgpu_top/config.toml or ~/.config/amdgpu_top/config.toml)\n",
+    "   --exec <Command> [Args ..]\n",
+    "       Run the command, and print a report of the GPU usage of it and its descendants to stderr\n",
+    "       on exit. (wall time, engine busy time, VRAM/GTT, evicted VRAM, energy, temperature)\n",
+    "       The usage is sampled at the refresh period (\"-s\"), and the report is in JSON with \"-J\".\n",
+    "       The arguments after the command are passed to it. Exits with the exit code of the command.\n",
+    "   --set-power-cap <W|default>\n",
+    "       Set the power cap (power1_cap) of the selected device in watts, or restore the default.\n",
+    "   --set-perf-level <Level>\n",
+    "       Set power_dpm_force_performance_level of the selected device.\n",
+    "       (auto, low, high, manual, profile_standard, profile_min_sclk, profile_min_mclk,\n",
+    "       profile_peak, perf_determinism)\n",
+    "   --set-power-profile <Name|Index>\n",
+    "       Set pp_power_profile_mode of the selected device. (e.g. \"COMPUTE\")\n",
+    "       The device is selected with \"-i\" or \"--pci\", and the values are validated\n",
+    "       against the range and the profiles advertised by the device. Requires root.\n",
+    "   --set-od <Command>\n",
+    "       Stage a change of pp_od_clk_voltage of the selected device, and commit the changes.\n",
+    "       (e.g. \"s 1 2500\", \"m 1 1000\", \"vc 2 2100 1100\", \"vo -50\")\n",
+    "       It can be specified multiple times, and the values are validated against OD_RANGE.\n",
+    "       If the driver rejects a change, the previous values are restored.\n",
+    "   --reset-od\n",
+    "       Reset pp_od_clk_voltage of the selected device to the defaults.\n",
+    "   --set-fan-speed <%|auto>\n",
+    "       Set the fan speed (pwm1) of the selected device in percent,\n",
+    "       or restore the automatic fan control (and the default fan curve).\n",
+    "   --set-fan-curve <Temp:Speed,..|default>\n",
+    "       Set gpu_od/fan_ctrl/fan_curve of the selected device (RDNA3 and later),\n",
+    "       or restore the default. (e.g. \"25:20,45:30,60:45,75:70,90:100\" (C:%))\n",
+    "   --profile-daemon <Path>\n",
+    "       Switch the power profile, performance level and power cap of the devices when\n",
+    "       the processes matching the rules in the specified file start or stop, and restore\n",
+    "       the previous settings on exit. The changes are printed (in JSON with \"-J\"). Requires root.\n",
+    "   --demo\n",
+    "       Run with simulated devices (dGPU and APU with NPU) instead of the installed AMD GPUs.\n",
+    "   --sysfs-root <Path>, --proc-root <Path>, --dev-root <Path>\n",
+    "       Read sysfs, procfs and device files from the specified directories\n",
+    "       instead of \"/sys\", \"/proc\" and \"/dev\".\n",
+    "       Also \"AGT_SYSFS_ROOT\", \"AGT_PROC_ROOT\" and \"AGT_DEV_ROOT\" environment variables.\n",
 );
 
 impl MainOpt {
@@ -153,6 +274,7 @@ impl MainOpt {
     pub fn parse() -> Self {
         let mut opt = Self::default();
         let mut skip = false;
+        let mut no_config = false;
 
         let args = &std::env::args().skip(1).collect::<Vec<String>>();
 
@@ -215,6 +337,28 @@ impl MainOpt {
                         std::process::exit(1);
                     }
                 },
+                "--prometheus" => {
+                    #[cfg(feature = "json")]
+                    {
+                        let s = match args.get(idx+1) {
+                            Some(val_str) if !val_str.starts_with('-') => {
+                                skip = true;
+                                String::from(val_str)
+                            },
+                            _ => {
+                                eprintln!("missing argument: \"--prometheus <addr:port>\"");
+                                std::process::exit(1);
+                            },
+                        };
+
+                        opt.app_mode = AppMode::PROMETHEUS(s);
+                    }
+                    #[cfg(not(feature = "json"))]
+                    {
+                        eprintln!("\"json\" feature is not enabled for this build.");
+                        std::process::exit(1);
+                    }
+                },
                 "-s" => {
                     if let Some(val_str) = args.get(idx+1) {
                         let tmp = if val_str.ends_with("ms") {
@@ -228,7 +372,7 @@ impl MainOpt {
                         };
 
                         if tmp != 0 {
-                            opt.refresh_period = tmp;
+                            opt.cli.refresh_period = Some(tmp);
                         }
 
                         skip = true;
@@ -242,7 +386,7 @@ impl MainOpt {
                         let tmp = val_str.parse::<u64>().unwrap();
 
                         if tmp != 0 {
-                            opt.update_process_index = tmp;
+                            opt.cli.update_process_index = Some(tmp);
                         }
 
                         skip = true;
@@ -312,6 +456,27 @@ impl MainOpt {
                 "-p" | "--process" => {
                     opt.dump_mode = DumpMode::Process;
                 },
+                "--pid" | "--name" | "--user" => {
+                    let val_str = match args.get(idx+1) {
+                        Some(val_str) if !val_str.starts_with('-') => val_str,
+                        _ => {
+                            eprintln!("missing argument: \"--pid <PIDs>\", \"--name <Name|Regex>\" or \"--user <Name|UID>\"");
+                            std::process::exit(1);
+                        },
+                    };
+                    let r = match arg.as_str() {
+                        "--pid" => opt.proc_filter.add_pids(val_str),
+                        "--name" => opt.proc_filter.add_name(val_str),
+                        _ => opt.proc_filter.add_user(val_str),
+                    };
+
+                    if let Err(err) = r {
+                        eprintln!("{err:#}");
+                        std::process::exit(1);
+                    }
+
+                    skip = true;
+                },
                 "--pp-table" | "--pp_table" => {
                     opt.dump_mode = DumpMode::PPTable;
                 },
@@ -319,7 +484,7 @@ impl MainOpt {
                     opt.single_gpu = true;
                 },
                 "--no-pc" => {
-                    opt.no_pc = true;
+                    opt.cli.no_pc = Some(true);
                 },
                 "-gm" | "--gpu-metrics" | "--gpu_metrics" => {
                     opt.opt_dump_mode = OptDumpMode::GpuMetrics;
@@ -334,18 +499,119 @@ impl MainOpt {
 
                     skip = true;
                 },
+                "--decode-devcoredump" => {
+                    opt.decode_devcoredump = args.get(idx+1).map(|s| s.to_string());
+
+                    if opt.decode_devcoredump.is_none() {
+                        eprintln!("missing argument: \"--decode-devcoredump <Path>\"");
+                        std::process::exit(1);
+                    }
+
+                    skip = true;
+                },
+                "--record" | "--replay" | "--alert-rules" | "--profile-daemon" | "--config" | "--devcoredump-dir" => {
+                    let path = match args.get(idx+1) {
+                        Some(val_str) if !val_str.starts_with('-') => val_str.to_string(),
+                        _ => {
+                            eprintln!("missing argument: \"{arg} <Path>\"");
+                            std::process::exit(1);
+                        },
+                    };
+
+                    match arg.as_str() {
+                        "--record" => opt.record = Some(path),
+                        "--replay" => opt.replay = Some(path),
+                        "--alert-rules" => opt.alert_rules = Some(path),
+                        "--profile-daemon" => opt.profile_daemon = Some(path),
+                        "--devcoredump-dir" => opt.devcoredump_dir = Some(PathBuf::from(path)),
+                        _ => opt.config_path = Some(PathBuf::from(path)),
+                    }
+
+                    skip = true;
+                },
+                "--sysfs-root" | "--proc-root" | "--dev-root" => {
+                    let path = match args.get(idx+1) {
+                        Some(val_str) if !val_str.starts_with('-') => PathBuf::from(val_str),
+                        _ => {
+                            eprintln!("missing argument: \"{arg} <Path>\"");
+                            std::process::exit(1);
+                        },
+                    };
+
+                    match arg.as_str() {
+                        "--sysfs-root" => opt.root.sysfs = path,
+                        "--proc-root" => opt.root.procfs = path,
+                        _ => opt.root.devfs = path,
+                    }
+
+                    skip = true;
+                },
                 "--drm-info" | "--drm_info" => {
                     opt.opt_dump_mode = OptDumpMode::DrmInfo;
                 },
                 "--dark" | "--dark-mode" => {
-                    opt.is_dark_mode = Some(true);
+                    opt.cli.is_dark_mode = Some(true);
                 },
                 "--light" | "--light-mode" => {
-                    opt.is_dark_mode = Some(false);
+                    opt.cli.is_dark_mode = Some(false);
+                },
+                "--hide-fdinfo" => opt.cli.hide_fdinfo = Some(true),
+                "--no-config" => no_config = true,
+                "--session-stats" => opt.cli.session_stats = Some(true),
+                "--exec" => {
+                    opt.exec = args[idx+1..].to_vec();
+
+                    if opt.exec.is_empty() {
+                        eprintln!("missing argument: \"--exec <Command> [Args ..]\"");
+                        std::process::exit(1);
+                    }
+
+                    break;
+                },
+                "--set-power-cap" |
+                "--set-perf-level" |
+                "--set-power-profile" |
+                "--set-od" |
+                "--set-fan-speed" |
+                "--set-fan-curve" => {
+                    let val_str = match args.get(idx+1) {
+                        Some(val_str) if !val_str.starts_with('-') => val_str,
+                        _ => {
+                            eprintln!("missing argument: \"{arg} <Value>\"");
+                            std::process::exit(1);
+                        },
+                    };
+                    let r = match arg.as_str() {
+                        "--set-power-cap" => ControlRequest::power_cap(val_str),
+                        "--set-perf-level" => val_str
+                            .parse()
+                            .map(ControlRequest::PerformanceLevel)
+                            .map_err(|err| err.to_string()),
+                        "--set-od" => val_str
+                            .parse()
+                            .map(ControlRequest::Overdrive)
+                            .map_err(|err| err.to_string()),
+                        "--set-fan-speed" => ControlRequest::fan_speed(val_str),
+                        "--set-fan-curve" => ControlRequest::fan_curve(val_str),
+                        _ => Ok(ControlRequest::PowerProfile(val_str.to_string())),
+                    };
+
+                    match r {
+                        Ok(request) => opt.control.push(request),
+                        Err(err) => {
+                            eprintln!("{err}");
+                            std::process::exit(1);
+                        },
+                    }
+
+                    skip = true;
                 },
-                "--hide-fdinfo" => opt.hide_fdinfo = true,
-                "--gl" | "--opengl" => opt.wgpu_backend = GuiWgpuBackend::Gl,
-                "--vk" | "--vulkan" => opt.wgpu_backend = GuiWgpuBackend::Vulkan,
+                "--reset-od" => opt.control.push(ControlRequest::ResetOverdrive),
+                "--enable-control" => opt.enable_control = true,
+                "--gfxoff-residency" => opt.gfxoff_residency = true,
+                "--demo" => opt.demo = true,
+                "--gl" | "--opengl" => opt.cli.gui_wgpu_backend = Some(GuiWgpuBackend::Gl),
+                "--vk" | "--vulkan" => opt.cli.gui_wgpu_backend = Some(GuiWgpuBackend::Vulkan),
                 "--xdna" => {
                     opt.dump_mode = DumpMode::Xdna;
                 },
@@ -356,6 +622,37 @@ impl MainOpt {
             }
         }
 
+        if no_config {
+            opt.config_path = None;
+        }
+
+        if let Some(path) = &opt.config_path {
+            opt.config = Config::load(path).unwrap_or_else(|err| {
+                eprintln!("{err:#}");
+                std::process::exit(1);
+            });
+        }
+
+        opt.apply_config(opt.pci);
+
         opt
     }
+
+    /// The config file merged with the command-line flags for the device
+    pub fn settings(&self, pci: Option<PCI::BUS_INFO>) -> Settings {
+        self.config.settings(pci).merge(&self.cli)
+    }
+
+    /// Apply the config file and the command-line flags for the device.
+    pub fn apply_config(&mut self, pci: Option<PCI::BUS_INFO>) {
+        let settings = self.settings(pci);
+
+        if let Some(v) = settings.refresh_period { self.refresh_period = v }
+        if let Some(v) = settings.update_process_index { self.update_process_index = v }
+        if let Some(v) = settings.no_pc { self.no_pc = v }
+        if let Some(v) = settings.hide_fdinfo { self.hide_fdinfo = v }
+        if let Some(v) = settings.session_stats { self.session_stats = v }
+        if let Some(v) = settings.gui_wgpu_backend { self.wgpu_backend = v }
+        if settings.is_dark_mode.is_some() { self.is_dark_mode = settings.is_dark_mode }
+    }
 }
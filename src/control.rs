@@ -0,0 +1,118 @@
+use libamdgpu_top::DevicePath;
+use libamdgpu_top::control::{FanControl, FanCurvePoints, OdEdit, OverdriveEditor, PerformanceLevel, PowerControl};
+
+#[derive(Debug, Clone)]
+pub enum ControlRequest {
+    /// `None` restores the default
+    PowerCap(Option<u32>),
+    PerformanceLevel(PerformanceLevel),
+    PowerProfile(String),
+    /// Staged, and committed after the other requests
+    Overdrive(OdEdit),
+    ResetOverdrive,
+    /// Fan speed in percent, `None` restores the automatic fan control
+    FanSpeed(Option<u32>),
+    /// `None` restores the default fan curve
+    FanCurve(Option<FanCurvePoints>),
+}
+
+impl ControlRequest {
+    pub fn power_cap(s: &str) -> Result<Self, String> {
+        if s == "default" {
+            return Ok(Self::PowerCap(None));
+        }
+
+        let watts = s.trim_end_matches('W').parse()
+            .map_err(|_| format!("invalid power cap: {s:?} (expected watts or \"default\")"))?;
+
+        Ok(Self::PowerCap(Some(watts)))
+    }
+
+    pub fn fan_speed(s: &str) -> Result<Self, String> {
+        if s == "auto" {
+            return Ok(Self::FanSpeed(None));
+        }
+
+        let percent = s.trim_end_matches('%').parse()
+            .map_err(|_| format!("invalid fan speed: {s:?} (expected percent or \"auto\")"))?;
+
+        Ok(Self::FanSpeed(Some(percent)))
+    }
+
+    pub fn fan_curve(s: &str) -> Result<Self, String> {
+        if s == "default" {
+            return Ok(Self::FanCurve(None));
+        }
+
+        s.parse().map(|points| Self::FanCurve(Some(points))).map_err(|err| format!("{err}"))
+    }
+}
+
+/// Apply the requests to the selected device, and exit.
+pub fn run_control(requests: &[ControlRequest], device_path: &DevicePath) -> ! {
+    let pci = device_path.pci;
+    let control = PowerControl::new(pci);
+    let fan_control = FanControl::new(pci);
+    let mut od_editor: Option<OverdriveEditor> = None;
+
+    for request in requests {
+        let r = match request {
+            ControlRequest::PowerCap(Some(watts)) => control.set_power_cap(*watts),
+            ControlRequest::PowerCap(None) => control.reset_power_cap(),
+            ControlRequest::PerformanceLevel(level) => control.set_performance_level(*level),
+            ControlRequest::PowerProfile(profile) => control.set_power_profile(profile),
+            ControlRequest::Overdrive(_) | ControlRequest::ResetOverdrive => {
+                let editor = od_editor.get_or_insert_with(|| {
+                    OverdriveEditor::new(pci).unwrap_or_else(exit_with_err)
+                });
+
+                match request {
+                    ControlRequest::Overdrive(edit) => editor.stage(edit.clone()),
+                    _ => editor.reset(),
+                }
+            },
+            ControlRequest::FanSpeed(Some(percent)) => fan_control.set_manual_speed(*percent),
+            ControlRequest::FanSpeed(None) => fan_control.set_auto(),
+            ControlRequest::FanCurve(Some(points)) => fan_control.set_fan_curve(&points.0),
+            ControlRequest::FanCurve(None) => fan_control.reset_fan_curve(),
+        };
+
+        if let Err(err) = r {
+            exit_with_err(err);
+        }
+    }
+
+    if let Some(editor) = &mut od_editor {
+        if let Err(err) = editor.commit() {
+            exit_with_err(err);
+        }
+
+        println!("{pci}: Overdrive:\n{}", editor.table);
+    }
+
+    if let Some(cap) = control.power_cap() {
+        println!("{pci}: Power Cap: {} W ({}-{} W, default: {} W)", cap.current, cap.min, cap.max, cap.default);
+    }
+    if let Some(level) = control.performance_level() {
+        println!("{pci}: Performance Level: {level}");
+    }
+    if let Some(profile) = control.power_profiles().iter().find(|mode| mode.is_current) {
+        println!("{pci}: Power Profile: {}", profile.name);
+    }
+    if let Some(fan) = fan_control.fan_stat() {
+        if let (Some(mode), Some(per)) = (fan.mode, fan.pwm_percent()) {
+            println!("{pci}: Fan: {mode}, PWM {per}%");
+        }
+    }
+    if let Some(curve) = fan_control.fan_curve() {
+        let points: Vec<String> = curve.points.iter().map(|p| format!("{}C:{}%", p.temp, p.speed)).collect();
+        println!("{pci}: Fan Curve: {}", points.join(", "));
+    }
+
+    std::process::exit(0);
+}
+
+fn exit_with_err<E: std::fmt::Display>(err: E) -> ! {
+    eprintln!("{err:#}");
+    std::process::exit(1);
+}
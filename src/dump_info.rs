@@ -8,9 +8,10 @@ use libamdgpu_top::{
         MetricsInfo,
     },
     AppDeviceInfo,
+    control::{self, OverdriveTable},
     // DeviceHandle,
     DevicePath,
-    stat::Sensors,
+    stat::{DevCoredumpReport, Sensors},
 };
 use crate::{OptDumpMode, drm_info};
 
@@ -86,6 +87,7 @@ pub fn dump(device_path: &DevicePath, opt_dump_mode: OptDumpMode) {
         let profiles: Vec<String> = info.power_profiles.iter().map(|p| p.to_string()).collect();
         println!("Supported Power Profiles: {profiles:?}");
     }
+    overdrive_info(&device_path.sysfs_path);
     info.cache_info();
     info.hw_ip_info();
     if !info.ip_die_entries.is_empty() {
@@ -119,6 +121,16 @@ pub fn dump(device_path: &DevicePath, opt_dump_mode: OptDumpMode) {
     }
 }
 
+fn overdrive_info(sysfs_path: &std::path::Path) {
+    if let Some(table) = OverdriveTable::from_sysfs(sysfs_path) {
+        println!("\nOverdrive (pp_od_clk_voltage):\n{table}");
+    }
+
+    for (name, table) in control::gpu_od_tables(sysfs_path) {
+        println!("\nOverdrive (gpu_od/{name}):\n{table}");
+    }
+}
+
 fn sensors_info(sensors: &Sensors) {
     println!();
     for temp in [&sensors.edge_temp, &sensors.junction_temp, &sensors.memory_temp] {
@@ -148,6 +160,17 @@ fn sensors_info(sensors: &Sensors) {
     if let Some(fan_rpm) = &sensors.fan_rpm {
         println!("Fan RPM (Current)   : {fan_rpm} RPM");
     }
+    if let Some(fan) = &sensors.fan {
+        if let Some(mode) = fan.mode {
+            println!("Fan Mode            : {mode}");
+        }
+        if let (Some(pwm), Some(per)) = (fan.pwm, fan.pwm_percent()) {
+            println!("Fan PWM             : {pwm} ({per}%, {}-{})", fan.pwm_min, fan.pwm_max);
+        }
+        if let Some(target) = fan.target_rpm {
+            println!("Fan RPM (Target)    : {target} RPM");
+        }
+    }
 
     const PCIE_LABEL: &str = "PCIe Link Speed";
     const PCIE_LEN: usize = 14;
@@ -413,3 +436,33 @@ pub fn decode_gpu_metrics(path: &str) -> GpuMetrics {
 
     GpuMetrics::read_file_with_buffer(&mut buf, path).unwrap()
 }
+
+pub fn decode_devcoredump(path: &str) -> DevCoredumpReport {
+    let buf = std::fs::read(path).unwrap();
+
+    DevCoredumpReport::parse(&String::from_utf8_lossy(&buf)).unwrap_or_else(|| {
+        eprintln!("{path} is not an amdgpu device coredump");
+        std::process::exit(1);
+    })
+}
+
+pub fn print_devcoredump(report: &DevCoredumpReport) {
+    let hex = |v: &[u64]| v.iter().map(|addr| format!("{addr:#x}")).collect::<Vec<_>>().join(", ");
+
+    println!("{report}");
+
+    for (label, val) in [
+        ("Kernel", report.kernel.clone()),
+        ("Uptime", report.uptime.as_ref().map(|t| format!("{t} s"))),
+        ("Process", report.process_name.as_ref().map(|name| format!("{name} ({})", report.pid.unwrap_or_default()))),
+        ("Timed Out Ring", report.ring.clone()),
+        ("Page Fault", report.page_fault.map(|addr| format!("{addr:#x}"))),
+        ("IBs", (!report.ibs.is_empty()).then(|| hex(&report.ibs))),
+        ("IP Dump", (!report.ip_dumps.is_empty()).then(|| report.ip_dumps.join(", "))),
+        ("VRAM Lost", Some(report.vram_lost.to_string())),
+    ] {
+        let Some(val) = val else { continue };
+
+        println!("    {label:<15}: {val}");
+    }
+}
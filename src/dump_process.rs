@@ -9,15 +9,15 @@ pub fn dump_process(title: &str, list: &[DevicePath]) {
     println!("{title}\n");
 
     for device_path in list {
-        let Ok(amdgpu_dev) = device_path.init() else { continue };
+        let Ok(amdgpu_dev) = device_path.init_backend() else { continue };
         let Ok(memory_info) = amdgpu_dev.memory_info() else { continue };
 
         let mut proc_index: Vec<ProcInfo> = Vec::new();
         stat::update_index(&mut proc_index, device_path);
 
         let mut fdinfo = FdInfoStat {
-            has_vcn: has_vcn(&amdgpu_dev),
-            has_vcn_unified: has_vcn_unified(&amdgpu_dev),
+            has_vcn: has_vcn(amdgpu_dev.as_ref()),
+            has_vcn_unified: has_vcn_unified(amdgpu_dev.as_ref()),
             ..Default::default()
         };
 
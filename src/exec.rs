@@ -0,0 +1,71 @@
+use libamdgpu_top::DevicePath;
+use libamdgpu_top::app::AppAmdgpuTop;
+use libamdgpu_top::exec_report::ExecTracker;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::args::MainOpt;
+
+/// Run the command, and print the GPU usage report to stderr like `time`.
+/// Returns the exit code of the command.
+pub fn run_exec(main_opt: &MainOpt, device_path_list: &[DevicePath]) -> i32 {
+    let (cmd, args) = main_opt.exec.split_first().unwrap();
+    // The devices suspended at the start are not tracked, to not wake them up.
+    let (apps, _) = AppAmdgpuTop::create_app_and_suspended_list(device_path_list, &Default::default());
+
+    // Orphaned descendants are reparented to this process, and can still be tracked.
+    unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0); }
+
+    let mut child = match Command::new(cmd).args(args).spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("Failed to execute {cmd:?}: {err}");
+            return 127;
+        },
+    };
+
+    // Like `time`, let the command handle SIGINT and SIGQUIT, and print the report after it exits.
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+        libc::signal(libc::SIGQUIT, libc::SIG_IGN);
+    }
+
+    let mut tracker = ExecTracker::new(child.id() as i32, apps);
+    let interval = Duration::from_millis(main_opt.refresh_period);
+    let (tx, rx) = mpsc::channel();
+
+    tracker.update();
+
+    std::thread::spawn(move || {
+        let _ = tx.send(child.wait());
+    });
+
+    let status = loop {
+        match rx.recv_timeout(interval) {
+            Ok(status) => break status,
+            Err(mpsc::RecvTimeoutError::Timeout) => tracker.update(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!(),
+        }
+    };
+    let exit_code = match status {
+        Ok(status) => status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0)),
+        Err(err) => {
+            eprintln!("Failed to wait for {cmd:?}: {err}");
+            1
+        },
+    };
+    let report = tracker.report(&main_opt.exec, exit_code);
+
+    #[cfg(feature = "json")]
+    if let crate::args::AppMode::JSON = main_opt.app_mode {
+        use amdgpu_top_json::OutputJson;
+        eprintln!("{}", report.json());
+        return exit_code;
+    }
+
+    eprint!("{report}");
+
+    exit_code
+}
@@ -1,4 +1,5 @@
 use libamdgpu_top::{DevicePath, UiArgs};
+use std::path::PathBuf;
 
 #[cfg(feature = "gui")]
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
@@ -12,9 +13,31 @@ mod dump_process;
 use dump_process::dump_process;
 mod dump_xdna_device;
 mod drm_info;
+mod exec;
+mod profile_daemon;
+mod control;
 
 fn main() {
-    let main_opt = MainOpt::parse();
+    let mut main_opt = MainOpt::parse();
+
+    // The tree of the demo is removed when main returns, also on SIGINT/SIGTERM.
+    let demo_dir = if main_opt.demo {
+        let demo_dir = libamdgpu_top::demo::setup().unwrap_or_else(|err| {
+            eprintln!("Failed to set up the demo: {err}");
+            std::process::exit(1);
+        });
+
+        libamdgpu_top::signal::catch_exit_signals();
+
+        Some(demo_dir)
+    } else {
+        let _ = libamdgpu_top::root::set(main_opt.root.clone());
+
+        None
+    };
+
+    libamdgpu_top::stat::set_proc_filter(main_opt.proc_filter.clone());
+    libamdgpu_top::stat::set_devcoredump_dir(main_opt.devcoredump_dir.clone());
 
     if let Some(path) = &main_opt.decode_gpu_metrics {
         let gm = dump_info::decode_gpu_metrics(path);
@@ -30,6 +53,25 @@ fn main() {
         return;
     }
 
+    if let Some(path) = &main_opt.decode_devcoredump {
+        let report = dump_info::decode_devcoredump(path);
+
+        #[cfg(feature = "json")]
+        if let AppMode::JSON = main_opt.app_mode {
+            use amdgpu_top_json::OutputJson;
+            println!("{}", report.json());
+            return;
+        }
+
+        dump_info::print_devcoredump(&report);
+        return;
+    }
+
+    if let Some(path) = main_opt.replay.clone() {
+        replay(&mut main_opt, &path);
+        return;
+    }
+
     let (device_path_list, device_path) = {
         let list = DevicePath::get_device_path_list();
 
@@ -51,6 +93,23 @@ fn main() {
         }
     };
 
+    main_opt.apply_config(Some(device_path.pci));
+
+    if !main_opt.control.is_empty() {
+        control::run_control(&main_opt.control, &device_path);
+    }
+
+    if !main_opt.exec.is_empty() {
+        let exit_code = exec::run_exec(&main_opt, &device_path_list);
+
+        drop(demo_dir);
+        std::process::exit(exit_code);
+    }
+
+    if let Some(path) = &main_opt.profile_daemon {
+        profile_daemon::run_profile_daemon(&main_opt, path, &device_path_list);
+    }
+
     #[cfg(feature = "json")]
     if let AppMode::JSON = main_opt.app_mode { match main_opt.dump_mode {
         DumpMode::Info => {
@@ -84,6 +143,13 @@ fn main() {
                 main_opt.no_pc,
             );
 
+            j.recorder = recorder(&main_opt);
+            j.alert_rules = alert_rules(&main_opt);
+
+            if main_opt.session_stats {
+                j.enable_session_stats();
+            }
+
             j.run();
 
             return;
@@ -134,6 +200,7 @@ fn main() {
     }
 
     let ui_args = UiArgs {
+        settings: main_opt.settings(Some(device_path.pci)),
         selected_device_path: device_path,
         device_path_list,
         update_process_index: main_opt.update_process_index,
@@ -141,6 +208,13 @@ fn main() {
         is_dark_mode: main_opt.is_dark_mode,
         hide_fdinfo: main_opt.hide_fdinfo,
         gui_wgpu_backend: main_opt.wgpu_backend,
+        record: main_opt.record.as_ref().map(PathBuf::from),
+        replay: None,
+        alert_rules: alert_rules(&main_opt),
+        config_path: main_opt.config_path.clone(),
+        session_stats: main_opt.session_stats,
+        enable_control: main_opt.enable_control,
+        gfxoff_residency: main_opt.gfxoff_residency,
     };
 
     match main_opt.app_mode {
@@ -162,7 +236,6 @@ fn main() {
         #[cfg(feature = "json")]
         AppMode::JSON_FIFO(path_string) => {
             use std::ffi::CString;
-            use std::path::PathBuf;
 
             let path = PathBuf::from(path_string.clone());
 
@@ -190,13 +263,102 @@ fn main() {
                 ui_args.no_pc,
             );
 
+            j.recorder = recorder(&main_opt);
+            j.alert_rules = alert_rules(&main_opt);
             j.run_fifo(path);
         },
+        #[cfg(feature = "json")]
+        AppMode::PROMETHEUS(addr) => {
+            let mut j = amdgpu_top_json::JsonApp::new(
+                TITLE,
+                &ui_args.device_path_list,
+                main_opt.refresh_period,
+                ui_args.update_process_index,
+                main_opt.json_iterations,
+                ui_args.no_pc,
+            );
+
+            j.recorder = recorder(&main_opt);
+            j.alert_rules = alert_rules(&main_opt);
+            j.run_prometheus(&addr);
+        },
         #[cfg(feature = "tui")]
         AppMode::SMI => amdgpu_top_tui::run_smi(TITLE, ui_args),
     }
 }
 
+fn replay(main_opt: &mut MainOpt, path: &str) {
+    use libamdgpu_top::record::Recording;
+    use std::sync::Arc;
+
+    let recording = Recording::load(path).unwrap_or_else(|err| {
+        eprintln!("{err:#}");
+        std::process::exit(1);
+    });
+    let device_path_list = recording.device_path_list();
+    let selected_device_path = if let Some(pci) = main_opt.pci {
+        device_path_list.iter().find(|d| d.pci == pci)
+    } else {
+        device_path_list.get(main_opt.instance.unwrap_or(0))
+    }.unwrap_or_else(|| {
+        eprintln!("The specified device is not in the recording.");
+        eprintln!("Device list: {device_path_list:#?}");
+        std::process::exit(1);
+    }).clone();
+
+    main_opt.apply_config(Some(selected_device_path.pci));
+
+    let ui_args = UiArgs {
+        settings: main_opt.settings(Some(selected_device_path.pci)),
+        selected_device_path,
+        device_path_list,
+        update_process_index: main_opt.update_process_index,
+        no_pc: main_opt.no_pc,
+        is_dark_mode: main_opt.is_dark_mode,
+        hide_fdinfo: main_opt.hide_fdinfo,
+        gui_wgpu_backend: main_opt.wgpu_backend,
+        record: None,
+        replay: Some(Arc::new(recording)),
+        alert_rules: alert_rules(main_opt),
+        config_path: main_opt.config_path.clone(),
+        session_stats: main_opt.session_stats,
+        enable_control: false,
+        gfxoff_residency: false,
+    };
+
+    match main_opt.app_mode {
+        #[cfg(feature = "tui")]
+        AppMode::TUI => amdgpu_top_tui::run(TITLE, ui_args),
+        #[cfg(feature = "gui")]
+        AppMode::GUI => amdgpu_top_gui::run(APP_NAME, TITLE, ui_args),
+        #[allow(unreachable_patterns)]
+        _ => {
+            eprintln!("\"--replay\" is only supported in TUI and GUI mode.");
+            std::process::exit(1);
+        },
+    }
+}
+
+#[cfg(feature = "json")]
+fn recorder(main_opt: &MainOpt) -> Option<libamdgpu_top::record::Recorder> {
+    let path = main_opt.record.as_ref()?;
+
+    Some(libamdgpu_top::record::Recorder::create(path).unwrap_or_else(|err| {
+        eprintln!("Failed to create {path:?}: {err}");
+        std::process::exit(1);
+    }))
+}
+
+fn alert_rules(main_opt: &MainOpt) -> Option<std::sync::Arc<libamdgpu_top::alert::AlertRules>> {
+    let path = main_opt.alert_rules.as_ref()?;
+    let rules = libamdgpu_top::alert::AlertRules::load(path).unwrap_or_else(|err| {
+        eprintln!("{err:#}");
+        std::process::exit(1);
+    });
+
+    Some(std::sync::Arc::new(rules))
+}
+
 pub fn device_list(list: &[DevicePath]) {
     println!("{TITLE}\n");
     for (i, device_path) in list.iter().enumerate() {
@@ -237,7 +399,7 @@ fn select_apu(list: &[DevicePath]) -> DevicePath {
             return false;
         }
 
-        let Ok(amdgpu_dev) = device_path.init() else { return false };
+        let Ok(amdgpu_dev) = device_path.init_backend() else { return false };
         let Ok(ext_info) = amdgpu_dev.device_info() else { return false };
 
         ext_info.is_apu()
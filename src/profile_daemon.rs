@@ -0,0 +1,49 @@
+use libamdgpu_top::DevicePath;
+use libamdgpu_top::app::AppAmdgpuTop;
+use libamdgpu_top::profile_daemon::{ProfileChange, ProfileDaemon, ProfileRules};
+use libamdgpu_top::{signal, stat};
+use std::time::Duration;
+
+use crate::args::MainOpt;
+
+/// Switch the power profiles of the devices by the rules until SIGINT/SIGTERM,
+/// and restore the previous settings on exit.
+pub fn run_profile_daemon(main_opt: &MainOpt, path: &str, device_path_list: &[DevicePath]) -> ! {
+    let rules = ProfileRules::load(path).unwrap_or_else(|err| {
+        eprintln!("{err:#}");
+        std::process::exit(1);
+    });
+    // The devices suspended at the start are not monitored, to not wake them up.
+    let (mut apps, _) = AppAmdgpuTop::create_app_and_suspended_list(device_path_list, &Default::default());
+    let interval = Duration::from_millis(main_opt.refresh_period);
+    let mut daemon = ProfileDaemon::new(rules);
+
+    stat::spawn_update_index_thread(device_path_list.to_vec(), main_opt.update_process_index);
+    signal::catch_exit_signals();
+
+    while !signal::exit_requested() {
+        std::thread::sleep(interval);
+
+        for app in apps.iter_mut() {
+            app.update(interval);
+            print_changes(main_opt, &daemon.check(app));
+        }
+    }
+
+    print_changes(main_opt, &daemon.restore());
+    std::process::exit(0);
+}
+
+#[cfg_attr(not(feature = "json"), allow(unused_variables))]
+fn print_changes(main_opt: &MainOpt, changes: &[ProfileChange]) {
+    for change in changes {
+        #[cfg(feature = "json")]
+        if let crate::args::AppMode::JSON = main_opt.app_mode {
+            use amdgpu_top_json::OutputJson;
+            println!("{}", change.json());
+            continue;
+        }
+
+        println!("{change}");
+    }
+}